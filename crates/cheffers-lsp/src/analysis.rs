@@ -0,0 +1,327 @@
+//! What the server knows about a recipe: diagnostics, hover text,
+//! definitions and completions, computed from the parser's [`Recipe`] and
+//! its source map.
+
+use cheffers::lint::{lint, LintConfig};
+use cheffers::types::{Measure, ParseError, Recipe};
+use cheffers::Parser;
+use serde_json::{json, Value};
+
+use crate::document::{Document, Position, Range};
+
+const SEVERITY_ERROR: u8 = 1;
+const SEVERITY_WARNING: u8 = 2;
+
+const COMPLETION_KIND_VARIABLE: u8 = 6;
+const COMPLETION_KIND_MODULE: u8 = 9;
+const COMPLETION_KIND_SNIPPET: u8 = 15;
+const INSERT_TEXT_FORMAT_SNIPPET: u8 = 2;
+
+/// Method sentences offered as completions, as (label, snippet) pairs.
+const INSTRUCTION_TEMPLATES: &[(&str, &str)] = &[
+    (
+        "Take ... from refrigerator",
+        "Take ${1:ingredient} from refrigerator.",
+    ),
+    (
+        "Put ... into the mixing bowl",
+        "Put ${1:ingredient} into the mixing bowl.",
+    ),
+    (
+        "Fold ... into the mixing bowl",
+        "Fold ${1:ingredient} into the mixing bowl.",
+    ),
+    ("Add ...", "Add ${1:ingredient}."),
+    ("Remove ...", "Remove ${1:ingredient}."),
+    ("Combine ...", "Combine ${1:ingredient}."),
+    ("Divide ...", "Divide ${1:ingredient}."),
+    (
+        "Add dry ingredients",
+        "Add dry ingredients to the mixing bowl.",
+    ),
+    ("Liquefy ...", "Liquefy ${1:ingredient}."),
+    (
+        "Liquefy contents of the mixing bowl",
+        "Liquefy contents of the mixing bowl.",
+    ),
+    (
+        "Stir for ... minutes",
+        "Stir the mixing bowl for ${1:number} minutes.",
+    ),
+    (
+        "Stir ... into the mixing bowl",
+        "Stir ${1:ingredient} into the mixing bowl.",
+    ),
+    ("Mix well", "Mix the mixing bowl well."),
+    ("Clean mixing bowl", "Clean the mixing bowl."),
+    (
+        "Pour contents of the mixing bowl",
+        "Pour contents of the mixing bowl into the baking dish.",
+    ),
+    (
+        "Verb the ... until verbed",
+        "${1:Sift} the ${2:ingredient}.\n$0\n${1:Sift} until ${3:sifted}.",
+    ),
+    ("Set aside", "Set aside."),
+    ("Serve with ...", "Serve with ${1:recipe}."),
+    ("Refrigerate", "Refrigerate."),
+    ("Serves ...", "Serves ${1:number}."),
+];
+
+/// Parses `document`, returning its diagnostics and the recipe when it
+/// parsed. A parse error is the only diagnostic; otherwise every lint
+/// becomes a warning.
+pub fn diagnose(document: &Document) -> (Vec<Value>, Option<Recipe>) {
+    match Parser::new(document.text()).parse_recipe() {
+        Ok(recipe) => {
+            let diagnostics = lint(&recipe, &LintConfig::default())
+                .into_iter()
+                .map(|warning| {
+                    let range = match &warning.location {
+                        Some(location) => document.range_of(location),
+                        None => document.find(&warning.recipe),
+                    };
+                    diagnostic(
+                        range,
+                        SEVERITY_WARNING,
+                        Some(warning.rule.id()),
+                        &warning.message,
+                    )
+                })
+                .collect();
+            (diagnostics, Some(recipe))
+        }
+        Err(error) => {
+            let range = document.find(error_needle(&error).unwrap_or(""));
+            let diagnostic = diagnostic(range, SEVERITY_ERROR, None, &error.to_string());
+            (vec![diagnostic], None)
+        }
+    }
+}
+
+fn diagnostic(range: Range, severity: u8, code: Option<&str>, message: &str) -> Value {
+    let mut diagnostic = json!({
+        "range": range.to_json(),
+        "severity": severity,
+        "source": "cheffers",
+        "message": message,
+    });
+    if let Some(code) = code {
+        diagnostic["code"] = json!(code);
+    }
+    diagnostic
+}
+
+/// Text of the source that a parse error is about, when it names any.
+/// Messages quote the offending text ('...') when they carry a sentence.
+fn error_needle(error: &ParseError) -> Option<&str> {
+    let payload = match error {
+        ParseError::MissingSection(text)
+        | ParseError::InvalidIngredient(text)
+        | ParseError::InvalidQuantity(text)
+        | ParseError::UnknownInstruction(text)
+        | ParseError::UnmatchedLoop(text)
+        | ParseError::InvalidTitle(text)
+        | ParseError::InvalidMeasure(text) => text.as_str(),
+        ParseError::LoopVerbMismatch { found, .. }
+        | ParseError::OrdinalSuffixMismatch { found, .. }
+        | ParseError::OrdinalWord { word: found, .. } => return Some(found.as_str()),
+        ParseError::MissingBlankLine { text, .. }
+        | ParseError::MetadataInIngredients { text, .. }
+        | ParseError::ServesInMethod { text, .. }
+        | ParseError::InvalidMetadata { text, .. }
+        | ParseError::UnexpectedParagraph { text, .. } => return Some(text.as_str()),
+        ParseError::HeaderNotOnOwnLine { header, .. } => return Some(header.as_str()),
+        ParseError::InvalidLoop
+        | ParseError::ExtraBlankLine { .. }
+        | ParseError::BlankLineInMethod { .. }
+        | ParseError::SectionOutOfOrder { .. } => return None,
+    };
+    let quoted = payload
+        .split_once('\'')
+        .and_then(|(_, rest)| rest.split_once('\''))
+        .map(|(inner, _)| inner);
+    Some(quoted.unwrap_or(payload))
+}
+
+/// The recipe (main or auxiliary) whose text contains the zero-based
+/// `line`: the one with the last title at or before it.
+fn recipe_at(recipe: &Recipe, line: usize) -> &Recipe {
+    let mut best = recipe;
+    let mut best_line = 0;
+    for auxiliary in recipe.auxiliary_recipes.values() {
+        if let Some(title) = &auxiliary.source_map.title {
+            let title_line = title.line.saturating_sub(1);
+            if title_line <= line && title_line >= best_line {
+                best = auxiliary;
+                best_line = title_line;
+            }
+        }
+    }
+    best
+}
+
+fn ingredient_names(recipe: &Recipe) -> impl Iterator<Item = &String> {
+    recipe
+        .ingredients
+        .keys()
+        .chain(recipe.unset_ingredients.keys())
+}
+
+/// The ingredient name under the cursor and its byte span in the line.
+/// The longest name wins, so "brown sugar" beats "sugar".
+fn ingredient_at<'r>(
+    document: &Document,
+    recipe: &'r Recipe,
+    position: Position,
+) -> Option<(&'r String, usize, usize)> {
+    let line = document.line(position.line);
+    let cursor = document.byte_in_line(position);
+    let mut found: Option<(&String, usize, usize)> = None;
+    for name in ingredient_names(recipe) {
+        for (start, _) in line.match_indices(name.as_str()) {
+            let end = start + name.len();
+            let bounded = !line[..start].ends_with(char::is_alphanumeric)
+                && !line[end..].starts_with(char::is_alphanumeric);
+            let longer = found.map_or(true, |(best, _, _)| name.len() > best.len());
+            if bounded && start <= cursor && cursor <= end && longer {
+                found = Some((name, start, end));
+            }
+        }
+    }
+    found
+}
+
+/// The auxiliary recipe named by a "Serve with" sentence under the cursor.
+fn served_recipe_at<'r>(
+    document: &Document,
+    recipe: &'r Recipe,
+    position: Position,
+) -> Option<&'r Recipe> {
+    let line = document.line(position.line);
+    let cursor = document.byte_in_line(position);
+    let lower = line.to_lowercase();
+    for (at, phrase) in lower.match_indices("serve with ") {
+        let start = at + phrase.len();
+        let end = line[start..]
+            .find('.')
+            .map(|idx| start + idx)
+            .unwrap_or(line.len());
+        if start <= cursor && cursor <= end {
+            let wanted = normalize(&line[start..end]);
+            return recipe
+                .auxiliary_recipes
+                .iter()
+                .find(|(title, _)| normalize(title) == wanted)
+                .map(|(_, auxiliary)| auxiliary);
+        }
+    }
+    None
+}
+
+/// Recipe names compare like the interpreter compares them.
+fn normalize(name: &str) -> String {
+    name.trim().trim_end_matches('.').to_lowercase()
+}
+
+fn describe_measure(measure: Measure) -> &'static str {
+    match measure {
+        Measure::Dry => "dry",
+        Measure::Liquid => "liquid",
+        Measure::Unspecified => "dry or liquid",
+    }
+}
+
+/// Markdown hover for the ingredient under the cursor.
+pub fn hover(document: &Document, recipe: &Recipe, position: Position) -> Option<Value> {
+    let scope = recipe_at(recipe, position.line);
+    let (name, start, end) = ingredient_at(document, scope, position)?;
+    let mut text = match scope.ingredients.get(name) {
+        Some(value) => format!(
+            "**{}**: {} ({})",
+            name,
+            value.amount,
+            describe_measure(value.measure)
+        ),
+        None => format!(
+            "**{}**: no initial value ({})",
+            name,
+            describe_measure(scope.unset_ingredients[name])
+        ),
+    };
+    if let Some(location) = scope.source_map.ingredients.get(name) {
+        if let Some(snippet) = &location.snippet {
+            text.push_str(&format!(
+                "\n\nDeclared on line {}: `{}`",
+                location.line,
+                snippet.trim()
+            ));
+        }
+    }
+    Some(json!({
+        "contents": { "kind": "markdown", "value": text },
+        "range": document.span(position.line, start, end).to_json(),
+    }))
+}
+
+/// Where the ingredient or served recipe under the cursor is declared.
+pub fn definition(
+    document: &Document,
+    recipe: &Recipe,
+    uri: &str,
+    position: Position,
+) -> Option<Value> {
+    let location = match served_recipe_at(document, recipe, position) {
+        Some(auxiliary) => auxiliary.source_map.title.as_ref()?,
+        None => {
+            let scope = recipe_at(recipe, position.line);
+            let (name, _, _) = ingredient_at(document, scope, position)?;
+            scope.source_map.ingredients.get(name)?
+        }
+    };
+    Some(json!({ "uri": uri, "range": document.range_of(location).to_json() }))
+}
+
+/// Ingredient names in scope, auxiliary recipe names and method
+/// templates. `recipe` is the last version of the document that parsed.
+pub fn completion(recipe: Option<&Recipe>, position: Position) -> Value {
+    let mut items = Vec::new();
+    if let Some(recipe) = recipe {
+        let scope = recipe_at(recipe, position.line);
+        let mut names: Vec<&String> = ingredient_names(scope).collect();
+        names.sort();
+        for name in names {
+            let detail = match scope.ingredients.get(name) {
+                Some(value) => format!("{} ({})", value.amount, describe_measure(value.measure)),
+                None => "no initial value".to_string(),
+            };
+            items.push(json!({
+                "label": name,
+                "kind": COMPLETION_KIND_VARIABLE,
+                "detail": detail,
+            }));
+        }
+        let mut titles: Vec<&str> = recipe
+            .auxiliary_recipes
+            .values()
+            .map(|auxiliary| auxiliary.title.trim().trim_end_matches('.'))
+            .collect();
+        titles.sort();
+        for title in titles {
+            items.push(json!({
+                "label": title,
+                "kind": COMPLETION_KIND_MODULE,
+                "detail": "auxiliary recipe",
+            }));
+        }
+    }
+    for (label, snippet) in INSTRUCTION_TEMPLATES {
+        items.push(json!({
+            "label": label,
+            "kind": COMPLETION_KIND_SNIPPET,
+            "insertText": snippet,
+            "insertTextFormat": INSERT_TEXT_FORMAT_SNIPPET,
+        }));
+    }
+    json!({ "isIncomplete": false, "items": items })
+}
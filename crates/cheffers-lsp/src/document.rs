@@ -0,0 +1,159 @@
+//! An open text document and the conversions between byte offsets, the
+//! parser's 1-based line/char-column locations and LSP positions (0-based,
+//! UTF-16 columns).
+
+use cheffers::error_context::SourceLocation;
+use serde_json::{json, Value};
+
+/// A zero-based LSP position; `character` counts UTF-16 code units.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Position {
+    pub line: usize,
+    pub character: usize,
+}
+
+impl Position {
+    pub fn from_json(value: &Value) -> Option<Position> {
+        Some(Position {
+            line: value.get("line")?.as_u64()? as usize,
+            character: value.get("character")?.as_u64()? as usize,
+        })
+    }
+
+    pub fn to_json(self) -> Value {
+        json!({ "line": self.line, "character": self.character })
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Range {
+    pub start: Position,
+    pub end: Position,
+}
+
+impl Range {
+    pub fn to_json(self) -> Value {
+        json!({ "start": self.start.to_json(), "end": self.end.to_json() })
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Document {
+    text: String,
+    line_starts: Vec<usize>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        let line_starts = std::iter::once(0)
+            .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self { text, line_starts }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The text of a zero-based line, without its line ending.
+    pub fn line(&self, line: usize) -> &str {
+        let Some(&start) = self.line_starts.get(line) else {
+            return "";
+        };
+        let end = self
+            .line_starts
+            .get(line + 1)
+            .map(|next| next - 1)
+            .unwrap_or(self.text.len());
+        self.text[start..end].trim_end_matches('\r')
+    }
+
+    /// Byte index into `line(position.line)` for an LSP position, clamped to
+    /// the end of the line.
+    pub fn byte_in_line(&self, position: Position) -> usize {
+        let line = self.line(position.line);
+        let mut units = 0;
+        for (idx, ch) in line.char_indices() {
+            if units >= position.character {
+                return idx;
+            }
+            units += ch.len_utf16();
+        }
+        line.len()
+    }
+
+    /// LSP position of a byte index into a line.
+    pub fn position_in_line(&self, line: usize, byte: usize) -> Position {
+        let text = self.line(line);
+        let byte = byte.min(text.len());
+        Position {
+            line,
+            character: text[..byte].encode_utf16().count(),
+        }
+    }
+
+    /// Range covering a byte span of one line.
+    pub fn span(&self, line: usize, start: usize, end: usize) -> Range {
+        Range {
+            start: self.position_in_line(line, start),
+            end: self.position_in_line(line, end),
+        }
+    }
+
+    /// Range of a parser location: from its column to the end of its
+    /// snippet, or to the end of the line when there is no snippet.
+    pub fn range_of(&self, location: &SourceLocation) -> Range {
+        let line = location.line.saturating_sub(1);
+        let text = self.line(line);
+        let column = location.column.unwrap_or(1).saturating_sub(1);
+        let start = text
+            .char_indices()
+            .nth(column)
+            .map(|(idx, _)| idx)
+            .unwrap_or(text.len());
+        let end = match &location.snippet {
+            Some(snippet) => {
+                let first_line = snippet.lines().next().unwrap_or("");
+                (start + first_line.len()).min(text.len())
+            }
+            None => text.len(),
+        };
+        self.span(line, start, end)
+    }
+
+    /// Range of the first line containing `needle`, or of the first line.
+    pub fn find(&self, needle: &str) -> Range {
+        let needle = needle.trim();
+        if !needle.is_empty() {
+            for line in 0..self.line_starts.len() {
+                if let Some(start) = self.line(line).find(needle) {
+                    return self.span(line, start, start + needle.len());
+                }
+            }
+        }
+        self.span(0, 0, self.line(0).len())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn columns_are_utf16_code_units() {
+        let doc = Document::new("Title.\n🍝 pasta\r\nlast".to_string());
+        assert_eq!(doc.line(1), "🍝 pasta");
+        let byte = doc.byte_in_line(Position {
+            line: 1,
+            character: 3,
+        });
+        assert_eq!(&doc.line(1)[byte..], "pasta");
+        assert_eq!(doc.position_in_line(1, byte).character, 3);
+
+        let location = SourceLocation::new(2)
+            .with_column(3)
+            .with_snippet("pasta".to_string());
+        let range = doc.range_of(&location);
+        assert_eq!((range.start.character, range.end.character), (3, 8));
+    }
+}
@@ -0,0 +1,230 @@
+//! A Language Server Protocol server for Chef recipes.
+//!
+//! The server speaks JSON-RPC over stdio and supports:
+//!
+//! - diagnostics: parse errors and `cheffers::lint` warnings, published on
+//!   every open and change (full document sync);
+//! - hover over an ingredient, showing its declared value and measure;
+//! - go to definition from an ingredient to its declaration, and from
+//!   `Serve with X` to the auxiliary recipe `X`;
+//! - completion of ingredient names, auxiliary recipe names and method
+//!   sentence templates.
+//!
+//! Positions use UTF-16 columns, the protocol's default encoding.
+
+pub mod analysis;
+pub mod document;
+pub mod transport;
+
+use std::collections::HashMap;
+use std::io::{self, BufRead, Write};
+
+use cheffers::types::Recipe;
+use serde_json::{json, Value};
+
+use crate::document::{Document, Position};
+use crate::transport::{read_message, write_message};
+
+const PARSE_ERROR: i64 = -32700;
+const INVALID_REQUEST: i64 = -32600;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+/// Text sync kind `Full`: every change sends the whole document.
+const TEXT_DOCUMENT_SYNC_FULL: u8 = 1;
+
+/// One open document and the last version of it that parsed.
+struct OpenDocument {
+    document: Document,
+    recipe: Option<Recipe>,
+    last_parsed: Option<Recipe>,
+}
+
+/// Server state between messages.
+#[derive(Default)]
+pub struct Server {
+    documents: HashMap<String, OpenDocument>,
+    shutdown_requested: bool,
+}
+
+impl Server {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Handles one message and returns the messages to send back: the
+    /// response to a request, and any notifications it triggers.
+    pub fn handle(&mut self, message: &Value) -> Vec<Value> {
+        let id = message.get("id").cloned();
+        let Some(method) = message.get("method").and_then(Value::as_str) else {
+            // A response to something we never ask, or garbage.
+            return match id {
+                Some(id) => vec![error_response(id, INVALID_REQUEST, "missing method")],
+                None => Vec::new(),
+            };
+        };
+        let params = message.get("params").cloned().unwrap_or(Value::Null);
+
+        match id {
+            Some(id) => vec![match self.request(method, &params) {
+                Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+                Err((code, text)) => error_response(id, code, &text),
+            }],
+            None => self.notification(method, &params),
+        }
+    }
+
+    /// Whether `exit` should end the process successfully.
+    pub fn shutdown_requested(&self) -> bool {
+        self.shutdown_requested
+    }
+
+    fn request(&mut self, method: &str, params: &Value) -> Result<Value, (i64, String)> {
+        match method {
+            "initialize" => Ok(json!({
+                "capabilities": {
+                    "textDocumentSync": {
+                        "openClose": true,
+                        "change": TEXT_DOCUMENT_SYNC_FULL,
+                    },
+                    "hoverProvider": true,
+                    "definitionProvider": true,
+                    "completionProvider": { "triggerCharacters": [" "] },
+                },
+                "serverInfo": {
+                    "name": "cheffers-lsp",
+                    "version": env!("CARGO_PKG_VERSION"),
+                },
+            })),
+            "shutdown" => {
+                self.shutdown_requested = true;
+                Ok(Value::Null)
+            }
+            "textDocument/hover" => {
+                let (open, position) = self.lookup(params)?;
+                Ok(open
+                    .recipe
+                    .as_ref()
+                    .and_then(|recipe| analysis::hover(&open.document, recipe, position))
+                    .unwrap_or(Value::Null))
+            }
+            "textDocument/definition" => {
+                let (open, position) = self.lookup(params)?;
+                let uri = document_uri(params).unwrap_or_default();
+                Ok(open
+                    .recipe
+                    .as_ref()
+                    .and_then(|recipe| analysis::definition(&open.document, recipe, uri, position))
+                    .unwrap_or(Value::Null))
+            }
+            "textDocument/completion" => {
+                let (open, position) = self.lookup(params)?;
+                Ok(analysis::completion(open.last_parsed.as_ref(), position))
+            }
+            _ => Err((METHOD_NOT_FOUND, format!("unsupported method '{}'", method))),
+        }
+    }
+
+    fn notification(&mut self, method: &str, params: &Value) -> Vec<Value> {
+        let Some(uri) = document_uri(params) else {
+            return Vec::new();
+        };
+        match method {
+            "textDocument/didOpen" => {
+                let text = params["textDocument"]["text"].as_str().unwrap_or_default();
+                self.update(uri, text.to_string())
+            }
+            "textDocument/didChange" => {
+                // Full sync: the last change holds the whole text.
+                let changes = params["contentChanges"].as_array();
+                match changes.and_then(|changes| changes.last()) {
+                    Some(change) => {
+                        let text = change["text"].as_str().unwrap_or_default();
+                        self.update(uri, text.to_string())
+                    }
+                    None => Vec::new(),
+                }
+            }
+            "textDocument/didClose" => {
+                self.documents.remove(uri);
+                vec![publish_diagnostics(uri, Vec::new())]
+            }
+            _ => Vec::new(),
+        }
+    }
+
+    fn update(&mut self, uri: &str, text: String) -> Vec<Value> {
+        let document = Document::new(text);
+        let (diagnostics, recipe) = analysis::diagnose(&document);
+        let last_parsed = match (&recipe, self.documents.remove(uri)) {
+            (Some(recipe), _) => Some(recipe.clone()),
+            (None, Some(previous)) => previous.last_parsed,
+            (None, None) => None,
+        };
+        self.documents.insert(
+            uri.to_string(),
+            OpenDocument {
+                document,
+                recipe,
+                last_parsed,
+            },
+        );
+        vec![publish_diagnostics(uri, diagnostics)]
+    }
+
+    fn lookup(&self, params: &Value) -> Result<(&OpenDocument, Position), (i64, String)> {
+        let uri = document_uri(params)
+            .ok_or_else(|| (INVALID_PARAMS, "missing textDocument.uri".to_string()))?;
+        let position = Position::from_json(&params["position"])
+            .ok_or_else(|| (INVALID_PARAMS, "missing position".to_string()))?;
+        let open = self
+            .documents
+            .get(uri)
+            .ok_or_else(|| (INVALID_PARAMS, format!("document '{}' is not open", uri)))?;
+        Ok((open, position))
+    }
+}
+
+fn document_uri(params: &Value) -> Option<&str> {
+    params.get("textDocument")?.get("uri")?.as_str()
+}
+
+fn publish_diagnostics(uri: &str, diagnostics: Vec<Value>) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "method": "textDocument/publishDiagnostics",
+        "params": { "uri": uri, "diagnostics": diagnostics },
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({
+        "jsonrpc": "2.0",
+        "id": id,
+        "error": { "code": code, "message": message },
+    })
+}
+
+/// Serves one client until it sends `exit` or closes the input. Returns
+/// whether the client asked to shut down first (the process exit status
+/// the protocol asks for).
+pub fn serve<R: BufRead, W: Write>(mut input: R, mut output: W) -> io::Result<bool> {
+    let mut server = Server::new();
+    while let Some(body) = read_message(&mut input)? {
+        let message: Value = match serde_json::from_slice(&body) {
+            Ok(message) => message,
+            Err(error) => {
+                let reply = error_response(Value::Null, PARSE_ERROR, &error.to_string());
+                write_message(&mut output, &reply)?;
+                continue;
+            }
+        };
+        if message.get("method").and_then(Value::as_str) == Some("exit") {
+            return Ok(server.shutdown_requested());
+        }
+        for reply in server.handle(&message) {
+            write_message(&mut output, &reply)?;
+        }
+    }
+    Ok(server.shutdown_requested())
+}
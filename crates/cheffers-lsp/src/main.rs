@@ -0,0 +1,19 @@
+//! `cheffers-lsp`: the Chef language server, over stdio.
+
+use std::io;
+use std::process;
+
+fn main() {
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    match cheffers_lsp::serve(stdin.lock(), stdout.lock()) {
+        Ok(true) => {}
+        // Exiting without a prior `shutdown` request is an error per the
+        // protocol.
+        Ok(false) => process::exit(1),
+        Err(error) => {
+            eprintln!("cheffers-lsp: {}", error);
+            process::exit(1);
+        }
+    }
+}
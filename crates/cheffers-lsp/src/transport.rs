@@ -0,0 +1,69 @@
+//! LSP base protocol framing: a `Content-Length` header, a blank line, then
+//! a JSON-RPC body.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::Value;
+
+/// Reads one message body. Returns `None` at end of input.
+pub fn read_message<R: BufRead>(reader: &mut R) -> io::Result<Option<Vec<u8>>> {
+    let mut content_length = None;
+    loop {
+        let mut header = String::new();
+        if reader.read_line(&mut header)? == 0 {
+            return Ok(None);
+        }
+        let header = header.trim_end();
+        if header.is_empty() {
+            if content_length.is_some() {
+                break;
+            }
+            // Tolerate stray blank lines between messages.
+            continue;
+        }
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("content-length") {
+                let length = value.trim().parse::<usize>().map_err(|_| {
+                    io::Error::new(io::ErrorKind::InvalidData, "bad Content-Length header")
+                })?;
+                content_length = Some(length);
+            }
+        }
+    }
+
+    let mut body = vec![0; content_length.unwrap_or(0)];
+    reader.read_exact(&mut body)?;
+    Ok(Some(body))
+}
+
+pub fn write_message<W: Write>(writer: &mut W, message: &Value) -> io::Result<()> {
+    let body = message.to_string();
+    write!(writer, "Content-Length: {}\r\n\r\n{}", body.len(), body)?;
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn messages_round_trip() {
+        let mut buffer = Vec::new();
+        write_message(&mut buffer, &json!({"jsonrpc": "2.0", "method": "exit"})).unwrap();
+        write_message(&mut buffer, &json!({"text": "Crème brûlée."})).unwrap();
+
+        let mut reader = io::Cursor::new(buffer);
+        let first = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&first).unwrap()["method"],
+            "exit"
+        );
+        let second = read_message(&mut reader).unwrap().unwrap();
+        assert_eq!(
+            serde_json::from_slice::<Value>(&second).unwrap()["text"],
+            "Crème brûlée."
+        );
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+}
@@ -12,14 +12,25 @@
 //!
 //! `input` is optional whitespace-separated numbers consumed one per
 //! `Take _ingredient_ from refrigerator` instruction (the playground's
-//! stand-in for stdin).
+//! stand-in for stdin). An optional third argument names an output style
+//! (`"plain"`, `"spaced"`, `"lines"` or `"perl"`, see
+//! `cheffers::OutputOptions`) that separates served numbers and dishes.
+//!
+//! [`compile_to_js`] returns the same shape, with `output` holding the
+//! JavaScript module the recipe compiles to (see `cheffers::compile`).
+//!
+//! [`classify_chef`] returns `[{ kind, from, to }]` highlighting spans from
+//! the library's own classifier (`cheffers::parser::highlight`), so editor
+//! highlighting agrees with the parser.
 //!
 //! Errors reuse the same [`ErrorFormatter`] the CLI uses, so the web editor
 //! shows the exact same rich, spec-referenced diagnostics.
 
+use cheffers::compile::compile_js;
 use cheffers::error_formatter::ErrorFormatter;
+use cheffers::parser::highlight::classify;
 use cheffers::types::ChefError;
-use cheffers::{Interpreter, Parser};
+use cheffers::{Interpreter, OutputOptions, Parser};
 use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
@@ -39,19 +50,96 @@ struct RunResult {
 ///
 /// `input` supplies the numbers consumed by `Take _ingredient_ from
 /// refrigerator`, whitespace-separated (there is no stdin in the browser).
-/// Omitting it is fine for recipes that take no input.
+/// Omitting it is fine for recipes that take no input. `output_style` picks
+/// how served numbers are separated; it defaults to `"plain"`.
 ///
 /// This never throws: parse and runtime failures are reported through the
 /// `error` field so the caller can render them however it likes.
 #[wasm_bindgen]
-pub fn run_chef(source: &str, input: Option<String>) -> JsValue {
-    let result = execute(source, input.as_deref());
+pub fn run_chef(source: &str, input: Option<String>, output_style: Option<String>) -> JsValue {
+    let result = execute(source, input.as_deref(), output_style.as_deref());
     // Serializing a small, owned struct cannot realistically fail; fall back
     // to null so the binding still never throws.
     serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
 }
 
-fn execute(source: &str, input: Option<&str>) -> RunResult {
+/// Compiles a Chef recipe to a standalone JavaScript ES module, returning
+/// `{ ok, output, error }` where `output` is the generated code.
+///
+/// Like [`run_chef`] this never throws; a recipe that does not parse is
+/// reported through the `error` field.
+#[wasm_bindgen]
+pub fn compile_to_js(source: &str) -> JsValue {
+    let result = compile(source);
+    serde_wasm_bindgen::to_value(&result).unwrap_or(JsValue::NULL)
+}
+
+/// One highlighted span, mirrored to a JS object.
+#[derive(Serialize)]
+struct TokenSpan {
+    /// A `TokenKind` name such as `"ingredient"` or `"loop-verb"`.
+    kind: &'static str,
+    /// Start offset in UTF-16 code units, as JS strings and CodeMirror count.
+    from: usize,
+    /// End offset (exclusive) in UTF-16 code units.
+    to: usize,
+}
+
+/// Classifies a Chef source for syntax highlighting, returning
+/// `[{ kind, from, to }]` in source order.
+#[wasm_bindgen]
+pub fn classify_chef(source: &str) -> JsValue {
+    serde_wasm_bindgen::to_value(&token_spans(source)).unwrap_or(JsValue::NULL)
+}
+
+fn token_spans(source: &str) -> Vec<TokenSpan> {
+    // UTF-16 offset of every byte offset that starts a char (and the end).
+    let mut utf16 = vec![0; source.len() + 1];
+    let mut units = 0;
+    for (idx, ch) in source.char_indices() {
+        utf16[idx] = units;
+        units += ch.len_utf16();
+    }
+    utf16[source.len()] = units;
+
+    classify(source)
+        .into_iter()
+        .map(|token| TokenSpan {
+            kind: token.kind.name(),
+            from: utf16[token.start],
+            to: utf16[token.end],
+        })
+        .collect()
+}
+
+fn compile(source: &str) -> RunResult {
+    match Parser::new(source).parse_recipe() {
+        Ok(recipe) => RunResult {
+            ok: true,
+            output: compile_js(&recipe),
+            error: String::new(),
+        },
+        Err(error) => RunResult {
+            ok: false,
+            output: String::new(),
+            error: ErrorFormatter::format(&ChefError::from(error)),
+        },
+    }
+}
+
+fn execute(source: &str, input: Option<&str>, output_style: Option<&str>) -> RunResult {
+    let style = output_style.unwrap_or("plain");
+    let Some(output_options) = OutputOptions::from_style(style) else {
+        return RunResult {
+            ok: false,
+            output: String::new(),
+            error: format!(
+                "unknown output style '{}' (expected one of: {})",
+                style,
+                OutputOptions::STYLES.join(", ")
+            ),
+        };
+    };
     let recipe = match Parser::new(source).parse_recipe() {
         Ok(recipe) => recipe,
         Err(error) => {
@@ -67,6 +155,7 @@ fn execute(source: &str, input: Option<&str>) -> RunResult {
     // There is no stdin in the browser: always run from a buffer, so a `Take`
     // without input reports "no more input values" instead of a stdin error.
     interpreter.set_input_text(input.unwrap_or(""));
+    interpreter.set_output_options(output_options);
     interpreter.add_recipe(recipe);
 
     match interpreter.run() {
@@ -106,7 +195,7 @@ mod tests {
 
     #[test]
     fn valid_recipe_reports_success_and_output() {
-        let result = execute(HELLO_WORLD, None);
+        let result = execute(HELLO_WORLD, None, None);
         assert!(result.ok, "expected success, got error: {}", result.error);
         assert_eq!(result.output, "Hello world!");
         assert!(result.error.is_empty());
@@ -119,14 +208,27 @@ mod tests {
         let recipe = "Number Nibble.\n\nIngredients.\n42 g answer\n\n\
             Method.\nPut answer into the mixing bowl. \
             Pour contents of the mixing bowl into the baking dish.\n\nServes 1.\n";
-        let result = execute(recipe, None);
+        let result = execute(recipe, None, None);
         assert!(result.ok, "expected success, got error: {}", result.error);
         assert_eq!(result.output, "42");
     }
 
+    #[test]
+    fn output_style_separates_numbers() {
+        let recipe = "Two Numbers.\n\nIngredients.\n1 g egg\n2 g ham\n\n\
+            Method.\nPut ham into the mixing bowl. Put egg into the mixing bowl. \
+            Pour contents of the mixing bowl into the baking dish.\n\nServes 1.\n";
+        assert_eq!(execute(recipe, None, Some("spaced")).output, "1 2 ");
+        assert_eq!(execute(recipe, None, Some("perl")).output, " 1 2");
+
+        let result = execute(recipe, None, Some("csv"));
+        assert!(!result.ok);
+        assert!(result.error.contains("unknown output style 'csv'"));
+    }
+
     #[test]
     fn parse_error_reports_failure_with_message() {
-        let result = execute("Totally not a recipe", None);
+        let result = execute("Totally not a recipe", None, None);
         assert!(!result.ok);
         assert!(result.output.is_empty());
         assert!(
@@ -141,7 +243,7 @@ mod tests {
         // "pepper" is never declared as an ingredient.
         let recipe = "Bad Soup.\n\nIngredients.\n1 g salt\n\n\
             Method.\nPut pepper into the mixing bowl.\n\nServes 1.\n";
-        let result = execute(recipe, None);
+        let result = execute(recipe, None, None);
         assert!(!result.ok);
         assert!(
             result.error.contains("undefined ingredient"),
@@ -157,14 +259,14 @@ mod tests {
 
     #[test]
     fn input_values_are_consumed_by_take() {
-        let result = execute(DOUBLER, Some("21"));
+        let result = execute(DOUBLER, Some("21"), None);
         assert!(result.ok, "expected success, got error: {}", result.error);
         assert_eq!(result.output, "42");
     }
 
     #[test]
     fn missing_input_reports_a_helpful_error() {
-        let result = execute(DOUBLER, None);
+        let result = execute(DOUBLER, None, None);
         assert!(!result.ok);
         assert!(
             result.error.contains("cannot read input"),
@@ -180,7 +282,7 @@ mod tests {
 
     #[test]
     fn non_numeric_input_token_reports_the_ingredient() {
-        let result = execute(DOUBLER, Some("nope"));
+        let result = execute(DOUBLER, Some("nope"), None);
         assert!(!result.ok);
         assert!(
             result.error.contains("sugar") && result.error.contains("nope"),
@@ -189,9 +291,42 @@ mod tests {
         );
     }
 
+    #[test]
+    fn compile_returns_a_javascript_module() {
+        let result = compile(HELLO_WORLD);
+        assert!(result.ok, "expected success, got error: {}", result.error);
+        assert!(result.output.contains("export function run("));
+    }
+
+    #[test]
+    fn compile_reports_parse_errors() {
+        let result = compile("Totally not a recipe");
+        assert!(!result.ok);
+        assert!(result.output.is_empty());
+        assert!(result.error.contains("invalid title"));
+    }
+
+    #[test]
+    fn token_spans_count_utf16_code_units() {
+        let source =
+            "Pasta 🍝.\n\nIngredients.\n1 g salt\n\nMethod.\nPut salt into the mixing bowl.";
+        let spans = token_spans(source);
+        let utf16: Vec<u16> = source.encode_utf16().collect();
+        let text = |span: &TokenSpan| String::from_utf16(&utf16[span.from..span.to]).unwrap();
+
+        assert_eq!(spans[0].kind, "title");
+        assert_eq!(text(&spans[0]), "Pasta 🍝.");
+        let ingredients: Vec<String> = spans
+            .iter()
+            .filter(|span| span.kind == "ingredient")
+            .map(text)
+            .collect();
+        assert_eq!(ingredients, vec!["salt", "salt"]);
+    }
+
     #[test]
     fn empty_source_is_a_handled_error_not_a_panic() {
-        let result = execute("", None);
+        let result = execute("", None, None);
         assert!(!result.ok);
         assert!(!result.error.is_empty());
     }
@@ -26,7 +26,8 @@ fn run(source: &str) -> RunResult {
 }
 
 fn run_with_input(source: &str, input: Option<String>) -> RunResult {
-    serde_wasm_bindgen::from_value(run_chef(source, input)).expect("run_chef must return an object")
+    serde_wasm_bindgen::from_value(run_chef(source, input, None))
+        .expect("run_chef must return an object")
 }
 
 const HELLO_WORLD: &str = "Hello World Souffle.\n\nIngredients.\n\
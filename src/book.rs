@@ -0,0 +1,249 @@
+//! Recipe books: auxiliary recipes kept in files of their own, so several
+//! recipes can `Serve with` the same sauce.
+//!
+//! Every recipe in a book file is shared, the first one included. A book
+//! is handed to [`Interpreter::add_book`](crate::Interpreter::add_book);
+//! recipes defined in the main recipe's own file take precedence over book
+//! recipes of the same name ([`RecipeBook::shadowed_by`] lists them).
+
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::interpreter::normalize_recipe_name;
+use crate::parser::{ParseOptions, Parser};
+use crate::types::{BookError, Recipe};
+
+/// The environment variable listing book files and directories, separated
+/// like `PATH`.
+pub const BOOK_PATH_VAR: &str = "CHEFFERS_BOOK_PATH";
+
+/// A recipe of a book and the file it came from.
+#[derive(Clone, Debug)]
+pub struct BookRecipe {
+    pub recipe: Recipe,
+    pub path: PathBuf,
+}
+
+/// Shared recipes by name, loaded from any number of files.
+#[derive(Clone, Debug, Default)]
+pub struct RecipeBook {
+    recipes: Vec<BookRecipe>,
+    index: HashMap<String, usize>,
+    /// The files already loaded, canonicalized.
+    loaded: HashSet<PathBuf>,
+}
+
+impl RecipeBook {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The book files and directories named by [`BOOK_PATH_VAR`].
+    pub fn search_path() -> Vec<PathBuf> {
+        env::var_os(BOOK_PATH_VAR)
+            .map(|paths| env::split_paths(&paths).collect())
+            .unwrap_or_default()
+    }
+
+    /// Loads a book file, or every `.chef` file in a directory (in name
+    /// order, not descending into subdirectories). A file that was already
+    /// loaded, under this path or another, is skipped.
+    pub fn load(&mut self, path: &Path, options: ParseOptions) -> Result<(), BookError> {
+        let unreadable = |source| BookError::Unreadable {
+            path: path.display().to_string(),
+            source,
+        };
+        if !path.is_dir() {
+            let canonical = fs::canonicalize(path).map_err(unreadable)?;
+            if !self.loaded.insert(canonical) {
+                return Ok(());
+            }
+            let source = fs::read_to_string(path).map_err(unreadable)?;
+            return self.add_source(&source, path, options);
+        }
+
+        let mut files = Vec::new();
+        for entry in fs::read_dir(path).map_err(unreadable)? {
+            let file = entry.map_err(unreadable)?.path();
+            if file.is_file() && file.extension().is_some_and(|ext| ext == "chef") {
+                files.push(file);
+            }
+        }
+        files.sort();
+        for file in files {
+            self.load(&file, options)?;
+        }
+        Ok(())
+    }
+
+    /// Parses `source` (read from `path`) and adds all of its recipes.
+    pub fn add_source(
+        &mut self,
+        source: &str,
+        path: &Path,
+        options: ParseOptions,
+    ) -> Result<(), BookError> {
+        let recipe = Parser::with_options(source, options)
+            .parse_recipe()
+            .map_err(|source| BookError::Parse {
+                path: path.display().to_string(),
+                source,
+            })?;
+        self.add(recipe, path)
+    }
+
+    /// Adds `recipe` and its auxiliary recipes. A name the book already
+    /// holds is a [`BookError::DuplicateRecipe`] naming both files.
+    pub fn add(&mut self, mut recipe: Recipe, path: &Path) -> Result<(), BookError> {
+        let mut auxiliaries: Vec<Recipe> =
+            recipe.auxiliary_recipes.drain().map(|(_, r)| r).collect();
+        auxiliaries.sort_by(|a, b| a.title.cmp(&b.title));
+        for recipe in std::iter::once(recipe).chain(auxiliaries) {
+            let key = normalize_recipe_name(&recipe.title);
+            if let Some(&existing) = self.index.get(&key) {
+                return Err(BookError::DuplicateRecipe {
+                    title: recipe.title,
+                    first: self.recipes[existing].path.display().to_string(),
+                    second: path.display().to_string(),
+                });
+            }
+            self.index.insert(key, self.recipes.len());
+            self.recipes.push(BookRecipe {
+                recipe,
+                path: path.to_path_buf(),
+            });
+        }
+        Ok(())
+    }
+
+    /// The recipe `Serve with title` would use, if the book has it.
+    pub fn get(&self, title: &str) -> Option<&BookRecipe> {
+        let idx = *self.index.get(&normalize_recipe_name(title))?;
+        Some(&self.recipes[idx])
+    }
+
+    /// Every recipe, in the order they were added.
+    pub fn recipes(&self) -> impl Iterator<Item = &BookRecipe> {
+        self.recipes.iter()
+    }
+
+    pub fn len(&self) -> usize {
+        self.recipes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.recipes.is_empty()
+    }
+
+    /// Book recipes that `recipe` or one of its auxiliary recipes has the
+    /// same name as, and so hides from its own `Serve with` instructions.
+    pub fn shadowed_by(&self, recipe: &Recipe) -> Vec<&BookRecipe> {
+        let mut shadowed: Vec<&BookRecipe> = std::iter::once(&recipe.title)
+            .chain(recipe.auxiliary_recipes.keys())
+            .filter_map(|title| self.get(title))
+            .collect();
+        shadowed.sort_by(|a, b| a.recipe.title.cmp(&b.recipe.title));
+        shadowed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interpreter;
+
+    const SAUCES: &str = "Caramel Sauce.\n\nIngredients.\n67 g sugar\n\nMethod.\n\
+        Clean the mixing bowl. Put sugar into the mixing bowl.\n\nServes 1.\n\n\
+        Toffee Sauce.\n\nIngredients.\n84 g butter\n\nMethod.\n\
+        Clean the mixing bowl. Put butter into the mixing bowl.\n";
+
+    const DESSERT: &str = "Ice Cream.\n\nMethod.\nServe with caramel sauce. \
+        Serve with toffee sauce. Liquefy contents of the mixing bowl. \
+        Pour contents of the mixing bowl into the baking dish.\n\nServes 1.\n";
+
+    fn book() -> RecipeBook {
+        let mut book = RecipeBook::new();
+        book.add_source(SAUCES, Path::new("sauces.chef"), ParseOptions::default())
+            .unwrap();
+        book
+    }
+
+    #[test]
+    fn serve_with_resolves_against_the_book() {
+        let book = book();
+        assert_eq!(book.len(), 2);
+        assert_eq!(
+            book.get("toffee sauce").unwrap().path,
+            Path::new("sauces.chef")
+        );
+
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(Parser::new(DESSERT).parse_recipe().unwrap());
+        interpreter.add_book(&book);
+        interpreter.run().unwrap();
+        assert_eq!(interpreter.output(), "TC");
+    }
+
+    #[test]
+    fn a_name_defined_twice_names_both_files() {
+        let mut book = book();
+        let error = book
+            .add_source(SAUCES, Path::new("more.chef"), ParseOptions::default())
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "recipe 'Caramel Sauce.' is defined in both sauces.chef and more.chef"
+        );
+    }
+
+    #[test]
+    fn a_file_loaded_twice_is_read_once() -> Result<(), BookError> {
+        let dir = std::env::temp_dir().join(format!("cheffers-book-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("sauces.chef");
+        fs::write(&file, SAUCES).unwrap();
+
+        // The directory on the search path, then the same file via --book.
+        let mut book = RecipeBook::new();
+        book.load(&dir, ParseOptions::default())?;
+        book.load(&file, ParseOptions::default())?;
+        book.load(&dir.join(".").join("sauces.chef"), ParseOptions::default())?;
+        assert_eq!(book.len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn the_recipes_own_auxiliaries_shadow_the_book() {
+        let book = book();
+        let source = format!(
+            "{}\nCaramel Sauce.\n\nIngredients.\n99 g sugar\n\nMethod.\n\
+             Clean the mixing bowl. Put sugar into the mixing bowl.\n",
+            DESSERT
+        );
+        let recipe = Parser::new(&source).parse_recipe().unwrap();
+        let shadowed: Vec<_> = book
+            .shadowed_by(&recipe)
+            .iter()
+            .map(|entry| entry.recipe.title.as_str())
+            .collect();
+        assert_eq!(shadowed, vec!["Caramel Sauce."]);
+
+        // Added before or after the recipe, the book never replaces it.
+        for book_first in [true, false] {
+            let mut interpreter = Interpreter::new();
+            if book_first {
+                interpreter.add_book(&book);
+            }
+            interpreter.add_recipe(recipe.clone());
+            if !book_first {
+                interpreter.add_book(&book);
+            }
+            interpreter.run().unwrap();
+            assert_eq!(interpreter.output(), "Tc");
+        }
+    }
+}
@@ -0,0 +1,216 @@
+//! Compatibility profiles: how the interpreter settles the points the spec
+//! leaves open, so recipes written for another Chef implementation can run
+//! the way their authors expected.
+//!
+//! A [`CompatProfile`] is a plain struct with one field per ambiguity. The
+//! named profiles ([`CompatProfile::from_name`]) are starting points; any
+//! field can be changed on top of them.
+
+use crate::interpreter::OutputOptions;
+
+/// How `Divide` rounds a quotient that is not whole.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Division {
+    /// -7 / 2 = -3, like Rust and C.
+    #[default]
+    TowardZero,
+    /// -7 / 2 = -4, like Python's `//`.
+    Floor,
+}
+
+/// The order `Pour` leaves the copied ingredients in.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum PourOrder {
+    /// The bowl's order is kept: its top becomes the dish's top.
+    #[default]
+    Retain,
+    /// The bowl is poured out one ingredient at a time, so its top ends up
+    /// lowest in the dish.
+    Reverse,
+}
+
+/// When `Serves` writes the baking dishes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ServesTiming {
+    /// Where the statement appears, as an instruction of the method.
+    #[default]
+    InMethod,
+    /// Once the method has run to its end, treating `Serves` as a property
+    /// of the recipe. A `Refrigerate` that ends the method early skips it.
+    AfterMethod,
+}
+
+/// Where a sous-chef's first mixing bowl lands in the caller's first bowl.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AuxiliaryBowl {
+    /// On top of the caller's ingredients.
+    #[default]
+    Prepend,
+    /// Underneath them.
+    Append,
+}
+
+/// What `Stir` does when asked to roll the top ingredient further down than
+/// the bowl is deep.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum StirPastBottom {
+    /// The ingredient stops at the bottom.
+    #[default]
+    Clamp,
+    /// The ingredient keeps going round: the depth is taken modulo the
+    /// number of places it can end up in.
+    Wrap,
+}
+
+/// Everything the interpreter does differently between implementations.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct CompatProfile {
+    pub division: Division,
+    pub pour: PourOrder,
+    pub serves: ServesTiming,
+    pub auxiliary_bowl: AuxiliaryBowl,
+    pub stir: StirPastBottom,
+    pub output: OutputOptions,
+}
+
+impl CompatProfile {
+    /// The names accepted by [`from_name`](Self::from_name).
+    pub const NAMES: [&'static str; 2] = ["cheffers", "acme"];
+
+    /// A named profile:
+    ///
+    /// - `cheffers`: this interpreter's own behavior (the default).
+    /// - `acme`: the reference Perl interpreter, Acme::Chef, which writes a
+    ///   space before every number and serves once the method has finished.
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "cheffers" => Some(Self::default()),
+            "acme" => Some(Self {
+                serves: ServesTiming::AfterMethod,
+                output: OutputOptions::from_style("perl")?,
+                ..Self::default()
+            }),
+            _ => None,
+        }
+    }
+
+    /// `dividend / divisor` under this profile. `divisor` is not zero.
+    pub(crate) fn divide(&self, dividend: i64, divisor: i64) -> i64 {
+        let quotient = dividend / divisor;
+        match self.division {
+            Division::TowardZero => quotient,
+            Division::Floor if dividend % divisor != 0 && (dividend < 0) != (divisor < 0) => {
+                quotient - 1
+            }
+            Division::Floor => quotient,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interpreter, Parser};
+
+    fn serve(profile: CompatProfile, method: &str) -> String {
+        let source = format!(
+            "Compat Cake.\n\nIngredients.\n1 g egg\n2 g ham\n3 g rice\n\nMethod.\n{}\n\nServes 1.\n\nSide.\n\nIngredients.\n9 g pea\n\nMethod.\nPut pea into the mixing bowl.\n",
+            method
+        );
+        let recipe = Parser::new(&source).parse_recipe().expect("recipe parses");
+        let mut interpreter = Interpreter::new();
+        interpreter.set_compat_profile(profile);
+        interpreter.add_recipe(recipe);
+        interpreter.run().expect("recipe runs");
+        interpreter.output().to_string()
+    }
+
+    const STACK: &str = "Put rice into the mixing bowl. Put ham into the mixing bowl. \
+        Put egg into the mixing bowl.";
+    const POUR: &str = "Pour contents of the mixing bowl into the baking dish.";
+
+    #[test]
+    fn division_rounding() {
+        let profile = CompatProfile::default();
+        assert_eq!(profile.divide(-7, 2), -3);
+        let floor = CompatProfile {
+            division: Division::Floor,
+            ..CompatProfile::default()
+        };
+        assert_eq!(floor.divide(-7, 2), -4);
+        assert_eq!(floor.divide(7, -2), -4);
+        assert_eq!(floor.divide(7, 2), 3);
+        assert_eq!(floor.divide(-8, 2), -4);
+    }
+
+    #[test]
+    fn pour_order() {
+        let method = format!("{} {}", STACK, POUR);
+        assert_eq!(serve(CompatProfile::default(), &method), "123");
+        let reverse = CompatProfile {
+            pour: PourOrder::Reverse,
+            ..CompatProfile::default()
+        };
+        assert_eq!(serve(reverse, &method), "321");
+    }
+
+    #[test]
+    fn serves_timing() {
+        // The lenient parser accepts a `Serves` in the middle of the method.
+        let method = format!(
+            "Put egg into the mixing bowl. {} Serves 1. Put ham into the mixing bowl. {}",
+            POUR, POUR
+        );
+        assert_eq!(serve(CompatProfile::default(), &method), "121");
+        let after = CompatProfile {
+            serves: ServesTiming::AfterMethod,
+            ..CompatProfile::default()
+        };
+        // Both `Serves` wait for the method to finish; the first one empties
+        // the dish both pours filled.
+        assert_eq!(serve(after.clone(), &method), "211");
+
+        let refrigerated = format!("Put egg into the mixing bowl. {} Refrigerate.", POUR);
+        assert_eq!(serve(after, &refrigerated), "");
+    }
+
+    #[test]
+    fn auxiliary_bowl_placement() {
+        let method = format!("Put egg into the mixing bowl. Serve with side. {}", POUR);
+        // The sous-chef's bowl starts as a copy of the caller's.
+        assert_eq!(serve(CompatProfile::default(), &method), "911");
+        let append = CompatProfile {
+            auxiliary_bowl: AuxiliaryBowl::Append,
+            ..CompatProfile::default()
+        };
+        assert_eq!(serve(append, &method), "191");
+    }
+
+    #[test]
+    fn stir_past_the_bottom() {
+        // Rolling "1" down four places in a bowl of three.
+        let method = format!("{} Stir for 4 minutes. {}", STACK, POUR);
+        assert_eq!(serve(CompatProfile::default(), &method), "231");
+        let wrap = CompatProfile {
+            stir: StirPastBottom::Wrap,
+            ..CompatProfile::default()
+        };
+        // Three places to land: 4 % 3 = 1.
+        assert_eq!(serve(wrap, &method), "213");
+        let method = format!("{} Stir for 3 minutes. {}", STACK, POUR);
+        assert_eq!(serve(CompatProfile::default(), &method), "231");
+        let wrap = CompatProfile {
+            stir: StirPastBottom::Wrap,
+            ..CompatProfile::default()
+        };
+        assert_eq!(serve(wrap, &method), "123");
+    }
+
+    #[test]
+    fn acme_profile_spaces_numbers() {
+        let method = format!("{} {}", STACK, POUR);
+        let acme = CompatProfile::from_name("acme").unwrap();
+        assert_eq!(serve(acme, &method), " 1 2 3");
+        assert!(CompatProfile::from_name("chef.py").is_none());
+    }
+}
@@ -0,0 +1,561 @@
+//! JavaScript backend: turns a recipe into a self-contained ES module.
+//!
+//! The generated module exports `run(options)`, which returns everything the
+//! recipe serves as a string:
+//!
+//! ```js
+//! import { run } from "./hello.js";
+//! run();                       // "Hello world!"
+//! run({ input: "21", seed: 7 }); // input for `Take`, seed for `Mix well`
+//! ```
+//!
+//! Values are `BigInt`s wrapped to 64 bits like the interpreter's `i64`s,
+//! stacks are arrays with the top at the end, and runtime failures throw an
+//! `Error` carrying the interpreter's message (and the partial output in
+//! `error.output`).
+
+use std::fmt::Write as _;
+
+use crate::compile::{recipe_indices, recipes_in_order};
+use crate::instruction::Instruction;
+use crate::interpreter::{normalize_recipe_name, MAX_CALL_DEPTH, MAX_LOOP_ITERATIONS};
+use crate::types::{Measure, Recipe};
+
+/// Runtime support shared by every generated module. It mirrors the
+/// interpreter's helpers one-to-one (`get_variable`, `read_input`,
+/// `stir_bowl`, `write_output`, the xorshift64* shuffle, ...).
+const RUNTIME: &str = r#"const DRY = "dry";
+const LIQUID = "liquid";
+const UNSPECIFIED = "unspecified";
+const MASK = (1n << 64n) - 1n;
+
+function chefError(message) {
+  return new Error(message);
+}
+
+function value(amount, measure) {
+  return { amount: BigInt.asIntN(64, amount), measure };
+}
+
+function get(ing, unset, name) {
+  const found = ing.get(name);
+  if (found !== undefined) return found;
+  if (unset.has(name)) {
+    throw chefError(`ingredient '${name}' was declared without a value`);
+  }
+  throw chefError(`ingredient '${name}' is not defined`);
+}
+
+function bowl(stacks, idx) {
+  while (stacks.length <= idx) stacks.push([]);
+  return stacks[idx];
+}
+
+function top(stack, idx, operation) {
+  if (stack.length === 0) {
+    throw chefError(`mixing bowl ${idx} is empty (attempted ${operation})`);
+  }
+  return stack[stack.length - 1];
+}
+
+function take(k, ing, unset, name) {
+  const fail = (reason) =>
+    chefError(`cannot read input for ingredient '${name}': ${reason}`);
+  if (k.input.length === 0) throw fail("no more input values are available");
+  const token = k.input.shift();
+  let amount;
+  if (/^[+-]?\d+$/.test(token)) amount = BigInt(token);
+  if (amount === undefined || BigInt.asIntN(64, amount) !== amount) {
+    throw fail(`'${token}' is not a numeric value`);
+  }
+  const previous = ing.get(name);
+  const measure = previous ? previous.measure : unset.get(name) ?? UNSPECIFIED;
+  ing.set(name, value(amount, measure));
+}
+
+function nextRandom(k) {
+  let x = k.rng;
+  x ^= x >> 12n;
+  x = (x ^ (x << 25n)) & MASK;
+  x ^= x >> 27n;
+  k.rng = x;
+  return (x * 0x2545f4914f6cdd1dn) & MASK;
+}
+
+// Bowls keep their top at the end of the array; the interpreter indexes from
+// the top, so positions are mirrored to shuffle and stir identically.
+function mix(k, stack) {
+  const len = stack.length;
+  for (let i = len - 1; i >= 1; i--) {
+    const j = Number(nextRandom(k) % BigInt(i + 1));
+    const a = len - 1 - i;
+    const b = len - 1 - j;
+    [stack[a], stack[b]] = [stack[b], stack[a]];
+  }
+}
+
+function stir(stack, positions) {
+  if (positions <= 0n || stack.length <= 1) return;
+  const moved = stack.pop();
+  const target = Math.min(Number(positions < 1n << 53n ? positions : 1n << 53n), stack.length);
+  stack.splice(stack.length - target, 0, moved);
+}
+
+function pour(bowls, from, dishes, to) {
+  const source = bowl(bowls, from).slice();
+  bowl(dishes, to).push(...source);
+}
+
+function serve(k, dishes, count) {
+  for (const dish of dishes.slice(0, count)) {
+    while (dish.length > 0) {
+      const { amount, measure } = dish.pop();
+      if (measure === LIQUID) {
+        const code = Number(amount);
+        if (amount < 0n || amount > 0x10ffffn || (code >= 0xd800 && code <= 0xdfff)) {
+          throw chefError(`value ${amount} is not a valid Unicode code point for liquid output`);
+        }
+        k.output += String.fromCodePoint(code);
+      } else {
+        k.output += amount.toString();
+      }
+    }
+  }
+}
+
+function serveWith(k, recipe, name, bowls, dishes) {
+  if (k.depth >= MAX_CALL_DEPTH) {
+    throw chefError(`recursion limit (${MAX_CALL_DEPTH}) exceeded for recipe '${name}'`);
+  }
+  // The sous-chef works on copies of the caller's bowls and dishes; values are
+  // never mutated in place, so copying the arrays is enough.
+  const subBowls = bowls.map((stack) => stack.slice());
+  const subDishes = dishes.map((stack) => stack.slice());
+  k.depth++;
+  try {
+    recipe(k, subBowls, subDishes);
+  } finally {
+    k.depth--;
+  }
+  bowl(bowls, 0).push(...subBowls[0]);
+}
+
+function defaultSeed() {
+  const seed = BigInt(Date.now()) * 1000000n;
+  return seed === 0n ? 0x9e3779b97f4a7c15n : seed & MASK;
+}
+"#;
+
+/// Compiles a recipe (and its auxiliary recipes) into a JavaScript ES module
+/// that exports `run(options)`.
+pub fn compile_js(recipe: &Recipe) -> String {
+    let recipes = recipes_in_order(recipe);
+    let indices = recipe_indices(&recipes);
+
+    let mut js = JsWriter::default();
+    js.line(&format!(
+        "// Generated by cheffers from {} - do not edit.",
+        recipe.title.trim()
+    ));
+    js.line("\"use strict\";");
+    js.blank();
+    js.line(&format!("const MAX_CALL_DEPTH = {};", MAX_CALL_DEPTH));
+    js.line(&format!(
+        "const MAX_LOOP_ITERATIONS = {};",
+        MAX_LOOP_ITERATIONS
+    ));
+    js.raw(RUNTIME);
+
+    for (idx, (_, current)) in recipes.iter().enumerate() {
+        js.blank();
+        let mut generator = RecipeGenerator {
+            js: &mut js,
+            indices: &indices,
+            loop_depth: 0,
+        };
+        generator.recipe(idx, current);
+    }
+
+    js.blank();
+    js.line("/**");
+    js.line(" * Prepares the recipe and returns everything it serves.");
+    js.line(" *");
+    js.line(" * options.input - numbers for `Take` (array, or whitespace-separated text)");
+    js.line(" * options.seed  - seed for `Mix well` (defaults to the clock)");
+    js.line(" */");
+    js.line("export function run(options = {}) {");
+    js.indent();
+    js.line("const input = options.input ?? [];");
+    js.line("const k = {");
+    js.indent();
+    js.line("output: \"\",");
+    js.line("input: (Array.isArray(input) ? input.map(String) : String(input).split(/\\s+/)).filter((t) => t !== \"\"),");
+    js.line("rng: options.seed === undefined ? defaultSeed() : BigInt(options.seed) & MASK || 0x9e3779b97f4a7c15n,");
+    js.line("depth: 0,");
+    js.dedent();
+    js.line("};");
+    js.line("try {");
+    js.indent();
+    js.line("recipe0(k, [[]], [[]]);");
+    js.dedent();
+    js.line("} catch (error) {");
+    js.indent();
+    js.line("error.output = k.output;");
+    js.line("throw error;");
+    js.dedent();
+    js.line("}");
+    js.line("return k.output;");
+    js.dedent();
+    js.line("}");
+    js.blank();
+    js.line("export default run;");
+
+    js.finish()
+}
+
+struct RecipeGenerator<'a> {
+    js: &'a mut JsWriter,
+    indices: &'a std::collections::HashMap<String, usize>,
+    loop_depth: usize,
+}
+
+impl RecipeGenerator<'_> {
+    fn recipe(&mut self, idx: usize, recipe: &Recipe) {
+        self.js
+            .line(&format!("// {}", recipe.title.trim().replace('\n', " ")));
+        self.js
+            .line(&format!("function recipe{}(k, bowls, dishes) {{", idx));
+        self.js.indent();
+
+        let mut ingredients: Vec<_> = recipe.ingredients.iter().collect();
+        ingredients.sort_by(|a, b| a.0.cmp(b.0));
+        self.js.line("const ing = new Map([");
+        self.js.indent();
+        for (name, value) in ingredients {
+            self.js.line(&format!(
+                "[{}, value({}n, {})],",
+                js_string(name),
+                value.amount,
+                measure_name(value.measure)
+            ));
+        }
+        self.js.dedent();
+        self.js.line("]);");
+
+        let mut unset: Vec<_> = recipe.unset_ingredients.iter().collect();
+        unset.sort_by(|a, b| a.0.cmp(b.0));
+        self.js.line("const unset = new Map([");
+        self.js.indent();
+        for (name, measure) in unset {
+            self.js.line(&format!(
+                "[{}, {}],",
+                js_string(name),
+                measure_name(*measure)
+            ));
+        }
+        self.js.dedent();
+        self.js.line("]);");
+
+        for instruction in &recipe.instructions {
+            self.instruction(instruction);
+        }
+
+        self.js.dedent();
+        self.js.line("}");
+    }
+
+    fn instruction(&mut self, inst: &Instruction) {
+        let js = &mut *self.js;
+        match inst {
+            Instruction::Put(ingredient, bowl_idx) => {
+                js.line(&format!(
+                    "{{ const b = bowl(bowls, {}); b.push(get(ing, unset, {})); }}",
+                    bowl_idx,
+                    js_string(ingredient)
+                ));
+            }
+            Instruction::Fold(ingredient, bowl_idx) => {
+                let operation = format!("Fold {} into mixing bowl", ingredient);
+                js.line(&format!(
+                    "{{ const b = bowl(bowls, {idx}); top(b, {idx}, {op}); ing.set({name}, b.pop()); }}",
+                    idx = bowl_idx,
+                    op = js_string(&operation),
+                    name = js_string(ingredient)
+                ));
+            }
+            Instruction::Add(ingredient, bowl_idx) => {
+                self.arithmetic(ingredient, *bowl_idx, "+", "Add {} to mixing bowl")
+            }
+            Instruction::Remove(ingredient, bowl_idx) => {
+                self.arithmetic(ingredient, *bowl_idx, "-", "Remove {} from mixing bowl")
+            }
+            Instruction::Combine(ingredient, bowl_idx) => {
+                self.arithmetic(ingredient, *bowl_idx, "*", "Combine {} into mixing bowl")
+            }
+            Instruction::Divide(ingredient, bowl_idx) => {
+                let operation = format!("Divide {} into mixing bowl", ingredient);
+                js.line("{");
+                js.indent();
+                js.line(&format!("const b = bowl(bowls, {});", bowl_idx));
+                js.line(&format!(
+                    "const v = get(ing, unset, {});",
+                    js_string(ingredient)
+                ));
+                js.line(&format!(
+                    "if (v.amount === 0n) throw chefError({});",
+                    js_string(&format!("division by zero (ingredient '{}')", ingredient))
+                ));
+                js.line(&format!(
+                    "const t = top(b, {}, {});",
+                    bowl_idx,
+                    js_string(&operation)
+                ));
+                // BigInt division truncates toward zero, like Rust's `/`.
+                js.line("b[b.length - 1] = value(t.amount / v.amount, t.measure);");
+                js.dedent();
+                js.line("}");
+            }
+            Instruction::AddDry(bowl_idx) => {
+                js.line(&format!(
+                    "{{ let sum = 0n; for (const v of ing.values()) if (v.measure === DRY) sum += v.amount; bowl(bowls, {}).push(value(sum, DRY)); }}",
+                    bowl_idx
+                ));
+            }
+            Instruction::Liquefy(ingredient) => {
+                js.line(&format!(
+                    "{{ const v = get(ing, unset, {name}); ing.set({name}, value(v.amount, LIQUID)); }}",
+                    name = js_string(ingredient)
+                ));
+            }
+            Instruction::LiquefyBowl(bowl_idx) => {
+                js.line(&format!(
+                    "{{ const b = bowl(bowls, {}); b.forEach((v, i) => {{ b[i] = value(v.amount, LIQUID); }}); }}",
+                    bowl_idx
+                ));
+            }
+            Instruction::Stir(_, 0) => {}
+            Instruction::Stir(bowl_idx, minutes) => {
+                js.line(&format!("stir(bowl(bowls, {}), {}n);", bowl_idx, minutes));
+            }
+            Instruction::StirIngredient(ingredient, bowl_idx) => {
+                js.line(&format!(
+                    "{{ const depth = get(ing, unset, {}).amount; if (depth > 0n) stir(bowl(bowls, {}), depth); }}",
+                    js_string(ingredient),
+                    bowl_idx
+                ));
+            }
+            Instruction::Mix(bowl_idx) => {
+                js.line(&format!("mix(k, bowl(bowls, {}));", bowl_idx));
+            }
+            Instruction::Clean(bowl_idx) => {
+                js.line(&format!("bowl(bowls, {}).length = 0;", bowl_idx));
+            }
+            Instruction::Pour(from_idx, to_idx) => {
+                js.line(&format!("pour(bowls, {}, dishes, {});", from_idx, to_idx));
+            }
+            Instruction::ServeWith(recipe_name) => {
+                match self.indices.get(&normalize_recipe_name(recipe_name)) {
+                    Some(idx) => js.line(&format!(
+                        "serveWith(k, recipe{}, {}, bowls, dishes);",
+                        idx,
+                        js_string(recipe_name)
+                    )),
+                    // Like the interpreter, an unknown recipe only fails when
+                    // the `Serve with` is actually reached.
+                    None => js.line(&format!(
+                        "throw chefError({});",
+                        js_string(&format!("recipe '{}' is not known", recipe_name))
+                    )),
+                }
+            }
+            Instruction::Take(ingredient) => {
+                js.line(&format!("take(k, ing, unset, {});", js_string(ingredient)));
+            }
+            Instruction::Serves(count) => {
+                js.line(&format!("serve(k, dishes, {});", count));
+            }
+            Instruction::Refrigerate(hours) => {
+                if let Some(count) = hours {
+                    js.line(&format!("serve(k, dishes, {});", count));
+                }
+                js.line("return;");
+            }
+            Instruction::SetAside => {
+                if self.loop_depth == 0 {
+                    js.line("throw chefError(\"'Set aside' executed outside of a loop\");");
+                } else {
+                    js.line(&format!("break loop{};", self.loop_depth));
+                }
+            }
+            Instruction::Loop {
+                condition_var,
+                verb,
+                body,
+                decrement_var,
+            } => {
+                self.loop_depth += 1;
+                let label = self.loop_depth;
+                let js = &mut *self.js;
+                js.line(&format!("// {} the {}", verb, condition_var));
+                js.line("{");
+                js.indent();
+                js.line("let iterations = 0;");
+                js.line(&format!("loop{}: while (true) {{", label));
+                js.indent();
+                js.line(&format!(
+                    "if (get(ing, unset, {}).amount === 0n) break;",
+                    js_string(condition_var)
+                ));
+                js.line("if (++iterations > MAX_LOOP_ITERATIONS) {");
+                js.indent();
+                js.line(&format!(
+                    "throw chefError(`loop on ingredient '${{{}}}' exceeded ${{MAX_LOOP_ITERATIONS}} iterations`);",
+                    js_string(condition_var)
+                ));
+                js.dedent();
+                js.line("}");
+                for instruction in body {
+                    self.instruction(instruction);
+                }
+                let js = &mut *self.js;
+                if let Some(decrement) = decrement_var {
+                    js.line(&format!(
+                        "{{ const v = get(ing, unset, {name}); ing.set({name}, value(v.amount - 1n, v.measure)); }}",
+                        name = js_string(decrement)
+                    ));
+                }
+                js.dedent();
+                js.line("}");
+                js.dedent();
+                js.line("}");
+                self.loop_depth -= 1;
+            }
+        }
+    }
+
+    fn arithmetic(&mut self, ingredient: &str, bowl_idx: usize, op: &str, operation: &str) {
+        let operation = operation.replace("{}", ingredient);
+        self.js.line(&format!(
+            "{{ const b = bowl(bowls, {idx}); const v = get(ing, unset, {name}); const t = top(b, {idx}, {op_name}); b[b.length - 1] = value(t.amount {op} v.amount, t.measure); }}",
+            idx = bowl_idx,
+            name = js_string(ingredient),
+            op_name = js_string(&operation),
+            op = op
+        ));
+    }
+}
+
+fn measure_name(measure: Measure) -> &'static str {
+    match measure {
+        Measure::Dry => "DRY",
+        Measure::Liquid => "LIQUID",
+        Measure::Unspecified => "UNSPECIFIED",
+    }
+}
+
+/// Quotes `text` as a double-quoted JavaScript string literal.
+fn js_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\t' => quoted.push_str("\\t"),
+            '\u{2028}' | '\u{2029}' => {
+                let _ = write!(quoted, "\\u{:04x}", ch as u32);
+            }
+            c if c.is_control() => {
+                let _ = write!(quoted, "\\u{:04x}", c as u32);
+            }
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Accumulates indented lines of generated code.
+#[derive(Default)]
+struct JsWriter {
+    out: String,
+    level: usize,
+}
+
+impl JsWriter {
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.level {
+            self.out.push_str("  ");
+        }
+        self.out.push_str(text);
+        self.out.push('\n');
+    }
+
+    fn raw(&mut self, text: &str) {
+        self.out.push_str(text);
+    }
+
+    fn blank(&mut self) {
+        self.out.push('\n');
+    }
+
+    fn indent(&mut self) {
+        self.level += 1;
+    }
+
+    fn dedent(&mut self) {
+        self.level = self.level.saturating_sub(1);
+    }
+
+    fn finish(self) -> String {
+        self.out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    fn compile(source: &str) -> String {
+        let recipe = Parser::new(source).parse_recipe().expect("recipe parses");
+        compile_js(&recipe)
+    }
+
+    #[test]
+    fn js_string_escapes_quotes_and_control_characters() {
+        assert_eq!(js_string("say \"hi\"\n"), "\"say \\\"hi\\\"\\n\"");
+        assert_eq!(js_string("a\\b"), "\"a\\\\b\"");
+    }
+
+    #[test]
+    fn module_exports_run_and_one_function_per_recipe() {
+        let js = compile(include_str!("../../tests/fixtures/fibonacci.chef"));
+        assert!(js.contains("export function run(options = {})"));
+        assert!(js.contains("function recipe0(k, bowls, dishes)"));
+        assert!(js.contains("function recipe1(k, bowls, dishes)"));
+        assert!(
+            js.contains("serveWith(k, recipe1, \"caramel sauce\", bowls, dishes);"),
+            "Serve with must resolve to the auxiliary function"
+        );
+    }
+
+    #[test]
+    fn set_aside_breaks_the_innermost_loop() {
+        let js = compile(
+            "Nested.\n\nIngredients.\n2 g a\n2 g b\n\nMethod.\n\
+             Bake the a. Stir the b. Set aside. Stir the b until stirred. \
+             Bake the a until baked.\n\nServes 1.\n",
+        );
+        assert!(js.contains("loop2: while (true)"));
+        assert!(js.contains("break loop2;"));
+    }
+
+    #[test]
+    fn unknown_auxiliary_recipe_throws_when_reached() {
+        let js = compile("Lonely.\n\nMethod.\nServe with ghost sauce.\n\nServes 1.\n");
+        assert!(js.contains("throw chefError(\"recipe 'ghost sauce' is not known\");"));
+    }
+}
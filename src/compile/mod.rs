@@ -0,0 +1,44 @@
+//! Code generators that translate a parsed [`Recipe`] into another
+//! language.
+//!
+//! Each backend mirrors the interpreter's semantics (stack order, `Stir`,
+//! `Pour` copying, sous-chef bowl copies, liquid output and the error
+//! messages), so a compiled recipe prints exactly what `Interpreter::run`
+//! would.
+
+pub mod js;
+pub mod wat;
+
+pub use js::compile_js;
+pub use wat::compile_wat;
+
+use std::collections::HashMap;
+
+use crate::interpreter::normalize_recipe_name;
+use crate::types::Recipe;
+
+/// Collects the main recipe and its auxiliary recipes in a stable order (main
+/// first, then auxiliaries sorted by title) keyed the way `Serve with`
+/// resolves names.
+pub(crate) fn recipes_in_order(main: &Recipe) -> Vec<(String, &Recipe)> {
+    let mut recipes = vec![(normalize_recipe_name(&main.title), main)];
+    let mut auxiliaries: Vec<&Recipe> = main.auxiliary_recipes.values().collect();
+    auxiliaries.sort_by(|a, b| a.title.cmp(&b.title));
+    for aux in auxiliaries {
+        let key = normalize_recipe_name(&aux.title);
+        if !recipes.iter().any(|(existing, _)| *existing == key) {
+            recipes.push((key, aux));
+        }
+    }
+    recipes
+}
+
+/// Maps each normalized recipe name to its position in
+/// [`recipes_in_order`], for resolving `Serve with` at compile time.
+pub(crate) fn recipe_indices(recipes: &[(String, &Recipe)]) -> HashMap<String, usize> {
+    recipes
+        .iter()
+        .enumerate()
+        .map(|(idx, (key, _))| (key.clone(), idx))
+        .collect()
+}
@@ -0,0 +1,879 @@
+//! WebAssembly backend: turns a recipe into a module in the WebAssembly text
+//! format (WAT), assemblable with `wat2wasm` or `wasm-tools parse`.
+//!
+//! Every recipe becomes its own function and its ingredients become locals,
+//! so a Chef program compiles to one small, dependency-free module. The host
+//! supplies input and output through four imports:
+//!
+//! - `env.read_input(name_ptr: i32, name_len: i32) -> i64`: the next number
+//!   for `Take`, given the ingredient's name;
+//! - `env.write_char(i64)`: serve a liquid value (the host validates the code
+//!   point);
+//! - `env.write_int(i64)`: serve a dry or unspecified value;
+//! - `env.fail(msg_ptr: i32, msg_len: i32)`: a runtime error; must not
+//!   return (throw or trap in the host).
+//!
+//! The module exports `memory` (where all strings live, UTF-8 encoded),
+//! `run()` and `set_seed(i64)` (the `Mix well` seed, like
+//! `Interpreter::set_mix_seed`).
+//!
+//! Mixing bowls and baking dishes live in linear memory, one region per
+//! sous-chef call depth, each stack holding up to [`STACK_CAPACITY`] values;
+//! exceeding that is reported through `fail`, like the interpreter's loop and
+//! recursion limits.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+use crate::compile::{recipe_indices, recipes_in_order};
+use crate::instruction::Instruction;
+use crate::interpreter::{normalize_recipe_name, MAX_CALL_DEPTH, MAX_LOOP_ITERATIONS};
+use crate::types::{Measure, Recipe};
+
+/// How many values each compiled mixing bowl or baking dish can hold.
+pub const STACK_CAPACITY: usize = 4096;
+
+const PAGE_SIZE: usize = 65_536;
+
+/// Measure codes stored next to every amount. Ingredients additionally use
+/// `UNSET_BASE + measure` for "declared without a value" and `UNDEFINED` for
+/// names that were never declared.
+const DRY: u32 = 0;
+const LIQUID: u32 = 1;
+const UNSPECIFIED: u32 = 2;
+const UNSET_BASE: u32 = 10;
+const UNDEFINED: u32 = 20;
+
+/// Shared helpers. `@NAME@` placeholders are filled in by [`compile_wat`].
+const RUNTIME: &str = r#"
+  ;; Address of the length word of stack $s in frame $f.
+  (func $len_addr (param $f i32) (param $s i32) (result i32)
+    (i32.add
+      (i32.add (i32.const @FRAME_BASE@) (i32.mul (local.get $f) (i32.const @FRAME_SIZE@)))
+      (i32.mul (local.get $s) (i32.const 4))))
+
+  ;; Address of entry $i (0 = bottom) of stack $s in frame $f.
+  (func $entry_addr (param $f i32) (param $s i32) (param $i i32) (result i32)
+    (i32.add
+      (i32.add
+        (i32.add (i32.const @FRAME_BASE@) (i32.mul (local.get $f) (i32.const @FRAME_SIZE@)))
+        (i32.const @HEADER@))
+      (i32.add
+        (i32.mul (local.get $s) (i32.const @STACK_BYTES@))
+        (i32.mul (local.get $i) (i32.const 16)))))
+
+  (func $len (param $s i32) (result i32)
+    (i32.load (call $len_addr (global.get $frame) (local.get $s))))
+
+  (func $set_len (param $s i32) (param $len i32)
+    (i32.store (call $len_addr (global.get $frame) (local.get $s)) (local.get $len)))
+
+  (func $overflow
+    (call $fail (i32.const @OVERFLOW_PTR@) (i32.const @OVERFLOW_LEN@))
+    (unreachable))
+
+  (func $push (param $s i32) (param $amount i64) (param $measure i32)
+    (local $len i32)
+    (local $addr i32)
+    (local.set $len (call $len (local.get $s)))
+    (if (i32.ge_u (local.get $len) (i32.const @CAPACITY@)) (then (call $overflow)))
+    (local.set $addr (call $entry_addr (global.get $frame) (local.get $s) (local.get $len)))
+    (i64.store (local.get $addr) (local.get $amount))
+    (i32.store offset=8 (local.get $addr) (local.get $measure))
+    (call $set_len (local.get $s) (i32.add (local.get $len) (i32.const 1))))
+
+  ;; Address of the top entry of stack $s, failing with the given message
+  ;; when the stack is empty.
+  (func $top (param $s i32) (param $msg i32) (param $msg_len i32) (result i32)
+    (local $len i32)
+    (local.set $len (call $len (local.get $s)))
+    (if (i32.eqz (local.get $len))
+      (then (call $fail (local.get $msg) (local.get $msg_len)) (unreachable)))
+    (call $entry_addr (global.get $frame) (local.get $s) (i32.sub (local.get $len) (i32.const 1))))
+
+  (func $drop_top (param $s i32)
+    (call $set_len (local.get $s) (i32.sub (call $len (local.get $s)) (i32.const 1))))
+
+  (func $liquefy (param $s i32)
+    (local $i i32)
+    (local $len i32)
+    (local.set $len (call $len (local.get $s)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.ge_u (local.get $i) (local.get $len)))
+        (i32.store offset=8
+          (call $entry_addr (global.get $frame) (local.get $s) (local.get $i))
+          (i32.const @LIQUID@))
+        (local.set $i (i32.add (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  ;; Rolls the top value down $positions places (to the bottom at most).
+  (func $stir (param $s i32) (param $positions i64)
+    (local $len i32)
+    (local $target i32)
+    (local $amount i64)
+    (local $measure i32)
+    (local $top i32)
+    (local $dest i32)
+    (local.set $len (call $len (local.get $s)))
+    (if (i64.le_s (local.get $positions) (i64.const 0)) (then (return)))
+    (if (i32.le_u (local.get $len) (i32.const 1)) (then (return)))
+    (local.set $target (i32.sub (local.get $len) (i32.const 1)))
+    (if (i64.lt_u (local.get $positions) (i64.extend_i32_u (local.get $target)))
+      (then (local.set $target (i32.wrap_i64 (local.get $positions)))))
+    (local.set $top
+      (call $entry_addr (global.get $frame) (local.get $s) (i32.sub (local.get $len) (i32.const 1))))
+    (local.set $amount (i64.load (local.get $top)))
+    (local.set $measure (i32.load offset=8 (local.get $top)))
+    (local.set $dest
+      (call $entry_addr (global.get $frame) (local.get $s)
+        (i32.sub (i32.sub (local.get $len) (i32.const 1)) (local.get $target))))
+    (memory.copy
+      (i32.add (local.get $dest) (i32.const 16))
+      (local.get $dest)
+      (i32.mul (local.get $target) (i32.const 16)))
+    (i64.store (local.get $dest) (local.get $amount))
+    (i32.store offset=8 (local.get $dest) (local.get $measure)))
+
+  ;; xorshift64*, identical to the interpreter's `Mix well` generator.
+  (func $next_random (result i64)
+    (local $x i64)
+    (local.set $x (global.get $rng))
+    (local.set $x (i64.xor (local.get $x) (i64.shr_u (local.get $x) (i64.const 12))))
+    (local.set $x (i64.xor (local.get $x) (i64.shl (local.get $x) (i64.const 25))))
+    (local.set $x (i64.xor (local.get $x) (i64.shr_u (local.get $x) (i64.const 27))))
+    (global.set $rng (local.get $x))
+    (i64.mul (local.get $x) (i64.const 0x2545F4914F6CDD1D)))
+
+  (func $swap (param $s i32) (param $a i32) (param $b i32)
+    (local $pa i32)
+    (local $pb i32)
+    (local $amount i64)
+    (local $measure i32)
+    (local.set $pa (call $entry_addr (global.get $frame) (local.get $s) (local.get $a)))
+    (local.set $pb (call $entry_addr (global.get $frame) (local.get $s) (local.get $b)))
+    (local.set $amount (i64.load (local.get $pa)))
+    (local.set $measure (i32.load offset=8 (local.get $pa)))
+    (i64.store (local.get $pa) (i64.load (local.get $pb)))
+    (i32.store offset=8 (local.get $pa) (i32.load offset=8 (local.get $pb)))
+    (i64.store (local.get $pb) (local.get $amount))
+    (i32.store offset=8 (local.get $pb) (local.get $measure)))
+
+  ;; Fisher-Yates over positions counted from the top, like the interpreter.
+  (func $mix (param $s i32)
+    (local $len i32)
+    (local $i i32)
+    (local $j i32)
+    (local.set $len (call $len (local.get $s)))
+    (local.set $i (i32.sub (local.get $len) (i32.const 1)))
+    (block $done
+      (loop $next
+        (br_if $done (i32.lt_s (local.get $i) (i32.const 1)))
+        (local.set $j
+          (i32.wrap_i64
+            (i64.rem_u (call $next_random) (i64.extend_i32_u (i32.add (local.get $i) (i32.const 1))))))
+        (call $swap (local.get $s)
+          (i32.sub (i32.sub (local.get $len) (i32.const 1)) (local.get $i))
+          (i32.sub (i32.sub (local.get $len) (i32.const 1)) (local.get $j)))
+        (local.set $i (i32.sub (local.get $i) (i32.const 1)))
+        (br $next))))
+
+  ;; Appends $count entries of stack $from in frame $from_frame on top of
+  ;; stack $to in the current frame, keeping their order.
+  (func $append (param $from_frame i32) (param $from i32) (param $to i32) (param $count i32)
+    (local $len i32)
+    (local.set $len (call $len (local.get $to)))
+    (if (i32.gt_u (i32.add (local.get $len) (local.get $count)) (i32.const @CAPACITY@))
+      (then (call $overflow)))
+    (memory.copy
+      (call $entry_addr (global.get $frame) (local.get $to) (local.get $len))
+      (call $entry_addr (local.get $from_frame) (local.get $from) (i32.const 0))
+      (i32.mul (local.get $count) (i32.const 16)))
+    (call $set_len (local.get $to) (i32.add (local.get $len) (local.get $count))))
+
+  (func $pour (param $from i32) (param $to i32)
+    (call $append (global.get $frame) (local.get $from) (local.get $to) (call $len (local.get $from))))
+
+  (func $serve (param $count i32)
+    (local $d i32)
+    (local $s i32)
+    (local $addr i32)
+    (block $dishes_done
+      (loop $next_dish
+        (br_if $dishes_done (i32.ge_u (local.get $d) (local.get $count)))
+        (br_if $dishes_done (i32.ge_u (local.get $d) (i32.const @DISHES@)))
+        (local.set $s (i32.add (i32.const @BOWLS@) (local.get $d)))
+        (block $dish_empty
+          (loop $next_value
+            (br_if $dish_empty (i32.eqz (call $len (local.get $s))))
+            (local.set $addr
+              (call $entry_addr (global.get $frame) (local.get $s)
+                (i32.sub (call $len (local.get $s)) (i32.const 1))))
+            (if (i32.eq (i32.load offset=8 (local.get $addr)) (i32.const @LIQUID@))
+              (then (call $write_char (i64.load (local.get $addr))))
+              (else (call $write_int (i64.load (local.get $addr)))))
+            (call $drop_top (local.get $s))
+            (br $next_value)))
+        (local.set $d (i32.add (local.get $d) (i32.const 1)))
+        (br $next_dish))))
+
+  ;; Gives a sous-chef copies of every bowl and dish in the next frame.
+  (func $enter
+    (local $next i32)
+    (local $pages i32)
+    (local $s i32)
+    (local $len i32)
+    (local.set $next (i32.add (global.get $frame) (i32.const 1)))
+    (local.set $pages
+      (i32.div_u
+        (i32.add
+          (i32.add (i32.const @FRAME_BASE@) (i32.mul (i32.add (local.get $next) (i32.const 1)) (i32.const @FRAME_SIZE@)))
+          (i32.const @PAGE_MINUS_ONE@))
+        (i32.const @PAGE_SIZE@)))
+    (if (i32.gt_u (local.get $pages) (memory.size))
+      (then
+        (if (i32.eq (memory.grow (i32.sub (local.get $pages) (memory.size))) (i32.const -1))
+          (then (call $overflow)))))
+    (block $done
+      (loop $next_stack
+        (br_if $done (i32.ge_u (local.get $s) (i32.const @STACKS@)))
+        (local.set $len (call $len (local.get $s)))
+        (i32.store (call $len_addr (local.get $next) (local.get $s)) (local.get $len))
+        (memory.copy
+          (call $entry_addr (local.get $next) (local.get $s) (i32.const 0))
+          (call $entry_addr (global.get $frame) (local.get $s) (i32.const 0))
+          (i32.mul (local.get $len) (i32.const 16)))
+        (local.set $s (i32.add (local.get $s) (i32.const 1)))
+        (br $next_stack)))
+    (global.set $frame (local.get $next)))
+
+  ;; Returns from a sous-chef: its first mixing bowl lands on top of ours.
+  (func $leave
+    (local $callee i32)
+    (local.set $callee (global.get $frame))
+    (global.set $frame (i32.sub (global.get $frame) (i32.const 1)))
+    (call $append (local.get $callee) (i32.const 0) (i32.const 0)
+      (i32.load (call $len_addr (local.get $callee) (i32.const 0)))))
+"#;
+
+/// Compiles a recipe (and its auxiliary recipes) into a WebAssembly text
+/// module exporting `run` and `set_seed`.
+pub fn compile_wat(recipe: &Recipe) -> String {
+    let recipes = recipes_in_order(recipe);
+    let indices = recipe_indices(&recipes);
+
+    let bowls = recipes
+        .iter()
+        .flat_map(|(_, r)| r.instructions.iter())
+        .map(max_bowl)
+        .max()
+        .unwrap_or(0)
+        + 1;
+    let dishes = recipes
+        .iter()
+        .flat_map(|(_, r)| r.instructions.iter())
+        .map(max_dish)
+        .max()
+        .unwrap_or(0)
+        + 1;
+
+    let mut strings = StringTable::default();
+    let overflow = strings.intern(&format!(
+        "a mixing bowl or baking dish exceeded {} values (the compiled stack capacity)",
+        STACK_CAPACITY
+    ));
+
+    let mut functions = String::new();
+    for (idx, (_, current)) in recipes.iter().enumerate() {
+        let mut generator = FunctionGenerator::new(current, &indices, &mut strings, bowls);
+        generator.recipe(idx);
+        functions.push_str(&generator.finish());
+    }
+
+    let stacks = bowls + dishes;
+    let header = align16(stacks * 4);
+    let stack_bytes = STACK_CAPACITY * 16;
+    let frame_size = header + stacks * stack_bytes;
+    let frame_base = align16(strings.len()).max(16);
+    let initial_pages = (frame_base + frame_size + PAGE_SIZE - 1) / PAGE_SIZE;
+
+    let runtime = RUNTIME
+        .replace("@FRAME_BASE@", &frame_base.to_string())
+        .replace("@FRAME_SIZE@", &frame_size.to_string())
+        .replace("@HEADER@", &header.to_string())
+        .replace("@STACK_BYTES@", &stack_bytes.to_string())
+        .replace("@CAPACITY@", &STACK_CAPACITY.to_string())
+        .replace("@OVERFLOW_PTR@", &overflow.0.to_string())
+        .replace("@OVERFLOW_LEN@", &overflow.1.to_string())
+        .replace("@LIQUID@", &LIQUID.to_string())
+        .replace("@BOWLS@", &bowls.to_string())
+        .replace("@DISHES@", &dishes.to_string())
+        .replace("@STACKS@", &stacks.to_string())
+        .replace("@PAGE_SIZE@", &PAGE_SIZE.to_string())
+        .replace("@PAGE_MINUS_ONE@", &(PAGE_SIZE - 1).to_string());
+
+    let mut wat = String::new();
+    let _ = writeln!(
+        wat,
+        ";; Generated by cheffers from {} - do not edit.",
+        recipe.title.trim()
+    );
+    wat.push_str("(module\n");
+    wat.push_str(
+        "  (import \"env\" \"read_input\" (func $read_input (param i32 i32) (result i64)))\n",
+    );
+    wat.push_str("  (import \"env\" \"write_char\" (func $write_char (param i64)))\n");
+    wat.push_str("  (import \"env\" \"write_int\" (func $write_int (param i64)))\n");
+    wat.push_str("  (import \"env\" \"fail\" (func $fail (param i32 i32)))\n");
+    let _ = writeln!(wat, "  (memory (export \"memory\") {})", initial_pages);
+    let _ = writeln!(wat, "  (data (i32.const 0) \"{}\")", strings.escaped());
+    wat.push_str("  (global $frame (mut i32) (i32.const 0))\n");
+    wat.push_str("  (global $rng (mut i64) (i64.const 0x9E3779B97F4A7C15))\n");
+    wat.push_str(&runtime);
+    wat.push_str(&functions);
+
+    wat.push_str("\n  (func (export \"set_seed\") (param $seed i64)\n");
+    wat.push_str("    (if (i64.eqz (local.get $seed))\n");
+    wat.push_str("      (then (global.set $rng (i64.const 0x9E3779B97F4A7C15)))\n");
+    wat.push_str("      (else (global.set $rng (local.get $seed)))))\n");
+
+    wat.push_str("\n  (func (export \"run\")\n");
+    wat.push_str("    (local $s i32)\n");
+    wat.push_str("    (global.set $frame (i32.const 0))\n");
+    wat.push_str("    (block $done\n");
+    wat.push_str("      (loop $next\n");
+    let _ = writeln!(
+        wat,
+        "        (br_if $done (i32.ge_u (local.get $s) (i32.const {})))",
+        stacks
+    );
+    wat.push_str("        (call $set_len (local.get $s) (i32.const 0))\n");
+    wat.push_str("        (local.set $s (i32.add (local.get $s) (i32.const 1)))\n");
+    wat.push_str("        (br $next)))\n");
+    wat.push_str("    (call $recipe0))\n");
+    wat.push_str(")\n");
+    wat
+}
+
+/// Emits one recipe's function body.
+struct FunctionGenerator<'a> {
+    recipe: &'a Recipe,
+    indices: &'a HashMap<String, usize>,
+    strings: &'a mut StringTable,
+    bowls: usize,
+    /// Ingredient name -> local index, in first-seen order.
+    locals: Vec<String>,
+    loops: usize,
+    open_loops: Vec<usize>,
+    body: String,
+    level: usize,
+}
+
+impl<'a> FunctionGenerator<'a> {
+    fn new(
+        recipe: &'a Recipe,
+        indices: &'a HashMap<String, usize>,
+        strings: &'a mut StringTable,
+        bowls: usize,
+    ) -> Self {
+        let mut locals: Vec<String> = recipe
+            .ingredients
+            .keys()
+            .chain(recipe.unset_ingredients.keys())
+            .cloned()
+            .collect();
+        locals.sort();
+        for instruction in &recipe.instructions {
+            collect_ingredients(instruction, &mut locals);
+        }
+        Self {
+            recipe,
+            indices,
+            strings,
+            bowls,
+            locals,
+            loops: 0,
+            open_loops: Vec::new(),
+            body: String::new(),
+            level: 2,
+        }
+    }
+
+    fn recipe(&mut self, idx: usize) {
+        for (slot, name) in self.locals.clone().iter().enumerate() {
+            let (amount, measure) = match self.recipe.ingredients.get(name) {
+                Some(value) => (value.amount, measure_code(value.measure)),
+                None => match self.recipe.unset_ingredients.get(name) {
+                    Some(measure) => (0, UNSET_BASE + measure_code(*measure)),
+                    None => (0, UNDEFINED),
+                },
+            };
+            self.line(&format!(";; {}", name));
+            self.line(&format!("(local.set $v{} (i64.const {}))", slot, amount));
+            self.line(&format!("(local.set $m{} (i32.const {}))", slot, measure));
+        }
+        for instruction in &self.recipe.instructions {
+            self.instruction(instruction);
+        }
+
+        // Locals are declared up front, but loop counters are only known once
+        // the body has been generated.
+        let mut function = String::new();
+        let _ = writeln!(
+            function,
+            "\n  ;; {}\n  (func $recipe{}",
+            self.recipe.title.trim().replace('\n', " "),
+            idx
+        );
+        for slot in 0..self.locals.len() {
+            let _ = writeln!(
+                function,
+                "    (local $v{} i64) (local $m{} i32)",
+                slot, slot
+            );
+        }
+        for counter in 0..self.loops {
+            let _ = writeln!(function, "    (local $iter{} i64)", counter);
+        }
+        function.push_str("    (local $addr i32) (local $sum i64)\n");
+        function.push_str(self.body.trim_end_matches('\n'));
+        function.push_str(")\n");
+        self.body = function;
+    }
+
+    fn finish(self) -> String {
+        self.body
+    }
+
+    fn line(&mut self, text: &str) {
+        for _ in 0..self.level {
+            self.body.push_str("  ");
+        }
+        self.body.push_str(text);
+        self.body.push('\n');
+    }
+
+    fn slot(&self, ingredient: &str) -> usize {
+        self.locals
+            .iter()
+            .position(|name| name == ingredient)
+            .expect("every ingredient in the method has a local")
+    }
+
+    fn fail(&mut self, message: &str) -> String {
+        let (ptr, len) = self.strings.intern(message);
+        format!(
+            "(call $fail (i32.const {}) (i32.const {})) (unreachable)",
+            ptr, len
+        )
+    }
+
+    /// Emits the interpreter's `get_variable` checks for `ingredient` and
+    /// returns its local slot.
+    fn read(&mut self, ingredient: &str) -> usize {
+        let slot = self.slot(ingredient);
+        let without_value = self.fail(&format!(
+            "ingredient '{}' was declared without a value",
+            ingredient
+        ));
+        let undefined = self.fail(&format!("ingredient '{}' is not defined", ingredient));
+        self.line(&format!(
+            "(if (i32.ge_u (local.get $m{s}) (i32.const {unset})) (then (if (i32.lt_u (local.get $m{s}) (i32.const {undef})) (then {a}) (else {b}))))",
+            s = slot,
+            unset = UNSET_BASE,
+            undef = UNDEFINED,
+            a = without_value,
+            b = undefined
+        ));
+        slot
+    }
+
+    fn top(&mut self, bowl_idx: usize, operation: &str) {
+        let (ptr, len) = self.strings.intern(&format!(
+            "mixing bowl {} is empty (attempted {})",
+            bowl_idx, operation
+        ));
+        self.line(&format!(
+            "(local.set $addr (call $top (i32.const {}) (i32.const {}) (i32.const {})))",
+            bowl_idx, ptr, len
+        ));
+    }
+
+    fn dish(&self, dish_idx: usize) -> usize {
+        self.bowls + dish_idx
+    }
+
+    fn instruction(&mut self, inst: &Instruction) {
+        match inst {
+            Instruction::Put(ingredient, bowl_idx) => {
+                let s = self.read(ingredient);
+                self.line(&format!(
+                    "(call $push (i32.const {}) (local.get $v{s}) (local.get $m{s}))",
+                    bowl_idx,
+                    s = s
+                ));
+            }
+            Instruction::Fold(ingredient, bowl_idx) => {
+                let s = self.slot(ingredient);
+                self.top(*bowl_idx, &format!("Fold {} into mixing bowl", ingredient));
+                self.line(&format!("(local.set $v{} (i64.load (local.get $addr)))", s));
+                self.line(&format!(
+                    "(local.set $m{} (i32.load offset=8 (local.get $addr)))",
+                    s
+                ));
+                self.line(&format!("(call $drop_top (i32.const {}))", bowl_idx));
+            }
+            Instruction::Add(ingredient, bowl_idx) => {
+                self.arithmetic(ingredient, *bowl_idx, "i64.add", "Add {} to mixing bowl")
+            }
+            Instruction::Remove(ingredient, bowl_idx) => self.arithmetic(
+                ingredient,
+                *bowl_idx,
+                "i64.sub",
+                "Remove {} from mixing bowl",
+            ),
+            Instruction::Combine(ingredient, bowl_idx) => self.arithmetic(
+                ingredient,
+                *bowl_idx,
+                "i64.mul",
+                "Combine {} into mixing bowl",
+            ),
+            Instruction::Divide(ingredient, bowl_idx) => {
+                let s = self.read(ingredient);
+                let zero = self.fail(&format!("division by zero (ingredient '{}')", ingredient));
+                self.line(&format!(
+                    "(if (i64.eqz (local.get $v{})) (then {}))",
+                    s, zero
+                ));
+                self.top(
+                    *bowl_idx,
+                    &format!("Divide {} into mixing bowl", ingredient),
+                );
+                // i64.div_s truncates toward zero, like Rust's `/`.
+                self.line(&format!(
+                    "(i64.store (local.get $addr) (i64.div_s (i64.load (local.get $addr)) (local.get $v{})))",
+                    s
+                ));
+            }
+            Instruction::AddDry(bowl_idx) => {
+                self.line("(local.set $sum (i64.const 0))");
+                for slot in 0..self.locals.len() {
+                    self.line(&format!(
+                        "(if (i32.eq (local.get $m{s}) (i32.const {dry})) (then (local.set $sum (i64.add (local.get $sum) (local.get $v{s})))))",
+                        s = slot,
+                        dry = DRY
+                    ));
+                }
+                self.line(&format!(
+                    "(call $push (i32.const {}) (local.get $sum) (i32.const {}))",
+                    bowl_idx, DRY
+                ));
+            }
+            Instruction::Liquefy(ingredient) => {
+                let s = self.read(ingredient);
+                self.line(&format!("(local.set $m{} (i32.const {}))", s, LIQUID));
+            }
+            Instruction::LiquefyBowl(bowl_idx) => {
+                self.line(&format!("(call $liquefy (i32.const {}))", bowl_idx));
+            }
+            Instruction::Stir(_, 0) => {}
+            Instruction::Stir(bowl_idx, minutes) => {
+                self.line(&format!(
+                    "(call $stir (i32.const {}) (i64.const {}))",
+                    bowl_idx, minutes
+                ));
+            }
+            Instruction::StirIngredient(ingredient, bowl_idx) => {
+                let s = self.read(ingredient);
+                self.line(&format!(
+                    "(call $stir (i32.const {}) (local.get $v{}))",
+                    bowl_idx, s
+                ));
+            }
+            Instruction::Mix(bowl_idx) => {
+                self.line(&format!("(call $mix (i32.const {}))", bowl_idx));
+            }
+            Instruction::Clean(bowl_idx) => {
+                self.line(&format!(
+                    "(call $set_len (i32.const {}) (i32.const 0))",
+                    bowl_idx
+                ));
+            }
+            Instruction::Pour(from_idx, to_idx) => {
+                let to = self.dish(*to_idx);
+                self.line(&format!(
+                    "(call $pour (i32.const {}) (i32.const {}))",
+                    from_idx, to
+                ));
+            }
+            Instruction::ServeWith(recipe_name) => {
+                match self
+                    .indices
+                    .get(&normalize_recipe_name(recipe_name))
+                    .copied()
+                {
+                    Some(idx) => {
+                        let limit = self.fail(&format!(
+                            "recursion limit ({}) exceeded for recipe '{}'",
+                            MAX_CALL_DEPTH, recipe_name
+                        ));
+                        self.line(&format!(
+                            "(if (i32.ge_u (global.get $frame) (i32.const {})) (then {}))",
+                            MAX_CALL_DEPTH, limit
+                        ));
+                        self.line("(call $enter)");
+                        self.line(&format!("(call $recipe{})", idx));
+                        self.line("(call $leave)");
+                    }
+                    None => {
+                        let unknown = self.fail(&format!("recipe '{}' is not known", recipe_name));
+                        self.line(&unknown);
+                    }
+                }
+            }
+            Instruction::Take(ingredient) => {
+                let s = self.slot(ingredient);
+                let (ptr, len) = self.strings.intern(ingredient);
+                self.line(&format!(
+                    "(local.set $v{} (call $read_input (i32.const {}) (i32.const {})))",
+                    s, ptr, len
+                ));
+                // Keep the declared measure; an undeclared name becomes
+                // unspecified.
+                self.line(&format!(
+                    "(if (i32.ge_u (local.get $m{s}) (i32.const {undef})) (then (local.set $m{s} (i32.const {unspec}))) (else (if (i32.ge_u (local.get $m{s}) (i32.const {unset})) (then (local.set $m{s} (i32.sub (local.get $m{s}) (i32.const {unset})))))))",
+                    s = s,
+                    undef = UNDEFINED,
+                    unspec = UNSPECIFIED,
+                    unset = UNSET_BASE
+                ));
+            }
+            Instruction::Serves(count) => {
+                self.line(&format!("(call $serve (i32.const {}))", count));
+            }
+            Instruction::Refrigerate(hours) => {
+                if let Some(count) = hours {
+                    self.line(&format!("(call $serve (i32.const {}))", count));
+                }
+                self.line("(return)");
+            }
+            Instruction::SetAside => match self.open_loops.last() {
+                Some(label) => {
+                    let label = *label;
+                    self.line(&format!("(br $exit{})", label));
+                }
+                None => {
+                    let outside = self.fail("'Set aside' executed outside of a loop");
+                    self.line(&outside);
+                }
+            },
+            Instruction::Loop {
+                condition_var,
+                verb,
+                body,
+                decrement_var,
+            } => {
+                let label = self.loops;
+                self.loops += 1;
+                self.open_loops.push(label);
+
+                self.line(&format!(";; {} the {}", verb, condition_var));
+                self.line(&format!("(local.set $iter{} (i64.const 0))", label));
+                self.line(&format!("(block $exit{}", label));
+                self.level += 1;
+                self.line(&format!("(loop $next{}", label));
+                self.level += 1;
+                let c = self.read(condition_var);
+                self.line(&format!(
+                    "(br_if $exit{} (i64.eqz (local.get $v{})))",
+                    label, c
+                ));
+                self.line(&format!(
+                    "(local.set $iter{l} (i64.add (local.get $iter{l}) (i64.const 1)))",
+                    l = label
+                ));
+                let limit = self.fail(&format!(
+                    "loop on ingredient '{}' exceeded {} iterations",
+                    condition_var, MAX_LOOP_ITERATIONS
+                ));
+                self.line(&format!(
+                    "(if (i64.gt_u (local.get $iter{}) (i64.const {})) (then {}))",
+                    label, MAX_LOOP_ITERATIONS, limit
+                ));
+                for instruction in body {
+                    self.instruction(instruction);
+                }
+                if let Some(decrement) = decrement_var {
+                    let d = self.read(decrement);
+                    self.line(&format!(
+                        "(local.set $v{d} (i64.sub (local.get $v{d}) (i64.const 1)))",
+                        d = d
+                    ));
+                }
+                self.line(&format!("(br $next{})))", label));
+                self.level -= 2;
+
+                self.open_loops.pop();
+            }
+        }
+    }
+
+    fn arithmetic(&mut self, ingredient: &str, bowl_idx: usize, op: &str, operation: &str) {
+        let s = self.read(ingredient);
+        self.top(bowl_idx, &operation.replace("{}", ingredient));
+        self.line(&format!(
+            "(i64.store (local.get $addr) ({} (i64.load (local.get $addr)) (local.get $v{})))",
+            op, s
+        ));
+    }
+}
+
+/// The module's data segment: every message and ingredient name the code
+/// refers to, stored once.
+#[derive(Default)]
+struct StringTable {
+    bytes: Vec<u8>,
+    offsets: HashMap<String, (usize, usize)>,
+}
+
+impl StringTable {
+    fn intern(&mut self, text: &str) -> (usize, usize) {
+        if let Some(entry) = self.offsets.get(text) {
+            return *entry;
+        }
+        let entry = (self.bytes.len(), text.len());
+        self.bytes.extend_from_slice(text.as_bytes());
+        self.offsets.insert(text.to_string(), entry);
+        entry
+    }
+
+    fn len(&self) -> usize {
+        self.bytes.len()
+    }
+
+    fn escaped(&self) -> String {
+        let mut out = String::with_capacity(self.bytes.len());
+        for &byte in &self.bytes {
+            if byte.is_ascii_graphic() && byte != b'"' && byte != b'\\' || byte == b' ' {
+                out.push(byte as char);
+            } else {
+                let _ = write!(out, "\\{:02x}", byte);
+            }
+        }
+        out
+    }
+}
+
+fn collect_ingredients(inst: &Instruction, names: &mut Vec<String>) {
+    let mut add = |name: &String| {
+        if !names.contains(name) {
+            names.push(name.clone());
+        }
+    };
+    match inst {
+        Instruction::Take(name)
+        | Instruction::Put(name, _)
+        | Instruction::Fold(name, _)
+        | Instruction::Add(name, _)
+        | Instruction::Remove(name, _)
+        | Instruction::Combine(name, _)
+        | Instruction::Divide(name, _)
+        | Instruction::Liquefy(name)
+        | Instruction::StirIngredient(name, _) => add(name),
+        Instruction::Loop {
+            condition_var,
+            body,
+            decrement_var,
+            ..
+        } => {
+            add(condition_var);
+            if let Some(decrement) = decrement_var {
+                add(decrement);
+            }
+            for nested in body {
+                collect_ingredients(nested, names);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn max_bowl(inst: &Instruction) -> usize {
+    match inst {
+        Instruction::Put(_, b)
+        | Instruction::Fold(_, b)
+        | Instruction::Add(_, b)
+        | Instruction::Remove(_, b)
+        | Instruction::Combine(_, b)
+        | Instruction::Divide(_, b)
+        | Instruction::AddDry(b)
+        | Instruction::LiquefyBowl(b)
+        | Instruction::Stir(b, _)
+        | Instruction::StirIngredient(_, b)
+        | Instruction::Mix(b)
+        | Instruction::Clean(b)
+        | Instruction::Pour(b, _) => *b,
+        Instruction::Loop { body, .. } => body.iter().map(max_bowl).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn max_dish(inst: &Instruction) -> usize {
+    match inst {
+        Instruction::Pour(_, d) => *d,
+        Instruction::Loop { body, .. } => body.iter().map(max_dish).max().unwrap_or(0),
+        _ => 0,
+    }
+}
+
+fn measure_code(measure: Measure) -> u32 {
+    match measure {
+        Measure::Dry => DRY,
+        Measure::Liquid => LIQUID,
+        Measure::Unspecified => UNSPECIFIED,
+    }
+}
+
+fn align16(n: usize) -> usize {
+    (n + 15) / 16 * 16
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    fn compile(source: &str) -> String {
+        let recipe = Parser::new(source).parse_recipe().expect("recipe parses");
+        compile_wat(&recipe)
+    }
+
+    #[test]
+    fn module_declares_the_host_interface() {
+        let wat = compile(include_str!("../../tests/fixtures/hello-world.chef"));
+        for import in ["read_input", "write_char", "write_int", "fail"] {
+            assert!(
+                wat.contains(&format!("(import \"env\" \"{}\"", import)),
+                "missing import {}",
+                import
+            );
+        }
+        assert!(wat.contains("(func (export \"run\")"));
+        assert!(wat.contains("(func (export \"set_seed\")"));
+        assert!(wat.contains("(memory (export \"memory\")"));
+    }
+
+    #[test]
+    fn auxiliary_recipes_become_functions() {
+        let wat = compile(include_str!("../../tests/fixtures/fibonacci.chef"));
+        assert!(wat.contains("(func $recipe1"));
+        assert!(wat.contains("(call $recipe1)"));
+    }
+
+    #[test]
+    fn data_segment_escapes_quotes_and_non_ascii() {
+        let mut strings = StringTable::default();
+        strings.intern("say \"é\"");
+        assert_eq!(strings.escaped(), "say \\22\\c3\\a9\\22");
+    }
+}
@@ -0,0 +1,291 @@
+//! Past participles for loop verbs ("Sift ... until sifted").
+//!
+//! The spec closes a loop with "until _verbed_", the past participle of the
+//! verb that opened it. Regular verbs follow the usual spelling rules
+//! (bake -> baked, fry -> fried, whip -> whipped); irregular ones come
+//! from a table (beat -> beaten, shake -> shaken, grind -> ground).
+
+/// Irregular verbs and their accepted past participles, most common first.
+const IRREGULAR: &[(&str, &[&str])] = &[
+    ("be", &["been"]),
+    ("bear", &["borne", "born"]),
+    ("beat", &["beaten", "beat"]),
+    ("become", &["become"]),
+    ("begin", &["begun"]),
+    ("bend", &["bent"]),
+    ("bet", &["bet"]),
+    ("bind", &["bound"]),
+    ("bite", &["bitten"]),
+    ("bleed", &["bled"]),
+    ("blow", &["blown"]),
+    ("break", &["broken"]),
+    ("breed", &["bred"]),
+    ("bring", &["brought"]),
+    ("build", &["built"]),
+    ("burn", &["burnt", "burned"]),
+    ("burst", &["burst"]),
+    ("buy", &["bought"]),
+    ("cast", &["cast"]),
+    ("catch", &["caught"]),
+    ("choose", &["chosen"]),
+    ("cling", &["clung"]),
+    ("come", &["come"]),
+    ("cost", &["cost"]),
+    ("creep", &["crept"]),
+    ("cut", &["cut"]),
+    ("deal", &["dealt"]),
+    ("dig", &["dug"]),
+    ("do", &["done"]),
+    ("draw", &["drawn"]),
+    ("dream", &["dreamt", "dreamed"]),
+    ("drink", &["drunk"]),
+    ("drive", &["driven"]),
+    ("eat", &["eaten"]),
+    ("fall", &["fallen"]),
+    ("feed", &["fed"]),
+    ("feel", &["felt"]),
+    ("fight", &["fought"]),
+    ("find", &["found"]),
+    ("fling", &["flung"]),
+    ("fly", &["flown"]),
+    ("forget", &["forgotten"]),
+    ("freeze", &["frozen"]),
+    ("get", &["got", "gotten"]),
+    ("give", &["given"]),
+    ("go", &["gone"]),
+    ("grind", &["ground"]),
+    ("grow", &["grown"]),
+    ("hang", &["hung", "hanged"]),
+    ("have", &["had"]),
+    ("hear", &["heard"]),
+    ("hide", &["hidden"]),
+    ("hit", &["hit"]),
+    ("hold", &["held"]),
+    ("hurt", &["hurt"]),
+    ("keep", &["kept"]),
+    ("knit", &["knit", "knitted"]),
+    ("know", &["known"]),
+    ("lay", &["laid"]),
+    ("lead", &["led"]),
+    ("lean", &["leant", "leaned"]),
+    ("leap", &["leapt", "leaped"]),
+    ("learn", &["learnt", "learned"]),
+    ("leave", &["left"]),
+    ("lend", &["lent"]),
+    ("let", &["let"]),
+    ("lie", &["lain"]),
+    ("light", &["lit", "lighted"]),
+    ("lose", &["lost"]),
+    ("make", &["made"]),
+    ("mean", &["meant"]),
+    ("meet", &["met"]),
+    ("mow", &["mown", "mowed"]),
+    ("pay", &["paid"]),
+    ("put", &["put"]),
+    ("quit", &["quit"]),
+    ("read", &["read"]),
+    ("rid", &["rid"]),
+    ("ride", &["ridden"]),
+    ("ring", &["rung"]),
+    ("rise", &["risen"]),
+    ("run", &["run"]),
+    ("say", &["said"]),
+    ("see", &["seen"]),
+    ("seek", &["sought"]),
+    ("sell", &["sold"]),
+    ("send", &["sent"]),
+    ("set", &["set"]),
+    ("sew", &["sewn", "sewed"]),
+    ("shake", &["shaken"]),
+    ("shed", &["shed"]),
+    ("shine", &["shone"]),
+    ("shoot", &["shot"]),
+    ("show", &["shown", "showed"]),
+    ("shrink", &["shrunk"]),
+    ("shut", &["shut"]),
+    ("sing", &["sung"]),
+    ("sink", &["sunk"]),
+    ("sit", &["sat"]),
+    ("sleep", &["slept"]),
+    ("slide", &["slid"]),
+    ("sling", &["slung"]),
+    ("slit", &["slit"]),
+    ("smell", &["smelt", "smelled"]),
+    ("sow", &["sown", "sowed"]),
+    ("speak", &["spoken"]),
+    ("spend", &["spent"]),
+    ("spill", &["spilt", "spilled"]),
+    ("spin", &["spun"]),
+    ("spit", &["spat"]),
+    ("split", &["split"]),
+    ("spoil", &["spoilt", "spoiled"]),
+    ("spread", &["spread"]),
+    ("spring", &["sprung"]),
+    ("stand", &["stood"]),
+    ("steal", &["stolen"]),
+    ("stick", &["stuck"]),
+    ("sting", &["stung"]),
+    ("stink", &["stunk"]),
+    ("strike", &["struck"]),
+    ("string", &["strung"]),
+    ("swear", &["sworn"]),
+    ("sweep", &["swept"]),
+    ("swell", &["swollen", "swelled"]),
+    ("swim", &["swum"]),
+    ("swing", &["swung"]),
+    ("take", &["taken"]),
+    ("teach", &["taught"]),
+    ("tear", &["torn"]),
+    ("tell", &["told"]),
+    ("think", &["thought"]),
+    ("throw", &["thrown"]),
+    ("thrust", &["thrust"]),
+    ("tread", &["trodden"]),
+    ("understand", &["understood"]),
+    ("upset", &["upset"]),
+    ("wake", &["woken"]),
+    ("wear", &["worn"]),
+    ("weave", &["woven"]),
+    ("weep", &["wept"]),
+    ("wet", &["wet", "wetted"]),
+    ("win", &["won"]),
+    ("wind", &["wound"]),
+    ("wring", &["wrung"]),
+    ("write", &["written"]),
+];
+
+/// Verbs of more than one syllable whose last syllable is stressed, so its
+/// final consonant doubles as in one-syllable verbs (omit -> omitted).
+const STRESSED_LAST: &[&str] = &[
+    "abet", "admit", "commit", "compel", "concur", "confer", "control", "defer", "deter", "emit",
+    "equip", "excel", "expel", "incur", "infer", "occur", "omit", "patrol", "permit", "prefer",
+    "propel", "rebel", "recur", "refer", "regret", "remit", "repel", "submit", "transfer",
+    "transmit",
+];
+
+/// The accepted past participles of `verb`, in lowercase; the first one is
+/// the preferred spelling.
+pub fn past_participles(verb: &str) -> Vec<String> {
+    let verb = verb.to_lowercase();
+    if let Ok(idx) = IRREGULAR.binary_search_by(|(base, _)| base.cmp(&verb.as_str())) {
+        return IRREGULAR[idx].1.iter().map(|p| p.to_string()).collect();
+    }
+    regular_participles(&verb)
+}
+
+/// The preferred past participle of `verb`, in lowercase.
+pub fn past_participle(verb: &str) -> String {
+    past_participles(verb).remove(0)
+}
+
+/// Whether `word` is a past participle of `verb`, ignoring case.
+pub fn is_past_participle(word: &str, verb: &str) -> bool {
+    let word = word.to_lowercase();
+    past_participles(verb).contains(&word)
+}
+
+fn is_vowel(ch: char) -> bool {
+    matches!(ch, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+fn regular_participles(verb: &str) -> Vec<String> {
+    let chars: Vec<char> = verb.chars().collect();
+    let Some(&last) = chars.last() else {
+        return vec![String::new()];
+    };
+    let before = chars.len().checked_sub(2).map(|idx| chars[idx]);
+
+    // bake -> baked, free -> freed
+    if last == 'e' {
+        return vec![format!("{}d", verb)];
+    }
+    // fry -> fried, but stay -> stayed
+    if last == 'y' && before.is_some_and(|ch| !is_vowel(ch)) {
+        return vec![format!("{}ied", &verb[..verb.len() - 1])];
+    }
+    // panic -> panicked
+    if last == 'c' && before.is_some_and(is_vowel) {
+        return vec![format!("{}ked", verb)];
+    }
+
+    // A final consonant after a single vowel doubles in one-syllable verbs
+    // (stir -> stirred, whip -> whipped) but not in "mix" or "sew". Longer
+    // verbs double it when their last syllable is stressed (omit ->
+    // omitted) and, if they end in "l", in British spelling. Stress cannot
+    // be told from the spelling, so both forms are accepted, the one that
+    // is usually right first.
+    let cvc = chars.len() >= 3
+        && !is_vowel(last)
+        && !matches!(last, 'w' | 'x' | 'y')
+        && is_vowel(chars[chars.len() - 2])
+        && !is_vowel(chars[chars.len() - 3]);
+    let syllables = chars
+        .iter()
+        .zip(std::iter::once(&' ').chain(chars.iter()))
+        .filter(|(ch, prev)| is_vowel(**ch) && !is_vowel(**prev))
+        .count();
+    let doubled = format!("{}{}ed", verb, last);
+    let plain = format!("{}ed", verb);
+    match (cvc, syllables) {
+        (true, 1) => vec![doubled],
+        (true, _) if last == 'l' || STRESSED_LAST.binary_search(&verb).is_ok() => {
+            vec![doubled, plain]
+        }
+        (true, _) => vec![plain, doubled],
+        _ => vec![plain],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn regular_spelling_rules() {
+        for (verb, participle) in [
+            ("Sift", "sifted"),
+            ("Bake", "baked"),
+            ("Caramelise", "caramelised"),
+            ("Fry", "fried"),
+            ("Stay", "stayed"),
+            ("Stir", "stirred"),
+            ("Whip", "whipped"),
+            ("Chop", "chopped"),
+            ("Mix", "mixed"),
+            ("Simmer", "simmered"),
+            ("Heat", "heated"),
+            ("Panic", "panicked"),
+            ("Travel", "travelled"),
+            ("Omit", "omitted"),
+            ("Prefer", "preferred"),
+            ("Occur", "occurred"),
+            ("Regret", "regretted"),
+            ("Admit", "admitted"),
+            ("Visit", "visited"),
+            ("Open", "opened"),
+        ] {
+            assert_eq!(past_participle(verb), participle, "{}", verb);
+        }
+        assert!(is_past_participle("traveled", "travel"));
+        assert!(is_past_participle("omited", "omit"));
+        // An unlisted verb with a stressed last syllable.
+        assert!(is_past_participle("embedded", "embed"));
+    }
+
+    #[test]
+    fn irregular_verbs_come_from_the_table() {
+        assert!(is_past_participle("beaten", "Beat"));
+        assert!(is_past_participle("shaken", "Shake"));
+        assert!(is_past_participle("Ground", "grind"));
+        assert!(is_past_participle("burned", "burn"));
+        assert!(!is_past_participle("shaked", "shake"));
+        assert!(!is_past_participle("stirringly", "stir"));
+    }
+
+    #[test]
+    fn irregular_table_is_sorted() {
+        // `past_participles` binary-searches the tables.
+        assert!(IRREGULAR.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(STRESSED_LAST.windows(2).all(|pair| pair[0] < pair[1]));
+    }
+}
@@ -0,0 +1,436 @@
+//! Which parts of a recipe ran: every instruction, both ways through every
+//! loop (entered or skipped), and every auxiliary recipe, gathered over any
+//! number of runs.
+//!
+//! Build a [`Coverage`] for the recipe, hand it to each interpreter with
+//! [`Interpreter::set_coverage`](crate::Interpreter::set_coverage) and take
+//! it back after the run. Instructions are found by their position in the
+//! recipe's `SourceMap::instructions`, so the report points at the sentences
+//! themselves even when a whole method sits on one line.
+
+use std::collections::HashMap;
+
+use crate::instruction::Instruction;
+use crate::interpreter::{normalize_recipe_name, InstructionId, Observer};
+use crate::types::Recipe;
+
+/// How often a loop was reached with something to do, and with nothing.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct LoopCoverage {
+    /// Times the loop made at least one pass.
+    pub entered: u64,
+    /// Times its condition was zero from the start.
+    pub skipped: u64,
+}
+
+/// One instruction and how often it ran.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionCoverage {
+    /// The sentence that opens the instruction.
+    pub text: String,
+    pub line: Option<usize>,
+    pub column: Option<usize>,
+    pub count: u64,
+    /// Set for loops.
+    pub branches: Option<LoopCoverage>,
+}
+
+/// One recipe, main or auxiliary, and its instructions in pre-order.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecipeCoverage {
+    pub title: String,
+    pub line: Option<usize>,
+    pub calls: u64,
+    pub instructions: Vec<InstructionCoverage>,
+}
+
+/// What ran, collected across runs.
+#[derive(Default)]
+pub struct Coverage {
+    recipes: Vec<RecipeCoverage>,
+    index: HashMap<String, usize>,
+    /// The entry in `recipes` of each recipe id the interpreter ran, if the
+    /// recipe is one of ours.
+    ids: HashMap<usize, Option<usize>>,
+    /// The recipes running, innermost last.
+    frames: Vec<Option<usize>>,
+    /// The instructions running, innermost last, with their loop passes.
+    running: Vec<(Option<(usize, usize)>, u64)>,
+}
+
+impl Coverage {
+    /// Coverage of `recipe` and its auxiliary recipes, none of them run yet.
+    /// Recipes the runs use from elsewhere (books) are not counted.
+    pub fn new(recipe: &Recipe) -> Self {
+        let mut coverage = Self::default();
+        let mut auxiliaries: Vec<&Recipe> = recipe.auxiliary_recipes.values().collect();
+        auxiliaries.sort_by_key(|aux| (aux.source_map.title.as_ref().map(|t| t.line), &aux.title));
+        for recipe in std::iter::once(recipe).chain(auxiliaries) {
+            let key = normalize_recipe_name(&recipe.title);
+            if coverage.index.contains_key(&key) {
+                continue;
+            }
+            coverage.index.insert(key, coverage.recipes.len());
+            let instructions = Instruction::preorder(&recipe.instructions)
+                .into_iter()
+                .enumerate()
+                .map(|(index, instruction)| {
+                    let location = recipe.source_map.instructions.get(index);
+                    InstructionCoverage {
+                        text: location
+                            .and_then(|location| location.snippet.clone())
+                            .unwrap_or_else(|| instruction.opening()),
+                        line: location.map(|location| location.line),
+                        column: location.and_then(|location| location.column),
+                        count: 0,
+                        branches: matches!(instruction, Instruction::Loop { .. })
+                            .then(LoopCoverage::default),
+                    }
+                })
+                .collect();
+            coverage.recipes.push(RecipeCoverage {
+                title: recipe.title.clone(),
+                line: recipe.source_map.title.as_ref().map(|title| title.line),
+                calls: 0,
+                instructions,
+            });
+        }
+        coverage
+    }
+
+    /// The main recipe first, then the auxiliary recipes in source order.
+    pub fn recipes(&self) -> &[RecipeCoverage] {
+        &self.recipes
+    }
+
+    /// Instructions that ran at least once, out of all of them.
+    pub fn instructions_hit(&self) -> (usize, usize) {
+        let all = self.recipes.iter().flat_map(|recipe| &recipe.instructions);
+        let hit = all.clone().filter(|inst| inst.count > 0).count();
+        (hit, all.count())
+    }
+
+    /// Loop branches (entered, skipped) taken at least once, out of all of
+    /// them.
+    pub fn branches_hit(&self) -> (usize, usize) {
+        let loops: Vec<LoopCoverage> = self
+            .recipes
+            .iter()
+            .flat_map(|recipe| &recipe.instructions)
+            .filter_map(|inst| inst.branches)
+            .collect();
+        let hit = loops
+            .iter()
+            .map(|branches| (branches.entered > 0) as usize + (branches.skipped > 0) as usize)
+            .sum();
+        (hit, loops.len() * 2)
+    }
+
+    /// Recipes that ran at least once, out of all of them.
+    pub fn recipes_hit(&self) -> (usize, usize) {
+        let hit = self
+            .recipes
+            .iter()
+            .filter(|recipe| recipe.calls > 0)
+            .count();
+        (hit, self.recipes.len())
+    }
+
+    /// The recipe's `source`, each line prefixed like `gcov` output: the
+    /// times its instructions ran, `#####` if none of them did, `-` for a
+    /// line without instructions. Notes under a line name the sentences on
+    /// it that never ran and the loops that were never entered or never
+    /// skipped. A summary ends the listing.
+    pub fn annotate(&self, source: &str) -> String {
+        let mut by_line: HashMap<usize, Vec<&InstructionCoverage>> = HashMap::new();
+        let mut titles: HashMap<usize, &RecipeCoverage> = HashMap::new();
+        for recipe in &self.recipes {
+            if let Some(line) = recipe.line {
+                titles.insert(line, recipe);
+            }
+            for inst in &recipe.instructions {
+                if let Some(line) = inst.line {
+                    by_line.entry(line).or_default().push(inst);
+                }
+            }
+        }
+
+        let mut text = String::new();
+        for (idx, source_line) in source.lines().enumerate() {
+            let number = idx + 1;
+            let instructions = by_line.get(&number).map(Vec::as_slice).unwrap_or(&[]);
+            let gutter = if let Some(recipe) = titles.get(&number) {
+                count_or_hashes(recipe.calls)
+            } else if instructions.is_empty() {
+                "-".to_string()
+            } else {
+                count_or_hashes(
+                    instructions
+                        .iter()
+                        .map(|inst| inst.count)
+                        .max()
+                        .unwrap_or(0),
+                )
+            };
+            text.push_str(&format!("{:>9}:{:>5}:{}\n", gutter, number, source_line));
+
+            let partly_run = instructions.iter().any(|inst| inst.count > 0);
+            for inst in instructions {
+                let note = match inst.branches {
+                    _ if inst.count == 0 && partly_run => format!("'{}' never ran", inst.text),
+                    Some(branches) if inst.count > 0 && branches.entered == 0 => {
+                        format!("loop '{}' was never entered", inst.text)
+                    }
+                    Some(branches) if inst.count > 0 && branches.skipped == 0 => {
+                        format!("loop '{}' was never skipped", inst.text)
+                    }
+                    _ => continue,
+                };
+                text.push_str(&format!("{:>9}:{:>5}: ^ {}\n", "", "", note));
+            }
+        }
+
+        let (hit, all) = self.instructions_hit();
+        text.push_str(&format!("\ninstructions: {}\n", ratio(hit, all)));
+        let (hit, all) = self.branches_hit();
+        text.push_str(&format!("loop branches: {}\n", ratio(hit, all)));
+        let (hit, all) = self.recipes_hit();
+        text.push_str(&format!("recipes: {}\n", ratio(hit, all)));
+        text
+    }
+
+    /// An lcov tracefile for the recipe read from `path`: recipes as
+    /// functions, loops as two branches (entered, skipped), and lines with
+    /// the most times any of their instructions ran.
+    pub fn lcov(&self, path: &str) -> String {
+        let mut text = format!("TN:\nSF:{}\n", path);
+        for recipe in &self.recipes {
+            if let Some(line) = recipe.line {
+                text.push_str(&format!("FN:{},{}\n", line, function_name(&recipe.title)));
+            }
+        }
+        for recipe in &self.recipes {
+            if recipe.line.is_some() {
+                text.push_str(&format!(
+                    "FNDA:{},{}\n",
+                    recipe.calls,
+                    function_name(&recipe.title)
+                ));
+            }
+        }
+        let functions: Vec<_> = self.recipes.iter().filter(|r| r.line.is_some()).collect();
+        text.push_str(&format!("FNF:{}\n", functions.len()));
+        text.push_str(&format!(
+            "FNH:{}\n",
+            functions.iter().filter(|r| r.calls > 0).count()
+        ));
+
+        let mut branches = 0;
+        let mut branches_hit = 0;
+        let mut lines: Vec<(usize, u64)> = Vec::new();
+        for (block, inst) in self
+            .recipes
+            .iter()
+            .flat_map(|recipe| &recipe.instructions)
+            .enumerate()
+        {
+            let Some(line) = inst.line else {
+                continue;
+            };
+            lines.push((line, inst.count));
+            let Some(loop_coverage) = inst.branches else {
+                continue;
+            };
+            for (branch, taken) in [loop_coverage.entered, loop_coverage.skipped]
+                .into_iter()
+                .enumerate()
+            {
+                // lcov writes `-` for a branch whose block never ran.
+                let taken = if inst.count == 0 {
+                    "-".to_string()
+                } else {
+                    taken.to_string()
+                };
+                text.push_str(&format!("BRDA:{},{},{},{}\n", line, block, branch, taken));
+                branches += 1;
+                branches_hit += (taken != "-" && taken != "0") as usize;
+            }
+        }
+        text.push_str(&format!("BRF:{}\nBRH:{}\n", branches, branches_hit));
+
+        lines.sort();
+        let mut merged: Vec<(usize, u64)> = Vec::new();
+        for (line, count) in lines {
+            match merged.last_mut() {
+                Some(last) if last.0 == line => last.1 = last.1.max(count),
+                _ => merged.push((line, count)),
+            }
+        }
+        for (line, count) in &merged {
+            text.push_str(&format!("DA:{},{}\n", line, count));
+        }
+        text.push_str(&format!(
+            "LF:{}\nLH:{}\nend_of_record\n",
+            merged.len(),
+            merged.iter().filter(|(_, count)| *count > 0).count()
+        ));
+        text
+    }
+}
+
+impl Observer for Coverage {
+    fn enter_recipe(&mut self, id: usize, recipe: &Recipe) {
+        let index = &self.index;
+        let frame = *self
+            .ids
+            .entry(id)
+            .or_insert_with(|| index.get(&normalize_recipe_name(&recipe.title)).copied());
+        if let Some(idx) = frame {
+            self.recipes[idx].calls += 1;
+        }
+        self.frames.push(frame);
+    }
+
+    fn exit_recipe(&mut self) {
+        self.frames.pop();
+    }
+
+    fn enter_instruction(&mut self, id: Option<InstructionId>, _: &Instruction) {
+        let recipe = self.frames.last().copied().flatten();
+        let position = recipe.zip(id).and_then(|(recipe, id)| {
+            let instruction = self.recipes[recipe].instructions.get_mut(id.index)?;
+            instruction.count += 1;
+            Some((recipe, id.index))
+        });
+        self.running.push((position, 0));
+    }
+
+    fn exit_instruction(&mut self, _: Option<InstructionId>) {
+        let Some((Some((recipe, index)), passes)) = self.running.pop() else {
+            return;
+        };
+        if let Some(branches) = &mut self.recipes[recipe].instructions[index].branches {
+            if passes > 0 {
+                branches.entered += 1;
+            } else {
+                branches.skipped += 1;
+            }
+        }
+    }
+
+    fn loop_pass(&mut self) {
+        if let Some((_, passes)) = self.running.last_mut() {
+            *passes += 1;
+        }
+    }
+}
+
+fn count_or_hashes(count: u64) -> String {
+    if count == 0 {
+        "#####".to_string()
+    } else {
+        count.to_string()
+    }
+}
+
+fn ratio(hit: usize, all: usize) -> String {
+    if all == 0 {
+        return "0/0".to_string();
+    }
+    format!("{}/{} ({:.1}%)", hit, all, hit as f64 * 100.0 / all as f64)
+}
+
+/// lcov separates a function's fields with commas.
+fn function_name(title: &str) -> String {
+    title.trim_end_matches('.').replace(',', "")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interpreter, Parser};
+
+    const SOURCE: &str = "Countdown.\n\nIngredients.\n0 g zero\n1 g count\n\nMethod.\n\
+        Take count from the refrigerator.\n\
+        Heat the count. Serve with sauce. Heat the count until heated. Boil the zero. \
+        Put zero into the mixing bowl.\nBoil until boiled.\n\nServes 1.\n\n\
+        Sauce.\n\nMethod.\nClean the mixing bowl.\n\nServes 1.\n\n\
+        Gravy.\n\nMethod.\nClean the mixing bowl.\n";
+
+    fn covered(inputs: &[&str]) -> Coverage {
+        let recipe = Parser::new(SOURCE).parse_recipe().unwrap();
+        let mut coverage = Coverage::new(&recipe);
+        for input in inputs {
+            let mut interpreter = Interpreter::new();
+            interpreter.add_recipe(recipe.clone());
+            interpreter.set_input_text(input);
+            interpreter.set_coverage(coverage);
+            interpreter.run().unwrap();
+            coverage = interpreter.take_coverage().unwrap();
+        }
+        coverage
+    }
+
+    #[test]
+    fn loops_count_as_entered_and_skipped_across_runs() {
+        let once = covered(&["2"]);
+        let heat = &once.recipes()[0].instructions[1];
+        assert_eq!(heat.text, "Heat the count");
+        assert_eq!(
+            heat.branches,
+            Some(LoopCoverage {
+                entered: 1,
+                skipped: 0
+            })
+        );
+        let boil = &once.recipes()[0].instructions[3];
+        assert_eq!(
+            boil.branches,
+            Some(LoopCoverage {
+                entered: 0,
+                skipped: 1
+            })
+        );
+        assert_eq!(once.recipes_hit(), (2, 3));
+        assert_eq!(once.branches_hit(), (2, 4));
+
+        let twice = covered(&["2", "0"]);
+        assert_eq!(
+            twice.recipes()[0].instructions[1].branches,
+            Some(LoopCoverage {
+                entered: 1,
+                skipped: 1
+            })
+        );
+        assert_eq!(twice.recipes()[0].instructions[0].count, 2);
+        assert_eq!(twice.branches_hit(), (3, 4));
+    }
+
+    #[test]
+    fn annotated_listing_marks_what_never_ran() {
+        let listing = covered(&["2"]).annotate(SOURCE);
+        let lines: Vec<&str> = listing.lines().collect();
+        assert_eq!(
+            lines[7],
+            "        1:    8:Take count from the refrigerator."
+        );
+        assert_eq!(
+            lines[9],
+            "         :     : ^ loop 'Heat the count' was never skipped"
+        );
+        assert!(lines.contains(&"         :     : ^ loop 'Boil the zero' was never entered"));
+        assert!(lines.contains(&"         :     : ^ 'Put zero into the mixing bowl' never ran"));
+        assert!(lines.contains(&"    #####:   21:Gravy."));
+        assert!(lines.contains(&"recipes: 2/3 (66.7%)"));
+    }
+
+    #[test]
+    fn lcov_lists_recipes_branches_and_lines() {
+        let lcov = covered(&["2"]).lcov("countdown.chef");
+        assert!(lcov.starts_with("TN:\nSF:countdown.chef\nFN:1,Countdown\n"));
+        assert!(lcov.contains("FNDA:2,Sauce\nFNDA:0,Gravy\nFNF:3\nFNH:2\n"));
+        assert!(lcov.contains("BRDA:9,1,0,1\nBRDA:9,1,1,0\n"));
+        assert!(lcov.contains("DA:8,1\nDA:9,2\nDA:12,1\nDA:17,2\nDA:19,2\nDA:24,0\n"));
+        assert!(lcov.ends_with("LF:6\nLH:5\nend_of_record\n"));
+    }
+}
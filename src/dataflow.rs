@@ -0,0 +1,1076 @@
+//! Static data-flow analysis of measures, amounts and bowl sizes.
+//!
+//! [`analyze`] runs a recipe without any input. In place of each value it
+//! tracks a fact: the range the amount lies in and the measures it may have.
+//! There is a fact for every ingredient and for each of the top places of
+//! every mixing bowl and baking dish, along with the range of each bowl's
+//! and dish's size. A loop whose condition is a known
+//! amount is run pass by pass; other loops are run until their facts stop
+//! changing, and ranges that keep growing are widened to 0 or past it.
+//! Recursive `Serve with` calls are run until the bowl the sous-chef hands
+//! back stops changing. The findings hold for every run, whatever the input:
+//! a liquid that may not be a character when it is served, a `Liquefy`
+//! whose liquid is never served, and an instruction that takes from a mixing
+//! bowl that is or may be empty.
+//!
+//! The analysis follows the default [`CompatProfile`](crate::CompatProfile),
+//! except that `Divide` may round either way.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+use std::fmt;
+
+use crate::compile::{recipe_indices, recipes_in_order};
+use crate::error_formatter::ordinal;
+use crate::instruction::Instruction;
+use crate::interpreter::normalize_recipe_name;
+use crate::types::{Ingredient, Measure, Recipe};
+
+/// Fixpoint rounds joined exactly before ranges are widened.
+const WIDEN_AFTER: usize = 3;
+
+/// Loop passes run one by one, over the whole analysis, while conditions
+/// are known amounts.
+const MAX_UNROLLED: usize = 100_000;
+
+/// Places at the top of a bowl or dish tracked one by one.
+const MAX_PLACES: usize = 64;
+
+/// What a finding is about.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FindingKind {
+    /// `Serves` or `Refrigerate` may print a liquid amount that is not a
+    /// character, failing with `RuntimeError::InvalidCharacter`.
+    LiquidNotACharacter,
+    /// The liquid a `Liquefy` makes is never served.
+    UnservedLiquefy,
+    /// `Fold`, `Add`, `Remove`, `Combine` or `Divide` always finds its
+    /// mixing bowl empty, failing with `RuntimeError::EmptyBowl`.
+    EmptyBowl,
+    /// `Fold`, `Add`, `Remove`, `Combine` or `Divide` may find its mixing
+    /// bowl empty.
+    MaybeEmptyBowl,
+}
+
+/// One thing the analysis found.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub kind: FindingKind,
+    /// The normalized name of the recipe the instruction is in.
+    pub recipe: String,
+    /// Pre-order index of the instruction, as in `SourceMap::instructions`.
+    pub instruction: usize,
+    pub message: String,
+}
+
+/// Analyzes `recipe` and the auxiliary recipes it serves. Findings come out
+/// recipe by recipe (main first), in instruction order.
+pub fn analyze(recipe: &Recipe) -> Vec<Finding> {
+    let recipes = recipes_in_order(recipe);
+    let mut analyzer = Analyzer {
+        indices: recipe_indices(&recipes),
+        recipes,
+        active: Vec::new(),
+        entries: HashMap::new(),
+        returns: HashMap::new(),
+        served: BTreeMap::new(),
+        liquefies: BTreeSet::new(),
+        liquids_served: BTreeSet::new(),
+        sizes: BTreeMap::new(),
+        unrolled: 0,
+    };
+    analyzer.serve(0, State::start(recipe));
+    analyzer.findings()
+}
+
+/// An instruction: the index of its recipe in `recipes_in_order`, then its
+/// pre-order index.
+type Site = (usize, usize);
+
+/// An inclusive range of amounts.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct Range {
+    lo: i64,
+    hi: i64,
+}
+
+impl Range {
+    const ANY: Range = Range {
+        lo: i64::MIN,
+        hi: i64::MAX,
+    };
+
+    fn exact(amount: i64) -> Self {
+        Self {
+            lo: amount,
+            hi: amount,
+        }
+    }
+
+    /// The range from `lo` to `hi`, or any amount if either overflows (the
+    /// run would wrap around).
+    fn checked(lo: i128, hi: i128) -> Self {
+        match (i64::try_from(lo), i64::try_from(hi)) {
+            (Ok(lo), Ok(hi)) => Self { lo, hi },
+            _ => Self::ANY,
+        }
+    }
+
+    /// The smallest range holding `amounts`.
+    fn spanning(amounts: impl IntoIterator<Item = i128>) -> Self {
+        let (lo, hi) = amounts
+            .into_iter()
+            .fold((i128::MAX, i128::MIN), |(lo, hi), amount| {
+                (lo.min(amount), hi.max(amount))
+            });
+        Self::checked(lo, hi)
+    }
+
+    fn contains(self, amount: i64) -> bool {
+        self.lo <= amount && amount <= self.hi
+    }
+
+    fn join(self, other: Range) -> Self {
+        Self {
+            lo: self.lo.min(other.lo),
+            hi: self.hi.max(other.hi),
+        }
+    }
+
+    /// Joins `newer` in, moving any bound it moves to 0 if that still
+    /// holds (where countdowns stop), and dropping it otherwise.
+    fn widen(self, newer: Range) -> Self {
+        let lo = match newer.lo {
+            lo if lo >= self.lo => self.lo,
+            lo if lo >= 0 => 0,
+            _ => i64::MIN,
+        };
+        let hi = match newer.hi {
+            hi if hi <= self.hi => self.hi,
+            hi if hi <= 0 => 0,
+            _ => i64::MAX,
+        };
+        Self { lo, hi }
+    }
+
+    /// The part of a size range where there is something to take, or `None`
+    /// if there never is.
+    fn nonempty(self) -> Option<Self> {
+        (self.hi > 0).then(|| Self {
+            lo: self.lo.max(1),
+            hi: self.hi,
+        })
+    }
+
+    /// The range without 0, or `None` if 0 is all it holds.
+    fn nonzero(self) -> Option<Self> {
+        match (self.lo, self.hi) {
+            (0, 0) => None,
+            (0, hi) => Some(Self { lo: 1, hi }),
+            (lo, 0) => Some(Self { lo, hi: -1 }),
+            _ => Some(self),
+        }
+    }
+
+    fn add(self, other: Range) -> Self {
+        Self::checked(
+            self.lo as i128 + other.lo as i128,
+            self.hi as i128 + other.hi as i128,
+        )
+    }
+
+    /// The sum of two sizes, which cannot wrap around like amounts do.
+    fn grow(self, other: Range) -> Self {
+        Self {
+            lo: self.lo.saturating_add(other.lo),
+            hi: self.hi.saturating_add(other.hi),
+        }
+    }
+
+    fn sub(self, other: Range) -> Self {
+        Self::checked(
+            self.lo as i128 - other.hi as i128,
+            self.hi as i128 - other.lo as i128,
+        )
+    }
+
+    fn mul(self, other: Range) -> Self {
+        Self::spanning(corners(self, other).map(|(a, b)| a * b))
+    }
+
+    /// The quotients, rounded toward zero or down; `None` if the divisor is
+    /// always 0.
+    fn div(self, divisor: Range) -> Option<Self> {
+        let mut quotients = Vec::new();
+        let negative = Self {
+            lo: divisor.lo,
+            hi: divisor.hi.min(-1),
+        };
+        let positive = Self {
+            lo: divisor.lo.max(1),
+            hi: divisor.hi,
+        };
+        for part in [negative, positive] {
+            if part.lo > part.hi {
+                continue;
+            }
+            for (a, b) in corners(self, part) {
+                let truncated = a / b;
+                quotients.push(truncated);
+                if a % b != 0 && (a < 0) != (b < 0) {
+                    quotients.push(truncated - 1);
+                }
+            }
+        }
+        (!quotients.is_empty()).then(|| Self::spanning(quotients))
+    }
+
+    /// Whether every amount in the range is a character.
+    fn is_character(self) -> bool {
+        ((0..=0xD7FF).contains(&self.lo) && (0..=0xD7FF).contains(&self.hi))
+            || ((0xE000..=0x10FFFF).contains(&self.lo) && (0xE000..=0x10FFFF).contains(&self.hi))
+    }
+
+    /// Whether no amount in the range is a character.
+    fn is_never_character(self) -> bool {
+        self.hi < 0 || self.lo > 0x10FFFF || (self.lo >= 0xD800 && self.hi <= 0xDFFF)
+    }
+}
+
+impl fmt::Display for Range {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.lo, self.hi) {
+            (i64::MIN, i64::MAX) => write!(f, "any amount"),
+            (lo, hi) if lo == hi => write!(f, "{}", lo),
+            (i64::MIN, hi) => write!(f, "at most {}", hi),
+            (lo, i64::MAX) => write!(f, "at least {}", lo),
+            (lo, hi) => write!(f, "{} to {}", lo, hi),
+        }
+    }
+}
+
+fn corners(a: Range, b: Range) -> impl Iterator<Item = (i128, i128)> {
+    [(a.lo, b.lo), (a.lo, b.hi), (a.hi, b.lo), (a.hi, b.hi)]
+        .into_iter()
+        .map(|(x, y)| (x as i128, y as i128))
+}
+
+/// What is known of one value: for each measure it may have, the range its
+/// amount lies in then.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Fact {
+    /// Indexed by [`slot`]; at least one is set.
+    ranges: [Option<Range>; 3],
+    /// The `Liquefy` instructions that may have made it liquid.
+    liquefied: BTreeSet<Site>,
+}
+
+fn slot(measure: Measure) -> usize {
+    match measure {
+        Measure::Dry => 0,
+        Measure::Liquid => 1,
+        Measure::Unspecified => 2,
+    }
+}
+
+impl Fact {
+    fn new(range: Range, measure: Measure) -> Self {
+        let mut ranges = [None; 3];
+        ranges[slot(measure)] = Some(range);
+        Self {
+            ranges,
+            liquefied: BTreeSet::new(),
+        }
+    }
+
+    /// The range of the amount when the value has `measure`, or `None` if it
+    /// never does.
+    fn when(&self, measure: Measure) -> Option<Range> {
+        self.ranges[slot(measure)]
+    }
+
+    /// The range of the amount, whatever the measure.
+    fn range(&self) -> Range {
+        self.ranges
+            .iter()
+            .flatten()
+            .copied()
+            .reduce(Range::join)
+            .unwrap_or(Range::ANY)
+    }
+
+    fn is(&self, measure: Measure) -> bool {
+        self.ranges
+            .iter()
+            .enumerate()
+            .all(|(idx, range)| range.is_some() == (idx == slot(measure)))
+    }
+
+    /// Maps the range of each measure, dropping the measures `operation`
+    /// rules out; `None` if it rules out all of them.
+    fn narrow(&mut self, operation: impl Fn(Range) -> Option<Range>) -> Option<()> {
+        for range in self.ranges.iter_mut() {
+            *range = range.and_then(&operation);
+        }
+        self.ranges.iter().any(Option::is_some).then_some(())
+    }
+
+    fn liquefy(&mut self, site: Site) {
+        *self = Self {
+            liquefied: std::mem::take(&mut self.liquefied),
+            ..Self::new(self.range(), Measure::Liquid)
+        };
+        self.liquefied.insert(site);
+    }
+}
+
+/// Joining facts where two paths meet.
+trait Merge: Clone {
+    /// Joins `other` in; with `widen`, moved range bounds jump ahead so
+    /// loops reach a fixpoint.
+    fn merge(&mut self, other: &Self, widen: bool);
+}
+
+fn merge<T: Merge>(target: &mut Option<T>, other: Option<T>, widen: bool) {
+    match (target.as_mut(), other) {
+        (Some(target), Some(other)) => target.merge(&other, widen),
+        (None, other) => *target = other,
+        (_, None) => {}
+    }
+}
+
+impl Merge for Range {
+    fn merge(&mut self, other: &Self, widen: bool) {
+        *self = if widen {
+            self.widen(*other)
+        } else {
+            self.join(*other)
+        };
+    }
+}
+
+impl Merge for Fact {
+    fn merge(&mut self, other: &Self, widen: bool) {
+        for (range, other) in self.ranges.iter_mut().zip(other.ranges) {
+            merge(range, other, widen);
+        }
+        self.liquefied.extend(other.liquefied.iter().copied());
+    }
+}
+
+/// What a mixing bowl or baking dish may hold: a fact for each of the top
+/// places, then any number of values (maybe none) that `rest` covers.
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct Contents {
+    top: VecDeque<Fact>,
+    rest: Option<Fact>,
+    /// How many values it holds.
+    size: Range,
+}
+
+impl Default for Contents {
+    fn default() -> Self {
+        Self {
+            top: VecDeque::new(),
+            rest: None,
+            size: Range::exact(0),
+        }
+    }
+}
+
+impl Contents {
+    fn push(&mut self, fact: Fact) {
+        self.size = self.size.grow(Range::exact(1));
+        self.top.push_front(fact);
+        if self.top.len() > MAX_PLACES {
+            let bottom = self.top.pop_back();
+            merge(&mut self.rest, bottom, false);
+        }
+    }
+
+    /// The top value, or `None` if the bowl is always empty.
+    fn pop(&mut self) -> Option<Fact> {
+        self.size = self.size.nonempty()?.grow(Range::exact(-1));
+        self.top.pop_front().or_else(|| self.rest.clone())
+    }
+
+    fn top_mut(&mut self) -> Option<&mut Fact> {
+        self.size = self.size.nonempty()?;
+        if self.top.is_empty() {
+            self.top.push_front(self.rest.clone()?);
+        }
+        self.top.front_mut()
+    }
+
+    fn facts(&self) -> impl Iterator<Item = &Fact> {
+        self.top.iter().chain(self.rest.iter())
+    }
+
+    fn facts_mut(&mut self) -> impl Iterator<Item = &mut Fact> {
+        self.top.iter_mut().chain(self.rest.iter_mut())
+    }
+
+    /// Stirring moves the top value into one of the top `places`, so each
+    /// of them may now hold any of their values.
+    fn blend(&mut self, places: usize) {
+        let deeper = places > self.top.len();
+        let mut joined = None;
+        for fact in self.top.iter().take(places) {
+            merge(&mut joined, Some(fact.clone()), false);
+        }
+        if deeper {
+            merge(&mut joined, self.rest.clone(), false);
+        }
+        let Some(joined) = joined else {
+            return;
+        };
+        for fact in self.top.iter_mut().take(places) {
+            fact.clone_from(&joined);
+        }
+        if let Some(rest) = self.rest.as_mut().filter(|_| deeper) {
+            rest.clone_from(&joined);
+        }
+    }
+
+    /// Puts `upper` on top, the way `Pour` and a returning sous-chef do.
+    fn stack(&mut self, upper: Contents) {
+        let lower = std::mem::replace(self, upper);
+        self.size = self.size.grow(lower.size);
+        if self.rest.is_some() {
+            for fact in lower.facts() {
+                merge(&mut self.rest, Some(fact.clone()), false);
+            }
+        } else {
+            for fact in lower.top {
+                self.top.push_back(fact);
+            }
+            self.rest = lower.rest;
+            while self.top.len() > MAX_PLACES {
+                let bottom = self.top.pop_back();
+                merge(&mut self.rest, bottom, false);
+            }
+        }
+    }
+}
+
+impl Merge for Contents {
+    fn merge(&mut self, other: &Self, widen: bool) {
+        let common = self.top.len().min(other.top.len());
+        for (fact, other) in self.top.iter_mut().zip(&other.top) {
+            fact.merge(other, widen);
+        }
+        let deeper: Vec<Fact> = self
+            .top
+            .drain(common..)
+            .chain(other.top.iter().skip(common).cloned())
+            .chain(other.rest.clone())
+            .collect();
+        for fact in deeper {
+            merge(&mut self.rest, Some(fact), widen);
+        }
+        self.size.merge(&other.size, widen);
+    }
+}
+
+/// The kitchen of one recipe: its ingredients, bowls and dishes. A missing
+/// ingredient has no value; a missing bowl or dish is empty.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+struct State {
+    variables: BTreeMap<Ingredient, Fact>,
+    bowls: Vec<Contents>,
+    dishes: Vec<Contents>,
+}
+
+impl State {
+    /// The kitchen when `recipe` starts, before any bowls are handed to it.
+    fn start(recipe: &Recipe) -> Self {
+        Self {
+            variables: recipe
+                .ingredients
+                .iter()
+                .map(|(name, value)| {
+                    (
+                        name.clone(),
+                        Fact::new(Range::exact(value.amount), value.measure),
+                    )
+                })
+                .collect(),
+            ..Self::default()
+        }
+    }
+
+    fn bowl(&mut self, idx: usize) -> &mut Contents {
+        grow(&mut self.bowls, idx)
+    }
+
+    fn dish(&mut self, idx: usize) -> &mut Contents {
+        grow(&mut self.dishes, idx)
+    }
+}
+
+fn grow(stacks: &mut Vec<Contents>, idx: usize) -> &mut Contents {
+    if stacks.len() <= idx {
+        stacks.resize_with(idx + 1, Contents::default);
+    }
+    &mut stacks[idx]
+}
+
+impl Merge for State {
+    fn merge(&mut self, other: &Self, widen: bool) {
+        // An ingredient without a value on one path stops that path when it
+        // is used, so only the other path's fact matters.
+        for (name, fact) in &other.variables {
+            match self.variables.get_mut(name) {
+                Some(known) => known.merge(fact, widen),
+                None => {
+                    self.variables.insert(name.clone(), fact.clone());
+                }
+            }
+        }
+        for (mine, theirs) in [
+            (&mut self.bowls, &other.bowls),
+            (&mut self.dishes, &other.dishes),
+        ] {
+            let len = mine.len().max(theirs.len());
+            mine.resize_with(len, Contents::default);
+            let empty = Contents::default();
+            for (idx, contents) in mine.iter_mut().enumerate() {
+                contents.merge(theirs.get(idx).unwrap_or(&empty), widen);
+            }
+        }
+    }
+}
+
+/// Where paths leave the method being analyzed other than at its end.
+struct Frame {
+    recipe: usize,
+    /// The kitchens a `Refrigerate` ended the method with.
+    ended: Option<State>,
+    /// For each enclosing loop, innermost last, the kitchens `Set aside`
+    /// left it with.
+    breaks: Vec<Option<State>>,
+}
+
+struct Analyzer<'a> {
+    recipes: Vec<(String, &'a Recipe)>,
+    indices: HashMap<String, usize>,
+    /// Recipes being analyzed, innermost last.
+    active: Vec<usize>,
+    /// The kitchens recursive calls hand each recipe, and how many times
+    /// they have grown.
+    entries: HashMap<usize, (State, usize)>,
+    /// The first mixing bowl each recipe may hand back to its caller.
+    returns: HashMap<usize, Contents>,
+    /// The amounts of served liquids that may not be characters, by
+    /// instruction and baking dish.
+    served: BTreeMap<(Site, usize), Range>,
+    /// The `Liquefy` instructions some run reaches.
+    liquefies: BTreeSet<Site>,
+    /// The `Liquefy` instructions whose liquid some run serves.
+    liquids_served: BTreeSet<Site>,
+    /// The sizes of the mixing bowl each instruction that takes from one
+    /// finds, over every run that reaches it.
+    sizes: BTreeMap<Site, Range>,
+    /// Loop passes run one by one so far.
+    unrolled: usize,
+}
+
+impl<'a> Analyzer<'a> {
+    /// Runs `recipe` from `entry` and returns the first mixing bowl it hands
+    /// back, or `None` if it never returns.
+    fn serve(&mut self, recipe: usize, entry: State) -> Option<Contents> {
+        if self.active.contains(&recipe) {
+            // A recursive call: its kitchen is analyzed by the outermost
+            // call, and it returns what that found so far.
+            match self.entries.get_mut(&recipe) {
+                Some((known, grown)) => {
+                    let mut merged = known.clone();
+                    merged.merge(&entry, *grown >= WIDEN_AFTER);
+                    if merged != *known {
+                        *known = merged;
+                        *grown += 1;
+                    }
+                }
+                None => {
+                    self.entries.insert(recipe, (entry, 0));
+                }
+            }
+            return self.returns.get(&recipe).cloned();
+        }
+
+        self.active.push(recipe);
+        let mut rounds = 0;
+        let returned = loop {
+            let before = (
+                self.entries.get(&recipe).cloned(),
+                self.returns.get(&recipe).cloned(),
+            );
+            let returned = self.method(recipe, entry.clone());
+            let mut all = returned.clone();
+            if let Some((recursive, _)) = &before.0 {
+                merge(&mut all, self.method(recipe, recursive.clone()), false);
+            }
+            if let Some(all) = all {
+                match self.returns.get_mut(&recipe) {
+                    Some(known) => known.merge(&all, rounds >= WIDEN_AFTER),
+                    None => {
+                        self.returns.insert(recipe, all);
+                    }
+                }
+            }
+            rounds += 1;
+            if before.0.as_ref() == self.entries.get(&recipe)
+                && before.1.as_ref() == self.returns.get(&recipe)
+            {
+                break returned;
+            }
+        };
+        self.active.pop();
+        returned
+    }
+
+    /// Runs the method of `recipe` and returns its first mixing bowl at the
+    /// end.
+    fn method(&mut self, recipe: usize, state: State) -> Option<Contents> {
+        let mut frame = Frame {
+            recipe,
+            ended: None,
+            breaks: Vec::new(),
+        };
+        let current: &'a Recipe = self.recipes[recipe].1;
+        let end = self.block(&mut frame, &current.instructions, 0, Some(state));
+        merge(&mut frame.ended, end, false);
+        frame.ended.map(|mut state| std::mem::take(state.bowl(0)))
+    }
+
+    /// Runs `instructions`, the first of which has pre-order index `first`.
+    fn block(
+        &mut self,
+        frame: &mut Frame,
+        instructions: &'a [Instruction],
+        first: usize,
+        mut state: Option<State>,
+    ) -> Option<State> {
+        let mut idx = first;
+        for instruction in instructions {
+            state = self.step(frame, instruction, idx, state?);
+            idx += Instruction::preorder(std::slice::from_ref(instruction)).len();
+        }
+        state
+    }
+
+    /// Runs one instruction; `None` if no run gets past it.
+    fn step(
+        &mut self,
+        frame: &mut Frame,
+        instruction: &'a Instruction,
+        idx: usize,
+        mut state: State,
+    ) -> Option<State> {
+        let site = (frame.recipe, idx);
+        if let Instruction::Fold(_, bowl)
+        | Instruction::Add(_, bowl)
+        | Instruction::Remove(_, bowl)
+        | Instruction::Combine(_, bowl)
+        | Instruction::Divide(_, bowl) = instruction
+        {
+            let size = state.bowl(*bowl).size;
+            self.sizes
+                .entry(site)
+                .and_modify(|known| *known = known.join(size))
+                .or_insert(size);
+        }
+        match instruction {
+            Instruction::Take(name) => {
+                let fact = match state.variables.get(name) {
+                    Some(known) => {
+                        let mut fact = known.clone();
+                        fact.narrow(|_| Some(Range::ANY));
+                        fact
+                    }
+                    None => {
+                        let declared = &self.recipes[frame.recipe].1.unset_ingredients;
+                        Fact::new(Range::ANY, declared.get(name).copied().unwrap_or_default())
+                    }
+                };
+                state.variables.insert(name.clone(), fact);
+            }
+            Instruction::Put(name, bowl) => {
+                let fact = state.variables.get(name)?.clone();
+                state.bowl(*bowl).push(fact);
+            }
+            Instruction::Fold(name, bowl) => {
+                let fact = state.bowl(*bowl).pop()?;
+                state.variables.insert(name.clone(), fact);
+            }
+            Instruction::Add(name, bowl) => {
+                arithmetic(&mut state, name, *bowl, |top, amount| Some(top.add(amount)))?
+            }
+            Instruction::Remove(name, bowl) => {
+                arithmetic(&mut state, name, *bowl, |top, amount| Some(top.sub(amount)))?
+            }
+            Instruction::Combine(name, bowl) => {
+                arithmetic(&mut state, name, *bowl, |top, amount| Some(top.mul(amount)))?
+            }
+            Instruction::Divide(name, bowl) => arithmetic(&mut state, name, *bowl, Range::div)?,
+            Instruction::AddDry(bowl) => {
+                let mut sum = Range::exact(0);
+                for fact in state.variables.values() {
+                    if let Some(dry) = fact.when(Measure::Dry) {
+                        let counted = if fact.is(Measure::Dry) {
+                            dry
+                        } else {
+                            dry.join(Range::exact(0))
+                        };
+                        sum = sum.add(counted);
+                    }
+                }
+                state.bowl(*bowl).push(Fact::new(sum, Measure::Dry));
+            }
+            Instruction::Liquefy(name) => {
+                self.liquefies.insert(site);
+                state.variables.get_mut(name)?.liquefy(site);
+            }
+            Instruction::LiquefyBowl(bowl) => {
+                self.liquefies.insert(site);
+                for fact in state.bowl(*bowl).facts_mut() {
+                    fact.liquefy(site);
+                }
+            }
+            Instruction::Stir(bowl, minutes) => {
+                if *minutes > 0 {
+                    state.bowl(*bowl).blend(minutes.saturating_add(1));
+                }
+            }
+            Instruction::StirIngredient(name, bowl) => {
+                let depth = state.variables.get(name)?.range().hi;
+                if depth > 0 {
+                    let places = usize::try_from(depth).unwrap_or(usize::MAX);
+                    state.bowl(*bowl).blend(places.saturating_add(1));
+                }
+            }
+            Instruction::Mix(bowl) => state.bowl(*bowl).blend(usize::MAX),
+            Instruction::Clean(bowl) => *state.bowl(*bowl) = Contents::default(),
+            Instruction::Pour(bowl, dish) => {
+                let poured = state.bowl(*bowl).clone();
+                state.dish(*dish).stack(poured);
+            }
+            Instruction::Loop {
+                condition_var,
+                body,
+                decrement_var,
+                ..
+            } => {
+                return self.run_loop(
+                    frame,
+                    condition_var,
+                    body,
+                    decrement_var.as_ref(),
+                    idx,
+                    state,
+                )
+            }
+            Instruction::SetAside => {
+                if let Some(breaks) = frame.breaks.last_mut() {
+                    merge(breaks, Some(state), false);
+                }
+                return None;
+            }
+            Instruction::ServeWith(name) => {
+                let callee = *self.indices.get(&normalize_recipe_name(name))?;
+                let entry = State {
+                    bowls: state.bowls.clone(),
+                    dishes: state.dishes.clone(),
+                    ..State::start(self.recipes[callee].1)
+                };
+                let returned = self.serve(callee, entry)?;
+                state.bowl(0).stack(returned);
+            }
+            Instruction::Refrigerate(hours) => {
+                if let Some(count) = hours {
+                    self.serve_dishes(site, &mut state, *count);
+                }
+                merge(&mut frame.ended, Some(state), false);
+                return None;
+            }
+            Instruction::Serves(count) => self.serve_dishes(site, &mut state, *count),
+        }
+        Some(state)
+    }
+
+    fn run_loop(
+        &mut self,
+        frame: &mut Frame,
+        condition_var: &str,
+        body: &'a [Instruction],
+        decrement_var: Option<&Ingredient>,
+        idx: usize,
+        state: State,
+    ) -> Option<State> {
+        // The condition ingredient needs a value before the first pass.
+        state.variables.get(condition_var)?;
+        let mut head = state;
+        let mut exit = None;
+        let mut joined = 0;
+        frame.breaks.push(None);
+        loop {
+            let condition = head.variables[condition_var].range();
+            let done = narrowed(&head, condition_var, |range| {
+                range.contains(0).then_some(Range::exact(0))
+            });
+            merge(&mut exit, done, false);
+            let Some(entered) = narrowed(&head, condition_var, Range::nonzero) else {
+                break;
+            };
+            let Some(after) = self
+                .block(frame, body, idx + 1, Some(entered))
+                .and_then(|state| match decrement_var {
+                    Some(name) => narrowed(&state, name, |range| Some(range.sub(Range::exact(1)))),
+                    None => Some(state),
+                })
+            else {
+                break;
+            };
+            // While the condition is a known amount, each pass is run as is.
+            if condition.lo == condition.hi && self.unrolled < MAX_UNROLLED {
+                self.unrolled += 1;
+                head = after;
+                continue;
+            }
+            let mut next = head.clone();
+            next.merge(&after, joined >= WIDEN_AFTER);
+            joined += 1;
+            if next == head {
+                break;
+            }
+            head = next;
+        }
+        let breaks = frame.breaks.pop().flatten();
+        merge(&mut exit, breaks, false);
+        exit
+    }
+
+    /// Prints and empties the first `count` baking dishes.
+    fn serve_dishes(&mut self, site: Site, state: &mut State, count: usize) {
+        for (dish, contents) in state.dishes.iter_mut().take(count).enumerate() {
+            for fact in contents.facts() {
+                let Some(liquid) = fact.when(Measure::Liquid) else {
+                    continue;
+                };
+                self.liquids_served.extend(fact.liquefied.iter().copied());
+                if !liquid.is_character() {
+                    let served = self.served.entry((site, dish));
+                    served
+                        .and_modify(|known| *known = known.join(liquid))
+                        .or_insert(liquid);
+                }
+            }
+            *contents = Contents::default();
+        }
+    }
+
+    fn findings(&self) -> Vec<Finding> {
+        let mut findings = Vec::new();
+        for (&((recipe, instruction), dish), range) in &self.served {
+            let message = if range.is_never_character() {
+                format!(
+                    "liquid served from the {} baking dish is {}, which is not a character",
+                    ordinal(dish + 1),
+                    range
+                )
+            } else {
+                format!(
+                    "liquid served from the {} baking dish may be {}, which is not always a character",
+                    ordinal(dish + 1),
+                    range
+                )
+            };
+            findings.push((
+                recipe,
+                instruction,
+                FindingKind::LiquidNotACharacter,
+                message,
+            ));
+        }
+        for &(recipe, instruction) in self.liquefies.difference(&self.liquids_served) {
+            let instructions = Instruction::preorder(&self.recipes[recipe].1.instructions);
+            let message = match instructions[instruction] {
+                Instruction::Liquefy(name) => format!("'{}' is liquefied but never served", name),
+                Instruction::LiquefyBowl(bowl) => format!(
+                    "the contents of the {} mixing bowl are liquefied but never served",
+                    ordinal(bowl + 1)
+                ),
+                _ => continue,
+            };
+            findings.push((recipe, instruction, FindingKind::UnservedLiquefy, message));
+        }
+        for (&(recipe, instruction), size) in &self.sizes {
+            let instructions = Instruction::preorder(&self.recipes[recipe].1.instructions);
+            let taking = instructions[instruction];
+            let (Instruction::Fold(_, bowl)
+            | Instruction::Add(_, bowl)
+            | Instruction::Remove(_, bowl)
+            | Instruction::Combine(_, bowl)
+            | Instruction::Divide(_, bowl)) = taking
+            else {
+                continue;
+            };
+            let (kind, message) = match (size.lo, size.hi) {
+                (_, hi) if hi <= 0 => (
+                    FindingKind::EmptyBowl,
+                    format!(
+                        "'{}' always finds the {} mixing bowl empty",
+                        taking,
+                        ordinal(bowl + 1)
+                    ),
+                ),
+                (lo, _) if lo <= 0 => (
+                    FindingKind::MaybeEmptyBowl,
+                    format!(
+                        "'{}' may find the {} mixing bowl empty",
+                        taking,
+                        ordinal(bowl + 1)
+                    ),
+                ),
+                _ => continue,
+            };
+            findings.push((recipe, instruction, kind, message));
+        }
+        findings.sort_by_key(|&(recipe, instruction, ..)| (recipe, instruction));
+        findings
+            .into_iter()
+            .map(|(recipe, instruction, kind, message)| Finding {
+                kind,
+                recipe: self.recipes[recipe].0.clone(),
+                instruction,
+                message,
+            })
+            .collect()
+    }
+}
+
+/// A copy of `state` with the ingredient's amount narrowed by `operation`;
+/// `None` if no run has such an amount.
+fn narrowed(
+    state: &State,
+    name: &str,
+    operation: impl Fn(Range) -> Option<Range>,
+) -> Option<State> {
+    let mut state = state.clone();
+    state.variables.get_mut(name)?.narrow(operation)?;
+    Some(state)
+}
+
+/// Applies `operation` to the top of `bowl` and the ingredient's amount;
+/// `None` if no run gets past it.
+fn arithmetic(
+    state: &mut State,
+    name: &str,
+    bowl: usize,
+    operation: impl Fn(Range, Range) -> Option<Range>,
+) -> Option<()> {
+    let amount = state.variables.get(name)?.range();
+    let top = state.bowl(bowl).top_mut()?;
+    top.narrow(|range| operation(range, amount))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    fn analyze_source(source: &str) -> Vec<Finding> {
+        let recipe = Parser::new(source).parse_recipe().expect("recipe parses");
+        analyze(&recipe)
+    }
+
+    #[test]
+    fn fixtures_that_serve_characters_are_clean() {
+        for source in [
+            include_str!("../tests/fixtures/hello-world.chef"),
+            include_str!("../tests/fixtures/abc-appetizer.chef"),
+            include_str!("../tests/fixtures/spaghetti-emoji.chef"),
+            include_str!("../tests/fixtures/fibonacci.chef"),
+        ] {
+            assert_eq!(analyze_source(source), Vec::new());
+        }
+    }
+
+    #[test]
+    fn a_negative_liquid_is_never_a_character() {
+        let source = "Sour Soup.\n\nIngredients.\n65 ml water\n100 g sugar\n\nMethod.\nPut water into the mixing bowl.\nRemove sugar from the mixing bowl.\nPour contents of the mixing bowl into the baking dish.\n\nServes 1.";
+        let findings = analyze_source(source);
+        assert_eq!(findings.len(), 1);
+        assert_eq!(findings[0].kind, FindingKind::LiquidNotACharacter);
+        assert_eq!(findings[0].instruction, 3);
+        assert_eq!(
+            findings[0].message,
+            "liquid served from the 1st baking dish is -35, which is not a character"
+        );
+    }
+
+    #[test]
+    fn loops_leave_their_condition_at_zero() {
+        // The count is read from input, so the loop runs any number of times,
+        // but it is 0 (a character) once the loop is done. The running total
+        // may be anything.
+        let source = "Letters.\n\nIngredients.\n0 g count\n65 ml letter\n0 g total\n\nMethod.\nTake count from refrigerator.\nPrint the count.\nPut letter into the 2nd mixing bowl.\nPut count into the mixing bowl.\nAdd total to the mixing bowl.\nFold total into the mixing bowl.\nPrint the count until printed.\nLiquefy count.\nPut count into the 2nd mixing bowl.\nPour contents of the 2nd mixing bowl into the baking dish.\nLiquefy total.\nPut total into the 3rd mixing bowl.\nPour contents of the 3rd mixing bowl into the 2nd baking dish.\n\nServes 2.";
+        let findings = analyze_source(source);
+        let messages: Vec<&str> = findings.iter().map(|f| f.message.as_str()).collect();
+        assert_eq!(
+            messages,
+            vec!["liquid served from the 2nd baking dish may be any amount, which is not always a character"]
+        );
+    }
+
+    #[test]
+    fn countdowns_are_run_pass_by_pass() {
+        // Three passes through the 2nd bowl take the counter from 70 to 'C'.
+        let source = include_str!("../tests/fixtures/spec/loop-different-decrement-test.chef");
+        assert_eq!(analyze_source(source), Vec::new());
+    }
+
+    #[test]
+    fn input_may_be_anything_and_unserved_liquids_are_flagged() {
+        let source = "Echo.\n\nIngredients.\n1 g shallot\n2 g leek\n\nMethod.\nTake shallot from refrigerator.\nLiquefy shallot.\nLiquefy leek.\nPut shallot into the mixing bowl.\nPour contents of the mixing bowl into the baking dish.\n\nServes 1.";
+        let findings = analyze_source(source);
+        let kinds: Vec<(FindingKind, usize)> =
+            findings.iter().map(|f| (f.kind, f.instruction)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (FindingKind::UnservedLiquefy, 2),
+                (FindingKind::LiquidNotACharacter, 5),
+            ]
+        );
+        assert_eq!(findings[0].message, "'leek' is liquefied but never served");
+        assert!(findings[1].message.contains("may be any amount"));
+    }
+
+    #[test]
+    fn bowl_sizes_show_empty_and_maybe_empty_bowls() {
+        let source = "Stack.\n\nIngredients.\n0 g count\n1 g item\n\nMethod.\nTake count from refrigerator.\nStack the count.\nPut item into the mixing bowl.\nStack the count until stacked.\nPut item into the 2nd mixing bowl.\nFold item into the 2nd mixing bowl.\nFold item into the mixing bowl.\nFold item into the 2nd mixing bowl.\n\nServes 1.";
+        let findings = analyze_source(source);
+        let kinds: Vec<(FindingKind, usize)> =
+            findings.iter().map(|f| (f.kind, f.instruction)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (FindingKind::MaybeEmptyBowl, 5),
+                (FindingKind::EmptyBowl, 6),
+            ]
+        );
+        assert_eq!(
+            findings[1].message,
+            "'Fold item into the 2nd mixing bowl' always finds the 2nd mixing bowl empty"
+        );
+    }
+
+    #[test]
+    fn a_sous_chef_fills_the_first_bowl() {
+        let source = "Main.\n\nIngredients.\n1 g item\n\nMethod.\nServe with sauce.\nFold item into the mixing bowl.\nFold item into the mixing bowl.\n\nServes 1.\n\nSauce.\n\nIngredients.\n2 g drop\n\nMethod.\nPut drop into the mixing bowl.";
+        let findings = analyze_source(source);
+        let kinds: Vec<(FindingKind, usize)> =
+            findings.iter().map(|f| (f.kind, f.instruction)).collect();
+        assert_eq!(kinds, vec![(FindingKind::EmptyBowl, 2)]);
+    }
+}
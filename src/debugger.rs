@@ -0,0 +1,922 @@
+//! A debugger that steps a run forwards and backwards.
+//!
+//! The run pauses before every instruction (see
+//! [`Interpreter::pause_after`]). Each step keeps the undo log of what the
+//! instruction changed (see [`history`](crate::history)), so
+//! [`step_back`](Debugger::step_back) puts the kitchen, the output and the
+//! unread input back the way they were, and [`origin`](Debugger::origin)
+//! finds the step that left a value where it is now. Going forwards,
+//! [watches](crate::watch) stop the run like breakpoints do.
+//!
+//! Only the last [`MAX_HISTORY`] steps are kept; a longer run can step back
+//! that far and no further.
+
+use std::collections::{BTreeSet, HashMap, VecDeque};
+
+use crate::history::{Change, Kitchen, Stack};
+use crate::instruction::Instruction;
+use crate::interpreter::Interpreter;
+use crate::repl::{show_containers, show_value, Reply};
+use crate::snapshot::ResumePoint;
+use crate::types::{ChefError, ExecutionContext, Recipe, Result, Value};
+use crate::watch::{Hit, Watch};
+
+/// How many steps a [`Debugger`] keeps by default.
+pub const MAX_HISTORY: usize = 100_000;
+
+pub const HELP: &str = "\
+step, s [N]               run the next instruction (or N of them)
+reverse-step, rs [N]      undo the last instruction (or N of them)
+continue, c               run until the next breakpoint or the end
+reverse-continue, rc      undo until the previous breakpoint or the start
+break, b LINE             stop before the instructions on LINE
+delete LINE               remove the breakpoint on LINE
+watch EXPR                stop when an ingredient, bowl N or dish N changes,
+                          or when a condition such as len(bowl 2) > 100
+                          turns true
+watches                   list the watches
+unwatch N                 remove the Nth watch
+where, w                  show the instruction about to run
+bowls / dishes            show the mixing bowls / baking dishes
+ingredients               show the ingredients' values
+output                    show what was served so far
+origin bowl N [P]         find the instruction that put the Pth value
+origin dish N [P]         (from the top, default 1) of the bowl or dish there
+help                      show this help
+quit                      leave";
+
+/// An instruction in a recipe's method.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Location {
+    pub recipe: String,
+    /// The instruction's place in the method, counted in pre-order (a loop
+    /// before its body) like `SourceMap::instructions`.
+    pub instruction: usize,
+    pub line: Option<usize>,
+    /// The instruction's (opening) sentence.
+    pub text: String,
+}
+
+impl std::fmt::Display for Location {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "line {} ({}): {}", line, self.recipe, self.text),
+            None => write!(f, "{}: {}", self.recipe, self.text),
+        }
+    }
+}
+
+/// Where a value in a bowl or dish came from.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Origin {
+    /// There is no such value.
+    Missing,
+    /// The value was there before the first step.
+    Start,
+    /// The value was there before the oldest step still kept, the
+    /// `step`th (counted from 1).
+    Dropped { step: usize },
+    /// The `step`th step (counted from 1) left it there by running the
+    /// instruction at `location`.
+    Step { step: usize, location: Location },
+}
+
+/// One instruction that ran: where the run was paused before it and how to
+/// undo it. Recipes are kept by their index in [`Titles`].
+struct Step {
+    /// The top-level recipe and the resume points of the pause.
+    recipe: usize,
+    resume: Box<[ResumePoint]>,
+    /// The recipe the instruction is in and its pre-order index there.
+    at: (usize, usize),
+    changes: Vec<Change>,
+    hits: Vec<Hit>,
+}
+
+/// The titles of the recipes the steps ran in, each kept once.
+#[derive(Default)]
+struct Titles {
+    titles: Vec<String>,
+    index: HashMap<String, usize>,
+}
+
+impl Titles {
+    fn intern(&mut self, title: &str) -> usize {
+        if let Some(&index) = self.index.get(title) {
+            return index;
+        }
+        self.titles.push(title.to_string());
+        self.index.insert(title.to_string(), self.titles.len() - 1);
+        self.titles.len() - 1
+    }
+}
+
+pub struct Debugger {
+    interpreter: Interpreter,
+    history: VecDeque<Step>,
+    max_history: usize,
+    /// How many of the oldest steps were dropped to keep `max_history`.
+    dropped: usize,
+    titles: Titles,
+    breakpoints: BTreeSet<usize>,
+    /// The error the last step ended the run with.
+    error: Option<ChefError>,
+}
+
+impl Debugger {
+    /// Starts the interpreter's main recipe, paused before its first
+    /// instruction. `Take` should read from
+    /// [`set_input_text`](Interpreter::set_input_text) input: values read
+    /// from stdin cannot be put back.
+    pub fn new(mut interpreter: Interpreter) -> Result<Self> {
+        interpreter.record_changes();
+        interpreter.pause_after(0);
+        interpreter.run()?;
+        interpreter.take_changes();
+        Ok(Self {
+            interpreter,
+            history: VecDeque::new(),
+            max_history: MAX_HISTORY,
+            dropped: 0,
+            titles: Titles::default(),
+            breakpoints: BTreeSet::new(),
+            error: None,
+        })
+    }
+
+    /// Keeps only the last `steps` steps (at least one) from now on,
+    /// instead of [`MAX_HISTORY`].
+    pub fn set_max_history(&mut self, steps: usize) {
+        self.max_history = steps.max(1);
+        while self.history.len() > self.max_history {
+            self.history.pop_front();
+            self.dropped += 1;
+        }
+    }
+
+    pub fn interpreter(&self) -> &Interpreter {
+        &self.interpreter
+    }
+
+    /// The instruction about to run, or `None` once the run has ended.
+    pub fn location(&self) -> Option<Location> {
+        let (title, resume) = self.interpreter.position()?;
+        locate(&self.interpreter, &title, &resume)
+    }
+
+    /// The `Serve with` instructions the run is inside, outermost first,
+    /// followed by the instruction about to run. Empty once the run has
+    /// ended.
+    pub fn call_stack(&self) -> Vec<Location> {
+        self.interpreter
+            .position()
+            .and_then(|(title, resume)| calls(&self.interpreter, &title, &resume))
+            .unwrap_or_default()
+    }
+
+    /// The error that ended the run, if it failed.
+    pub fn error(&self) -> Option<&ChefError> {
+        self.error.as_ref()
+    }
+
+    /// How many instructions have run.
+    pub fn steps(&self) -> usize {
+        self.dropped + self.history.len()
+    }
+
+    /// How many of the first steps were dropped and cannot be stepped back
+    /// over.
+    pub fn dropped_steps(&self) -> usize {
+        self.dropped
+    }
+
+    /// The watches the last step hit.
+    pub fn hits(&self) -> &[Hit] {
+        self.history.back().map_or(&[], |step| &step.hits)
+    }
+
+    /// Watches the kitchen of the recipe running now; see
+    /// [`Interpreter::watch`].
+    pub fn watch(&mut self, watch: Watch) {
+        self.interpreter.watch(watch);
+    }
+
+    pub fn unwatch(&mut self, index: usize) -> Option<Watch> {
+        self.interpreter.unwatch(index)
+    }
+
+    /// Runs the next instruction (a loop or `Serve with` up to the first
+    /// instruction inside it). Returns `false` if the run had already ended.
+    pub fn step(&mut self) -> bool {
+        let Some((title, resume)) = self.interpreter.position() else {
+            return false;
+        };
+        let Some((in_recipe, index, _)) =
+            walk(&self.interpreter, &title, &resume).and_then(|mut calls| calls.pop())
+        else {
+            return false;
+        };
+        let at = (self.titles.intern(&in_recipe.title), index);
+        let recipe = self.titles.intern(&title);
+        self.interpreter.pause_after(1);
+        if let Err(error) = self.interpreter.resume() {
+            self.error = Some(error);
+        }
+        let changes = self.interpreter.take_changes();
+        let hits = self.interpreter.take_watch_hits();
+        if self.history.len() == self.max_history {
+            self.history.pop_front();
+            self.dropped += 1;
+        }
+        self.history.push_back(Step {
+            recipe,
+            resume: resume.into(),
+            at,
+            changes,
+            hits,
+        });
+        true
+    }
+
+    /// Undoes the last instruction. Returns `false` at the start of the run,
+    /// or at the oldest step kept.
+    pub fn step_back(&mut self) -> bool {
+        let Some(step) = self.history.pop_back() else {
+            return false;
+        };
+        self.interpreter.undo(step.changes);
+        let title = self.titles.titles[step.recipe].clone();
+        self.interpreter
+            .set_position(Some((title, step.resume.into_vec())));
+        self.interpreter.forget_watched_values();
+        self.error = None;
+        true
+    }
+
+    /// Steps until a watch is hit, the run is about to run an instruction on
+    /// a breakpoint's line, or the run has ended. Returns the number of
+    /// steps taken.
+    pub fn continue_forward(&mut self) -> usize {
+        let mut steps = 0;
+        while self.step() {
+            steps += 1;
+            if !self.hits().is_empty() || self.at_breakpoint() {
+                break;
+            }
+        }
+        steps
+    }
+
+    /// Steps back until the run is about to run an instruction on a
+    /// breakpoint's line or one that hit a watch, or is back at the start.
+    /// Returns the number of steps undone.
+    pub fn continue_backward(&mut self) -> usize {
+        let mut steps = 0;
+        loop {
+            let hit = !self.hits().is_empty();
+            if !self.step_back() {
+                break;
+            }
+            steps += 1;
+            if hit || self.at_breakpoint() {
+                break;
+            }
+        }
+        steps
+    }
+
+    pub fn add_breakpoint(&mut self, line: usize) {
+        self.breakpoints.insert(line);
+    }
+
+    /// Returns whether there was a breakpoint on `line`.
+    pub fn remove_breakpoint(&mut self, line: usize) -> bool {
+        self.breakpoints.remove(&line)
+    }
+
+    pub fn breakpoints(&self) -> impl Iterator<Item = usize> + '_ {
+        self.breakpoints.iter().copied()
+    }
+
+    fn at_breakpoint(&self) -> bool {
+        self.location()
+            .and_then(|location| location.line)
+            .is_some_and(|line| self.breakpoints.contains(&line))
+    }
+
+    /// Finds the step that put the value `depth` places below the top of
+    /// `stack` (0 is the top) where it is: the latest step before which
+    /// that place of the stack, counted from its bottom, held something
+    /// else. Bowls and dishes are those of the recipe running now.
+    pub fn origin(&self, stack: Stack, depth: usize) -> Origin {
+        let mut context = self.interpreter.context().clone();
+        let level = context.call_stack.len();
+        let Some(current) = Kitchen::at(&context, level).and_then(|kitchen| kitchen.stack(stack))
+        else {
+            return Origin::Missing;
+        };
+        let Some(&value) = current.get(depth) else {
+            return Origin::Missing;
+        };
+        let from_bottom = current.len() - 1 - depth;
+        let holds = |context: &ExecutionContext| {
+            Kitchen::at(context, level)
+                .and_then(|kitchen| kitchen.stack(stack))
+                .is_some_and(|values| {
+                    values.len() > from_bottom && values[values.len() - 1 - from_bottom] == value
+                })
+        };
+        for (index, step) in self.history.iter().enumerate().rev() {
+            for change in step.changes.iter().rev() {
+                change.clone().undo_in(&mut context);
+            }
+            if !holds(&context) {
+                let (recipe, instruction) = step.at;
+                let location = self
+                    .interpreter
+                    .recipe(&self.titles.titles[recipe])
+                    .and_then(|recipe| {
+                        let preorder = Instruction::preorder(&recipe.instructions);
+                        Some(location(recipe, instruction, preorder.get(instruction)?))
+                    });
+                return match location {
+                    Some(location) => Origin::Step {
+                        step: self.dropped + index + 1,
+                        location,
+                    },
+                    None => Origin::Missing,
+                };
+            }
+        }
+        if self.dropped > 0 {
+            return Origin::Dropped {
+                step: self.dropped + 1,
+            };
+        }
+        Origin::Start
+    }
+
+    /// Runs one debugger command (see [`HELP`]).
+    pub fn eval(&mut self, line: &str) -> Reply {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let Some((&command, arguments)) = words.split_first() else {
+            return Reply::Text(String::new());
+        };
+        let rest = line.trim().strip_prefix(command).unwrap_or("").trim();
+        let text = match command {
+            "q" | "quit" | "exit" => return Reply::Quit,
+            "h" | "help" => HELP.to_string(),
+            "s" | "step" => match count(arguments) {
+                Ok(n) => {
+                    let dropped = self.dropped;
+                    for _ in 0..n {
+                        if !self.step() || !self.hits().is_empty() {
+                            break;
+                        }
+                    }
+                    self.status_after(dropped)
+                }
+                Err(error) => error,
+            },
+            "rs" | "reverse-step" => match count(arguments) {
+                Ok(n) => {
+                    for _ in 0..n {
+                        if !self.step_back() {
+                            break;
+                        }
+                    }
+                    self.status()
+                }
+                Err(error) => error,
+            },
+            "c" | "continue" => {
+                let dropped = self.dropped;
+                self.continue_forward();
+                self.status_after(dropped)
+            }
+            "rc" | "reverse-continue" => {
+                self.continue_backward();
+                self.status()
+            }
+            "b" | "break" => match line_number(arguments) {
+                Ok(line) => {
+                    self.add_breakpoint(line);
+                    format!("breakpoint on line {}", line)
+                }
+                Err(error) => error,
+            },
+            "delete" => match line_number(arguments) {
+                Ok(line) if self.remove_breakpoint(line) => {
+                    format!("removed the breakpoint on line {}", line)
+                }
+                Ok(line) => format!("no breakpoint on line {}", line),
+                Err(error) => error,
+            },
+            "watch" => match rest.parse::<Watch>().and_then(|watch| {
+                let location = self.location();
+                let recipe = location.and_then(|at| self.interpreter.recipe(&at.recipe));
+                recipe.map_or(Ok(()), |recipe| watch.check(recipe))?;
+                Ok(watch)
+            }) {
+                Ok(watch) => {
+                    let text = format!("watching {}", watch);
+                    self.watch(watch);
+                    text
+                }
+                Err(error) => format!("error: {}", error),
+            },
+            "watches" => self.show_watches(),
+            "unwatch" => match line_number(arguments) {
+                Ok(n) => match n.checked_sub(1).and_then(|index| self.unwatch(index)) {
+                    Some(watch) => format!("stopped watching {}", watch),
+                    None => format!("there is no watch {}", n),
+                },
+                Err(_) => "usage: unwatch N (see watches)".to_string(),
+            },
+            "w" | "where" => self.status(),
+            "bowls" => show_containers("mixing bowl", self.interpreter.mixing_bowls()),
+            "dishes" => show_containers("baking dish", self.interpreter.baking_dishes()),
+            "ingredients" => self.show_ingredients(),
+            "output" => format!("{:?}", self.interpreter.output()),
+            "origin" => self.show_origin(arguments),
+            _ => format!("unknown command '{}' (try help)", command),
+        };
+        Reply::Text(text)
+    }
+
+    /// Where the run is, after how many steps, and what the last step hit.
+    fn status(&self) -> String {
+        let at = match (self.location(), &self.error) {
+            (Some(location), _) => format!("at {}", location),
+            (None, Some(error)) => format!("failed: {}", error),
+            (None, None) => "finished".to_string(),
+        };
+        let mut lines = vec![format!("[step {}] {}", self.steps(), at)];
+        lines.extend(self.hits().iter().map(|hit| format!("watch: {}", hit)));
+        if self.history.is_empty() && self.dropped > 0 {
+            lines.push(format!(
+                "cannot step back further: the {} steps before were dropped",
+                self.dropped
+            ));
+        }
+        lines.join("\n")
+    }
+
+    /// The status after stepping forwards, telling when the steps began to
+    /// be dropped; `dropped` is how many were dropped before.
+    fn status_after(&self, dropped: usize) -> String {
+        let status = self.status();
+        if dropped == 0 && self.dropped > 0 {
+            format!(
+                "{}\nnote: only the last {} steps are kept; older ones can no longer be stepped back over",
+                status, self.max_history
+            )
+        } else {
+            status
+        }
+    }
+
+    fn show_watches(&self) -> String {
+        let watches: Vec<String> = self
+            .interpreter
+            .watches()
+            .enumerate()
+            .map(|(index, watch)| format!("{}: {}", index + 1, watch))
+            .collect();
+        if watches.is_empty() {
+            "no watches".to_string()
+        } else {
+            watches.join("\n")
+        }
+    }
+
+    fn show_ingredients(&self) -> String {
+        let mut variables: Vec<(&String, &Value)> =
+            self.interpreter.context().variables.iter().collect();
+        variables.sort_by_key(|(name, _)| name.as_str());
+        variables
+            .into_iter()
+            .map(|(name, value)| format!("{}: {}", name, show_value(value)))
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    fn show_origin(&self, arguments: &[&str]) -> String {
+        const USAGE: &str = "usage: origin bowl|dish N [POSITION]";
+        let (kind, number, position) = match arguments {
+            [kind, number] => (*kind, *number, "1"),
+            [kind, number, position] => (*kind, *number, *position),
+            _ => return USAGE.to_string(),
+        };
+        let (Ok(number @ 1..), Ok(position @ 1..)) =
+            (number.parse::<usize>(), position.parse::<usize>())
+        else {
+            return USAGE.to_string();
+        };
+        let stack = match kind {
+            "bowl" => Stack::Bowl(number - 1),
+            "dish" => Stack::Dish(number - 1),
+            _ => return USAGE.to_string(),
+        };
+        match self.origin(stack, position - 1) {
+            Origin::Missing => format!("there is no value {} in {} {}", position, kind, number),
+            Origin::Start => "it was there before the first step".to_string(),
+            Origin::Dropped { step } => format!(
+                "it was there before step {}, the oldest step kept; older steps were dropped",
+                step
+            ),
+            Origin::Step { step, location } => format!("step {}: {}", step, location),
+        }
+    }
+}
+
+/// The number of steps a command argument asks for, 1 by default.
+fn count(arguments: &[&str]) -> std::result::Result<usize, String> {
+    match arguments {
+        [] => Ok(1),
+        [n] => n
+            .parse()
+            .map_err(|_| format!("'{}' is not a number of steps", n)),
+        _ => Err("expected at most one number of steps".to_string()),
+    }
+}
+
+fn line_number(arguments: &[&str]) -> std::result::Result<usize, String> {
+    match arguments {
+        [line] => line
+            .parse()
+            .map_err(|_| format!("'{}' is not a line number", line)),
+        _ => Err("expected a line number".to_string()),
+    }
+}
+
+/// The instruction a run paused at `resume` (outermost first) in the recipe
+/// titled `title` runs next.
+fn locate(interpreter: &Interpreter, title: &str, resume: &[ResumePoint]) -> Option<Location> {
+    calls(interpreter, title, resume)?.pop()
+}
+
+/// The `Serve with` instructions a run paused at `resume` is inside,
+/// outermost first, followed by the instruction it runs next.
+fn calls(interpreter: &Interpreter, title: &str, resume: &[ResumePoint]) -> Option<Vec<Location>> {
+    let calls = walk(interpreter, title, resume)?;
+    Some(
+        calls
+            .into_iter()
+            .map(|(recipe, index, instruction)| location(recipe, index, instruction))
+            .collect(),
+    )
+}
+
+/// What [`calls`] finds, as each instruction's recipe and pre-order index.
+fn walk<'a>(
+    interpreter: &'a Interpreter,
+    title: &str,
+    resume: &[ResumePoint],
+) -> Option<Vec<(&'a Recipe, usize, &'a Instruction)>> {
+    let mut recipe = interpreter.recipe(title)?;
+    let mut block: &[Instruction] = &recipe.instructions;
+    let mut base = 0;
+    let mut calls = Vec::new();
+    let mut points = resume.iter().peekable();
+    while let Some(&point) = points.next() {
+        let ResumePoint::Instruction(k) = point else {
+            return None;
+        };
+        let instruction = block.get(k)?;
+        let index = base
+            + block[..k]
+                .iter()
+                .map(Instruction::preorder_len)
+                .sum::<usize>();
+        match (instruction, points.peek()) {
+            (Instruction::Loop { body, .. }, Some(ResumePoint::Loop(_))) => {
+                points.next();
+                block = body;
+                base = index + 1;
+                continue;
+            }
+            (_, None) | (Instruction::ServeWith(_), Some(_)) => {}
+            _ => return None,
+        }
+        calls.push((recipe, index, instruction));
+        if let Instruction::ServeWith(name) = instruction {
+            if points.peek().is_some() {
+                recipe = interpreter.recipe(name)?;
+                block = &recipe.instructions;
+                base = 0;
+            }
+        }
+    }
+    Some(calls)
+}
+
+fn location(recipe: &Recipe, index: usize, instruction: &Instruction) -> Location {
+    Location {
+        recipe: recipe.title.trim_end_matches('.').to_string(),
+        instruction: index,
+        line: recipe
+            .source_map
+            .instructions
+            .get(index)
+            .map(|location| location.line),
+        text: instruction.opening(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::compat::CompatProfile;
+    use crate::parser::Parser;
+
+    const SOURCE: &str = "\
+Countdown.
+
+Ingredients.
+3 g counter
+65 ml letter
+
+Method.
+Put counter into the mixing bowl.
+Count the counter.
+Put letter into the mixing bowl.
+Add counter to the mixing bowl.
+Count the counter until counted.
+Take letter from refrigerator.
+Serve with doubled.
+Liquefy contents of the mixing bowl.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Doubled.
+
+Ingredients.
+10 g letter
+
+Method.
+Put letter into the 2nd mixing bowl.
+Add letter to the 2nd mixing bowl.
+Fold letter into the 2nd mixing bowl.
+Put letter into the mixing bowl.
+";
+
+    fn debugger() -> Debugger {
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(Parser::new(SOURCE).parse_recipe().unwrap());
+        interpreter.set_input_text("7");
+        Debugger::new(interpreter).unwrap()
+    }
+
+    fn finished_output() -> String {
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(Parser::new(SOURCE).parse_recipe().unwrap());
+        interpreter.set_input_text("7");
+        interpreter.run().unwrap();
+        interpreter.output().to_string()
+    }
+
+    #[test]
+    fn steps_through_loops_and_auxiliary_recipes() {
+        let mut debugger = debugger();
+        let lines: Vec<Option<usize>> = std::iter::from_fn(|| {
+            let line = debugger.location().map(|location| location.line);
+            debugger.step();
+            line
+        })
+        .collect();
+        // The loop's condition is checked before every pass, but only the
+        // first check is a step of its own.
+        let mut expected = vec![8, 9, 10, 11, 10, 11, 10, 11, 13, 14];
+        expected.extend([26, 27, 28, 29, 15, 16, 18]);
+        assert_eq!(lines, expected.into_iter().map(Some).collect::<Vec<_>>());
+        assert!(debugger.error().is_none());
+        assert_eq!(debugger.interpreter().output(), finished_output());
+    }
+
+    #[test]
+    fn stepping_back_restores_everything() {
+        let mut debugger = debugger();
+        let mut states = Vec::new();
+        loop {
+            let interpreter = debugger.interpreter();
+            states.push((
+                debugger.location(),
+                interpreter.context().clone(),
+                interpreter.output().to_string(),
+            ));
+            if !debugger.step() {
+                break;
+            }
+        }
+        states.pop();
+        while let Some((location, context, output)) = states.pop() {
+            assert!(debugger.step_back());
+            assert_eq!(debugger.location(), location);
+            assert_eq!(debugger.interpreter().context(), &context);
+            assert_eq!(debugger.interpreter().output(), output);
+        }
+        assert!(!debugger.step_back());
+
+        // The input read by `Take` is read again on the way forward.
+        debugger.continue_forward();
+        assert_eq!(debugger.interpreter().output(), finished_output());
+    }
+
+    #[test]
+    fn continues_to_breakpoints_both_ways() {
+        let mut debugger = debugger();
+        debugger.add_breakpoint(11);
+        debugger.continue_forward();
+        assert_eq!(debugger.steps(), 3);
+        debugger.continue_forward();
+        assert_eq!(debugger.steps(), 5);
+        debugger.continue_backward();
+        assert_eq!(debugger.steps(), 3);
+        debugger.continue_backward();
+        assert_eq!(debugger.steps(), 0);
+        assert!(debugger.remove_breakpoint(11));
+        debugger.continue_forward();
+        assert!(debugger.location().is_none());
+    }
+
+    #[test]
+    fn origin_finds_the_instruction_that_left_a_value() {
+        let mut debugger = debugger();
+        debugger.add_breakpoint(15);
+        debugger.continue_forward();
+        debugger.add_breakpoint(26);
+        debugger.continue_backward();
+        let calls: Vec<_> = debugger
+            .call_stack()
+            .into_iter()
+            .map(|location| (location.recipe, location.line))
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                ("Countdown".to_string(), Some(14)),
+                ("Doubled".to_string(), Some(26))
+            ]
+        );
+        debugger.remove_breakpoint(26);
+        debugger.continue_forward();
+
+        // The sous-chef's copy of the bowl came back on top of it.
+        assert_eq!(debugger.interpreter().mixing_bowls()[0].len(), 9);
+
+        let Origin::Step { location, .. } = debugger.origin(Stack::Bowl(0), 0) else {
+            panic!("no origin");
+        };
+        assert_eq!(
+            (location.recipe.as_str(), location.line),
+            ("Doubled", Some(29))
+        );
+        // `Add` changed the value the loop's last pass put in.
+        let Origin::Step { location, .. } = debugger.origin(Stack::Bowl(0), 5) else {
+            panic!("no origin");
+        };
+        assert_eq!(location.line, Some(11));
+        let Origin::Step { step, location } = debugger.origin(Stack::Bowl(0), 8) else {
+            panic!("no origin");
+        };
+        assert_eq!((step, location.line), (1, Some(8)));
+        assert_eq!(debugger.origin(Stack::Bowl(0), 9), Origin::Missing);
+    }
+
+    #[test]
+    fn keeps_only_the_last_steps() {
+        let mut debugger = debugger();
+        debugger.set_max_history(3);
+        let Reply::Text(text) = debugger.eval("s 2") else {
+            panic!("quit");
+        };
+        assert!(!text.contains("note"));
+        let Reply::Text(text) = debugger.eval("s 3") else {
+            panic!("quit");
+        };
+        assert!(text.starts_with("[step 5] at line 11"), "{}", text);
+        assert!(text.contains("only the last 3 steps are kept"), "{}", text);
+        assert_eq!(debugger.dropped_steps(), 2);
+
+        // The first value was put in by the dropped first step.
+        assert_eq!(
+            debugger.origin(Stack::Bowl(0), 2),
+            Origin::Dropped { step: 3 }
+        );
+        let Origin::Step { step, location } = debugger.origin(Stack::Bowl(0), 0) else {
+            panic!("no origin");
+        };
+        assert_eq!((step, location.line), (5, Some(10)));
+
+        let Reply::Text(text) = debugger.eval("rc") else {
+            panic!("quit");
+        };
+        assert!(text.starts_with("[step 2] at line 10"), "{}", text);
+        assert!(text.contains("the 2 steps before were dropped"), "{}", text);
+        assert!(!debugger.step_back());
+    }
+
+    #[test]
+    fn stepping_back_over_a_failed_sous_chef_restores_pending_serves() {
+        let source = "\
+Sundae.
+
+Ingredients.
+1 g sugar
+
+Method.
+Put sugar into the mixing bowl.
+Serve with burnt sauce.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Burnt Sauce.
+
+Method.
+Put salt into the mixing bowl.
+";
+        let mut interpreter = Interpreter::new();
+        interpreter.set_compat_profile(CompatProfile::from_name("acme").unwrap());
+        interpreter.add_recipe(Parser::new(source).parse_recipe().unwrap());
+        let mut debugger = Debugger::new(interpreter).unwrap();
+        debugger.step();
+        debugger.step();
+        let before = debugger.interpreter().snapshot().pending_serves;
+        assert_eq!(before, [vec![], vec![]]);
+        debugger.step();
+        assert!(debugger.error().is_some());
+        assert!(debugger.interpreter().snapshot().pending_serves.is_empty());
+        debugger.step_back();
+        assert_eq!(debugger.interpreter().snapshot().pending_serves, before);
+    }
+
+    #[test]
+    fn watches_stop_the_run_both_ways() {
+        let mut debugger = debugger();
+        debugger.watch("len(bowl 1) > 2".parse().unwrap());
+        debugger.continue_forward();
+        assert_eq!(debugger.steps(), 5);
+        assert_eq!(
+            debugger.hits()[0].to_string(),
+            "len(bowl 1) > 2 became true, at line 10 (Countdown): \
+             Put letter into the mixing bowl"
+        );
+        debugger.step();
+        assert!(debugger.hits().is_empty());
+
+        // Going back stops before the instruction that hit the watch, and
+        // going forward again hits it again.
+        debugger.continue_backward();
+        assert_eq!(debugger.steps(), 4);
+        debugger.step();
+        assert_eq!(debugger.hits().len(), 1);
+        assert_eq!(debugger.unwatch(0), "len(bowl 1) > 2".parse().ok());
+        debugger.continue_forward();
+        assert!(debugger.location().is_none());
+    }
+
+    #[test]
+    fn commands_report_where_the_run_is() {
+        let mut debugger = debugger();
+        let text = |reply| match reply {
+            Reply::Text(text) => text,
+            Reply::Quit => panic!("unexpected quit"),
+        };
+        assert_eq!(
+            text(debugger.eval("step 2")),
+            "[step 2] at line 10 (Countdown): Put letter into the mixing bowl"
+        );
+        assert_eq!(
+            text(debugger.eval("rs")),
+            "[step 1] at line 9 (Countdown): Count the counter"
+        );
+        assert_eq!(
+            text(debugger.eval("bowls")),
+            "mixing bowl 1: [3] (top first)"
+        );
+        assert_eq!(
+            text(debugger.eval("origin bowl 1")),
+            "step 1: line 8 (Countdown): Put counter into the mixing bowl"
+        );
+        assert!(text(debugger.eval("origin bowl x")).starts_with("usage"));
+        assert_eq!(
+            text(debugger.eval("watch salt")),
+            "error: Countdown declares no ingredient 'salt'"
+        );
+        assert_eq!(text(debugger.eval("watch letter")), "watching letter");
+        assert_eq!(text(debugger.eval("watches")), "1: letter");
+        assert_eq!(
+            text(debugger.eval("c")),
+            "[step 9] at line 14 (Countdown): Serve with doubled\n\
+             watch: letter: 65 'A' -> 7, at line 13 (Countdown): \
+             Take letter from the refrigerator"
+        );
+        assert_eq!(text(debugger.eval("unwatch 1")), "stopped watching letter");
+        assert!(text(debugger.eval("watch len(pan 2) > 1")).starts_with("error: "));
+        assert_eq!(text(debugger.eval("c")), "[step 17] finished");
+        assert_eq!(debugger.eval("quit"), Reply::Quit);
+    }
+}
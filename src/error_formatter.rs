@@ -1,6 +1,8 @@
 /// Rich error formatting for Chef interpreter errors
 use crate::error_context::{RuntimeContext, SpecReference};
-use crate::types::{ChefError, ParseError, RuntimeError};
+use crate::types::{
+    BookError, ChefError, GenerateError, ParseError, RuntimeError, SnapshotError, WatchError,
+};
 
 /// ANSI color codes for terminal output
 struct Colors;
@@ -33,6 +35,10 @@ impl ErrorFormatter {
         match error {
             ChefError::Runtime(runtime_err) => Self::format_runtime_error(runtime_err),
             ChefError::Parse(parse_err) => Self::format_parse_error(parse_err),
+            ChefError::Generate(generate_err) => Self::format_generate_error(generate_err),
+            ChefError::Book(book_err) => Self::format_book_error(book_err),
+            ChefError::Snapshot(snapshot_err) => Self::format_snapshot_error(snapshot_err),
+            ChefError::Watch(watch_err) => Self::format_watch_error(watch_err),
             ChefError::Io(io_err) => Self::format_io_error(io_err),
         }
     }
@@ -108,6 +114,10 @@ impl ErrorFormatter {
                 // This is not really an error, just a control flow signal
                 String::from("Loop break (Set aside instruction)")
             }
+            RuntimeError::Paused => {
+                // This is not really an error, just a control flow signal
+                String::from("Recipe paused (see Interpreter::pause_after)")
+            }
         }
     }
 
@@ -703,12 +713,123 @@ impl ErrorFormatter {
             "  {}\n",
             colorize("suggestion:", Colors::CYAN, true)
         ));
+        if let ParseError::LoopVerbMismatch { verb, expected, .. } = error {
+            output.push_str(&format!(
+                "  Close the '{}' loop with \"until {}\".\n",
+                verb, expected
+            ));
+            return output;
+        }
+        match error {
+            ParseError::OrdinalSuffixMismatch { expected, .. } => {
+                output.push_str(&format!("  Write '{}'.\n", expected));
+                return output;
+            }
+            ParseError::OrdinalWord { expected, .. } => {
+                output.push_str(&format!(
+                    "  Write '{}', or enable ordinal words in the parse options.\n",
+                    expected
+                ));
+                return output;
+            }
+            _ => {}
+        }
         output.push_str("  Check the Chef language specification for proper syntax.\n");
-        output.push_str("  Make sure your recipe follows the required structure:\n");
+        output.push_str("  Make sure your recipe follows the required structure, with one\n");
+        output.push_str("  blank line between the parts:\n");
         output.push_str("    1. Recipe Title.\n");
-        output.push_str("    2. Ingredients. (optional)\n");
-        output.push_str("    3. Method. (required)\n");
-        output.push_str("    4. Serves N. (optional)\n");
+        output.push_str("    2. Comments. (optional)\n");
+        output.push_str("    3. Ingredients. (optional)\n");
+        output.push_str("    4. Cooking time: N minutes. (optional)\n");
+        output.push_str("    5. Pre-heat oven to N degrees Celsius. (optional)\n");
+        output.push_str("    6. Method. (required)\n");
+        output.push_str("    7. Serves N. (optional)\n");
+
+        output
+    }
+
+    fn format_generate_error(error: &GenerateError) -> String {
+        let mut output = String::new();
+
+        output.push_str(&colorize("error", Colors::RED, true));
+        output.push_str(": ");
+        output.push_str(&colorize(&format!("{}", error), Colors::WHITE, true));
+        output.push('\n');
+        output.push('\n');
+        output.push_str(&format!(
+            "  {} Failed to generate a recipe from the stack program\n",
+            colorize("=", Colors::BLUE, true)
+        ));
+        output.push('\n');
+        output.push_str(&format!(
+            "  {}\n",
+            colorize("suggestion:", Colors::CYAN, true)
+        ));
+        output.push_str("  Stack programs use one operation per word: push N, pop, dup, swap,\n");
+        output.push_str("  add, sub, mul, div, print-char, print-num, read, loop ... end,\n");
+        output.push_str("  def NAME ... end and call NAME. '#' starts a comment.\n");
+
+        output
+    }
+
+    fn format_book_error(error: &BookError) -> String {
+        if let BookError::Parse { path, source } = error {
+            return format!(
+                "{}  {} in recipe book {}\n",
+                Self::format_parse_error(source),
+                colorize("note:", Colors::YELLOW, true),
+                path
+            );
+        }
+
+        let mut output = String::new();
+        output.push_str(&colorize("error", Colors::RED, true));
+        output.push_str(": ");
+        output.push_str(&colorize(&format!("{}", error), Colors::WHITE, true));
+        output.push('\n');
+        if let BookError::DuplicateRecipe { .. } = error {
+            output.push('\n');
+            output.push_str(&format!(
+                "  {}\n",
+                colorize("suggestion:", Colors::CYAN, true)
+            ));
+            output.push_str("  Recipe titles must be unique across all recipe books. Rename one\n");
+            output.push_str("  of the recipes, or load only one of the files.\n");
+        }
+
+        output
+    }
+
+    fn format_snapshot_error(error: &SnapshotError) -> String {
+        let mut output = String::new();
+        output.push_str(&colorize("error", Colors::RED, true));
+        output.push_str(": ");
+        output.push_str(&colorize(&format!("{}", error), Colors::WHITE, true));
+        output.push('\n');
+        output.push('\n');
+        output.push_str(&format!(
+            "  {}\n",
+            colorize("suggestion:", Colors::CYAN, true)
+        ));
+        output.push_str("  Resume from a file written by --snapshot, unchanged.\n");
+
+        output
+    }
+
+    fn format_watch_error(error: &WatchError) -> String {
+        let mut output = String::new();
+        output.push_str(&colorize("error", Colors::RED, true));
+        output.push_str(": ");
+        output.push_str(&colorize(&format!("{}", error), Colors::WHITE, true));
+        output.push('\n');
+        if let WatchError::UnknownIngredient { .. } = error {
+            output.push('\n');
+            output.push_str(&format!(
+                "  {}\n",
+                colorize("suggestion:", Colors::CYAN, true)
+            ));
+            output.push_str("  Watch an ingredient from the recipe's ingredient list.\n");
+        }
 
         output
     }
@@ -733,7 +854,7 @@ impl ErrorFormatter {
 }
 
 /// Helper function to convert a number to ordinal form (1st, 2nd, 3rd, etc.)
-fn ordinal(n: usize) -> String {
+pub(crate) fn ordinal(n: usize) -> String {
     let suffix = match n % 10 {
         1 if n % 100 != 11 => "st",
         2 if n % 100 != 12 => "nd",
@@ -840,4 +961,15 @@ mod tests {
         assert!(output.contains("Beat the batter"));
         assert!(output.contains("until"));
     }
+
+    #[test]
+    fn format_loop_verb_mismatch_suggests_the_participle() {
+        let output = ErrorFormatter::format(&ChefError::Parse(ParseError::LoopVerbMismatch {
+            verb: "Shake".to_string(),
+            found: "shaked".to_string(),
+            expected: "shaken".to_string(),
+        }));
+        assert!(output.contains("until shaked"));
+        assert!(output.contains("\"until shaken\""));
+    }
 }
@@ -0,0 +1,261 @@
+//! Generators that write Chef recipes from other notations.
+//!
+//! Generators build the method as [`Instruction`]s and render it with their
+//! `Display` form, so every generated recipe reads back through
+//! [`Parser::parse_recipe`](crate::Parser::parse_recipe) into the same
+//! instructions.
+
+pub mod stack;
+pub mod text;
+
+pub use stack::stack_to_recipe;
+pub use text::{text_to_recipe, TextOptions};
+
+use std::collections::HashSet;
+
+use crate::instruction::Instruction;
+use crate::types::Measure;
+
+/// Food-themed ingredient names handed out by [`IngredientNamer`]. None of
+/// them is a measure word or modifier, or contains a word the instruction
+/// patterns look for ("into", "to", "from", "the"...).
+pub const FOODS: &[&str] = &[
+    "flour",
+    "sugar",
+    "butter",
+    "eggs",
+    "milk",
+    "cream",
+    "salt",
+    "honey",
+    "cocoa",
+    "vanilla",
+    "cinnamon",
+    "nutmeg",
+    "yeast",
+    "oats",
+    "raisins",
+    "almonds",
+    "walnuts",
+    "hazelnuts",
+    "apples",
+    "pears",
+    "lemons",
+    "oranges",
+    "cherries",
+    "strawberries",
+    "blueberries",
+    "bananas",
+    "carrots",
+    "potatoes",
+    "onions",
+    "garlic",
+    "ginger",
+    "rice",
+    "beans",
+    "lentils",
+    "cheese",
+    "yoghurt",
+    "chocolate",
+    "caramel",
+    "syrup",
+    "jam",
+    "marzipan",
+    "coconut",
+    "pepper",
+    "basil",
+    "thyme",
+    "rosemary",
+    "parsley",
+    "mint",
+    "saffron",
+    "pistachios",
+];
+
+/// Words combined with [`FOODS`] once the plain names run out.
+const ADJECTIVES: &[&str] = &[
+    "brown", "white", "fresh", "dried", "sweet", "toasted", "ground", "candied", "golden",
+    "roasted",
+];
+
+/// Loop verbs, one per nesting depth. They are not instruction keywords and
+/// take a regular past participle, so "Whisk ... until whisked" round-trips.
+pub const LOOP_VERBS: &[&str] = &[
+    "Whisk", "Knead", "Sift", "Bake", "Heat", "Melt", "Boil", "Roast",
+];
+
+/// Hands out unique, food-themed ingredient names for one recipe.
+#[derive(Debug, Default)]
+pub struct IngredientNamer {
+    used: HashSet<String>,
+    next: usize,
+}
+
+impl IngredientNamer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Returns the next unused name: the plain foods first, then
+    /// "adjective food" pairs, then numbered ones.
+    pub fn fresh(&mut self) -> String {
+        loop {
+            let candidate = Self::nth_name(self.next);
+            self.next += 1;
+            if self.used.insert(candidate.clone()) {
+                return candidate;
+            }
+        }
+    }
+
+    fn nth_name(n: usize) -> String {
+        let foods = FOODS.len();
+        if n < foods {
+            return FOODS[n].to_string();
+        }
+        let n = n - foods;
+        if n < foods * ADJECTIVES.len() {
+            return format!("{} {}", ADJECTIVES[n / foods], FOODS[n % foods]);
+        }
+        let n = n - foods * ADJECTIVES.len();
+        format!("{} batch {}", FOODS[n % foods], n / foods + 2)
+    }
+}
+
+/// One line of a generated ingredient list.
+#[derive(Clone, Debug)]
+pub struct IngredientLine {
+    pub name: String,
+    /// `None` declares the ingredient without an initial value.
+    pub amount: Option<i64>,
+    pub measure: Measure,
+}
+
+impl IngredientLine {
+    fn render(&self) -> String {
+        let measure = match self.measure {
+            Measure::Dry => "g ",
+            Measure::Liquid => "ml ",
+            Measure::Unspecified => "",
+        };
+        match self.amount {
+            Some(amount) => format!("{} {}{}", amount, measure, self.name),
+            None => format!("{}{}", measure, self.name),
+        }
+    }
+}
+
+/// A generated recipe, kept in declaration order until it is rendered.
+#[derive(Clone, Debug, Default)]
+pub struct RecipeDraft {
+    /// Title without the closing period.
+    pub title: String,
+    pub description: Option<String>,
+    pub ingredients: Vec<IngredientLine>,
+    pub method: Vec<Instruction>,
+}
+
+/// Renders a main recipe followed by its auxiliary recipes as Chef source.
+/// Every recipe but the last ends with "Serves 1." (the parser starts an
+/// auxiliary recipe after a `Serves` line), so `main` should pour what it
+/// serves into the first baking dish.
+pub fn render_recipes(main: &RecipeDraft, auxiliaries: &[RecipeDraft]) -> String {
+    let mut out = String::new();
+    let count = auxiliaries.len() + 1;
+    for (idx, draft) in std::iter::once(main).chain(auxiliaries).enumerate() {
+        if idx > 0 {
+            out.push('\n');
+        }
+        out.push_str(&draft.title);
+        out.push_str(".\n\n");
+        if let Some(description) = &draft.description {
+            out.push_str(description);
+            out.push_str("\n\n");
+        }
+        if !draft.ingredients.is_empty() {
+            out.push_str("Ingredients.\n");
+            for line in &draft.ingredients {
+                out.push_str(&line.render());
+                out.push('\n');
+            }
+            out.push('\n');
+        }
+        out.push_str("Method.\n");
+        render_method(&draft.method, 0, &mut out);
+        if idx == 0 || idx + 1 < count {
+            out.push_str("\nServes 1.\n");
+        }
+    }
+    out
+}
+
+/// One sentence per line; loop bodies are indented for readability (the
+/// parser ignores the whitespace).
+fn render_method(method: &[Instruction], depth: usize, out: &mut String) {
+    let indent = "  ".repeat(depth);
+    for instruction in method {
+        match instruction {
+            Instruction::Loop {
+                condition_var,
+                verb,
+                body,
+                decrement_var,
+            } => {
+                out.push_str(&format!(
+                    "{}{}.\n",
+                    indent,
+                    Instruction::loop_opening(verb, condition_var)
+                ));
+                render_method(body, depth + 1, out);
+                out.push_str(&format!(
+                    "{}{}.\n",
+                    indent,
+                    Instruction::loop_closing(verb, decrement_var.as_deref())
+                ));
+            }
+            other => out.push_str(&format!("{}{}.\n", indent, other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn namer_never_repeats_a_name() {
+        let mut namer = IngredientNamer::new();
+        let names: Vec<String> = (0..1_000).map(|_| namer.fresh()).collect();
+        let unique: HashSet<&String> = names.iter().collect();
+        assert_eq!(unique.len(), names.len());
+        assert_eq!(names[0], "flour");
+    }
+
+    #[test]
+    fn rendered_recipes_parse_back() {
+        let main = RecipeDraft {
+            title: "Test Pie".to_string(),
+            description: Some("A small test.".to_string()),
+            ingredients: vec![IngredientLine {
+                name: "flour".to_string(),
+                amount: Some(3),
+                measure: Measure::Dry,
+            }],
+            method: vec![
+                Instruction::Put("flour".to_string(), 0),
+                Instruction::ServeWith("Sauce".to_string()),
+                Instruction::Pour(0, 0),
+            ],
+        };
+        let sauce = RecipeDraft {
+            title: "Sauce".to_string(),
+            method: vec![Instruction::Clean(0)],
+            ..RecipeDraft::default()
+        };
+        let source = render_recipes(&main, &[sauce]);
+        let recipe = crate::Parser::new(&source).parse_recipe().unwrap();
+        // The three method steps plus the closing "Serves 1."
+        assert_eq!(recipe.instructions.len(), 4);
+        assert_eq!(recipe.auxiliary_recipes.len(), 1);
+    }
+}
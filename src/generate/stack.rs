@@ -0,0 +1,509 @@
+//! `chefgen`: writes a Chef recipe from a tiny stack language.
+//!
+//! A program is a sequence of whitespace-separated operations; `#` starts a
+//! comment that runs to the end of the line.
+//!
+//! | Operation          | Effect                                                 |
+//! | ------------------ | ------------------------------------------------------ |
+//! | `push N`           | push the integer `N`                                   |
+//! | `pop`              | discard the top value                                  |
+//! | `dup`, `swap`      | duplicate the top value / swap the top two             |
+//! | `add` `sub` `mul` `div` | pop `b`, pop `a`, push `a op b` (`div` truncates) |
+//! | `print-char`       | pop and print as a Unicode character                   |
+//! | `print-num`        | pop and print as a number                              |
+//! | `read`             | push the next number from the input                    |
+//! | `loop` ... `end`   | repeat while the top value is non-zero (not popped)    |
+//! | `def NAME` ... `end` | define a procedure (top level only)                  |
+//! | `call NAME`        | run a procedure                                        |
+//!
+//! The stack is the first mixing bowl. Printed values are collected at the
+//! bottom of the second mixing bowl and served when the program ends, since
+//! Chef only prints from baking dishes. Procedures become auxiliary recipes
+//! and follow Chef's `Serve with` rules: the procedure runs on a copy of the
+//! stack, and whatever its stack holds at the end is pushed on top of the
+//! caller's. For the same reason a procedure cannot print.
+
+use std::collections::HashMap;
+
+use crate::generate::{render_recipes, IngredientLine, IngredientNamer, RecipeDraft, LOOP_VERBS};
+use crate::instruction::Instruction;
+use crate::types::{GenerateError, Measure};
+
+const STACK: usize = 0;
+const OUTPUT: usize = 1;
+
+#[derive(Clone, Debug)]
+enum Op {
+    Push(i64),
+    Pop,
+    Dup,
+    Swap,
+    Add,
+    Sub,
+    Mul,
+    Div,
+    PrintChar,
+    PrintNum,
+    Read,
+    Loop(Vec<Op>),
+    Call { name: String, line: usize },
+}
+
+struct Procedure {
+    name: String,
+    body: Vec<Op>,
+}
+
+/// Translates a stack program into Chef source titled `title`.
+pub fn stack_to_recipe(program: &str, title: &str) -> Result<String, GenerateError> {
+    let (main, procedures) = parse_program(program)?;
+    check_calls(&main, &procedures)?;
+    for procedure in &procedures {
+        check_calls(&procedure.body, &procedures)?;
+    }
+
+    let prints = contains_print(&main);
+    let mut builder = RecipeBuilder::default();
+    let mut method = Vec::new();
+    builder.emit(&main, 0, &mut method);
+    if prints {
+        method.push(Instruction::Pour(OUTPUT, 0));
+    }
+    let main_draft = RecipeDraft {
+        title: title.trim().trim_end_matches('.').to_string(),
+        description: Some(
+            "Generated by chefgen from a stack program. The first mixing bowl is the stack; \
+             the second collects everything printed, in order."
+                .to_string(),
+        ),
+        ingredients: builder.ingredients,
+        method,
+    };
+
+    let auxiliaries = procedures
+        .iter()
+        .map(|procedure| {
+            let mut builder = RecipeBuilder::default();
+            let mut method = Vec::new();
+            builder.emit(&procedure.body, 0, &mut method);
+            RecipeDraft {
+                title: procedure_title(&procedure.name),
+                description: None,
+                ingredients: builder.ingredients,
+                method,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    Ok(render_recipes(&main_draft, &auxiliaries))
+}
+
+enum Block {
+    Loop {
+        line: usize,
+        ops: Vec<Op>,
+    },
+    Def {
+        line: usize,
+        name: String,
+        ops: Vec<Op>,
+    },
+}
+
+fn parse_program(program: &str) -> Result<(Vec<Op>, Vec<Procedure>), GenerateError> {
+    let mut main = Vec::new();
+    let mut procedures: Vec<Procedure> = Vec::new();
+    let mut blocks: Vec<Block> = Vec::new();
+
+    for (idx, raw_line) in program.lines().enumerate() {
+        let line = idx + 1;
+        let code = raw_line.split('#').next().unwrap_or("");
+        let mut words = code.split_whitespace();
+
+        while let Some(word) = words.next() {
+            let in_def = blocks
+                .iter()
+                .any(|block| matches!(block, Block::Def { .. }));
+            let op = match word.to_lowercase().as_str() {
+                "push" => {
+                    let text = words.next().ok_or(GenerateError::MissingArgument {
+                        line,
+                        op: word.to_string(),
+                        expected: "a number",
+                    })?;
+                    let value = text.parse().map_err(|_| GenerateError::InvalidNumber {
+                        line,
+                        text: text.to_string(),
+                    })?;
+                    Op::Push(value)
+                }
+                "pop" => Op::Pop,
+                "dup" => Op::Dup,
+                "swap" => Op::Swap,
+                "add" => Op::Add,
+                "sub" => Op::Sub,
+                "mul" => Op::Mul,
+                "div" => Op::Div,
+                "print-char" | "print-num" if in_def => {
+                    return Err(GenerateError::Misplaced {
+                        line,
+                        reason: format!(
+                            "'{}' cannot be used inside 'def' (procedures work on copies of the bowls)",
+                            word
+                        ),
+                    });
+                }
+                "print-char" => Op::PrintChar,
+                "print-num" => Op::PrintNum,
+                "read" => Op::Read,
+                "call" => Op::Call {
+                    name: procedure_name(&mut words, line, word)?,
+                    line,
+                },
+                "loop" => {
+                    blocks.push(Block::Loop {
+                        line,
+                        ops: Vec::new(),
+                    });
+                    continue;
+                }
+                "def" => {
+                    if !blocks.is_empty() {
+                        return Err(GenerateError::Misplaced {
+                            line,
+                            reason: "'def' is only allowed at the top level".to_string(),
+                        });
+                    }
+                    let name = procedure_name(&mut words, line, word)?;
+                    blocks.push(Block::Def {
+                        line,
+                        name,
+                        ops: Vec::new(),
+                    });
+                    continue;
+                }
+                "end" => match blocks.pop() {
+                    None => return Err(GenerateError::UnmatchedEnd { line }),
+                    Some(Block::Loop { ops, .. }) => Op::Loop(ops),
+                    Some(Block::Def { name, ops, .. }) => {
+                        let key = name.to_lowercase();
+                        if procedures.iter().any(|p| p.name.to_lowercase() == key) {
+                            return Err(GenerateError::DuplicateProcedure { line, name });
+                        }
+                        procedures.push(Procedure { name, body: ops });
+                        continue;
+                    }
+                },
+                _ => {
+                    return Err(GenerateError::UnknownOperation {
+                        line,
+                        op: word.to_string(),
+                    })
+                }
+            };
+
+            match blocks.last_mut() {
+                Some(Block::Loop { ops, .. }) | Some(Block::Def { ops, .. }) => ops.push(op),
+                None => main.push(op),
+            }
+        }
+    }
+
+    match blocks.pop() {
+        Some(Block::Loop { line, .. }) => Err(GenerateError::UnclosedBlock {
+            line,
+            op: "loop".to_string(),
+        }),
+        Some(Block::Def { line, .. }) => Err(GenerateError::UnclosedBlock {
+            line,
+            op: "def".to_string(),
+        }),
+        None => Ok((main, procedures)),
+    }
+}
+
+fn procedure_name<'a>(
+    words: &mut impl Iterator<Item = &'a str>,
+    line: usize,
+    op: &str,
+) -> Result<String, GenerateError> {
+    let missing = || GenerateError::MissingArgument {
+        line,
+        op: op.to_string(),
+        expected: "a procedure name",
+    };
+    let name = words.next().ok_or_else(missing)?;
+    let mut chars = name.chars();
+    let valid = matches!(chars.next(), Some(c) if c.is_ascii_alphabetic())
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(name.to_string())
+    } else {
+        Err(missing())
+    }
+}
+
+fn check_calls(ops: &[Op], procedures: &[Procedure]) -> Result<(), GenerateError> {
+    for op in ops {
+        match op {
+            Op::Call { name, line } => {
+                let key = name.to_lowercase();
+                if !procedures.iter().any(|p| p.name.to_lowercase() == key) {
+                    return Err(GenerateError::UnknownProcedure {
+                        line: *line,
+                        name: name.clone(),
+                    });
+                }
+            }
+            Op::Loop(body) => check_calls(body, procedures)?,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn contains_print(ops: &[Op]) -> bool {
+    ops.iter().any(|op| match op {
+        Op::PrintChar | Op::PrintNum => true,
+        Op::Loop(body) => contains_print(body),
+        _ => false,
+    })
+}
+
+/// "double-it" -> "Double It".
+fn procedure_title(name: &str) -> String {
+    name.split(['-', '_'])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect::<String>(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Names and declares the ingredients one recipe needs while its method is
+/// emitted.
+#[derive(Default)]
+struct RecipeBuilder {
+    namer: IngredientNamer,
+    ingredients: Vec<IngredientLine>,
+    constants: HashMap<i64, String>,
+    scratch: Vec<String>,
+    input: Option<String>,
+    counter: Option<String>,
+}
+
+impl RecipeBuilder {
+    fn declare(&mut self, amount: Option<i64>, measure: Measure) -> String {
+        let name = self.namer.fresh();
+        self.ingredients.push(IngredientLine {
+            name: name.clone(),
+            amount,
+            measure,
+        });
+        name
+    }
+
+    fn constant(&mut self, value: i64) -> String {
+        if let Some(name) = self.constants.get(&value) {
+            return name.clone();
+        }
+        let name = self.declare(Some(value), Measure::Dry);
+        self.constants.insert(value, name.clone());
+        name
+    }
+
+    /// Working ingredient `n` (0 or 1), used to move values off the stack.
+    fn scratch(&mut self, n: usize) -> String {
+        while self.scratch.len() <= n {
+            let name = self.declare(None, Measure::Unspecified);
+            self.scratch.push(name);
+        }
+        self.scratch[n].clone()
+    }
+
+    fn input(&mut self) -> String {
+        if self.input.is_none() {
+            self.input = Some(self.declare(None, Measure::Unspecified));
+        }
+        self.input.clone().unwrap_or_default()
+    }
+
+    /// How many values the output bowl holds, so new ones can be stirred to
+    /// its bottom.
+    fn counter(&mut self) -> String {
+        if self.counter.is_none() {
+            self.counter = Some(self.declare(Some(0), Measure::Dry));
+        }
+        self.counter.clone().unwrap_or_default()
+    }
+
+    fn emit(&mut self, ops: &[Op], depth: usize, method: &mut Vec<Instruction>) {
+        for op in ops {
+            match op {
+                Op::Push(value) => {
+                    let name = self.constant(*value);
+                    method.push(Instruction::Put(name, STACK));
+                }
+                Op::Pop => method.push(Instruction::Fold(self.scratch(0), STACK)),
+                Op::Dup => {
+                    let a = self.scratch(0);
+                    method.push(Instruction::Fold(a.clone(), STACK));
+                    method.push(Instruction::Put(a.clone(), STACK));
+                    method.push(Instruction::Put(a, STACK));
+                }
+                Op::Swap => {
+                    let a = self.scratch(0);
+                    let b = self.scratch(1);
+                    method.push(Instruction::Fold(a.clone(), STACK));
+                    method.push(Instruction::Fold(b.clone(), STACK));
+                    method.push(Instruction::Put(a, STACK));
+                    method.push(Instruction::Put(b, STACK));
+                }
+                Op::Add | Op::Sub | Op::Mul | Op::Div => {
+                    let b = self.scratch(0);
+                    method.push(Instruction::Fold(b.clone(), STACK));
+                    method.push(match op {
+                        Op::Add => Instruction::Add(b, STACK),
+                        Op::Sub => Instruction::Remove(b, STACK),
+                        Op::Mul => Instruction::Combine(b, STACK),
+                        _ => Instruction::Divide(b, STACK),
+                    });
+                }
+                Op::PrintChar | Op::PrintNum => {
+                    let a = self.scratch(0);
+                    method.push(Instruction::Fold(a.clone(), STACK));
+                    if matches!(op, Op::PrintChar) {
+                        method.push(Instruction::Liquefy(a.clone()));
+                    }
+                    method.push(Instruction::Put(a, OUTPUT));
+                    let counter = self.counter();
+                    let one = self.constant(1);
+                    method.push(Instruction::StirIngredient(counter.clone(), OUTPUT));
+                    method.push(Instruction::Put(counter.clone(), STACK));
+                    method.push(Instruction::Add(one, STACK));
+                    method.push(Instruction::Fold(counter, STACK));
+                }
+                Op::Read => {
+                    let input = self.input();
+                    method.push(Instruction::Take(input.clone()));
+                    method.push(Instruction::Put(input, STACK));
+                }
+                Op::Loop(body) => {
+                    // The condition ingredient copies the top value before
+                    // the loop and again at the end of every pass.
+                    let condition = self.declare(None, Measure::Unspecified);
+                    let peek = [
+                        Instruction::Fold(condition.clone(), STACK),
+                        Instruction::Put(condition.clone(), STACK),
+                    ];
+                    method.extend(peek.iter().cloned());
+                    let mut inner = Vec::new();
+                    self.emit(body, depth + 1, &mut inner);
+                    inner.extend(peek);
+                    method.push(Instruction::Loop {
+                        condition_var: condition,
+                        verb: LOOP_VERBS[depth % LOOP_VERBS.len()].to_string(),
+                        body: inner,
+                        decrement_var: None,
+                    });
+                }
+                Op::Call { name, .. } => {
+                    method.push(Instruction::ServeWith(procedure_title(name)));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interpreter, Parser};
+
+    fn run(program: &str, input: &str) -> String {
+        let source = stack_to_recipe(program, "Test Program").expect("program compiles");
+        let recipe = Parser::new(&source)
+            .parse_recipe()
+            .unwrap_or_else(|e| panic!("generated recipe must parse: {}\n{}", e, source));
+        let mut interpreter = Interpreter::new();
+        interpreter.set_input_text(input);
+        interpreter.add_recipe(recipe);
+        interpreter
+            .run()
+            .unwrap_or_else(|e| panic!("generated recipe must run: {}\n{}", e, source));
+        interpreter.output().to_string()
+    }
+
+    #[test]
+    fn prints_characters_in_program_order() {
+        assert_eq!(run("push 72 print-char push 105 print-char", ""), "Hi");
+    }
+
+    #[test]
+    fn arithmetic_pops_b_then_a() {
+        let program = "push 6 push 7 mul print-num\n\
+                       push 10 push 3 sub print-num\n\
+                       push 7 push 2 div print-num\n\
+                       push 1 push -4 add print-num";
+        assert_eq!(run(program, ""), "4273-3");
+    }
+
+    #[test]
+    fn loop_repeats_while_top_is_non_zero() {
+        let program = "push 5\nloop   # count down\n  dup print-num push 1 sub\nend";
+        assert_eq!(run(program, ""), "54321");
+    }
+
+    #[test]
+    fn nested_loops_use_their_own_condition() {
+        let program = "push 2 loop push 2 loop dup print-num push 1 sub end pop push 1 sub end";
+        assert_eq!(run(program, ""), "2121");
+    }
+
+    #[test]
+    fn read_and_swap() {
+        assert_eq!(run("read read swap sub print-num", "3 10"), "7");
+    }
+
+    #[test]
+    fn call_pushes_the_procedure_stack_on_top() {
+        // The procedure sees a copy of [1] and leaves [1, 7]; that lands on
+        // top of the caller's [1].
+        let program = "def seven push 7 end\npush 1 call seven add print-num print-num";
+        assert_eq!(run(program, ""), "81");
+    }
+
+    #[test]
+    fn reports_errors_with_line_numbers() {
+        let cases = [
+            (
+                "push 1\nfrobnicate",
+                "line 2: unknown operation 'frobnicate'",
+            ),
+            ("push", "line 1: 'push' needs a number"),
+            ("push x", "line 1: 'x' is not a valid number"),
+            ("end", "line 1: 'end' without a matching 'loop' or 'def'"),
+            ("push 1\nloop", "line 2: 'loop' is never closed with 'end'"),
+            ("call nothing", "line 1: procedure 'nothing' is not defined"),
+            (
+                "def a end\ndef a end",
+                "line 2: procedure 'a' is defined twice",
+            ),
+        ];
+        for (program, expected) in cases {
+            let error = stack_to_recipe(program, "Bad").expect_err(program);
+            assert_eq!(error.to_string(), expected);
+        }
+    }
+
+    #[test]
+    fn procedures_cannot_print() {
+        let error = stack_to_recipe("def shout push 1 print-num end", "Bad").unwrap_err();
+        assert!(matches!(error, GenerateError::Misplaced { line: 1, .. }));
+    }
+}
@@ -0,0 +1,145 @@
+//! Writes a recipe that serves a given text, in the style of
+//! `hello-world.chef`.
+//!
+//! Every distinct character becomes one liquid ingredient holding its code
+//! point, so repeated characters share an ingredient. The method puts the
+//! characters into the mixing bowl last-to-first, pours the bowl into the
+//! baking dish and serves it.
+
+use std::collections::HashMap;
+
+use crate::generate::{render_recipes, IngredientLine, IngredientNamer, RecipeDraft};
+use crate::instruction::Instruction;
+use crate::types::Measure;
+
+/// How [`text_to_recipe`] names and titles the recipe.
+#[derive(Clone, Debug)]
+pub struct TextOptions {
+    /// Recipe title, without the closing period.
+    pub title: String,
+    /// Name ingredients after foods ("flour", "sugar"...) instead of after
+    /// the character they hold ("letter H", "space").
+    pub food_names: bool,
+}
+
+impl Default for TextOptions {
+    fn default() -> Self {
+        Self {
+            title: "Message Souffle".to_string(),
+            food_names: false,
+        }
+    }
+}
+
+/// Returns Chef source that serves exactly `text`.
+pub fn text_to_recipe(text: &str, options: &TextOptions) -> String {
+    let mut namer = IngredientNamer::new();
+    let mut ingredients = Vec::new();
+    let mut names: HashMap<char, String> = HashMap::new();
+
+    for ch in text.chars() {
+        if names.contains_key(&ch) {
+            continue;
+        }
+        let name = if options.food_names {
+            namer.fresh()
+        } else {
+            character_name(ch)
+        };
+        ingredients.push(IngredientLine {
+            name: name.clone(),
+            amount: Some(ch as i64),
+            measure: Measure::Liquid,
+        });
+        names.insert(ch, name);
+    }
+
+    // The dish is served top first, so the first character goes in last.
+    let mut method: Vec<Instruction> = text
+        .chars()
+        .rev()
+        .map(|ch| Instruction::Put(names[&ch].clone(), 0))
+        .collect();
+    method.push(Instruction::Pour(0, 0));
+
+    let count = text.chars().count();
+    let draft = RecipeDraft {
+        title: options.title.trim().trim_end_matches('.').to_string(),
+        description: Some(format!(
+            "This recipe serves a message of {} character{}, one liquid ingredient per distinct character.",
+            count,
+            if count == 1 { "" } else { "s" }
+        )),
+        ingredients,
+        method,
+    };
+    render_recipes(&draft, &[])
+}
+
+/// A readable ingredient name for `ch` that the parser accepts as one.
+fn character_name(ch: char) -> String {
+    match ch {
+        'A'..='Z' | 'a'..='z' => format!("letter {}", ch),
+        '0'..='9' => format!("digit {}", ch),
+        ' ' => "space".to_string(),
+        '\n' => "newline".to_string(),
+        '\t' => "tab".to_string(),
+        _ => format!("character {}", ch as u32),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interpreter, Parser};
+
+    fn serve(source: &str) -> String {
+        let recipe = Parser::new(source)
+            .parse_recipe()
+            .unwrap_or_else(|e| panic!("generated recipe must parse: {}\n{}", e, source));
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(recipe);
+        interpreter.run().expect("generated recipe runs");
+        interpreter.output().to_string()
+    }
+
+    #[test]
+    fn serves_the_exact_text() {
+        for text in [
+            "Hello world!",
+            "",
+            "a",
+            "Line one.\nServes 2.\nMethod. Ingredients.",
+            "naïve café — 🍝 spaghetti",
+        ] {
+            for food_names in [false, true] {
+                let options = TextOptions {
+                    food_names,
+                    ..TextOptions::default()
+                };
+                assert_eq!(serve(&text_to_recipe(text, &options)), text);
+            }
+        }
+    }
+
+    #[test]
+    fn repeated_characters_share_an_ingredient() {
+        let source = text_to_recipe("abba", &TextOptions::default());
+        let recipe = Parser::new(&source).parse_recipe().unwrap();
+        assert_eq!(recipe.ingredients.len(), 2);
+        assert!(source.contains("97 ml letter a\n"));
+        assert!(source.contains("98 ml letter b\n"));
+    }
+
+    #[test]
+    fn food_names_come_from_the_vocabulary() {
+        let options = TextOptions {
+            title: "Hi".to_string(),
+            food_names: true,
+        };
+        let source = text_to_recipe("Hi", &options);
+        assert!(source.starts_with("Hi.\n"));
+        assert!(source.contains("72 ml flour\n"));
+        assert!(source.contains("105 ml sugar\n"));
+    }
+}
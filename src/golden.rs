@@ -0,0 +1,422 @@
+//! Golden-output tests for collections of recipes, behind `cheffers test`.
+//!
+//! Every `NAME.chef` under the given paths is run and its result compared
+//! with the files next to it:
+//!
+//! - `NAME.in`: the numbers `Take` reads (no file means no input);
+//! - `NAME.out`: everything the recipe serves, byte for byte;
+//! - `NAME.err`: the error that stops the recipe, on one line.
+//!
+//! A recipe with neither `NAME.out` nor `NAME.err` fails until its current
+//! result is recorded with [`GoldenOptions::bless`].
+//!
+//! With [`GoldenOptions::doc`] the recipes check themselves instead: each
+//! `input:`/`expect:` annotation in a recipe's comments (see
+//! [`Example`](crate::types::Example)) is run, and files without any are
+//! skipped.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::interpreter::{Interpreter, MAX_CALL_DEPTH, MAX_LOOP_ITERATIONS};
+use crate::parser::Parser;
+use crate::types::{ChefError, Recipe};
+
+/// How each recipe is run, and whether expectations are checked or written.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GoldenOptions {
+    /// The seed for `Mix [the bowl] well`, so shuffles are reproducible.
+    pub seed: u64,
+    pub max_loop_iterations: usize,
+    pub max_call_depth: usize,
+    /// Write each recipe's current result as its expectation instead of
+    /// checking it.
+    pub bless: bool,
+    /// Run the examples in recipe comments instead of comparing with
+    /// expectation files.
+    pub doc: bool,
+}
+
+impl Default for GoldenOptions {
+    fn default() -> Self {
+        Self {
+            seed: 1,
+            max_loop_iterations: MAX_LOOP_ITERATIONS,
+            max_call_depth: MAX_CALL_DEPTH,
+            bless: false,
+            doc: false,
+        }
+    }
+}
+
+/// What happened to one recipe.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Outcome {
+    Passed,
+    /// The recipe did not do what its expectation files say; `report`
+    /// explains how, with a line diff of the output.
+    Failed {
+        report: String,
+    },
+    /// [`GoldenOptions::bless`] changed the expectation files.
+    Blessed,
+}
+
+/// The outcome of one `.chef` file.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TestCase {
+    pub path: PathBuf,
+    pub outcome: Outcome,
+}
+
+/// Every `.chef` file among `paths`, searching directories recursively, in
+/// path order.
+pub fn discover(paths: &[PathBuf]) -> io::Result<Vec<PathBuf>> {
+    let mut found = Vec::new();
+    for path in paths {
+        if path.is_dir() {
+            let mut children = Vec::new();
+            for entry in fs::read_dir(path)? {
+                let child = entry?.path();
+                if child.is_dir() || is_recipe(&child) {
+                    children.push(child);
+                }
+            }
+            found.extend(discover(&children)?);
+        } else {
+            found.push(path.clone());
+        }
+    }
+    found.sort();
+    Ok(found)
+}
+
+fn is_recipe(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "chef")
+}
+
+/// Runs (or blesses) every recipe found under `paths`.
+pub fn run_all(paths: &[PathBuf], options: &GoldenOptions) -> io::Result<Vec<TestCase>> {
+    let mut cases = Vec::new();
+    for path in discover(paths)? {
+        let outcome = if options.doc {
+            match run_examples(&path, options)? {
+                Some(outcome) => outcome,
+                None => continue,
+            }
+        } else {
+            run_one(&path, options)?
+        };
+        cases.push(TestCase { path, outcome });
+    }
+    Ok(cases)
+}
+
+/// Runs the examples in the comments of every recipe in the file at
+/// `path`, or returns `None` if there are none.
+pub fn run_examples(path: &Path, options: &GoldenOptions) -> io::Result<Option<Outcome>> {
+    let source = fs::read_to_string(path)?;
+    let main = match Parser::new(&source).parse_recipe() {
+        Ok(recipe) => recipe,
+        Err(error) => {
+            // Annotations cannot be found in a recipe that does not parse;
+            // only report it if it looks like it had some.
+            let annotated = source
+                .lines()
+                .any(|line| line.trim_start().to_lowercase().starts_with("expect:"));
+            return Ok(annotated.then(|| Outcome::Failed {
+                report: error.to_string(),
+            }));
+        }
+    };
+
+    let mut recipes: Vec<&Recipe> = std::iter::once(&main)
+        .chain(main.auxiliary_recipes.values())
+        .filter(|recipe| !recipe.examples.is_empty())
+        .collect();
+    if recipes.is_empty() {
+        return Ok(None);
+    }
+    recipes.sort_by_key(|recipe| recipe.examples[0].line);
+
+    let mut report = String::new();
+    for recipe in recipes {
+        for example in &recipe.examples {
+            let mut interpreter = interpreter(options);
+            interpreter.set_input_text(&example.input);
+            interpreter.add_recipe(main.clone());
+            let result = interpreter.run_recipe(&recipe.title);
+            let output = interpreter.output();
+            match result {
+                Ok(()) if output == example.expect => {}
+                Ok(()) => report.push_str(&format!(
+                    "line {}: {} expected {:?}, served {:?}\n",
+                    example.line, recipe.title, example.expect, output
+                )),
+                Err(error) => report.push_str(&format!(
+                    "line {}: {} expected {:?}, failed: {}\n",
+                    example.line, recipe.title, example.expect, error
+                )),
+            }
+        }
+    }
+    Ok(Some(if report.is_empty() {
+        Outcome::Passed
+    } else {
+        Outcome::Failed { report }
+    }))
+}
+
+/// Runs the recipe at `path` and checks, or with `bless` records, its
+/// expectations.
+pub fn run_one(path: &Path, options: &GoldenOptions) -> io::Result<Outcome> {
+    let source = fs::read_to_string(path)?;
+    let input = read_optional(&path.with_extension("in"))?;
+    let (output, error) = run_recipe(&source, input.as_deref().unwrap_or(""), options);
+
+    let out_path = path.with_extension("out");
+    let err_path = path.with_extension("err");
+    let expected_output = read_optional(&out_path)?;
+    let expected_error = read_optional(&err_path)?;
+
+    if options.bless {
+        let mut changed = false;
+        // An error with no output before it needs no `.out` file.
+        let output = (error.is_none() || !output.is_empty()).then_some(output);
+        changed |= write_optional(&out_path, expected_output, output)?;
+        let error = error.map(|error| format!("{}\n", error));
+        changed |= write_optional(&err_path, expected_error, error)?;
+        return Ok(if changed {
+            Outcome::Blessed
+        } else {
+            Outcome::Passed
+        });
+    }
+
+    if expected_output.is_none() && expected_error.is_none() {
+        return Ok(Outcome::Failed {
+            report: format!(
+                "no {} or {}; run with --bless to record the current result",
+                file_name(&out_path),
+                file_name(&err_path)
+            ),
+        });
+    }
+
+    let mut report = String::new();
+    let expected_output = expected_output.unwrap_or_default();
+    if expected_output != output {
+        report.push_str("output differs (-expected +actual):\n");
+        report.push_str(&diff(&expected_output, &output));
+    }
+    let expected_error = expected_error.map(|error| error.trim_end().to_string());
+    if expected_error != error {
+        let describe = |error: &Option<String>| match error {
+            Some(error) => format!("'{}'", error),
+            None => "no error".to_string(),
+        };
+        report.push_str(&format!(
+            "expected {}, got {}\n",
+            describe(&expected_error),
+            describe(&error)
+        ));
+    }
+
+    Ok(if report.is_empty() {
+        Outcome::Passed
+    } else {
+        Outcome::Failed { report }
+    })
+}
+
+/// What the recipe served, and the error that stopped it.
+fn run_recipe(source: &str, input: &str, options: &GoldenOptions) -> (String, Option<String>) {
+    let recipe = match Parser::new(source).parse_recipe() {
+        Ok(recipe) => recipe,
+        Err(error) => return (String::new(), Some(error.to_string())),
+    };
+    let mut interpreter = interpreter(options);
+    interpreter.set_input_text(input);
+    interpreter.add_recipe(recipe);
+    let error = interpreter
+        .run()
+        .err()
+        .map(|error: ChefError| error.to_string());
+    (interpreter.output().to_string(), error)
+}
+
+/// An interpreter with the seed and limits of `options`.
+fn interpreter(options: &GoldenOptions) -> Interpreter {
+    let mut interpreter = Interpreter::new();
+    interpreter.set_mix_seed(options.seed);
+    interpreter.set_loop_limit(options.max_loop_iterations);
+    interpreter.set_call_depth_limit(options.max_call_depth);
+    interpreter
+}
+
+fn read_optional(path: &Path) -> io::Result<Option<String>> {
+    match fs::read_to_string(path) {
+        Ok(text) => Ok(Some(text)),
+        Err(error) if error.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(error) => Err(error),
+    }
+}
+
+/// Makes the file at `path` hold `wanted`, or not exist for `None`.
+/// Returns whether anything changed.
+fn write_optional(
+    path: &Path,
+    current: Option<String>,
+    wanted: Option<String>,
+) -> io::Result<bool> {
+    if current == wanted {
+        return Ok(false);
+    }
+    match wanted {
+        Some(text) => fs::write(path, text)?,
+        None => fs::remove_file(path)?,
+    }
+    Ok(true)
+}
+
+fn file_name(path: &Path) -> String {
+    path.file_name().map_or_else(
+        || path.display().to_string(),
+        |name| name.to_string_lossy().into_owned(),
+    )
+}
+
+/// A line diff of `expected` against `actual`: unchanged lines start with
+/// two spaces, removed ones with `-` and added ones with `+`. A last line
+/// without a line break is marked, since that alone can fail a test.
+pub fn diff(expected: &str, actual: &str) -> String {
+    let old: Vec<&str> = expected.split_inclusive('\n').collect();
+    let new: Vec<&str> = actual.split_inclusive('\n').collect();
+
+    // lcs[i][j]: the longest common subsequence of old[i..] and new[j..].
+    let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut text = String::new();
+    let mut line = |marker: char, line: &str| {
+        text.push(marker);
+        text.push(' ');
+        match line.strip_suffix('\n') {
+            Some(line) => {
+                text.push_str(line);
+                text.push('\n');
+            }
+            None => {
+                text.push_str(line);
+                text.push_str("\n\\ no line break at the end\n");
+            }
+        }
+    };
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            line(' ', old[i]);
+            i += 1;
+            j += 1;
+        } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+            line('-', old[i]);
+            i += 1;
+        } else {
+            line('+', new[j]);
+            j += 1;
+        }
+    }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const COUNTDOWN: &str = "Countdown.\n\nIngredients.\n3 g counter\n\nMethod.\n\
+        Count the counter. Put counter into the mixing bowl. \
+        Count the counter until counted. \
+        Pour contents of the mixing bowl into the baking dish.\n\nServes 1.\n";
+
+    #[test]
+    fn doc_mode_runs_comment_examples() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cheffers-doc-{}", std::process::id()));
+        fs::create_dir_all(&dir)?;
+        let doubler = "Doubler.\n\nDoubles a number.\ninput: 21\nexpect: 42\n\
+            input: 5\nexpect: 11\n\nIngredients.\nn\n\nMethod.\n\
+            Take n from refrigerator. Put n into the mixing bowl. \
+            Add n to the mixing bowl. \
+            Pour contents of the mixing bowl into the baking dish.\n\nServes 1.\n";
+        fs::write(dir.join("doubler.chef"), doubler)?;
+        fs::write(dir.join("plain.chef"), COUNTDOWN)?;
+
+        let options = GoldenOptions {
+            doc: true,
+            ..GoldenOptions::default()
+        };
+        let cases = run_all(std::slice::from_ref(&dir), &options)?;
+        assert_eq!(cases.len(), 1, "files without examples are skipped");
+        match &cases[0].outcome {
+            Outcome::Failed { report } => {
+                assert_eq!(report, "line 7: Doubler. expected \"11\", served \"10\"\n")
+            }
+            other => panic!("expected a failure, got {:?}", other),
+        }
+
+        fs::remove_dir_all(&dir)
+    }
+
+    #[test]
+    fn diff_marks_changed_lines() {
+        assert_eq!(diff("a\nb\nc\n", "a\nx\nc\n"), "  a\n- b\n+ x\n  c\n");
+        assert_eq!(diff("1\n", "1"), "- 1\n+ 1\n\\ no line break at the end\n");
+    }
+
+    #[test]
+    fn bless_records_and_later_runs_check() -> io::Result<()> {
+        let dir = std::env::temp_dir().join(format!("cheffers-golden-{}", std::process::id()));
+        fs::create_dir_all(dir.join("nested"))?;
+        let recipe = dir.join("nested").join("countdown.chef");
+        fs::write(&recipe, COUNTDOWN)?;
+        let broken = dir.join("broken.chef");
+        fs::write(&broken, "Not a recipe")?;
+
+        let paths = [dir.clone()];
+        let run = |bless| {
+            let options = GoldenOptions {
+                bless,
+                ..GoldenOptions::default()
+            };
+            run_all(&paths, &options).map(|cases| {
+                cases
+                    .into_iter()
+                    .map(|case| case.outcome)
+                    .collect::<Vec<_>>()
+            })
+        };
+
+        let missing = run(false)?;
+        assert!(matches!(&missing[0], Outcome::Failed { report } if report.contains("--bless")));
+        assert_eq!(run(true)?, vec![Outcome::Blessed, Outcome::Blessed]);
+        assert_eq!(fs::read_to_string(dir.join("nested/countdown.out"))?, "123");
+        assert!(!dir.join("broken.out").exists());
+        assert_eq!(run(false)?, vec![Outcome::Passed, Outcome::Passed]);
+
+        fs::write(dir.join("nested/countdown.out"), "1234")?;
+        match &run(false)?[1] {
+            Outcome::Failed { report } => assert!(report.contains("- 1234\n"), "{}", report),
+            other => panic!("expected a failure, got {:?}", other),
+        }
+
+        fs::remove_dir_all(&dir)
+    }
+}
@@ -0,0 +1,144 @@
+//! The undo log behind reverse debugging.
+//!
+//! While [`Interpreter`](crate::Interpreter) records changes, every mutation
+//! of an ingredient, bowl, dish, the output, the unread input or the `Mix`
+//! generator is logged with what it replaced, so undoing the log backwards
+//! restores the state before it. Pushes and pops are logged one value at a
+//! time, which also lets [`Debugger::origin`](crate::debugger::Debugger::origin)
+//! follow a value back to the instruction that put it where it is.
+
+use std::cmp::Ordering;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use crate::types::{BakingDish, ExecutionContext, Ingredient, MixingBowl, Value};
+
+/// A mixing bowl or a baking dish of the current recipe, counted from 0.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stack {
+    Bowl(usize),
+    Dish(usize),
+}
+
+impl fmt::Display for Stack {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Stack::Bowl(index) => write!(f, "bowl {}", index + 1),
+            Stack::Dish(index) => write!(f, "dish {}", index + 1),
+        }
+    }
+}
+
+/// The ingredients, bowls and dishes of one recipe of a run: the one
+/// running now, or a caller waiting on `Serve with`.
+pub(crate) struct Kitchen<'a> {
+    pub variables: &'a HashMap<Ingredient, Value>,
+    pub mixing_bowls: &'a [MixingBowl],
+    pub baking_dishes: &'a [BakingDish],
+}
+
+impl<'a> Kitchen<'a> {
+    /// The kitchen of the recipe at call depth `level` (0 is the main
+    /// recipe), or `None` if no recipe is running that deep.
+    pub(crate) fn at(context: &'a ExecutionContext, level: usize) -> Option<Self> {
+        match context.call_stack.len().cmp(&level) {
+            Ordering::Greater => {
+                let frame = &context.call_stack[level];
+                Some(Self {
+                    variables: &frame.variables,
+                    mixing_bowls: &frame.mixing_bowls,
+                    baking_dishes: &frame.baking_dishes,
+                })
+            }
+            Ordering::Equal => Some(Self {
+                variables: &context.variables,
+                mixing_bowls: &context.mixing_bowls,
+                baking_dishes: &context.baking_dishes,
+            }),
+            Ordering::Less => None,
+        }
+    }
+
+    pub(crate) fn stack(&self, stack: Stack) -> Option<&'a VecDeque<Value>> {
+        match stack {
+            Stack::Bowl(index) => self.mixing_bowls.get(index),
+            Stack::Dish(index) => self.baking_dishes.get(index),
+        }
+    }
+}
+
+/// One change a run made, holding what it replaced.
+#[derive(Clone, Debug)]
+pub(crate) enum Change {
+    /// An ingredient was set; `None` if it had no value.
+    Variable {
+        name: Ingredient,
+        old: Option<Value>,
+    },
+    /// A value was pushed onto the stack.
+    Pushed(Stack),
+    /// This value was popped off the stack.
+    Popped(Stack, Value),
+    /// The top of the stack had this value before it changed in place.
+    Top(Stack, Value),
+    /// The whole stack before it was reordered, emptied or added to.
+    Replaced(Stack, VecDeque<Value>),
+    /// There were this many mixing bowls before more were added.
+    Bowls(usize),
+    /// There were this many baking dishes before more were added.
+    Dishes(usize),
+    /// The output was this many bytes long.
+    Output(usize),
+    /// `Take` read this input value.
+    Input(String),
+    /// The state of the `Mix` generator.
+    Rng(u64),
+    /// The `Serves` counts waiting for the end of each method.
+    Serves(Vec<Vec<usize>>),
+    /// The whole kitchen, before a `Serve with` handed it to a sous-chef or
+    /// took it back.
+    Kitchen(Box<ExecutionContext>),
+}
+
+impl Change {
+    /// Undoes the change in `context`. Changes outside the kitchen (output,
+    /// input, generator, `Serves`) are left to the interpreter.
+    pub(crate) fn undo_in(self, context: &mut ExecutionContext) {
+        match self {
+            Change::Variable { name, old } => match old {
+                Some(value) => {
+                    context.variables.insert(name, value);
+                }
+                None => {
+                    context.variables.remove(&name);
+                }
+            },
+            Change::Pushed(stack) => {
+                stack_mut(context, stack).pop_front();
+            }
+            Change::Popped(stack, value) => stack_mut(context, stack).push_front(value),
+            Change::Top(stack, value) => {
+                if let Some(top) = stack_mut(context, stack).front_mut() {
+                    *top = value;
+                }
+            }
+            Change::Replaced(stack, values) => *stack_mut(context, stack) = values,
+            Change::Bowls(count) => context.mixing_bowls.truncate(count),
+            Change::Dishes(count) => context.baking_dishes.truncate(count),
+            Change::Kitchen(kitchen) => *context = *kitchen,
+            Change::Output(_) | Change::Input(_) | Change::Rng(_) | Change::Serves(_) => {}
+        }
+    }
+}
+
+/// The stack, added (empty) if the context does not have it yet.
+pub(crate) fn stack_mut(context: &mut ExecutionContext, stack: Stack) -> &mut VecDeque<Value> {
+    let (stacks, index) = match stack {
+        Stack::Bowl(index) => (&mut context.mixing_bowls, index),
+        Stack::Dish(index) => (&mut context.baking_dishes, index),
+    };
+    while stacks.len() <= index {
+        stacks.push(VecDeque::new());
+    }
+    &mut stacks[index]
+}
@@ -1,3 +1,7 @@
+use std::fmt;
+
+use crate::conjugation::past_participle;
+use crate::error_formatter::ordinal;
 use crate::types::Ingredient;
 
 #[allow(dead_code)]
@@ -30,6 +34,157 @@ pub enum Instruction {
     Serves(usize),
 }
 
+impl Instruction {
+    /// The sentence opening a loop: "Verb the ingredient".
+    pub fn loop_opening(verb: &str, condition_var: &str) -> String {
+        format!("{} the {}", verb, condition_var)
+    }
+
+    /// The sentence that starts the instruction: the opening of a loop, the
+    /// whole sentence otherwise.
+    pub fn opening(&self) -> String {
+        match self {
+            Instruction::Loop {
+                verb,
+                condition_var,
+                ..
+            } => Self::loop_opening(verb, condition_var),
+            other => other.to_string(),
+        }
+    }
+
+    /// Every instruction in `instructions` in pre-order (a loop before its
+    /// body), the order of `SourceMap::instructions`.
+    pub fn preorder(instructions: &[Instruction]) -> Vec<&Instruction> {
+        let mut all = Vec::new();
+        for instruction in instructions {
+            all.push(instruction);
+            if let Instruction::Loop { body, .. } = instruction {
+                all.extend(Self::preorder(body));
+            }
+        }
+        all
+    }
+
+    /// How many instructions of a pre-order walk this one takes up: one,
+    /// plus its body for a loop.
+    pub fn preorder_len(&self) -> usize {
+        match self {
+            Instruction::Loop { body, .. } => {
+                1 + body.iter().map(Instruction::preorder_len).sum::<usize>()
+            }
+            _ => 1,
+        }
+    }
+
+    /// The sentence closing a loop: "Verb [the ingredient] until verbed".
+    pub fn loop_closing(verb: &str, decrement_var: Option<&str>) -> String {
+        match decrement_var {
+            Some(ingredient) => format!(
+                "{} the {} until {}",
+                verb,
+                ingredient,
+                past_participle(verb)
+            ),
+            None => format!("{} until {}", verb, past_participle(verb)),
+        }
+    }
+}
+
+/// Renders the instruction as the method sentence the parser reads back into
+/// the same instruction (without the closing period). Loops render their
+/// whole body, sentences separated by ". ".
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Instruction::Take(ingredient) => write!(f, "Take {} from the refrigerator", ingredient),
+            Instruction::Put(ingredient, bowl_idx) => {
+                write!(f, "Put {} into {}", ingredient, bowl(*bowl_idx))
+            }
+            Instruction::Fold(ingredient, bowl_idx) => {
+                write!(f, "Fold {} into {}", ingredient, bowl(*bowl_idx))
+            }
+            Instruction::Add(ingredient, bowl_idx) => {
+                write!(f, "Add {} to {}", ingredient, bowl(*bowl_idx))
+            }
+            Instruction::Remove(ingredient, bowl_idx) => {
+                write!(f, "Remove {} from {}", ingredient, bowl(*bowl_idx))
+            }
+            Instruction::Combine(ingredient, bowl_idx) => {
+                write!(f, "Combine {} into {}", ingredient, bowl(*bowl_idx))
+            }
+            Instruction::Divide(ingredient, bowl_idx) => {
+                write!(f, "Divide {} into {}", ingredient, bowl(*bowl_idx))
+            }
+            Instruction::AddDry(bowl_idx) => {
+                write!(f, "Add dry ingredients to {}", bowl(*bowl_idx))
+            }
+            Instruction::Liquefy(ingredient) => write!(f, "Liquefy {}", ingredient),
+            Instruction::LiquefyBowl(bowl_idx) => {
+                write!(f, "Liquefy contents of {}", bowl(*bowl_idx))
+            }
+            Instruction::Stir(bowl_idx, minutes) => write!(
+                f,
+                "Stir {} for {} minute{}",
+                bowl(*bowl_idx),
+                minutes,
+                if *minutes == 1 { "" } else { "s" }
+            ),
+            Instruction::StirIngredient(ingredient, bowl_idx) => {
+                write!(f, "Stir {} into {}", ingredient, bowl(*bowl_idx))
+            }
+            Instruction::Mix(bowl_idx) => write!(f, "Mix {} well", bowl(*bowl_idx)),
+            Instruction::Clean(bowl_idx) => write!(f, "Clean {}", bowl(*bowl_idx)),
+            Instruction::Pour(from_idx, to_idx) => write!(
+                f,
+                "Pour contents of {} into {}",
+                bowl(*from_idx),
+                dish(*to_idx)
+            ),
+            Instruction::Loop {
+                condition_var,
+                verb,
+                body,
+                decrement_var,
+            } => {
+                write!(f, "{}. ", Instruction::loop_opening(verb, condition_var))?;
+                for instruction in body {
+                    write!(f, "{}. ", instruction)?;
+                }
+                write!(
+                    f,
+                    "{}",
+                    Instruction::loop_closing(verb, decrement_var.as_deref())
+                )
+            }
+            Instruction::SetAside => write!(f, "Set aside"),
+            Instruction::ServeWith(recipe_name) => write!(f, "Serve with {}", recipe_name),
+            Instruction::Refrigerate(None) => write!(f, "Refrigerate"),
+            Instruction::Refrigerate(Some(hours)) => write!(
+                f,
+                "Refrigerate for {} hour{}",
+                hours,
+                if *hours == 1 { "" } else { "s" }
+            ),
+            Instruction::Serves(count) => write!(f, "Serves {}", count),
+        }
+    }
+}
+
+fn bowl(idx: usize) -> String {
+    match idx {
+        0 => "the mixing bowl".to_string(),
+        _ => format!("the {} mixing bowl", ordinal(idx + 1)),
+    }
+}
+
+fn dish(idx: usize) -> String {
+    match idx {
+        0 => "the baking dish".to_string(),
+        _ => format!("the {} baking dish", ordinal(idx + 1)),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::Instruction;
@@ -68,4 +223,42 @@ mod tests {
             _ => panic!("expected Loop variant"),
         }
     }
+
+    #[test]
+    fn display_round_trips_through_the_parser() {
+        let instructions = vec![
+            Instruction::Take("milk".to_string()),
+            Instruction::Put("flour".to_string(), 0),
+            Instruction::Fold("sugar".to_string(), 1),
+            Instruction::Add("butter".to_string(), 2),
+            Instruction::AddDry(0),
+            Instruction::Liquefy("honey".to_string()),
+            Instruction::LiquefyBowl(1),
+            Instruction::Stir(0, 1),
+            Instruction::StirIngredient("salt".to_string(), 1),
+            Instruction::Mix(0),
+            Instruction::Clean(2),
+            Instruction::Pour(1, 2),
+            Instruction::Loop {
+                condition_var: "eggs".to_string(),
+                verb: "Bake".to_string(),
+                body: vec![Instruction::SetAside],
+                decrement_var: Some("eggs".to_string()),
+            },
+            Instruction::ServeWith("Caramel Sauce".to_string()),
+            Instruction::Refrigerate(Some(2)),
+        ];
+        let method: String = instructions
+            .iter()
+            .map(|instruction| format!("{}.\n", instruction))
+            .collect();
+        let source = format!("Round Trip.\n\nMethod.\n{}", method);
+        let recipe = crate::Parser::new(&source)
+            .parse_recipe()
+            .expect("rendered method parses");
+
+        let rendered: Vec<String> = recipe.instructions.iter().map(|i| i.to_string()).collect();
+        let expected: Vec<String> = instructions.iter().map(|i| i.to_string()).collect();
+        assert_eq!(rendered, expected);
+    }
 }
@@ -1,17 +1,26 @@
 use std::collections::{HashMap, VecDeque};
 use std::fmt::Write as _;
+use std::sync::atomic::{AtomicUsize, Ordering};
 
+use crate::book::RecipeBook;
+use crate::compat::{AuxiliaryBowl, CompatProfile, PourOrder, ServesTiming, StirPastBottom};
+use crate::coverage::Coverage;
+use crate::history::{Change, Stack};
 use crate::instruction::Instruction;
+use crate::profile::Profiler;
+use crate::snapshot::{ResumePoint, Snapshot};
 use crate::types::{
-    CallFrame, ExecutionContext, Measure, Recipe, Result, RuntimeError, RuntimeResult, Value,
+    BakingDish, CallFrame, ExecutionContext, Ingredient, Measure, MixingBowl, Recipe, Result,
+    RuntimeError, RuntimeResult, Value,
 };
+use crate::watch::{Hit, Watch, Watcher};
 
-const MAX_CALL_DEPTH: usize = 64;
+pub(crate) const MAX_CALL_DEPTH: usize = 64;
 
 /// Safety net for non-terminating loops (the spec loop condition can simply
 /// never reach zero). Reported as a runtime error instead of hanging the CLI
 /// or the browser.
-const MAX_LOOP_ITERATIONS: usize = 10_000_000;
+pub(crate) const MAX_LOOP_ITERATIONS: usize = 10_000_000;
 
 /// Where `Take _ingredient_ from refrigerator` reads its numbers from.
 enum InputSource {
@@ -23,13 +32,103 @@ enum InputSource {
     Buffer(VecDeque<String>),
 }
 
+/// How `Serves` writes the values it takes from the baking dishes. Liquid
+/// values are always written as bare characters; the separators apply to
+/// numbers (dry and unspecified values) and to dish boundaries.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct OutputOptions {
+    /// Written before each number.
+    pub before_number: String,
+    /// Written after each number.
+    pub after_number: String,
+    /// Written between two dishes served by the same `Serves`.
+    pub between_dishes: String,
+}
+
+impl OutputOptions {
+    /// The names accepted by [`from_style`](Self::from_style).
+    pub const STYLES: [&'static str; 4] = ["plain", "spaced", "lines", "perl"];
+
+    /// A named preset:
+    ///
+    /// - `plain`: numbers run together ("123"), as the spec leaves it.
+    /// - `spaced`: a space after each number, a newline between dishes.
+    /// - `lines`: each number on its own line, a blank line between dishes.
+    /// - `perl`: a space before each number, like the reference Perl
+    ///   interpreter (Acme::Chef).
+    pub fn from_style(name: &str) -> Option<Self> {
+        let (before_number, after_number, between_dishes) = match name {
+            "plain" => ("", "", ""),
+            "spaced" => ("", " ", "\n"),
+            "lines" => ("", "\n", "\n"),
+            "perl" => (" ", "", ""),
+            _ => return None,
+        };
+        Some(Self {
+            before_number: before_number.to_string(),
+            after_number: after_number.to_string(),
+            between_dishes: between_dishes.to_string(),
+        })
+    }
+}
+
+/// Where an instruction sits: the id of its recipe (see
+/// [`Observer::enter_recipe`]) and its position in the recipe's pre-order
+/// walk, which is the order of `SourceMap::instructions`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub(crate) struct InstructionId {
+    pub recipe: usize,
+    pub index: usize,
+}
+
+/// Follows a run instruction by instruction, for the profiler, coverage and
+/// watches.
+/// Every `enter_*` is matched by an `exit_*`, also when the run fails.
+pub(crate) trait Observer {
+    /// `id` is the same each time the recipe runs, so what an observer
+    /// works out about a recipe can be kept by its id.
+    fn enter_recipe(&mut self, id: usize, recipe: &Recipe);
+    fn exit_recipe(&mut self);
+    /// `id` is `None` for an instruction run outside of any recipe (the
+    /// REPL).
+    fn enter_instruction(&mut self, id: Option<InstructionId>, instruction: &Instruction);
+    fn exit_instruction(&mut self, id: Option<InstructionId>);
+    /// The loop whose instruction is running starts another pass.
+    fn loop_pass(&mut self);
+}
+
 pub struct Interpreter {
     context: ExecutionContext,
     recipes: HashMap<String, Recipe>,
+    /// The id of each recipe in `recipes`. A recipe added again under the
+    /// same title gets a new id.
+    recipe_ids: HashMap<String, usize>,
     main_recipe_key: Option<String>,
     output: String,
+    profile: CompatProfile,
+    /// `Serves` counts waiting for the end of the method, innermost recipe
+    /// last, under [`ServesTiming::AfterMethod`].
+    pending_serves: Vec<Vec<usize>>,
+    /// The kitchen a run starts with; empty means one empty bowl or dish.
+    initial_bowls: Vec<MixingBowl>,
+    initial_dishes: Vec<BakingDish>,
+    max_loop_iterations: usize,
+    max_call_depth: usize,
     input: InputSource,
     rng_state: u64,
+    profiler: Option<Profiler>,
+    coverage: Option<Coverage>,
+    watcher: Option<Watcher>,
+    /// Instructions to run before pausing, once [`pause_after`](Self::pause_after) is called.
+    steps_before_pause: Option<u64>,
+    /// The title of the recipe a paused run was running.
+    paused_recipe: Option<String>,
+    /// Where a paused run stopped, innermost first: gathered while the
+    /// pause unwinds, used up (from the end) while the run resumes.
+    resume: Vec<ResumePoint>,
+    /// What the instructions changed since [`take_changes`](Self::take_changes),
+    /// while [`record_changes`](Self::record_changes) is on.
+    changes: Option<Vec<Change>>,
 }
 
 impl Interpreter {
@@ -37,10 +136,24 @@ impl Interpreter {
         Self {
             context: ExecutionContext::new(),
             recipes: HashMap::new(),
+            recipe_ids: HashMap::new(),
             main_recipe_key: None,
             output: String::new(),
+            profile: CompatProfile::default(),
+            pending_serves: Vec::new(),
+            initial_bowls: Vec::new(),
+            initial_dishes: Vec::new(),
+            max_loop_iterations: MAX_LOOP_ITERATIONS,
+            max_call_depth: MAX_CALL_DEPTH,
             input: InputSource::Stdin,
             rng_state: default_rng_seed(),
+            profiler: None,
+            coverage: None,
+            watcher: None,
+            steps_before_pause: None,
+            paused_recipe: None,
+            resume: Vec::new(),
+            changes: None,
         }
     }
 
@@ -70,6 +183,239 @@ impl Interpreter {
         };
     }
 
+    /// Lowers (or raises) the number of passes a single loop may make before
+    /// the run fails with [`RuntimeError::LoopLimit`].
+    pub fn set_loop_limit(&mut self, max_iterations: usize) {
+        self.max_loop_iterations = max_iterations;
+    }
+
+    /// Changes how deeply `Serve with` may nest before the run fails with
+    /// [`RuntimeError::RecursionLimit`].
+    pub fn set_call_depth_limit(&mut self, max_depth: usize) {
+        self.max_call_depth = max_depth;
+    }
+
+    /// Changes how served numbers and dishes are separated in the output.
+    pub fn set_output_options(&mut self, options: OutputOptions) {
+        self.profile.output = options;
+    }
+
+    /// Settles the spec's ambiguities the way another implementation does;
+    /// this includes the output options.
+    pub fn set_compat_profile(&mut self, profile: CompatProfile) {
+        self.profile = profile;
+    }
+
+    /// Starts counting executions and wall time per instruction, loop and
+    /// recipe; [`profiler`](Self::profiler) reports them.
+    pub fn enable_profiling(&mut self) {
+        self.profiler = Some(Profiler::new());
+    }
+
+    /// What the runs so far cost, if profiling is enabled.
+    pub fn profiler(&self) -> Option<&Profiler> {
+        self.profiler.as_ref()
+    }
+
+    /// Records what the runs execute into `coverage` (usually built for
+    /// the recipe with [`Coverage::new`]) until
+    /// [`take_coverage`](Self::take_coverage) hands it back.
+    pub fn set_coverage(&mut self, coverage: Coverage) {
+        self.coverage = Some(coverage);
+    }
+
+    pub fn take_coverage(&mut self) -> Option<Coverage> {
+        self.coverage.take()
+    }
+
+    /// Watches the kitchen of the recipe running now (the main recipe
+    /// before a run) from the next instruction on; see
+    /// [`take_watch_hits`](Self::take_watch_hits).
+    pub fn watch(&mut self, watch: Watch) {
+        let level = self.context.call_stack.len();
+        self.watcher
+            .get_or_insert_with(Watcher::default)
+            .add(watch, level);
+    }
+
+    /// Stops watching the `index`th watch (counted from 0 in the order they
+    /// were added), returning it.
+    pub fn unwatch(&mut self, index: usize) -> Option<Watch> {
+        self.watcher.as_mut()?.remove(index)
+    }
+
+    pub fn watches(&self) -> impl Iterator<Item = &Watch> {
+        self.watcher.iter().flat_map(Watcher::watches)
+    }
+
+    /// Calls `report` with each watch hit as it happens, from then on,
+    /// instead of keeping it for [`take_watch_hits`](Self::take_watch_hits).
+    pub fn report_watch_hits(&mut self, report: impl FnMut(&Hit) + 'static) {
+        self.watcher
+            .get_or_insert_with(Watcher::default)
+            .report_hits(Box::new(report));
+    }
+
+    /// The watches hit since the last call, in the order they were hit.
+    pub fn take_watch_hits(&mut self) -> Vec<Hit> {
+        self.watcher
+            .as_mut()
+            .map(Watcher::take_hits)
+            .unwrap_or_default()
+    }
+
+    /// Makes the watches forget what they last saw, after the kitchen was
+    /// put back to an earlier state.
+    pub(crate) fn forget_watched_values(&mut self) {
+        if let Some(watcher) = &mut self.watcher {
+            watcher.forget();
+        }
+    }
+
+    fn check_watches(&mut self) {
+        if let Some(watcher) = &mut self.watcher {
+            watcher.check(&self.context);
+        }
+    }
+
+    /// Makes the run stop after `steps` more instructions (every sentence
+    /// counts, loops and `Serve with` included), leaving it paused; see
+    /// [`snapshot`](Self::snapshot) and [`resume`](Self::resume). A run that
+    /// ends sooner is not affected.
+    pub fn pause_after(&mut self, steps: u64) {
+        self.steps_before_pause = Some(steps);
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_recipe.is_some()
+    }
+
+    /// Continues a paused run, which may pause again. Does nothing when no
+    /// run is paused.
+    pub fn resume(&mut self) -> Result<()> {
+        let Some(title) = self.paused_recipe.take() else {
+            return Ok(());
+        };
+        let recipe = self
+            .recipes
+            .get(&normalize_recipe_name(&title))
+            .cloned()
+            .ok_or(RuntimeError::UnknownRecipe { recipe_name: title })?;
+        self.continue_with(&recipe)?;
+        Ok(())
+    }
+
+    /// Everything a run has done so far and where it paused, if it did.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            recipe: self.paused_recipe.clone(),
+            resume: self.resume.iter().rev().copied().collect(),
+            context: self.context.clone(),
+            output: self.output.clone(),
+            pending_serves: self.pending_serves.clone(),
+            input: match &self.input {
+                InputSource::Stdin => None,
+                InputSource::Buffer(tokens) => Some(tokens.iter().cloned().collect()),
+            },
+            rng_state: self.rng_state,
+        }
+    }
+
+    /// Puts the interpreter in the state `snapshot` captured, so that
+    /// [`resume`](Self::resume) continues the run. The interpreter must
+    /// already hold the recipe (and its auxiliary recipes) the snapshot was
+    /// taken from.
+    pub fn restore(&mut self, snapshot: Snapshot) {
+        self.paused_recipe = snapshot.recipe;
+        self.resume = snapshot.resume.into_iter().rev().collect();
+        self.context = snapshot.context;
+        self.output = snapshot.output;
+        self.pending_serves = snapshot.pending_serves;
+        self.input = match snapshot.input {
+            Some(tokens) => InputSource::Buffer(tokens.into()),
+            None => InputSource::Stdin,
+        };
+        self.rng_state = snapshot.rng_state;
+    }
+
+    /// Starts logging what every instruction changes, for the debugger to
+    /// step back through.
+    pub(crate) fn record_changes(&mut self) {
+        self.changes.get_or_insert_with(Vec::new);
+    }
+
+    /// The changes logged since the last call, oldest first.
+    pub(crate) fn take_changes(&mut self) -> Vec<Change> {
+        self.changes
+            .as_mut()
+            .map(std::mem::take)
+            .unwrap_or_default()
+    }
+
+    /// Reverts `changes` (as returned by [`take_changes`](Self::take_changes)),
+    /// newest first.
+    pub(crate) fn undo(&mut self, changes: Vec<Change>) {
+        for change in changes.into_iter().rev() {
+            match change {
+                Change::Output(len) => self.output.truncate(len),
+                Change::Input(token) => {
+                    if let InputSource::Buffer(tokens) = &mut self.input {
+                        tokens.push_front(token);
+                    }
+                }
+                Change::Rng(state) => self.rng_state = state,
+                Change::Serves(pending) => self.pending_serves = pending,
+                change => change.undo_in(&mut self.context),
+            }
+        }
+    }
+
+    /// The recipe a paused run was running and where in it it stopped,
+    /// outermost first.
+    pub(crate) fn position(&self) -> Option<(String, Vec<ResumePoint>)> {
+        let title = self.paused_recipe.clone()?;
+        Some((title, self.resume.iter().rev().copied().collect()))
+    }
+
+    /// Pauses the run at a `position` taken earlier, or marks it as not
+    /// paused.
+    pub(crate) fn set_position(&mut self, position: Option<(String, Vec<ResumePoint>)>) {
+        self.steps_before_pause = None;
+        match position {
+            Some((title, resume)) => {
+                self.paused_recipe = Some(title);
+                self.resume = resume.into_iter().rev().collect();
+            }
+            None => {
+                self.paused_recipe = None;
+                self.resume.clear();
+            }
+        }
+    }
+
+    /// The recipe `Serve with` would find under `name`.
+    pub fn recipe(&self, name: &str) -> Option<&Recipe> {
+        self.recipes.get(&normalize_recipe_name(name))
+    }
+
+    pub(crate) fn context(&self) -> &ExecutionContext {
+        &self.context
+    }
+
+    /// Fills the mixing bowls a run starts with, first bowl first and each
+    /// bowl's top at its front, instead of a single empty bowl. Together with
+    /// [`run_recipe`](Self::run_recipe) this tests an auxiliary recipe on the
+    /// bowls a caller would hand it.
+    pub fn set_mixing_bowls(&mut self, bowls: Vec<MixingBowl>) {
+        self.initial_bowls = bowls;
+    }
+
+    /// Like [`set_mixing_bowls`](Self::set_mixing_bowls), for the baking
+    /// dishes.
+    pub fn set_baking_dishes(&mut self, dishes: Vec<BakingDish>) {
+        self.initial_dishes = dishes;
+    }
+
     /// Returns the output produced so far by `run`.
     ///
     /// The interpreter accumulates everything that a recipe "serves" into an
@@ -79,17 +425,79 @@ impl Interpreter {
         &self.output
     }
 
+    /// The mixing bowls, first bowl first; each bowl's top is its front.
+    pub fn mixing_bowls(&self) -> &[MixingBowl] {
+        &self.context.mixing_bowls
+    }
+
+    /// The baking dishes, first dish first; each dish's top is its front.
+    pub fn baking_dishes(&self) -> &[BakingDish] {
+        &self.context.baking_dishes
+    }
+
+    /// Declares ingredients in the current kitchen, replacing earlier
+    /// declarations of the same names (used by the REPL).
+    pub(crate) fn declare_ingredients(
+        &mut self,
+        ingredients: HashMap<Ingredient, Value>,
+        unset_ingredients: HashMap<Ingredient, Measure>,
+    ) {
+        for (name, value) in ingredients {
+            self.context.unset_ingredients.remove(&name);
+            self.context.variables.insert(name, value);
+        }
+        for (name, measure) in unset_ingredients {
+            self.context.variables.remove(&name);
+            self.context.unset_ingredients.insert(name, measure);
+        }
+    }
+
+    /// Forgets every ingredient and empties the kitchen down to one mixing
+    /// bowl and one baking dish, keeping the recipes, input and settings
+    /// (used by the REPL).
+    pub(crate) fn clear_kitchen(&mut self) {
+        self.context = ExecutionContext::new();
+        self.forget_watched_values();
+    }
+
+    /// Makes `recipe` available to `Serve with` without making it the main
+    /// recipe, replacing any recipe of the same name.
+    pub fn add_auxiliary_recipe(&mut self, recipe: Recipe) {
+        self.insert_recipe(normalize_recipe_name(&recipe.title), recipe);
+    }
+
+    /// Makes the book's recipes available to `Serve with`. Recipes the
+    /// interpreter already has, from [`add_recipe`](Self::add_recipe) or
+    /// earlier books, are kept; `add_recipe` replaces book recipes.
+    pub fn add_book(&mut self, book: &RecipeBook) {
+        for entry in book.recipes() {
+            let key = normalize_recipe_name(&entry.recipe.title);
+            if !self.recipes.contains_key(&key) {
+                self.insert_recipe(key, entry.recipe.clone());
+            }
+        }
+    }
+
     pub fn add_recipe(&mut self, recipe: Recipe) {
         let main_key = normalize_recipe_name(&recipe.title);
         self.main_recipe_key = Some(main_key.clone());
-        self.recipes.insert(main_key.clone(), recipe.clone());
+        self.insert_recipe(main_key, recipe.clone());
 
         for (title, aux) in &recipe.auxiliary_recipes {
-            let key = normalize_recipe_name(title);
-            self.recipes.insert(key, aux.clone());
+            self.insert_recipe(normalize_recipe_name(title), aux.clone());
         }
     }
 
+    /// Adds `recipe` under `key` with a new id. Ids are never reused, not
+    /// even by another interpreter, so a [`Coverage`] handed from one run to
+    /// the next can keep what it learned about each id.
+    fn insert_recipe(&mut self, key: String, recipe: Recipe) {
+        static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+        let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+        self.recipe_ids.insert(key.clone(), id);
+        self.recipes.insert(key, recipe);
+    }
+
     pub fn run(&mut self) -> Result<()> {
         let key = self.main_recipe_key.clone().ok_or(RuntimeError::NoRecipe)?;
         let recipe = self
@@ -101,25 +509,134 @@ impl Interpreter {
         Ok(())
     }
 
+    /// Runs the recipe titled `name` (main or auxiliary, matched the way
+    /// `Serve with` matches) as if it were the main recipe.
+    pub fn run_recipe(&mut self, name: &str) -> Result<()> {
+        let recipe = self
+            .recipes
+            .get(&normalize_recipe_name(name))
+            .cloned()
+            .ok_or_else(|| RuntimeError::UnknownRecipe {
+                recipe_name: name.to_string(),
+            })?;
+        self.execute(&recipe)?;
+        Ok(())
+    }
+
     fn execute(&mut self, recipe: &Recipe) -> RuntimeResult<()> {
+        self.paused_recipe = None;
+        self.resume.clear();
         self.context.variables = recipe.ingredients.clone();
         self.context.unset_ingredients = recipe.unset_ingredients.clone();
-        self.context.mixing_bowls.clear();
-        self.context.mixing_bowls.push(VecDeque::new());
-        self.context.baking_dishes.clear();
-        self.context.baking_dishes.push(VecDeque::new());
-
-        for instruction in &recipe.instructions {
-            match self.execute_instruction(instruction) {
-                Err(RuntimeError::EarlyTermination) => break,
-                // "Set aside" ends the innermost loop; outside of any loop the
-                // signal would otherwise leak to the caller as a phantom error.
-                Err(RuntimeError::BreakLoop) => return Err(RuntimeError::SetAsideOutsideLoop),
-                Err(e) => return Err(e),
-                Ok(()) => {}
+        self.context.mixing_bowls = self.initial_bowls.clone();
+        if self.context.mixing_bowls.is_empty() {
+            self.context.mixing_bowls.push(VecDeque::new());
+        }
+        self.context.baking_dishes = self.initial_dishes.clone();
+        if self.context.baking_dishes.is_empty() {
+            self.context.baking_dishes.push(VecDeque::new());
+        }
+
+        self.pending_serves.push(Vec::new());
+        self.continue_with(recipe)
+    }
+
+    /// Runs the method of the top-level `recipe` on the current kitchen, from
+    /// where a paused run stopped if there is one.
+    fn continue_with(&mut self, recipe: &Recipe) -> RuntimeResult<()> {
+        match self.run_method(recipe) {
+            Ok(true) => {
+                let served = self.serve_pending();
+                self.check_watches();
+                served
+            }
+            Ok(false) => {
+                self.record(|s| Change::Serves(s.pending_serves.clone()));
+                self.pending_serves.pop();
+                Ok(())
+            }
+            Err(RuntimeError::Paused) => {
+                self.paused_recipe = Some(recipe.title.clone());
+                Ok(())
             }
+            Err(e) => {
+                self.record(|s| Change::Serves(s.pending_serves.clone()));
+                self.pending_serves.pop();
+                Err(e)
+            }
+        }
+    }
+
+    /// Runs the method of `recipe` on the current kitchen. `Ok(false)`
+    /// means `Refrigerate` ended it early.
+    fn run_method(&mut self, recipe: &Recipe) -> RuntimeResult<bool> {
+        let id = self.observed().then(|| {
+            let key = normalize_recipe_name(&recipe.title);
+            self.recipe_ids.get(&key).copied().unwrap_or(usize::MAX)
+        });
+        for observer in self.observers() {
+            observer.enter_recipe(id.unwrap_or(usize::MAX), recipe);
+        }
+        let start = id.map(|recipe| InstructionId { recipe, index: 0 });
+        let result = match self.run_block(&recipe.instructions, start) {
+            Ok(()) => Ok(true),
+            Err(RuntimeError::EarlyTermination) => Ok(false),
+            // "Set aside" ends the innermost loop; outside of any loop the
+            // signal would otherwise leak to the caller as a phantom error.
+            Err(RuntimeError::BreakLoop) => Err(RuntimeError::SetAsideOutsideLoop),
+            Err(e) => Err(e),
+        };
+        for observer in self.observers() {
+            observer.exit_recipe();
         }
+        result
+    }
 
+    /// Runs a method or loop body, starting with the instruction a resumed
+    /// run stopped at. A pause records where it happened on its way out.
+    /// `first` is where the block's first instruction sits, while the run
+    /// is observed.
+    fn run_block(
+        &mut self,
+        instructions: &[Instruction],
+        first: Option<InstructionId>,
+    ) -> RuntimeResult<()> {
+        let start = match self.resume.last() {
+            Some(&ResumePoint::Instruction(index)) => {
+                self.resume.pop();
+                index
+            }
+            _ => 0,
+        };
+        let mut id = first.map(|first| InstructionId {
+            index: first.index
+                + instructions[..start]
+                    .iter()
+                    .map(Instruction::preorder_len)
+                    .sum::<usize>(),
+            ..first
+        });
+        for (index, instruction) in instructions.iter().enumerate().skip(start) {
+            let result = self.execute_at(instruction, id);
+            if let Some(id) = &mut id {
+                id.index += instruction.preorder_len();
+            }
+            if let Err(error) = result {
+                if let RuntimeError::Paused = error {
+                    self.resume.push(ResumePoint::Instruction(index));
+                }
+                return Err(error);
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the dishes of every `Serves` the finished method deferred.
+    fn serve_pending(&mut self) -> RuntimeResult<()> {
+        self.record(|s| Change::Serves(s.pending_serves.clone()));
+        for count in self.pending_serves.pop().unwrap_or_default() {
+            self.write_output(count)?;
+        }
         Ok(())
     }
 
@@ -150,6 +667,7 @@ impl Interpreter {
                         ingredient: ingredient.to_string(),
                         reason: "no more input values are available".to_string(),
                     })?;
+                self.record(|_| Change::Input(token.clone()));
                 token
                     .parse::<i64>()
                     .map_err(|_| RuntimeError::InputUnavailable {
@@ -192,11 +710,80 @@ impl Interpreter {
         x.wrapping_mul(0x2545_F491_4F6C_DD1D)
     }
 
-    fn execute_instruction(&mut self, inst: &Instruction) -> RuntimeResult<()> {
+    /// Executes one instruction against the current kitchen state.
+    ///
+    /// `Refrigerate` reports [`RuntimeError::EarlyTermination`] and a
+    /// `Set aside` outside a loop reports [`RuntimeError::BreakLoop`]; `run`
+    /// turns those signals into the end of the recipe and an error.
+    /// [`RuntimeError::Paused`] means [`pause_after`](Self::pause_after)'s
+    /// steps are used up and the instruction did not run.
+    pub fn execute_instruction(&mut self, inst: &Instruction) -> RuntimeResult<()> {
+        self.execute_at(inst, None)
+    }
+
+    fn execute_at(&mut self, inst: &Instruction, id: Option<InstructionId>) -> RuntimeResult<()> {
+        // What the enclosing loop or `Serve with` did since its last nested
+        // instruction (a decrement, say) is put down to it.
+        self.check_watches();
+        // Re-entering the loops and recipes of a resumed run is not a step.
+        if self.resume.is_empty() {
+            match &mut self.steps_before_pause {
+                Some(0) => {
+                    self.steps_before_pause = None;
+                    return Err(RuntimeError::Paused);
+                }
+                Some(steps) => *steps -= 1,
+                None => {}
+            }
+        }
+        if !self.observed() {
+            return self.run_instruction(inst, None);
+        }
+        for observer in self.observers() {
+            observer.enter_instruction(id, inst);
+        }
+        let result = self.run_instruction(inst, id);
+        self.check_watches();
+        for observer in self.observers() {
+            observer.exit_instruction(id);
+        }
+        result
+    }
+
+    /// Whether the profiler, coverage or watches follow the run.
+    fn observed(&self) -> bool {
+        self.profiler.is_some() || self.coverage.is_some() || self.watcher.is_some()
+    }
+
+    /// Logs a change when [`record_changes`](Self::record_changes) is on;
+    /// `change` reads what is about to be replaced.
+    fn record(&mut self, change: impl FnOnce(&Self) -> Change) {
+        if self.changes.is_some() {
+            let change = change(self);
+            if let Some(changes) = &mut self.changes {
+                changes.push(change);
+            }
+        }
+    }
+
+    fn observers(&mut self) -> impl Iterator<Item = &mut dyn Observer> {
+        let profiler = self.profiler.iter_mut().map(|p| p as &mut dyn Observer);
+        let coverage = self.coverage.iter_mut().map(|c| c as &mut dyn Observer);
+        let watcher = self.watcher.iter_mut().map(|w| w as &mut dyn Observer);
+        profiler.chain(coverage).chain(watcher)
+    }
+
+    /// Runs `inst`, found at `id` while the run is observed.
+    fn run_instruction(
+        &mut self,
+        inst: &Instruction,
+        id: Option<InstructionId>,
+    ) -> RuntimeResult<()> {
         match inst {
             Instruction::Put(ingredient, bowl_idx) => {
                 self.ensure_bowl(*bowl_idx);
                 let value = self.get_variable(ingredient)?;
+                self.record(|_| Change::Pushed(Stack::Bowl(*bowl_idx)));
                 self.context.mixing_bowls[*bowl_idx].push_front(value);
             }
             Instruction::Fold(ingredient, bowl_idx) => {
@@ -207,6 +794,8 @@ impl Interpreter {
                         bowl_index: *bowl_idx,
                         operation: format!("Fold {} into mixing bowl", ingredient),
                     })?;
+                self.record(|_| Change::Popped(Stack::Bowl(*bowl_idx), value));
+                self.record_variable(ingredient);
                 self.context.variables.insert(ingredient.clone(), value);
             }
             Instruction::Add(ingredient, bowl_idx) => {
@@ -218,7 +807,9 @@ impl Interpreter {
                         bowl_index: *bowl_idx,
                         operation: format!("Add {} to mixing bowl", ingredient),
                     })?;
+                let old = *top;
                 top.amount += ing_val.amount;
+                self.record(|_| Change::Top(Stack::Bowl(*bowl_idx), old));
             }
             Instruction::Remove(ingredient, bowl_idx) => {
                 self.ensure_bowl(*bowl_idx);
@@ -229,7 +820,9 @@ impl Interpreter {
                         bowl_index: *bowl_idx,
                         operation: format!("Remove {} from mixing bowl", ingredient),
                     })?;
+                let old = *top;
                 top.amount -= ing_val.amount;
+                self.record(|_| Change::Top(Stack::Bowl(*bowl_idx), old));
             }
             Instruction::Combine(ingredient, bowl_idx) => {
                 self.ensure_bowl(*bowl_idx);
@@ -240,7 +833,9 @@ impl Interpreter {
                         bowl_index: *bowl_idx,
                         operation: format!("Combine {} into mixing bowl", ingredient),
                     })?;
+                let old = *top;
                 top.amount *= ing_val.amount;
+                self.record(|_| Change::Top(Stack::Bowl(*bowl_idx), old));
             }
             Instruction::Divide(ingredient, bowl_idx) => {
                 self.ensure_bowl(*bowl_idx);
@@ -257,9 +852,11 @@ impl Interpreter {
                         bowl_index: *bowl_idx,
                         operation: format!("Divide {} into mixing bowl", ingredient),
                     })?;
-                // All Chef values are integers, so division truncates toward
-                // zero (the spec is silent on fractional results).
-                top.amount /= ing_val.amount;
+                // All Chef values are integers; the spec is silent on
+                // fractional results, so the profile decides the rounding.
+                let old = *top;
+                top.amount = self.profile.divide(top.amount, ing_val.amount);
+                self.record(|_| Change::Top(Stack::Bowl(*bowl_idx), old));
             }
             Instruction::AddDry(bowl_idx) => {
                 self.ensure_bowl(*bowl_idx);
@@ -270,6 +867,7 @@ impl Interpreter {
                     .filter(|value| matches!(value.measure, Measure::Dry))
                     .map(|value| value.amount)
                     .sum();
+                self.record(|_| Change::Pushed(Stack::Bowl(*bowl_idx)));
                 self.context.mixing_bowls[*bowl_idx].push_front(Value {
                     amount: sum,
                     measure: Measure::Dry,
@@ -289,6 +887,8 @@ impl Interpreter {
                 // Spec: "This randomises the order of the ingredients."
                 // Fisher-Yates with the interpreter's seedable PRNG.
                 self.ensure_bowl(*bowl_idx);
+                self.record(|s| Change::Rng(s.rng_state));
+                self.record_stack(Stack::Bowl(*bowl_idx));
                 let len = self.context.mixing_bowls[*bowl_idx].len();
                 for i in (1..len).rev() {
                     let j = (self.next_random() % (i as u64 + 1)) as usize;
@@ -297,6 +897,7 @@ impl Interpreter {
             }
             Instruction::Clean(bowl_idx) => {
                 self.ensure_bowl(*bowl_idx);
+                self.record_stack(Stack::Bowl(*bowl_idx));
                 self.context.mixing_bowls[*bowl_idx].clear();
             }
             Instruction::ServeWith(recipe_name) => {
@@ -305,12 +906,14 @@ impl Interpreter {
             Instruction::Liquefy(ingredient) => {
                 // Reuse the lookup for its declared-without-value diagnostics.
                 self.get_variable(ingredient)?;
+                self.record_variable(ingredient);
                 if let Some(value) = self.context.variables.get_mut(ingredient) {
                     value.measure = Measure::Liquid;
                 }
             }
             Instruction::LiquefyBowl(bowl_idx) => {
                 self.ensure_bowl(*bowl_idx);
+                self.record_stack(Stack::Bowl(*bowl_idx));
                 for value in self.context.mixing_bowls[*bowl_idx].iter_mut() {
                     value.measure = Measure::Liquid;
                 }
@@ -322,17 +925,37 @@ impl Interpreter {
                 // bowl keeps its contents.
                 self.ensure_bowl(*from_idx);
                 self.ensure_dish(*to_idx);
+                self.record_stack(Stack::Dish(*to_idx));
                 let copied = self.context.mixing_bowls[*from_idx].clone();
                 let dish = &mut self.context.baking_dishes[*to_idx];
-                // Front is the top: push bottom-most first so the copy lands
-                // on top of the dish in its original order.
-                for value in copied.iter().rev() {
-                    dish.push_front(*value);
+                match self.profile.pour {
+                    // Front is the top: push bottom-most first so the copy
+                    // lands on top of the dish in its original order.
+                    PourOrder::Retain => {
+                        for value in copied.iter().rev() {
+                            dish.push_front(*value);
+                        }
+                    }
+                    PourOrder::Reverse => {
+                        for value in copied.iter() {
+                            dish.push_front(*value);
+                        }
+                    }
                 }
             }
-            Instruction::Serves(count) => {
-                self.write_output(*count)?;
-            }
+            Instruction::Serves(count) => match self.profile.serves {
+                ServesTiming::InMethod => self.write_output(*count)?,
+                ServesTiming::AfterMethod if self.pending_serves.is_empty() => {
+                    // Outside of a recipe (the REPL) there is no end to wait for.
+                    self.write_output(*count)?
+                }
+                ServesTiming::AfterMethod => {
+                    self.record(|s| Change::Serves(s.pending_serves.clone()));
+                    if let Some(pending) = self.pending_serves.last_mut() {
+                        pending.push(*count);
+                    }
+                }
+            },
             Instruction::Loop {
                 condition_var,
                 verb: _,
@@ -340,35 +963,56 @@ impl Interpreter {
                 decrement_var,
             } => {
                 let mut iterations = 0usize;
+                // A resumed run continues the pass it paused in, without
+                // checking the condition again.
+                let mut resumed = false;
+                if let Some(&ResumePoint::Loop(passes)) = self.resume.last() {
+                    self.resume.pop();
+                    iterations = passes;
+                    resumed = true;
+                }
                 loop {
-                    // Spec: the ingredient named in the loop START statement is
-                    // checked before every pass; the (possibly different)
-                    // ingredient in the "until" statement is only decremented.
-                    let condition_value = self.get_variable(condition_var)?.amount;
-                    if condition_value == 0 {
-                        break;
-                    }
+                    if !resumed {
+                        // Spec: the ingredient named in the loop START statement is
+                        // checked before every pass; the (possibly different)
+                        // ingredient in the "until" statement is only decremented.
+                        let condition_value = self.get_variable(condition_var)?.amount;
+                        if condition_value == 0 {
+                            break;
+                        }
 
-                    iterations += 1;
-                    if iterations > MAX_LOOP_ITERATIONS {
-                        return Err(RuntimeError::LoopLimit {
-                            ingredient: condition_var.clone(),
-                            max_iterations: MAX_LOOP_ITERATIONS,
-                        });
+                        iterations += 1;
+                        if iterations > self.max_loop_iterations {
+                            return Err(RuntimeError::LoopLimit {
+                                ingredient: condition_var.clone(),
+                                max_iterations: self.max_loop_iterations,
+                            });
+                        }
+                        for observer in self.observers() {
+                            observer.loop_pass();
+                        }
                     }
+                    resumed = false;
 
-                    for instruction in body {
-                        match self.execute_instruction(instruction) {
-                            Err(RuntimeError::BreakLoop) => return Ok(()), // Break out of loop
-                            Err(e) => return Err(e),
-                            Ok(()) => {}
+                    let body_id = id.map(|id| InstructionId {
+                        index: id.index + 1,
+                        ..id
+                    });
+                    match self.run_block(body, body_id) {
+                        Err(RuntimeError::BreakLoop) => return Ok(()), // Break out of loop
+                        Err(RuntimeError::Paused) => {
+                            self.resume.push(ResumePoint::Loop(iterations));
+                            return Err(RuntimeError::Paused);
                         }
+                        Err(e) => return Err(e),
+                        Ok(()) => {}
                     }
 
                     // Decrement the ingredient if specified in the until statement
                     if let Some(ref decr_var) = decrement_var {
                         // Validate first for declared-without-value diagnostics.
                         self.get_variable(decr_var)?;
+                        self.record_variable(decr_var);
                         if let Some(value) = self.context.variables.get_mut(decr_var) {
                             value.amount -= 1;
                         }
@@ -390,6 +1034,7 @@ impl Interpreter {
                     .map(|value| value.measure)
                     .or_else(|| self.context.unset_ingredients.get(ingredient).copied())
                     .unwrap_or(Measure::Unspecified);
+                self.record_variable(ingredient);
                 self.context
                     .variables
                     .insert(ingredient.clone(), Value { amount, measure });
@@ -415,45 +1060,58 @@ impl Interpreter {
                     recipe_name: recipe_name.to_string(),
                 })?;
 
-        if self.context.call_stack.len() >= MAX_CALL_DEPTH {
-            return Err(RuntimeError::RecursionLimit {
-                recipe_name: recipe_name.to_string(),
-                depth: self.context.call_stack.len(),
-                max_depth: MAX_CALL_DEPTH,
-            });
-        }
-
-        let frame = CallFrame {
-            variables: self.context.variables.clone(),
-            unset_ingredients: self.context.unset_ingredients.clone(),
-            mixing_bowls: self.context.mixing_bowls.clone(),
-            baking_dishes: self.context.baking_dishes.clone(),
-            return_address: 0,
-        };
-        self.context.call_stack.push(frame);
+        // A resumed run that paused inside the sous-chef's method finds its
+        // frame already on the call stack.
+        if self.resume.is_empty() {
+            if self.context.call_stack.len() >= self.max_call_depth {
+                return Err(RuntimeError::RecursionLimit {
+                    recipe_name: recipe_name.to_string(),
+                    depth: self.context.call_stack.len(),
+                    max_depth: self.max_call_depth,
+                });
+            }
 
-        // The sous-chef gets copies of the caller's bowls and dishes (the
-        // current ones are restored from the frame afterwards), but only the
-        // auxiliary recipe's own ingredient list.
-        self.context.variables = aux_recipe.ingredients.clone();
-        self.context.unset_ingredients = aux_recipe.unset_ingredients.clone();
+            let frame = CallFrame {
+                variables: self.context.variables.clone(),
+                unset_ingredients: self.context.unset_ingredients.clone(),
+                mixing_bowls: self.context.mixing_bowls.clone(),
+                baking_dishes: self.context.baking_dishes.clone(),
+                return_address: 0,
+            };
+            self.record_kitchen();
+            self.context.call_stack.push(frame);
+
+            // The sous-chef gets copies of the caller's bowls and dishes (the
+            // current ones are restored from the frame afterwards), but only the
+            // auxiliary recipe's own ingredient list.
+            self.context.variables = aux_recipe.ingredients.clone();
+            self.context.unset_ingredients = aux_recipe.unset_ingredients.clone();
+            self.pending_serves.push(Vec::new());
+        }
 
         // Execute auxiliary recipe's instructions without clearing mixing bowls
-        for instruction in &aux_recipe.instructions {
-            match self.execute_instruction(instruction) {
-                Err(RuntimeError::EarlyTermination) => break,
-                Err(e) => {
-                    // Clean up call stack before propagating error
-                    let error = if matches!(e, RuntimeError::BreakLoop) {
-                        RuntimeError::SetAsideOutsideLoop
-                    } else {
-                        e
-                    };
-                    self.context.call_stack.pop();
-                    return Err(error);
-                }
-                Ok(()) => {}
+        let finished = match self.run_method(&aux_recipe) {
+            Ok(finished) => finished,
+            // Paused with the sous-chef still at work: keep its frame.
+            Err(RuntimeError::Paused) => return Err(RuntimeError::Paused),
+            Err(error) => {
+                // Clean up call stack before propagating error
+                self.record(|s| Change::Kitchen(Box::new(s.context.clone())));
+                self.context.call_stack.pop();
+                self.record(|s| Change::Serves(s.pending_serves.clone()));
+                self.pending_serves.pop();
+                return Err(error);
             }
+        };
+        if finished {
+            if let Err(error) = self.serve_pending() {
+                self.record_kitchen();
+                self.context.call_stack.pop();
+                return Err(error);
+            }
+        } else {
+            self.record(|s| Change::Serves(s.pending_serves.clone()));
+            self.pending_serves.pop();
         }
 
         // Get the auxiliary's first mixing bowl before restoring state
@@ -463,6 +1121,7 @@ impl Interpreter {
             VecDeque::new()
         };
 
+        self.record_kitchen();
         if let Some(frame) = self.context.call_stack.pop() {
             self.context.variables = frame.variables;
             self.context.unset_ingredients = frame.unset_ingredients;
@@ -472,8 +1131,13 @@ impl Interpreter {
             // Transfer auxiliary's first mixing bowl to caller's first mixing bowl
             // "empties it into his first mixing bowl" means we add all values from aux bowl
             self.ensure_bowl(0);
-            for value in aux_first_bowl.iter().rev() {
-                self.context.mixing_bowls[0].push_front(*value);
+            match self.profile.auxiliary_bowl {
+                AuxiliaryBowl::Prepend => {
+                    for value in aux_first_bowl.iter().rev() {
+                        self.context.mixing_bowls[0].push_front(*value);
+                    }
+                }
+                AuxiliaryBowl::Append => self.context.mixing_bowls[0].extend(aux_first_bowl),
             }
         }
 
@@ -481,7 +1145,21 @@ impl Interpreter {
     }
 
     fn write_output(&mut self, dish_count: usize) -> RuntimeResult<()> {
-        for dish in self.context.baking_dishes.iter_mut().take(dish_count) {
+        self.record(|s| Change::Output(s.output.len()));
+        for idx in 0..dish_count.min(self.context.baking_dishes.len()) {
+            self.record_stack(Stack::Dish(idx));
+        }
+        let options = &self.profile.output;
+        for (idx, dish) in self
+            .context
+            .baking_dishes
+            .iter_mut()
+            .take(dish_count)
+            .enumerate()
+        {
+            if idx > 0 {
+                self.output.push_str(&options.between_dishes);
+            }
             while let Some(value) = dish.pop_front() {
                 match value.measure {
                     Measure::Liquid => {
@@ -495,7 +1173,11 @@ impl Interpreter {
                     }
                     _ => {
                         // Writing to a String is infallible.
-                        let _ = write!(self.output, "{}", value.amount);
+                        let _ = write!(
+                            self.output,
+                            "{}{}{}",
+                            options.before_number, value.amount, options.after_number
+                        );
                     }
                 }
             }
@@ -504,13 +1186,46 @@ impl Interpreter {
         Ok(())
     }
 
+    /// Logs the whole stack before an instruction reorders, empties or adds
+    /// to it.
+    fn record_stack(&mut self, stack: Stack) {
+        self.record(|s| {
+            let stacks = match stack {
+                Stack::Bowl(_) => &s.context.mixing_bowls,
+                Stack::Dish(_) => &s.context.baking_dishes,
+            };
+            let (Stack::Bowl(idx) | Stack::Dish(idx)) = stack;
+            Change::Replaced(stack, stacks[idx].clone())
+        });
+    }
+
+    fn record_variable(&mut self, ingredient: &str) {
+        self.record(|s| Change::Variable {
+            name: ingredient.to_string(),
+            old: s.context.variables.get(ingredient).copied(),
+        });
+    }
+
+    /// Logs the kitchen and the deferred `Serves` before a `Serve with`
+    /// swaps kitchens.
+    fn record_kitchen(&mut self) {
+        self.record(|s| Change::Kitchen(Box::new(s.context.clone())));
+        self.record(|s| Change::Serves(s.pending_serves.clone()));
+    }
+
     fn ensure_bowl(&mut self, idx: usize) {
+        if self.context.mixing_bowls.len() <= idx {
+            self.record(|s| Change::Bowls(s.context.mixing_bowls.len()));
+        }
         while self.context.mixing_bowls.len() <= idx {
             self.context.mixing_bowls.push(VecDeque::new());
         }
     }
 
     fn ensure_dish(&mut self, idx: usize) {
+        if self.context.baking_dishes.len() <= idx {
+            self.record(|s| Change::Dishes(s.context.baking_dishes.len()));
+        }
         while self.context.baking_dishes.len() <= idx {
             self.context.baking_dishes.push(VecDeque::new());
         }
@@ -521,17 +1236,22 @@ impl Interpreter {
             return;
         }
         self.ensure_bowl(idx);
-        let bowl = &mut self.context.mixing_bowls[idx];
-        if bowl.len() <= 1 {
+        if self.context.mixing_bowls[idx].len() <= 1 {
             return;
         }
+        self.record_stack(Stack::Bowl(idx));
+        let bowl = &mut self.context.mixing_bowls[idx];
         if let Some(top) = bowl.pop_front() {
             let len = bowl.len();
             if len == 0 {
                 bowl.push_front(top);
                 return;
             }
-            let target = positions.min(len);
+            let target = match self.profile.stir {
+                StirPastBottom::Clamp => positions.min(len),
+                // The top can land in any of len + 1 places.
+                StirPastBottom::Wrap => positions % (len + 1),
+            };
             bowl.insert(target, top);
         }
     }
@@ -543,7 +1263,7 @@ impl Default for Interpreter {
     }
 }
 
-fn normalize_recipe_name(name: &str) -> String {
+pub(crate) fn normalize_recipe_name(name: &str) -> String {
     name.trim().trim_end_matches('.').to_lowercase()
 }
 
@@ -590,6 +1310,7 @@ mod tests {
                 Instruction::Serves(0),
             ],
             auxiliary_recipes: HashMap::new(),
+            ..Recipe::default()
         }
     }
 
@@ -628,6 +1349,33 @@ mod tests {
             .expect("recipe with auxiliary should execute");
     }
 
+    #[test]
+    fn run_recipe_starts_an_auxiliary_recipe_on_seeded_bowls() {
+        let source = "Main Dish.\n\nMethod.\nServe with caramel sauce.\n\nServes 1.\n\n\
+            Caramel Sauce.\n\nIngredients.\n5 g sugar\n\nMethod.\n\
+            Add sugar to the mixing bowl. \
+            Pour contents of the mixing bowl into the baking dish.\n\nServes 1.\n";
+        let number = |amount| Value {
+            amount,
+            measure: Measure::Unspecified,
+        };
+
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(crate::Parser::new(source).parse_recipe().unwrap());
+        interpreter.set_mixing_bowls(vec![VecDeque::from([number(10), number(1)])]);
+        interpreter.set_baking_dishes(vec![VecDeque::from([number(7)])]);
+        interpreter
+            .run_recipe("Caramel Sauce")
+            .expect("auxiliary recipe should run on its own");
+        assert_eq!(interpreter.output(), "1517");
+
+        let result = interpreter.run_recipe("Toffee Sauce");
+        assert!(matches!(
+            result,
+            Err(ChefError::Runtime(RuntimeError::UnknownRecipe { .. }))
+        ));
+    }
+
     #[test]
     fn stir_moves_top_down_by_minutes() {
         let mut interpreter = Interpreter::new();
@@ -964,6 +1712,7 @@ mod tests {
             unset_ingredients: HashMap::new(),
             instructions: vec![Instruction::Serves(0)],
             auxiliary_recipes: HashMap::new(),
+            ..Recipe::default()
         };
 
         let mut aux_map = HashMap::new();
@@ -978,6 +1727,7 @@ mod tests {
                 Instruction::Serves(0),
             ],
             auxiliary_recipes: aux_map,
+            ..Recipe::default()
         }
     }
 
@@ -997,6 +1747,18 @@ mod tests {
             .all(|value| matches!(value.measure, Measure::Liquid)));
     }
 
+    #[test]
+    fn a_failed_run_leaves_no_pending_serves() {
+        let source = "Burnt.\n\nMethod.\nFold sugar into the mixing bowl.\n\nServes 1.";
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(crate::Parser::new(source).parse_recipe().unwrap());
+        for _ in 0..2 {
+            assert!(interpreter.run().is_err());
+            assert!(interpreter.pending_serves.is_empty());
+        }
+        assert!(interpreter.snapshot().pending_serves.is_empty());
+    }
+
     #[test]
     fn loop_decrements_ingredient() {
         let mut interpreter = Interpreter::new();
@@ -1,17 +1,39 @@
+pub mod book;
+pub mod compat;
+pub mod compile;
+pub mod conjugation;
+pub mod coverage;
+pub mod dataflow;
+pub mod debugger;
 pub mod error_context;
 pub mod error_formatter;
+pub mod generate;
+pub mod golden;
+pub mod history;
 pub mod instruction;
 pub mod interpreter;
+pub mod lint;
 pub mod parser;
+pub mod profile;
+pub mod repl;
+pub mod snapshot;
 pub mod types;
+pub mod visual;
+pub mod watch;
 
+pub use book::RecipeBook;
+pub use compat::CompatProfile;
+pub use coverage::Coverage;
 pub use error_formatter::ErrorFormatter;
-pub use interpreter::Interpreter;
-pub use parser::Parser;
+pub use interpreter::{Interpreter, OutputOptions};
+pub use parser::{Ordinals, ParseOptions, Parser, Strictness, VerbMatching};
+pub use profile::Profiler;
+pub use snapshot::Snapshot;
 pub use types::{
-    ChefError, ExecutionContext, Measure, ParseError, ParseResult, Recipe, Result, RuntimeError,
-    RuntimeResult, Value,
+    BookError, ChefError, ExecutionContext, GenerateError, Measure, ParseError, ParseResult,
+    Recipe, Result, RuntimeError, RuntimeResult, SnapshotError, Value, WatchError,
 };
+pub use watch::Watch;
 
 #[cfg(test)]
 mod tests {
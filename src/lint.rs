@@ -0,0 +1,726 @@
+//! Static checks that flag suspicious but legal recipes.
+//!
+//! Lints are warnings: a recipe that triggers them still parses and runs.
+//! Every rule has a stable id (see [`LintRule::id`]) so it can be switched
+//! off with [`LintConfig`], e.g. from `cheffers lint --disable dead-fold`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use crate::compile::recipes_in_order;
+use crate::conjugation;
+use crate::dataflow::{self, FindingKind};
+use crate::error_context::SourceLocation;
+use crate::error_formatter::ordinal;
+use crate::instruction::Instruction;
+use crate::interpreter::normalize_recipe_name;
+use crate::parser::Parser;
+use crate::types::{Measure, Recipe};
+
+/// A named lint rule.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum LintRule {
+    UnusedIngredient,
+    TakeOnlyIngredient,
+    DeadFold,
+    UnreachableAfterRefrigerate,
+    StirZeroMinutes,
+    UnpouredBowl,
+    UnusedAuxiliary,
+    ServesTooMany,
+    LoopVerbMismatch,
+    LiquidNotACharacter,
+    UnservedLiquefy,
+    EmptyBowl,
+    MaybeEmptyBowl,
+}
+
+impl LintRule {
+    pub const ALL: [LintRule; 13] = [
+        LintRule::UnusedIngredient,
+        LintRule::TakeOnlyIngredient,
+        LintRule::DeadFold,
+        LintRule::UnreachableAfterRefrigerate,
+        LintRule::StirZeroMinutes,
+        LintRule::UnpouredBowl,
+        LintRule::UnusedAuxiliary,
+        LintRule::ServesTooMany,
+        LintRule::LoopVerbMismatch,
+        LintRule::LiquidNotACharacter,
+        LintRule::UnservedLiquefy,
+        LintRule::EmptyBowl,
+        LintRule::MaybeEmptyBowl,
+    ];
+
+    /// The id used on the command line and in output.
+    pub fn id(self) -> &'static str {
+        match self {
+            LintRule::UnusedIngredient => "unused-ingredient",
+            LintRule::TakeOnlyIngredient => "take-only-ingredient",
+            LintRule::DeadFold => "dead-fold",
+            LintRule::UnreachableAfterRefrigerate => "unreachable-after-refrigerate",
+            LintRule::StirZeroMinutes => "stir-zero-minutes",
+            LintRule::UnpouredBowl => "unpoured-bowl",
+            LintRule::UnusedAuxiliary => "unused-auxiliary",
+            LintRule::ServesTooMany => "serves-too-many",
+            LintRule::LoopVerbMismatch => "loop-verb-mismatch",
+            LintRule::LiquidNotACharacter => "liquid-not-a-character",
+            LintRule::UnservedLiquefy => "unserved-liquefy",
+            LintRule::EmptyBowl => "empty-bowl",
+            LintRule::MaybeEmptyBowl => "maybe-empty-bowl",
+        }
+    }
+
+    /// One-line explanation for `--list-rules`.
+    pub fn description(self) -> &'static str {
+        match self {
+            LintRule::UnusedIngredient => "an ingredient is declared but never used",
+            LintRule::TakeOnlyIngredient => {
+                "an ingredient is only read from input; its value is never used"
+            }
+            LintRule::DeadFold => "a value is folded into an ingredient that is never read again",
+            LintRule::UnreachableAfterRefrigerate => {
+                "instructions follow a 'Refrigerate' that is not inside a loop"
+            }
+            LintRule::StirZeroMinutes => "'Stir for 0 minutes' has no effect",
+            LintRule::UnpouredBowl => "a mixing bowl is filled but never poured or folded from",
+            LintRule::UnusedAuxiliary => "an auxiliary recipe is never served",
+            LintRule::ServesTooMany => "'Serves' names more dishes than are ever poured into",
+            LintRule::LoopVerbMismatch => {
+                "a loop ends on a word that starts with its verb but is not its past participle"
+            }
+            LintRule::LiquidNotACharacter => {
+                "a served liquid may be an amount that is not a character"
+            }
+            LintRule::UnservedLiquefy => "the liquid a 'Liquefy' makes is never served",
+            LintRule::EmptyBowl => "an instruction always takes from an empty mixing bowl",
+            LintRule::MaybeEmptyBowl => "an instruction may take from an empty mixing bowl",
+        }
+    }
+
+    pub fn from_id(id: &str) -> Option<LintRule> {
+        LintRule::ALL.into_iter().find(|rule| rule.id() == id)
+    }
+}
+
+impl fmt::Display for LintRule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.id())
+    }
+}
+
+/// Which rules run. All rules are enabled by default.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct LintConfig {
+    disabled: HashSet<LintRule>,
+}
+
+impl LintConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn enable(&mut self, rule: LintRule) -> &mut Self {
+        self.disabled.remove(&rule);
+        self
+    }
+
+    pub fn disable(&mut self, rule: LintRule) -> &mut Self {
+        self.disabled.insert(rule);
+        self
+    }
+
+    pub fn is_enabled(&self, rule: LintRule) -> bool {
+        !self.disabled.contains(&rule)
+    }
+}
+
+/// One warning.
+#[derive(Clone, Debug)]
+pub struct Lint {
+    pub rule: LintRule,
+    /// Title of the recipe the warning is about, without the period.
+    pub recipe: String,
+    pub message: String,
+    /// Where in the source, when the recipe came from the parser.
+    pub location: Option<SourceLocation>,
+}
+
+impl fmt::Display for Lint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "warning[{}]: {}", self.rule, self.message)
+    }
+}
+
+/// The rules behind [`dataflow::analyze`].
+const DATAFLOW_RULES: [LintRule; 4] = [
+    LintRule::LiquidNotACharacter,
+    LintRule::UnservedLiquefy,
+    LintRule::EmptyBowl,
+    LintRule::MaybeEmptyBowl,
+];
+
+/// Runs the enabled rules over `recipe` and its auxiliary recipes. Warnings
+/// come out recipe by recipe (main first), in source order.
+pub fn lint(recipe: &Recipe, config: &LintConfig) -> Vec<Lint> {
+    let recipes = recipes_in_order(recipe);
+    let poured_anywhere = recipes
+        .iter()
+        .map(|(_, r)| dishes_poured(&r.instructions))
+        .max()
+        .unwrap_or(0);
+    let served = served_recipes(&recipes);
+    let findings = if DATAFLOW_RULES
+        .into_iter()
+        .any(|rule| config.is_enabled(rule))
+    {
+        dataflow::analyze(recipe)
+    } else {
+        Vec::new()
+    };
+
+    let mut lints = Vec::new();
+    for (idx, (key, current)) in recipes.iter().enumerate() {
+        let is_main = idx == 0;
+        let mut checker = Checker::new(current, config);
+        checker.ingredients();
+        checker.dead_folds();
+        checker.unreachable_after_refrigerate();
+        checker.stir_zero_minutes();
+        checker.unpoured_bowls(is_main);
+        // A sous-chef serves copies of its caller's dishes.
+        let dishes = if is_main {
+            dishes_poured(&current.instructions)
+        } else {
+            poured_anywhere
+        };
+        checker.serves_too_many(dishes);
+        checker.loop_verb_mismatches();
+        if !is_main && !served.contains(key) {
+            checker.push(
+                LintRule::UnusedAuxiliary,
+                format!(
+                    "auxiliary recipe '{}' is never served",
+                    checker.recipe_name()
+                ),
+                current.source_map.title.clone(),
+            );
+        }
+        for finding in findings.iter().filter(|finding| finding.recipe == *key) {
+            let rule = match finding.kind {
+                FindingKind::LiquidNotACharacter => LintRule::LiquidNotACharacter,
+                FindingKind::UnservedLiquefy => LintRule::UnservedLiquefy,
+                FindingKind::EmptyBowl => LintRule::EmptyBowl,
+                FindingKind::MaybeEmptyBowl => LintRule::MaybeEmptyBowl,
+            };
+            checker.push(
+                rule,
+                finding.message.clone(),
+                checker.step_location(finding.instruction),
+            );
+        }
+
+        let mut found = checker.lints;
+        found.sort_by_key(|lint| {
+            lint.location
+                .as_ref()
+                .map(|l| (l.line, l.column.unwrap_or(0)))
+                .unwrap_or((usize::MAX, 0))
+        });
+        lints.extend(found);
+    }
+    lints
+}
+
+/// One instruction in a depth-first walk of a method.
+struct Step<'a> {
+    instruction: &'a Instruction,
+    /// Pre-order index of the outermost loop around this step, if any.
+    outer_loop: Option<usize>,
+}
+
+struct Checker<'a> {
+    recipe: &'a Recipe,
+    config: &'a LintConfig,
+    steps: Vec<Step<'a>>,
+    lints: Vec<Lint>,
+}
+
+impl<'a> Checker<'a> {
+    fn new(recipe: &'a Recipe, config: &'a LintConfig) -> Self {
+        let mut steps = Vec::new();
+        flatten(&recipe.instructions, None, &mut steps);
+        Self {
+            recipe,
+            config,
+            steps,
+            lints: Vec::new(),
+        }
+    }
+
+    fn recipe_name(&self) -> String {
+        self.recipe.title.trim().trim_end_matches('.').to_string()
+    }
+
+    fn push(&mut self, rule: LintRule, message: String, location: Option<SourceLocation>) {
+        if self.config.is_enabled(rule) {
+            self.lints.push(Lint {
+                rule,
+                recipe: self.recipe_name(),
+                message,
+                location,
+            });
+        }
+    }
+
+    fn step_location(&self, idx: usize) -> Option<SourceLocation> {
+        self.recipe.source_map.instructions.get(idx).cloned()
+    }
+
+    fn has_add_dry(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|step| matches!(step.instruction, Instruction::AddDry(_)))
+    }
+
+    /// unused-ingredient and take-only-ingredient.
+    fn ingredients(&mut self) {
+        let add_dry = self.has_add_dry();
+        let mut declared: Vec<(&String, Measure)> = self
+            .recipe
+            .ingredients
+            .iter()
+            .map(|(name, value)| (name, value.measure))
+            .chain(
+                self.recipe
+                    .unset_ingredients
+                    .iter()
+                    .map(|(name, measure)| (name, *measure)),
+            )
+            .collect();
+        declared.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (name, measure) in declared {
+            // "Add dry ingredients" uses every dry ingredient.
+            if add_dry && matches!(measure, Measure::Dry) {
+                continue;
+            }
+            let mentions: Vec<usize> = (0..self.steps.len())
+                .filter(|&idx| mentions(self.steps[idx].instruction, name))
+                .collect();
+            let location = self.recipe.source_map.ingredients.get(name).cloned();
+            if mentions.is_empty() {
+                self.push(
+                    LintRule::UnusedIngredient,
+                    format!("ingredient '{}' is declared but never used", name),
+                    location,
+                );
+            }
+        }
+
+        // Only-taken ingredients, declared or not.
+        let mut taken: Vec<(&String, usize)> = Vec::new();
+        for (idx, step) in self.steps.iter().enumerate() {
+            if let Instruction::Take(name) = step.instruction {
+                if !taken.iter().any(|(seen, _)| *seen == name) {
+                    taken.push((name, idx));
+                }
+            }
+        }
+        for (name, first_take) in taken {
+            if add_dry {
+                break;
+            }
+            let only_taken = self.steps.iter().all(|step| {
+                !mentions(step.instruction, name)
+                    || matches!(step.instruction, Instruction::Take(_))
+            });
+            if only_taken {
+                self.push(
+                    LintRule::TakeOnlyIngredient,
+                    format!(
+                        "ingredient '{}' is taken from the refrigerator but its value is never used",
+                        name
+                    ),
+                    self.step_location(first_take),
+                );
+            }
+        }
+    }
+
+    fn dead_folds(&mut self) {
+        let add_dry = self.has_add_dry();
+        let mut found = Vec::new();
+        for (idx, step) in self.steps.iter().enumerate() {
+            let Instruction::Fold(name, _) = step.instruction else {
+                continue;
+            };
+            // Inside a loop, earlier steps of the loop run again afterwards.
+            let from = step.outer_loop.unwrap_or(idx + 1);
+            let read_later = add_dry
+                || self.steps[from..]
+                    .iter()
+                    .enumerate()
+                    .any(|(offset, later)| from + offset != idx && reads(later.instruction, name));
+            if !read_later {
+                found.push((idx, name.clone()));
+            }
+        }
+        for (idx, name) in found {
+            self.push(
+                LintRule::DeadFold,
+                format!(
+                    "'{}' is folded out of the mixing bowl but never read again",
+                    name
+                ),
+                self.step_location(idx),
+            );
+        }
+    }
+
+    fn unreachable_after_refrigerate(&mut self) {
+        // Only the top level: a loop may skip its Refrigerate.
+        let mut idx = 0;
+        let mut found = None;
+        for (position, instruction) in self.recipe.instructions.iter().enumerate() {
+            if matches!(instruction, Instruction::Refrigerate(_))
+                && position + 1 < self.recipe.instructions.len()
+            {
+                found = Some(idx + 1);
+                break;
+            }
+            idx += instruction.preorder_len();
+        }
+        if let Some(next) = found {
+            self.push(
+                LintRule::UnreachableAfterRefrigerate,
+                "instructions after 'Refrigerate' are never reached".to_string(),
+                self.step_location(next),
+            );
+        }
+    }
+
+    fn stir_zero_minutes(&mut self) {
+        let found: Vec<usize> = (0..self.steps.len())
+            .filter(|&idx| matches!(self.steps[idx].instruction, Instruction::Stir(_, 0)))
+            .collect();
+        for idx in found {
+            self.push(
+                LintRule::StirZeroMinutes,
+                "stirring for 0 minutes does nothing".to_string(),
+                self.step_location(idx),
+            );
+        }
+    }
+
+    fn unpoured_bowls(&mut self, is_main: bool) {
+        let mut first_write: HashMap<usize, usize> = HashMap::new();
+        let mut emptied: HashSet<usize> = HashSet::new();
+        // A sous-chef hands its first mixing bowl back to the caller. So may
+        // a main recipe that serves nothing, such as a sauce in a book.
+        let serves = self.steps.iter().any(|step| {
+            matches!(
+                step.instruction,
+                Instruction::Serves(_) | Instruction::Refrigerate(Some(_))
+            )
+        });
+        if !is_main || !serves {
+            emptied.insert(0);
+        }
+        for (idx, step) in self.steps.iter().enumerate() {
+            match step.instruction {
+                Instruction::Put(_, bowl) | Instruction::AddDry(bowl) => {
+                    first_write.entry(*bowl).or_insert(idx);
+                }
+                Instruction::ServeWith(_) => {
+                    first_write.entry(0).or_insert(idx);
+                }
+                Instruction::Pour(bowl, _) | Instruction::Fold(_, bowl) => {
+                    emptied.insert(*bowl);
+                }
+                _ => {}
+            }
+        }
+        let mut unpoured: Vec<(usize, usize)> = first_write
+            .into_iter()
+            .filter(|(bowl, _)| !emptied.contains(bowl))
+            .collect();
+        unpoured.sort();
+        for (bowl, idx) in unpoured {
+            self.push(
+                LintRule::UnpouredBowl,
+                format!(
+                    "the {} mixing bowl is filled but never poured into a baking dish",
+                    ordinal(bowl + 1)
+                ),
+                self.step_location(idx),
+            );
+        }
+    }
+
+    fn serves_too_many(&mut self, dishes: usize) {
+        let found: Vec<(usize, usize)> = self
+            .steps
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, step)| match step.instruction {
+                Instruction::Serves(count) | Instruction::Refrigerate(Some(count))
+                    if *count > dishes =>
+                {
+                    Some((idx, *count))
+                }
+                _ => None,
+            })
+            .collect();
+        for (idx, count) in found {
+            let message = match dishes {
+                0 => format!(
+                    "serves {} but nothing is ever poured into a baking dish",
+                    count
+                ),
+                1 => format!(
+                    "serves {} but only 1 baking dish is ever poured into",
+                    count
+                ),
+                _ => format!(
+                    "serves {} but only {} baking dishes are ever poured into",
+                    count, dishes
+                ),
+            };
+            self.push(LintRule::ServesTooMany, message, self.step_location(idx));
+        }
+    }
+
+    /// loop-verb-mismatch: an "until" sentence the parser accepted only
+    /// leniently, because its word starts with the loop verb.
+    fn loop_verb_mismatches(&mut self) {
+        let found: Vec<(String, String, SourceLocation)> = self
+            .steps
+            .iter()
+            .enumerate()
+            .filter_map(|(idx, step)| {
+                let Instruction::Loop { verb, .. } = step.instruction else {
+                    return None;
+                };
+                let end = self.recipe.source_map.loop_ends.get(&idx)?;
+                let word = Parser::until_participle(end.snippet.as_deref()?)?;
+                (!conjugation::is_past_participle(word, verb))
+                    .then(|| (verb.clone(), word.to_string(), end.clone()))
+            })
+            .collect();
+        for (verb, word, location) in found {
+            self.push(
+                LintRule::LoopVerbMismatch,
+                format!(
+                    "'{}' is not a past participle of '{}'; expected '{}'",
+                    word,
+                    verb,
+                    conjugation::past_participle(&verb)
+                ),
+                Some(location),
+            );
+        }
+    }
+}
+
+fn flatten<'a>(
+    instructions: &'a [Instruction],
+    outer_loop: Option<usize>,
+    out: &mut Vec<Step<'a>>,
+) {
+    for instruction in instructions {
+        let idx = out.len();
+        out.push(Step {
+            instruction,
+            outer_loop,
+        });
+        if let Instruction::Loop { body, .. } = instruction {
+            flatten(body, outer_loop.or(Some(idx)), out);
+        }
+    }
+}
+
+/// Whether the instruction itself (not its loop body) reads the
+/// ingredient's value.
+fn reads(instruction: &Instruction, name: &str) -> bool {
+    match instruction {
+        Instruction::Put(ingredient, _)
+        | Instruction::Add(ingredient, _)
+        | Instruction::Remove(ingredient, _)
+        | Instruction::Combine(ingredient, _)
+        | Instruction::Divide(ingredient, _)
+        | Instruction::Liquefy(ingredient)
+        | Instruction::StirIngredient(ingredient, _) => ingredient == name,
+        Instruction::Loop {
+            condition_var,
+            decrement_var,
+            ..
+        } => condition_var == name || decrement_var.as_deref() == Some(name),
+        _ => false,
+    }
+}
+
+/// Whether the instruction itself names the ingredient at all.
+fn mentions(instruction: &Instruction, name: &str) -> bool {
+    match instruction {
+        Instruction::Take(ingredient) | Instruction::Fold(ingredient, _) => ingredient == name,
+        other => reads(other, name),
+    }
+}
+
+fn dishes_poured(instructions: &[Instruction]) -> usize {
+    instructions
+        .iter()
+        .map(|instruction| match instruction {
+            Instruction::Pour(_, dish) => dish + 1,
+            Instruction::Loop { body, .. } => dishes_poured(body),
+            _ => 0,
+        })
+        .max()
+        .unwrap_or(0)
+}
+
+/// Normalized names of the recipes reachable from the main recipe.
+fn served_recipes(recipes: &[(String, &Recipe)]) -> HashSet<String> {
+    let by_name: HashMap<&str, &Recipe> = recipes
+        .iter()
+        .map(|(key, recipe)| (key.as_str(), *recipe))
+        .collect();
+    let mut served = HashSet::new();
+    let mut pending = vec![recipes[0].1];
+    while let Some(recipe) = pending.pop() {
+        let mut steps = Vec::new();
+        flatten(&recipe.instructions, None, &mut steps);
+        for step in steps {
+            if let Instruction::ServeWith(name) = step.instruction {
+                let key = normalize_recipe_name(name);
+                if let Some(callee) = by_name.get(key.as_str()) {
+                    if served.insert(key) {
+                        pending.push(callee);
+                    }
+                }
+            }
+        }
+    }
+    served
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Parser;
+
+    fn lint_source(source: &str) -> Vec<Lint> {
+        let recipe = Parser::new(source).parse_recipe().expect("recipe parses");
+        lint(&recipe, &LintConfig::default())
+    }
+
+    fn rules(lints: &[Lint]) -> Vec<&'static str> {
+        lints.iter().map(|lint| lint.rule.id()).collect()
+    }
+
+    #[test]
+    fn clean_fixtures_have_no_warnings() {
+        for source in [
+            include_str!("../tests/fixtures/hello-world.chef"),
+            include_str!("../tests/fixtures/countdown-cake.chef"),
+            include_str!("../tests/fixtures/stack-reversal-stew.chef"),
+            include_str!("../tests/fixtures/factorial-fruitcake.chef"),
+            include_str!("../tests/fixtures/book/sauces/caramel-sauce.chef"),
+            include_str!("../tests/fixtures/book/sauces/chocolate-sauce.chef"),
+        ] {
+            let lints = lint_source(source);
+            assert!(lints.is_empty(), "unexpected warnings: {:?}", rules(&lints));
+        }
+    }
+
+    #[test]
+    fn flags_each_rule_with_a_location() {
+        let source = "\
+Suspicious Stew.
+
+Ingredients.
+1 g flour
+2 g sugar
+salt
+
+Method.
+Take salt from refrigerator.
+Put flour into the mixing bowl.
+Fold sugar into the mixing bowl.
+Stir for 0 minutes.
+Put flour into the 2nd mixing bowl.
+Refrigerate.
+Put flour into the mixing bowl.
+Serves 2.
+
+Unused Sauce.
+
+Method.
+Clean mixing bowl.";
+
+        let lints = lint_source(source);
+        assert_eq!(
+            rules(&lints),
+            vec![
+                "take-only-ingredient",
+                "dead-fold",
+                "stir-zero-minutes",
+                "unpoured-bowl",
+                "unreachable-after-refrigerate",
+                "serves-too-many",
+                "unused-auxiliary",
+            ]
+        );
+        let dead_fold = &lints[1];
+        assert_eq!(dead_fold.recipe, "Suspicious Stew");
+        assert_eq!(dead_fold.location.as_ref().map(|l| l.line), Some(11));
+        assert_eq!(lints[6].recipe, "Unused Sauce");
+    }
+
+    #[test]
+    fn unused_ingredient_points_at_its_declaration() {
+        let source = "Plain.\n\nIngredients.\n1 g flour\n2 g sugar\n\nMethod.\nPut flour into the mixing bowl.\nPour contents of the mixing bowl into the baking dish.\n\nServes 1.";
+        let lints = lint_source(source);
+        assert_eq!(rules(&lints), vec!["unused-ingredient"]);
+        assert!(lints[0].message.contains("'sugar'"));
+        assert_eq!(lints[0].location.as_ref().map(|l| l.line), Some(5));
+    }
+
+    #[test]
+    fn folds_read_earlier_in_a_loop_are_live() {
+        let source = "Loopy.\n\nIngredients.\n3 g flour\n\nMethod.\nSift the flour.\nPut flour into the mixing bowl.\nFold flour into the mixing bowl.\nSift until sifted.\nServes 0.";
+        assert!(lint_source(source).is_empty());
+    }
+
+    #[test]
+    fn prefix_only_participles_name_the_expected_one() {
+        let source = "Stirred.\n\nIngredients.\n3 g flour\n\nMethod.\nStir the flour.\nPut flour into the mixing bowl.\nStir the flour until stirringly.\nBeat the flour until beaten.\nPour contents of the mixing bowl into the baking dish.\n\nServes 1.";
+        let lints = lint_source(source);
+        assert_eq!(rules(&lints), vec!["loop-verb-mismatch"]);
+        assert_eq!(
+            lints[0].message,
+            "'stirringly' is not a past participle of 'Stir'; expected 'stirred'"
+        );
+        let location = lints[0].location.as_ref().unwrap();
+        assert_eq!((location.line, location.column), (9, Some(1)));
+    }
+
+    #[test]
+    fn disabled_rules_are_skipped() {
+        let source = "Idle.\n\nMethod.\nStir for 0 minutes.";
+        let recipe = Parser::new(source).parse_recipe().unwrap();
+        let mut config = LintConfig::new();
+        assert_eq!(lint(&recipe, &config).len(), 1);
+        config.disable(LintRule::StirZeroMinutes);
+        assert!(lint(&recipe, &config).is_empty());
+    }
+
+    #[test]
+    fn rule_ids_round_trip() {
+        for rule in LintRule::ALL {
+            assert_eq!(LintRule::from_id(rule.id()), Some(rule));
+        }
+        assert_eq!(LintRule::from_id("no-such-rule"), None);
+    }
+}
@@ -1,26 +1,259 @@
+use cheffers::compile::{compile_js, compile_wat};
+use cheffers::debugger::Debugger;
 use cheffers::error_formatter::ErrorFormatter;
-use cheffers::{Interpreter, Parser};
+use cheffers::generate::{stack_to_recipe, text_to_recipe, TextOptions};
+use cheffers::golden::{run_all, GoldenOptions, Outcome};
+use cheffers::lint::{lint, LintConfig, LintRule};
+use cheffers::repl::{Repl, Reply};
+use cheffers::visual::{Key, Visual};
+use cheffers::{
+    CompatProfile, Coverage, Interpreter, Ordinals, OutputOptions, ParseOptions, Parser,
+    RecipeBook, Snapshot, VerbMatching, Watch,
+};
 
-use std::io::Write;
+use std::io::{BufRead, Read, Write};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
 use std::{env, fs, process};
 
+const USAGE: &str = "\
+usage: cheffers [--strict | --strict-verbs] [--ordinal-words] [--compat cheffers|acme]
+                [--output-style plain|spaced|lines|perl] [--book sauces.chef]...
+                [--entry \"Recipe Title\"] [--profile] [--profile-folded stacks.txt]
+                [--snapshot state.txt [--pause-after N | --checkpoint-every N]]
+                [--resume state.txt] [--watch EXPR]... [recipe.chef]
+       cheffers compile [--target js|wasm] [-o output] <recipe.chef>
+       cheffers chefgen [--title title] [-o output] <program.stack>
+       cheffers chefgen --text [--food-names] [--title title] [-o output] <message.txt>
+       cheffers lint [--enable rule,...] [--disable rule,...] <recipe.chef>
+       cheffers lint --list-rules
+       cheffers coverage [--input values.in]... [--lcov lcov.info] <recipe.chef>
+       cheffers debug [--input values.in] <recipe.chef>
+       cheffers watch [--input values.in] <recipe.chef>
+       cheffers repl
+       cheffers test [--bless | --doc] [--seed N] [--max-loop-iterations N] [--max-call-depth N]
+                [path...]";
+
+/// What the command line asked for.
+// Parsed once per process, so the size of `Run` does not matter.
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, PartialEq)]
+enum Command {
+    /// Interpret a recipe (the default).
+    Run {
+        path: String,
+        options: ParseOptions,
+        profile: CompatProfile,
+        /// Recipe book files or directories, after those in the search path.
+        books: Vec<String>,
+        /// The recipe to run instead of the file's first one.
+        entry: Option<String>,
+        profiling: Profiling,
+        checkpoints: Checkpoints,
+    },
+    /// Translate a recipe into another language.
+    Compile {
+        target: Target,
+        path: String,
+        output: Option<String>,
+    },
+    /// Write a recipe from a stack-language program or a text to serve.
+    Chefgen {
+        input: GenInput,
+        path: String,
+        title: Option<String>,
+        output: Option<String>,
+    },
+    /// Report suspicious constructs without running the recipe.
+    Lint { path: String, config: LintConfig },
+    /// Print the lint rule ids and what they check.
+    LintRules,
+    /// Run a recipe once per input file and report what never ran.
+    Coverage {
+        path: String,
+        inputs: Vec<String>,
+        lcov: Option<String>,
+    },
+    /// Step through a run, forwards and backwards.
+    Debug { path: String, input: Option<String> },
+    /// Animate a run's bowls and dishes in the terminal.
+    Watch { path: String, input: Option<String> },
+    /// Run method sentences interactively.
+    Repl,
+    /// Check recipes against their expected output files.
+    Test {
+        paths: Vec<String>,
+        options: GoldenOptions,
+    },
+}
+
+/// What a run reports on the side: what it cost, and the watches it hit.
+#[derive(Debug, Default, PartialEq)]
+struct Profiling {
+    /// Print a table to stderr.
+    report: bool,
+    /// Write folded stacks for flamegraph tools to this file.
+    folded: Option<String>,
+    /// Log every hit of these to stderr.
+    watches: Vec<Watch>,
+}
+
+/// Saving a run's state part way through, and picking it up again.
+#[derive(Debug, Default, PartialEq)]
+struct Checkpoints {
+    /// Stop after this many instructions.
+    pause_after: Option<u64>,
+    /// Save the state every this many instructions and go on.
+    every: Option<u64>,
+    /// Where the state is saved.
+    snapshot: Option<String>,
+    /// A saved state to continue from.
+    resume: Option<String>,
+}
+
+/// What `chefgen` reads from its input file.
+#[derive(Debug, PartialEq)]
+enum GenInput {
+    StackProgram,
+    /// A message the recipe should serve verbatim.
+    Text {
+        food_names: bool,
+    },
+}
+
+#[derive(Debug, PartialEq)]
+enum Target {
+    JavaScript,
+    /// WebAssembly, emitted in the text format (WAT).
+    Wasm,
+}
+
 fn main() {
-    let result = run();
-    if let Err(error) = result {
+    let command = match parse_args(env::args()) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+
+    if let Err(error) = execute(command) {
         eprintln!("{}", ErrorFormatter::format(&error));
         process::exit(1);
     }
 }
 
-fn run() -> cheffers::Result<()> {
-    let path = recipe_path_from_args(env::args());
-    let source = fs::read_to_string(&path)?;
-    let parser = Parser::new(&source);
+fn execute(command: Command) -> cheffers::Result<()> {
+    match command {
+        Command::Run {
+            path,
+            options,
+            profile,
+            books,
+            entry,
+            profiling,
+            checkpoints,
+        } => run(
+            &path,
+            options,
+            profile,
+            &books,
+            entry.as_deref(),
+            &profiling,
+            &checkpoints,
+        ),
+        Command::Compile {
+            target,
+            path,
+            output,
+        } => compile(target, &path, output.as_deref()),
+        Command::Chefgen {
+            input,
+            path,
+            title,
+            output,
+        } => chefgen(input, &path, title, output.as_deref()),
+        Command::Lint { path, config } => lint_recipe(&path, &config),
+        Command::Coverage { path, inputs, lcov } => coverage(&path, &inputs, lcov.as_deref()),
+        Command::Debug { path, input } => debug(&path, input.as_deref()),
+        Command::Watch { path, input } => watch(&path, input.as_deref()),
+        Command::Repl => repl(),
+        Command::Test { paths, options } => golden_tests(&paths, &options),
+        Command::LintRules => {
+            for rule in LintRule::ALL {
+                println!("{:<30} {}", rule.id(), rule.description());
+            }
+            Ok(())
+        }
+    }
+}
+
+fn run(
+    path: &str,
+    options: ParseOptions,
+    profile: CompatProfile,
+    books: &[String],
+    entry: Option<&str>,
+    profiling: &Profiling,
+    checkpoints: &Checkpoints,
+) -> cheffers::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let parser = Parser::with_options(&source, options);
     let recipe = parser.parse_recipe()?;
 
+    let mut book = RecipeBook::new();
+    let book_paths = RecipeBook::search_path()
+        .into_iter()
+        .chain(books.iter().map(PathBuf::from));
+    for book_path in book_paths {
+        book.load(&book_path, options)?;
+    }
+    for shadowed in book.shadowed_by(&recipe) {
+        eprintln!(
+            "warning: '{}' in {} hides the recipe of the same name in {}",
+            shadowed.recipe.title,
+            path,
+            shadowed.path.display()
+        );
+    }
+
+    let title = recipe.title.clone();
     let mut interpreter = Interpreter::new();
+    interpreter.set_compat_profile(profile);
     interpreter.add_recipe(recipe);
-    interpreter.run()?;
+    interpreter.add_book(&book);
+    if profiling.report || profiling.folded.is_some() {
+        interpreter.enable_profiling();
+    }
+    // The watches look at the kitchen of the recipe the run starts with.
+    let watched = interpreter.recipe(entry.unwrap_or(&title));
+    for watch in &profiling.watches {
+        if let Some(recipe) = watched {
+            watch.check(recipe)?;
+        }
+    }
+    for watch in &profiling.watches {
+        interpreter.watch(watch.clone());
+    }
+    if !profiling.watches.is_empty() {
+        interpreter.report_watch_hits(|hit| eprintln!("watch: {}", hit));
+    }
+    let result = run_with_checkpoints(&mut interpreter, entry, checkpoints);
+
+    // A run that failed (a loop limit, say) is often the one worth profiling.
+    if let Some(profiler) = interpreter.profiler() {
+        if profiling.report {
+            eprint!("{}", profiler.report());
+        }
+        if let Some(folded) = &profiling.folded {
+            fs::write(folded, profiler.folded())?;
+        }
+    }
+    if !result? {
+        // Paused: the output so far is part of the saved state.
+        return Ok(());
+    }
 
     // The interpreter accumulates output in a buffer; flush it to stdout.
     print!("{}", interpreter.output());
@@ -29,34 +262,1194 @@ fn run() -> cheffers::Result<()> {
     Ok(())
 }
 
-fn recipe_path_from_args<I>(mut args: I) -> String
+/// Runs (or resumes) the recipe, saving its state as `checkpoints` asks.
+/// Returns whether the run finished rather than paused.
+fn run_with_checkpoints(
+    interpreter: &mut Interpreter,
+    entry: Option<&str>,
+    checkpoints: &Checkpoints,
+) -> cheffers::Result<bool> {
+    if let Some(steps) = checkpoints.pause_after.or(checkpoints.every) {
+        interpreter.pause_after(steps);
+    }
+    match (&checkpoints.resume, entry) {
+        (Some(saved), _) => {
+            let snapshot: Snapshot = fs::read_to_string(saved)?.parse()?;
+            interpreter.restore(snapshot);
+            interpreter.resume()?;
+        }
+        (None, Some(name)) => interpreter.run_recipe(name)?,
+        (None, None) => interpreter.run()?,
+    }
+
+    let Some(file) = &checkpoints.snapshot else {
+        return Ok(true);
+    };
+    while interpreter.is_paused() {
+        fs::write(file, interpreter.snapshot().to_string())?;
+        if let Some(steps) = checkpoints.pause_after {
+            eprintln!(
+                "paused after {} instructions; continue with --resume {}",
+                steps, file
+            );
+            return Ok(false);
+        }
+        if let Some(steps) = checkpoints.every {
+            interpreter.pause_after(steps);
+        }
+        interpreter.resume()?;
+    }
+    // A finished run leaves no checkpoint to resume by mistake.
+    if checkpoints.every.is_some() {
+        let _ = fs::remove_file(file);
+    }
+    Ok(true)
+}
+
+fn compile(target: Target, path: &str, output: Option<&str>) -> cheffers::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let recipe = Parser::new(&source).parse_recipe()?;
+
+    let code = match target {
+        Target::JavaScript => compile_js(&recipe),
+        Target::Wasm => compile_wat(&recipe),
+    };
+
+    write_result(&code, output)
+}
+
+fn chefgen(
+    input: GenInput,
+    path: &str,
+    title: Option<String>,
+    output: Option<&str>,
+) -> cheffers::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let recipe = match input {
+        GenInput::StackProgram => {
+            stack_to_recipe(&source, title.as_deref().unwrap_or("Stack Program Stew"))?
+        }
+        GenInput::Text { food_names } => {
+            let mut options = TextOptions {
+                food_names,
+                ..TextOptions::default()
+            };
+            if let Some(title) = title {
+                options.title = title;
+            }
+            text_to_recipe(&source, &options)
+        }
+    };
+    write_result(&recipe, output)
+}
+
+/// Prints one line per recipe and a summary, like `cargo test`, and exits
+/// with status 1 if any recipe failed.
+fn golden_tests(paths: &[String], options: &GoldenOptions) -> cheffers::Result<()> {
+    let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+    let cases = run_all(&paths, options)?;
+
+    let (mut passed, mut blessed) = (0, 0);
+    let mut failures = Vec::new();
+    for case in &cases {
+        let status = match &case.outcome {
+            Outcome::Passed => {
+                passed += 1;
+                "ok"
+            }
+            Outcome::Blessed => {
+                blessed += 1;
+                "blessed"
+            }
+            Outcome::Failed { report } => {
+                failures.push((&case.path, report));
+                "FAILED"
+            }
+        };
+        println!("test {} ... {}", case.path.display(), status);
+    }
+
+    if !failures.is_empty() {
+        println!("\nfailures:");
+        for (path, report) in &failures {
+            println!("\n---- {} ----\n{}", path.display(), report.trim_end());
+        }
+    }
+    println!(
+        "\ntest result: {}. {} passed; {} failed; {} blessed",
+        if failures.is_empty() { "ok" } else { "FAILED" },
+        passed,
+        failures.len(),
+        blessed
+    );
+    let _ = std::io::stdout().flush();
+
+    if !failures.is_empty() {
+        process::exit(1);
+    }
+    Ok(())
+}
+
+/// Prints warnings to stderr. Lints never fail the command.
+fn lint_recipe(path: &str, config: &LintConfig) -> cheffers::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let recipe = Parser::new(&source).parse_recipe()?;
+
+    for warning in lint(&recipe, config) {
+        eprintln!("{}", warning);
+        match &warning.location {
+            Some(location) => eprintln!(
+                "  --> {}:{}:{} ({})",
+                path,
+                location.line,
+                location.column.unwrap_or(1),
+                warning.recipe
+            ),
+            None => eprintln!("  --> {} ({})", path, warning.recipe),
+        }
+    }
+    Ok(())
+}
+
+/// Prints the annotated recipe. A run that fails still counts; its error is
+/// reported and the next run goes ahead.
+fn coverage(path: &str, inputs: &[String], lcov: Option<&str>) -> cheffers::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let recipe = Parser::new(&source).parse_recipe()?;
+
+    let mut coverage = Coverage::new(&recipe);
+    // Without input files, one run reads stdin like `cheffers recipe.chef`.
+    let runs: Vec<Option<&String>> = if inputs.is_empty() {
+        vec![None]
+    } else {
+        inputs.iter().map(Some).collect()
+    };
+    for input in runs {
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(recipe.clone());
+        if let Some(input) = input {
+            interpreter.set_input_text(&fs::read_to_string(input)?);
+        }
+        interpreter.set_coverage(coverage);
+        let result = interpreter.run();
+        coverage = interpreter.take_coverage().unwrap_or_default();
+        if let Err(error) = result {
+            let name = input.map_or("stdin", String::as_str);
+            eprintln!(
+                "run with {} failed:\n{}",
+                name,
+                ErrorFormatter::format(&error)
+            );
+        }
+    }
+
+    print!("{}", coverage.annotate(&source));
+    if let Some(lcov) = lcov {
+        fs::write(lcov, coverage.lcov(path))?;
+    }
+    Ok(())
+}
+
+/// A debugger paused before the recipe's first instruction. `Take` reads
+/// the input file, since stdin holds the user's commands.
+fn start_debugger(source: &str, input: Option<&str>) -> cheffers::Result<Debugger> {
+    let mut interpreter = Interpreter::new();
+    interpreter.add_recipe(Parser::new(source).parse_recipe()?);
+    let input = match input {
+        Some(input) => fs::read_to_string(input)?,
+        None => String::new(),
+    };
+    interpreter.set_input_text(&input);
+    Debugger::new(interpreter)
+}
+
+/// Reads debugger commands until `quit` or the end of stdin.
+fn debug(path: &str, input: Option<&str>) -> cheffers::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let mut debugger = start_debugger(&source, input)?;
+
+    println!("Cheffers debugger. Type help for commands, quit to leave.");
+    match debugger.location() {
+        Some(location) => println!("at {}", location),
+        None => println!("the method is empty"),
+    }
+    let stdin = std::io::stdin();
+    loop {
+        print!("(debug) ");
+        let _ = std::io::stdout().flush();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        match debugger.eval(&line) {
+            Reply::Quit => return Ok(()),
+            Reply::Text(text) if text.is_empty() => {}
+            Reply::Text(text) => println!("{}", text),
+        }
+    }
+}
+
+/// Animates the run until `q`. Keys are read as they are typed by switching
+/// the terminal with `stty`, which is put back on the way out.
+fn watch(path: &str, input: Option<&str>) -> cheffers::Result<()> {
+    let source = fs::read_to_string(path)?;
+    let mut visual = Visual::new(start_debugger(&source, input)?, &source);
+
+    let _terminal = RawTerminal::enter()?;
+    animate(&mut visual)
+}
+
+/// The terminal switched to read keys as they are typed, on the alternate
+/// screen. Dropping it puts the terminal back, also when `cheffers watch`
+/// returns early with an error or panics.
+struct RawTerminal {
+    /// The settings `stty -g` printed before the switch.
+    saved: String,
+}
+
+impl RawTerminal {
+    fn enter() -> cheffers::Result<Self> {
+        let terminal = Self {
+            saved: stty(&["-g"])?.trim().to_string(),
+        };
+        // A read returns within a tenth of a second, with or without a key.
+        stty(&["-icanon", "-echo", "-isig", "min", "0", "time", "1"])?;
+        // The alternate screen keeps the shell's scrollback as it was.
+        print!("\x1b[?1049h\x1b[?25l");
+        Ok(terminal)
+    }
+}
+
+impl Drop for RawTerminal {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = std::io::stdout().flush();
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+fn animate(visual: &mut Visual) -> cheffers::Result<()> {
+    let mut stdin = std::io::stdin();
+    let mut buffer = [0; 64];
+    let (mut rows, mut columns) = terminal_size();
+    let mut resized = Instant::now();
+    let mut stepped = Instant::now();
+    loop {
+        print!("{}", visual.render(columns, rows));
+        let _ = std::io::stdout().flush();
+
+        let read = stdin.read(&mut buffer)?;
+        for key in Key::parse(&buffer[..read]) {
+            if !visual.key(key) {
+                return Ok(());
+            }
+        }
+        if visual.is_playing() {
+            let interval = Duration::from_secs(1) / visual.steps_per_second();
+            let due = stepped.elapsed().as_nanos() / interval.as_nanos();
+            if due > 0 {
+                visual.advance(due as usize);
+                stepped = Instant::now();
+            }
+        } else {
+            stepped = Instant::now();
+        }
+        if resized.elapsed() >= Duration::from_secs(1) {
+            (rows, columns) = terminal_size();
+            resized = Instant::now();
+        }
+    }
+}
+
+/// Runs `stty` on the terminal cheffers reads from, returning what it
+/// printed.
+fn stty(args: &[&str]) -> cheffers::Result<String> {
+    let output = process::Command::new("stty")
+        .args(args)
+        .stdin(Stdio::inherit())
+        .output()?;
+    if !output.status.success() {
+        let message = format!(
+            "cheffers watch needs a terminal: stty failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        );
+        return Err(std::io::Error::new(std::io::ErrorKind::Other, message).into());
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Rows and columns of the terminal, or 24 by 80 if `stty` cannot tell.
+fn terminal_size() -> (usize, usize) {
+    let size = stty(&["size"]).unwrap_or_default();
+    let mut numbers = size.split_whitespace().map(str::parse);
+    match (numbers.next(), numbers.next()) {
+        (Some(Ok(rows)), Some(Ok(columns))) => (rows, columns),
+        _ => (24, 80),
+    }
+}
+
+fn repl() -> cheffers::Result<()> {
+    println!("Cheffers REPL. Type :help for commands, :quit to leave.");
+    let mut session = Repl::new();
+    let stdin = std::io::stdin();
+    loop {
+        print!("{}", session.prompt());
+        let _ = std::io::stdout().flush();
+
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            println!();
+            return Ok(());
+        }
+        match session.eval(&line) {
+            Reply::Quit => return Ok(()),
+            Reply::Text(text) if text.is_empty() => {}
+            Reply::Text(text) => println!("{}", text),
+        }
+    }
+}
+
+/// Writes generated text to `output`, or to stdout when no file is given.
+fn write_result(text: &str, output: Option<&str>) -> cheffers::Result<()> {
+    match output {
+        Some(out_path) => fs::write(out_path, text)?,
+        None => {
+            print!("{}", text);
+            let _ = std::io::stdout().flush();
+        }
+    }
+    Ok(())
+}
+
+fn parse_args<I>(mut args: I) -> Result<Command, String>
 where
     I: Iterator<Item = String>,
 {
     // Skip binary name
     let _ = args.next();
+    let args: Vec<String> = args.collect();
+
+    match args.first().map(String::as_str) {
+        Some("compile") => parse_compile_args(&args[1..]),
+        Some("chefgen") => parse_chefgen_args(&args[1..]),
+        Some("lint") => parse_lint_args(&args[1..]),
+        Some("test") => parse_test_args(&args[1..]),
+        Some("coverage") => parse_coverage_args(&args[1..]),
+        Some("debug") => {
+            let (path, input) = parse_debug_args("debug", &args[1..])?;
+            Ok(Command::Debug { path, input })
+        }
+        Some("watch") => {
+            let (path, input) = parse_debug_args("watch", &args[1..])?;
+            Ok(Command::Watch { path, input })
+        }
+        Some("repl") if args.len() == 1 => Ok(Command::Repl),
+        Some("repl") => Err("repl takes no arguments".to_string()),
+        _ => parse_run_args(&args),
+    }
+}
+
+fn parse_run_args(args: &[String]) -> Result<Command, String> {
+    let mut options = ParseOptions::default();
+    let mut profile = CompatProfile::default();
+    let mut output = None;
+    let mut books = Vec::new();
+    let mut entry = None;
+    let mut profiling = Profiling::default();
+    let mut checkpoints = Checkpoints::default();
+    let mut paths = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--strict" => {
+                options = ParseOptions {
+                    ordinals: options.ordinals,
+                    ..ParseOptions::strict()
+                }
+            }
+            "--strict-verbs" => options.verb_matching = VerbMatching::Strict,
+            "--ordinal-words" => options.ordinals = Ordinals::Words,
+            "--output-style" => {
+                let style = iter
+                    .next()
+                    .ok_or_else(|| "--output-style needs a value".to_string())?;
+                output = Some(OutputOptions::from_style(style).ok_or_else(|| {
+                    format!(
+                        "unknown output style '{}' (expected one of: {})",
+                        style,
+                        OutputOptions::STYLES.join(", ")
+                    )
+                })?);
+            }
+            "--book" => {
+                let book = iter
+                    .next()
+                    .ok_or_else(|| "--book needs a file or directory".to_string())?;
+                books.push(book.clone());
+            }
+            "--entry" => {
+                let name = iter
+                    .next()
+                    .ok_or_else(|| "--entry needs a recipe title".to_string())?;
+                entry = Some(name.clone());
+            }
+            "--profile" => profiling.report = true,
+            "--watch" => {
+                let watch = iter.next().ok_or_else(|| {
+                    "--watch needs an ingredient, bowl, dish or condition".to_string()
+                })?;
+                let watch = watch
+                    .parse()
+                    .map_err(|error| format!("--watch: {}", error))?;
+                profiling.watches.push(watch);
+            }
+            "--profile-folded" => {
+                let file = iter
+                    .next()
+                    .ok_or_else(|| "--profile-folded needs a file".to_string())?;
+                profiling.folded = Some(file.clone());
+            }
+            "--snapshot" | "--resume" => {
+                let file = iter.next().ok_or_else(|| format!("{} needs a file", arg))?;
+                if arg == "--snapshot" {
+                    checkpoints.snapshot = Some(file.clone());
+                } else {
+                    checkpoints.resume = Some(file.clone());
+                }
+            }
+            "--pause-after" | "--checkpoint-every" => {
+                let steps = iter
+                    .next()
+                    .and_then(|steps| steps.parse().ok())
+                    .ok_or_else(|| format!("{} needs a number of instructions", arg))?;
+                if arg == "--pause-after" {
+                    checkpoints.pause_after = Some(steps);
+                } else {
+                    checkpoints.every = Some(steps);
+                }
+            }
+            "--compat" => {
+                let name = iter
+                    .next()
+                    .ok_or_else(|| "--compat needs a value".to_string())?;
+                profile = CompatProfile::from_name(name).ok_or_else(|| {
+                    format!(
+                        "unknown compatibility profile '{}' (expected one of: {})",
+                        name,
+                        CompatProfile::NAMES.join(", ")
+                    )
+                })?;
+            }
+            _ => paths.push(arg.clone()),
+        }
+    }
+    if checkpoints.pause_after.is_some() && checkpoints.every.is_some() {
+        return Err("--pause-after and --checkpoint-every cannot be combined".to_string());
+    }
+    if (checkpoints.pause_after.is_some() || checkpoints.every.is_some())
+        && checkpoints.snapshot.is_none()
+    {
+        return Err("--pause-after and --checkpoint-every need --snapshot FILE".to_string());
+    }
+    // An explicit output style wins over the profile's, in either order.
+    if let Some(output) = output {
+        profile.output = output;
+    }
+    Ok(Command::Run {
+        path: recipe_path_from_args(paths.into_iter()),
+        options,
+        profile,
+        books,
+        entry,
+        profiling,
+        checkpoints,
+    })
+}
+
+fn parse_compile_args(args: &[String]) -> Result<Command, String> {
+    let mut target = Target::JavaScript;
+    let mut output = None;
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--target" => {
+                target = match iter.next().map(String::as_str) {
+                    Some("js") | Some("javascript") => Target::JavaScript,
+                    Some("wasm") | Some("wat") => Target::Wasm,
+                    Some(other) => return Err(format!("unknown compile target '{}'", other)),
+                    None => return Err("--target needs a value".to_string()),
+                }
+            }
+            "-o" | "--output" => {
+                output = Some(
+                    iter.next()
+                        .ok_or_else(|| format!("{} needs a file name", arg))?
+                        .clone(),
+                );
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option '{}'", other));
+            }
+            other => path = Some(other.to_string()),
+        }
+    }
+
+    Ok(Command::Compile {
+        target,
+        path: path.ok_or_else(|| "compile needs a recipe file".to_string())?,
+        output,
+    })
+}
+
+fn parse_chefgen_args(args: &[String]) -> Result<Command, String> {
+    let mut input = GenInput::StackProgram;
+    let mut food_names = false;
+    let mut title = None;
+    let mut output = None;
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--text" => input = GenInput::Text { food_names: false },
+            "--food-names" => food_names = true,
+            "--title" => {
+                title = Some(
+                    iter.next()
+                        .ok_or_else(|| "--title needs a value".to_string())?
+                        .clone(),
+                );
+            }
+            "-o" | "--output" => {
+                output = Some(
+                    iter.next()
+                        .ok_or_else(|| format!("{} needs a file name", arg))?
+                        .clone(),
+                );
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option '{}'", other));
+            }
+            other => path = Some(other.to_string()),
+        }
+    }
+
+    if food_names {
+        match &mut input {
+            GenInput::Text { food_names } => *food_names = true,
+            GenInput::StackProgram => {
+                return Err("--food-names only applies with --text".to_string())
+            }
+        }
+    }
+
+    Ok(Command::Chefgen {
+        input,
+        path: path.ok_or_else(|| "chefgen needs an input file".to_string())?,
+        title,
+        output,
+    })
+}
+
+fn parse_test_args(args: &[String]) -> Result<Command, String> {
+    let mut options = GoldenOptions::default();
+    let mut paths = Vec::new();
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--bless" => options.bless = true,
+            "--doc" => options.doc = true,
+            "--seed" | "--max-loop-iterations" | "--max-call-depth" => {
+                let value = iter
+                    .next()
+                    .ok_or_else(|| format!("{} needs a number", arg))?;
+                let number = value
+                    .parse()
+                    .map_err(|_| format!("{} needs a number, not '{}'", arg, value))?;
+                match arg.as_str() {
+                    "--seed" => options.seed = number,
+                    "--max-loop-iterations" => options.max_loop_iterations = number as usize,
+                    _ => options.max_call_depth = number as usize,
+                }
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option '{}'", other));
+            }
+            other => paths.push(other.to_string()),
+        }
+    }
+    if options.bless && options.doc {
+        return Err("--bless cannot rewrite the examples of --doc".to_string());
+    }
+    if paths.is_empty() {
+        paths.push(".".to_string());
+    }
+
+    Ok(Command::Test { paths, options })
+}
+
+fn parse_lint_args(args: &[String]) -> Result<Command, String> {
+    let mut config = LintConfig::new();
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--list-rules" => return Ok(Command::LintRules),
+            "--enable" | "--disable" => {
+                let ids = iter
+                    .next()
+                    .ok_or_else(|| format!("{} needs a rule id", arg))?;
+                for rule in parse_rule_ids(ids)? {
+                    if arg == "--enable" {
+                        config.enable(rule);
+                    } else {
+                        config.disable(rule);
+                    }
+                }
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option '{}'", other));
+            }
+            other => path = Some(other.to_string()),
+        }
+    }
+
+    Ok(Command::Lint {
+        path: path.ok_or_else(|| "lint needs a recipe file".to_string())?,
+        config,
+    })
+}
+
+fn parse_coverage_args(args: &[String]) -> Result<Command, String> {
+    let mut inputs = Vec::new();
+    let mut lcov = None;
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--input" => {
+                let input = iter
+                    .next()
+                    .ok_or_else(|| "--input needs a file".to_string())?;
+                inputs.push(input.clone());
+            }
+            "--lcov" => {
+                let file = iter
+                    .next()
+                    .ok_or_else(|| "--lcov needs a file".to_string())?;
+                lcov = Some(file.clone());
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option '{}'", other));
+            }
+            other => path = Some(other.to_string()),
+        }
+    }
+
+    Ok(Command::Coverage {
+        path: path.ok_or_else(|| "coverage needs a recipe file".to_string())?,
+        inputs,
+        lcov,
+    })
+}
+
+/// The recipe file and the `--input` file of `debug` and `watch`.
+fn parse_debug_args(command: &str, args: &[String]) -> Result<(String, Option<String>), String> {
+    let mut input = None;
+    let mut path = None;
+
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "--input" => {
+                let file = iter
+                    .next()
+                    .ok_or_else(|| "--input needs a file".to_string())?;
+                input = Some(file.clone());
+            }
+            other if other.starts_with('-') => {
+                return Err(format!("unknown option '{}'", other));
+            }
+            other => path = Some(other.to_string()),
+        }
+    }
+
+    let path = path.ok_or_else(|| format!("{} needs a recipe file", command))?;
+    Ok((path, input))
+}
+
+/// A comma-separated list of rule ids; `all` names every rule.
+fn parse_rule_ids(ids: &str) -> Result<Vec<LintRule>, String> {
+    let mut rules = Vec::new();
+    for id in ids.split(',').map(str::trim).filter(|id| !id.is_empty()) {
+        if id == "all" {
+            rules.extend(LintRule::ALL);
+            continue;
+        }
+        rules.push(LintRule::from_id(id).ok_or_else(|| {
+            format!(
+                "unknown lint rule '{}' (see `cheffers lint --list-rules`)",
+                id
+            )
+        })?);
+    }
+    Ok(rules)
+}
+
+fn recipe_path_from_args<I>(mut args: I) -> String
+where
+    I: Iterator<Item = String>,
+{
     args.next().unwrap_or_else(|| "hello.chef".to_string())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::recipe_path_from_args;
+    use super::{
+        parse_args, Checkpoints, Command, CompatProfile, GenInput, GoldenOptions, LintConfig,
+        LintRule, Ordinals, OutputOptions, ParseOptions, Profiling, Target, VerbMatching, Watch,
+    };
+
+    fn args(list: &[&str]) -> impl Iterator<Item = String> {
+        list.iter()
+            .map(|arg| arg.to_string())
+            .collect::<Vec<_>>()
+            .into_iter()
+    }
 
     #[test]
     fn defaults_to_hello_chef_when_no_argument() {
-        let args = vec!["cheffers".to_string()];
-        assert_eq!(recipe_path_from_args(args.into_iter()), "hello.chef");
+        assert_eq!(
+            parse_args(args(&["cheffers"])),
+            Ok(Command::Run {
+                path: "hello.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
     }
 
     #[test]
     fn uses_first_argument_as_path() {
-        let args = vec![
-            "cheffers".to_string(),
-            "tests/fixtures/hello-world.chef".to_string(),
-        ];
         assert_eq!(
-            recipe_path_from_args(args.into_iter()),
-            "tests/fixtures/hello-world.chef"
+            parse_args(args(&["cheffers", "tests/fixtures/hello-world.chef"])),
+            Ok(Command::Run {
+                path: "tests/fixtures/hello-world.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn strict_flags_select_strict_parsing() {
+        assert_eq!(
+            parse_args(args(&["cheffers", "--strict-verbs", "a.chef"])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions {
+                    verb_matching: VerbMatching::Strict,
+                    ..ParseOptions::default()
+                },
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
+        assert_eq!(
+            parse_args(args(&["cheffers", "--strict", "a.chef"])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions::strict(),
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn ordinal_words_combine_with_strict_parsing() {
+        assert_eq!(
+            parse_args(args(&["cheffers", "--ordinal-words", "--strict", "a.chef"])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions {
+                    ordinals: Ordinals::Words,
+                    ..ParseOptions::strict()
+                },
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn book_flags_collect_in_order() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "--book",
+                "sauces.chef",
+                "a.chef",
+                "--book",
+                "books/",
+            ])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: CompatProfile::default(),
+                books: vec!["sauces.chef".to_string(), "books/".to_string()],
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn profile_flags_choose_the_reports() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "--profile",
+                "--profile-folded",
+                "stacks.txt",
+                "a.chef",
+            ])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling {
+                    report: true,
+                    folded: Some("stacks.txt".to_string()),
+                    watches: Vec::new(),
+                },
+                checkpoints: Checkpoints::default(),
+            })
+        );
+        assert!(parse_args(args(&["cheffers", "a.chef", "--profile-folded"])).is_err());
+    }
+
+    #[test]
+    fn coverage_collects_input_files() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "coverage",
+                "--input",
+                "one.in",
+                "--input",
+                "two.in",
+                "--lcov",
+                "lcov.info",
+                "a.chef",
+            ])),
+            Ok(Command::Coverage {
+                path: "a.chef".to_string(),
+                inputs: vec!["one.in".to_string(), "two.in".to_string()],
+                lcov: Some("lcov.info".to_string()),
+            })
+        );
+        assert!(parse_args(args(&["cheffers", "coverage", "--input", "one.in"])).is_err());
+    }
+
+    #[test]
+    fn watches_are_read_when_the_arguments_are() {
+        let Ok(Command::Run { profiling, .. }) = parse_args(args(&[
+            "cheffers",
+            "--watch",
+            "sugar",
+            "--watch",
+            "len(bowl 2) > 100",
+            "a.chef",
+        ])) else {
+            panic!("not a run");
+        };
+        assert_eq!(
+            profiling.watches,
+            [
+                "sugar".parse::<Watch>().unwrap(),
+                "len(bowl 2) > 100".parse().unwrap()
+            ]
+        );
+        assert!(parse_args(args(&["cheffers", "--watch", "bowl x", "a.chef"])).is_err());
+    }
+
+    #[test]
+    fn debug_reads_input_from_a_file() {
+        assert_eq!(
+            parse_args(args(&["cheffers", "debug", "--input", "one.in", "a.chef"])),
+            Ok(Command::Debug {
+                path: "a.chef".to_string(),
+                input: Some("one.in".to_string()),
+            })
+        );
+        assert!(parse_args(args(&["cheffers", "debug"])).is_err());
+        assert_eq!(
+            parse_args(args(&["cheffers", "watch", "a.chef"])),
+            Ok(Command::Watch {
+                path: "a.chef".to_string(),
+                input: None,
+            })
+        );
+    }
+
+    #[test]
+    fn checkpoints_need_a_snapshot_file() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "--snapshot",
+                "state.txt",
+                "--checkpoint-every",
+                "1000",
+                "a.chef",
+            ])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints {
+                    every: Some(1000),
+                    snapshot: Some("state.txt".to_string()),
+                    ..Checkpoints::default()
+                },
+            })
+        );
+        assert!(parse_args(args(&["cheffers", "--pause-after", "5", "a.chef"])).is_err());
+        assert!(parse_args(args(&["cheffers", "--pause-after", "x", "a.chef"])).is_err());
+    }
+
+    #[test]
+    fn entry_names_the_recipe_to_run() {
+        assert_eq!(
+            parse_args(args(&["cheffers", "--entry", "Caramel Sauce", "a.chef"])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: CompatProfile::default(),
+                books: Vec::new(),
+                entry: Some("Caramel Sauce".to_string()),
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
         );
     }
+
+    #[test]
+    fn test_command_reads_limits_and_paths() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "test",
+                "--bless",
+                "--seed",
+                "7",
+                "--max-loop-iterations",
+                "100",
+                "recipes/",
+            ])),
+            Ok(Command::Test {
+                paths: vec!["recipes/".to_string()],
+                options: GoldenOptions {
+                    seed: 7,
+                    max_loop_iterations: 100,
+                    bless: true,
+                    ..GoldenOptions::default()
+                },
+            })
+        );
+        assert!(parse_args(args(&["cheffers", "test", "--seed", "x"])).is_err());
+        assert!(parse_args(args(&["cheffers", "test", "--doc", "--bless"])).is_err());
+    }
+
+    #[test]
+    fn output_style_selects_a_preset() {
+        assert_eq!(
+            parse_args(args(&["cheffers", "--output-style", "perl", "a.chef"])),
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: CompatProfile {
+                    output: OutputOptions::from_style("perl").unwrap(),
+                    ..CompatProfile::default()
+                },
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
+        let error = parse_args(args(&["cheffers", "--output-style", "csv", "a.chef"]))
+            .expect_err("csv is not a style");
+        assert!(error.contains("plain, spaced, lines, perl"), "{}", error);
+    }
+
+    #[test]
+    fn compat_profile_keeps_an_explicit_output_style() {
+        let command = parse_args(args(&[
+            "cheffers",
+            "--output-style",
+            "lines",
+            "--compat",
+            "acme",
+            "a.chef",
+        ]));
+        let mut expected = CompatProfile::from_name("acme").unwrap();
+        expected.output = OutputOptions::from_style("lines").unwrap();
+        assert_eq!(
+            command,
+            Ok(Command::Run {
+                path: "a.chef".to_string(),
+                options: ParseOptions::default(),
+                profile: expected,
+                books: Vec::new(),
+                entry: None,
+                profiling: Profiling::default(),
+                checkpoints: Checkpoints::default(),
+            })
+        );
+    }
+
+    #[test]
+    fn compile_defaults_to_javascript_on_stdout() {
+        assert_eq!(
+            parse_args(args(&["cheffers", "compile", "hello.chef"])),
+            Ok(Command::Compile {
+                target: Target::JavaScript,
+                path: "hello.chef".to_string(),
+                output: None,
+            })
+        );
+    }
+
+    #[test]
+    fn compile_accepts_wasm_target() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "compile",
+                "--target",
+                "wasm",
+                "-o",
+                "hello.wat",
+                "hello.chef"
+            ])),
+            Ok(Command::Compile {
+                target: Target::Wasm,
+                path: "hello.chef".to_string(),
+                output: Some("hello.wat".to_string()),
+            })
+        );
+    }
+
+    #[test]
+    fn chefgen_takes_a_title() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "chefgen",
+                "--title",
+                "Countdown Crumble",
+                "countdown.stack"
+            ])),
+            Ok(Command::Chefgen {
+                input: GenInput::StackProgram,
+                path: "countdown.stack".to_string(),
+                title: Some("Countdown Crumble".to_string()),
+                output: None,
+            })
+        );
+    }
+
+    #[test]
+    fn chefgen_text_mode_with_food_names() {
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "chefgen",
+                "--food-names",
+                "--text",
+                "message.txt"
+            ])),
+            Ok(Command::Chefgen {
+                input: GenInput::Text { food_names: true },
+                path: "message.txt".to_string(),
+                title: None,
+                output: None,
+            })
+        );
+    }
+
+    #[test]
+    fn compile_rejects_unknown_targets() {
+        let error = parse_args(args(&[
+            "cheffers", "compile", "--target", "cobol", "a.chef",
+        ]))
+        .expect_err("cobol is not a target");
+        assert!(error.contains("cobol"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn lint_rules_can_be_toggled() {
+        let mut expected = LintConfig::new();
+        for rule in LintRule::ALL {
+            expected.disable(rule);
+        }
+        expected.enable(LintRule::DeadFold);
+        assert_eq!(
+            parse_args(args(&[
+                "cheffers",
+                "lint",
+                "--disable",
+                "all",
+                "--enable",
+                "dead-fold",
+                "stew.chef"
+            ])),
+            Ok(Command::Lint {
+                path: "stew.chef".to_string(),
+                config: expected,
+            })
+        );
+
+        let error = parse_args(args(&["cheffers", "lint", "--disable", "typo", "a.chef"]))
+            .expect_err("typo is not a rule");
+        assert!(error.contains("typo"), "unexpected error: {}", error);
+    }
+
+    #[test]
+    fn repl_takes_no_arguments() {
+        assert_eq!(parse_args(args(&["cheffers", "repl"])), Ok(Command::Repl));
+        assert!(parse_args(args(&["cheffers", "repl", "x.chef"])).is_err());
+    }
 }
@@ -1,29 +1,109 @@
+pub mod highlight;
+mod strict;
+
 use std::collections::HashMap;
 use std::sync::OnceLock;
 
 use regex::Regex;
 
+use crate::conjugation;
+use crate::error_context::SourceLocation;
+use crate::error_formatter::ordinal;
 use crate::instruction::Instruction;
-use crate::types::{Ingredient, Measure, ParseError, ParseResult, Recipe, Value};
+use crate::types::{
+    Example, Ingredient, Measure, ParseError, ParseResult, Recipe, SourceMap, Value,
+};
+
+/// How the parser pairs a loop verb with the participle in its "until"
+/// sentence.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum VerbMatching {
+    /// Accept the verb's past participle ("Shake ... until shaken") or any
+    /// word starting with the verb, as older versions did.
+    #[default]
+    Lenient,
+    /// Accept only a past participle of the verb; an "until" sentence that
+    /// merely starts with the verb is a [`ParseError::LoopVerbMismatch`].
+    Strict,
+}
+
+/// How closely the parser holds a recipe to the spec's layout.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Strictness {
+    /// Find titles and sections wherever they appear, tolerating missing or
+    /// extra blank lines, metadata inside the ingredient list and `Serves`
+    /// inside the method.
+    #[default]
+    Lenient,
+    /// Require the spec's section order, separated by exactly one blank line;
+    /// each deviation is reported with its own [`ParseError`] variant.
+    Strict,
+}
+
+/// Which spellings name a mixing bowl or baking dish.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Ordinals {
+    /// Only the spec's digit ordinals ("2nd"). An ordinal word is a
+    /// [`ParseError::OrdinalWord`] naming the digit form.
+    #[default]
+    Digits,
+    /// Also the English words "first" through "twentieth", as many recipes
+    /// found online write them ("the second mixing bowl").
+    Words,
+}
+
+/// Settings that change what the parser accepts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseOptions {
+    pub verb_matching: VerbMatching,
+    pub strictness: Strictness,
+    pub ordinals: Ordinals,
+}
+
+impl ParseOptions {
+    /// Everything the spec requires and nothing more.
+    pub fn strict() -> Self {
+        Self {
+            verb_matching: VerbMatching::Strict,
+            strictness: Strictness::Strict,
+            ordinals: Ordinals::Digits,
+        }
+    }
+}
 
 pub struct Parser<'a> {
     input: &'a str,
+    options: ParseOptions,
 }
 
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
-        Self { input }
+        Self::with_options(input, ParseOptions::default())
+    }
+
+    pub fn with_options(input: &'a str, options: ParseOptions) -> Self {
+        Self { input, options }
     }
 
     pub fn parse_recipe(&self) -> ParseResult<Recipe> {
-        let blocks = Self::split_recipes(self.input);
+        let blocks = match self.options.strictness {
+            Strictness::Lenient => Self::split_recipes(self.input),
+            Strictness::Strict => strict::split_recipes(self.input)?,
+        };
         if blocks.is_empty() {
             return Err(ParseError::MissingSection("Recipe".into()));
         }
 
+        let lines = LineIndex::new(self.input);
         let mut parsed = Vec::with_capacity(blocks.len());
         for block in blocks {
-            parsed.push(Self::parse_single_recipe(block)?);
+            let offset = block.as_ptr() as usize - self.input.as_ptr() as usize;
+            parsed.push(Self::parse_single_recipe(
+                block,
+                offset,
+                &lines,
+                &self.options,
+            )?);
         }
 
         let mut recipes = parsed.into_iter();
@@ -111,8 +191,19 @@ impl<'a> Parser<'a> {
         blocks
     }
 
-    fn parse_single_recipe(block: &str) -> ParseResult<Recipe> {
+    /// Parses one recipe. `offset` is where `block` starts in the whole
+    /// source, so the source map can point back into it.
+    fn parse_single_recipe(
+        block: &str,
+        offset: usize,
+        lines: &LineIndex,
+        options: &ParseOptions,
+    ) -> ParseResult<Recipe> {
         let title = Self::parse_title(block)?;
+        let mut source_map = SourceMap {
+            title: Some(lines.locate(offset + (block.len() - block.trim_start().len()), &title)),
+            ..SourceMap::default()
+        };
 
         // Validate title ends with period (Chef spec requirement)
         if !title.ends_with('.') {
@@ -126,14 +217,31 @@ impl<'a> Parser<'a> {
             .find("Method.")
             .ok_or_else(|| ParseError::MissingSection("Method".into()))?;
 
+        // Comments run from the title line to the first section header.
+        let comments_start = block.find('\n').unwrap_or(block.len());
+        let comments_end = block
+            .find("Ingredients.")
+            .map_or(method_idx, |idx| idx.min(method_idx))
+            .max(comments_start);
+        let examples = Self::parse_examples(
+            &block[comments_start..comments_end],
+            offset + comments_start,
+            lines,
+        );
+
         // Ingredients section is optional
         let (ingredients, unset_ingredients) =
             if let Some(ingredients_idx) = block.find("Ingredients.") {
                 if method_idx <= ingredients_idx {
                     return Err(ParseError::MissingSection("Method".into()));
                 }
-                let ingredients_text = &block[ingredients_idx + "Ingredients.".len()..method_idx];
-                Self::parse_ingredients(ingredients_text)?
+                let start = ingredients_idx + "Ingredients.".len();
+                let section = Self::parse_ingredients(&block[start..method_idx])?;
+                for (name, (line_offset, line)) in section.declarations {
+                    let location = lines.locate(offset + start + line_offset, &line);
+                    source_map.ingredients.insert(name, location);
+                }
+                (section.ingredients, section.unset_ingredients)
             } else {
                 // Check if "Ingredients" (without period) exists - this is an error
                 if block.contains("Ingredients\n") || block.contains("Ingredients ") {
@@ -144,8 +252,17 @@ impl<'a> Parser<'a> {
                 (HashMap::new(), HashMap::new())
             };
 
-        let method_text = &block[method_idx + "Method.".len()..];
-        let instructions = Self::parse_method(method_text)?;
+        let method_start = method_idx + "Method.".len();
+        let (instructions, sentences, closings) =
+            Self::parse_method(&block[method_start..], *options)?;
+        let locate = |(sentence, sentence_offset): &(String, usize)| {
+            lines.locate(offset + method_start + sentence_offset, sentence)
+        };
+        source_map.instructions = sentences.iter().map(locate).collect();
+        source_map.loop_ends = closings
+            .iter()
+            .map(|(idx, sentence)| (*idx, locate(sentence)))
+            .collect();
 
         Ok(Recipe {
             title,
@@ -153,9 +270,40 @@ impl<'a> Parser<'a> {
             unset_ingredients,
             instructions,
             auxiliary_recipes: HashMap::new(),
+            source_map,
+            examples,
         })
     }
 
+    /// Test annotations in a recipe's comments: any number of `input:`
+    /// lines, then an `expect:` line closing the example. A value may be
+    /// wrapped in double quotes to keep surrounding spaces, and `\n`, `\t`,
+    /// `\"` and `\\` stand for the characters they usually do. `offset` is
+    /// where `text` starts in the whole source.
+    fn parse_examples(text: &str, offset: usize, lines: &LineIndex) -> Vec<Example> {
+        let mut examples = Vec::new();
+        let mut input: Vec<String> = Vec::new();
+        let mut line_start = offset;
+        for raw_line in text.split_inclusive('\n') {
+            let line = raw_line.trim();
+            let location = line_start;
+            line_start += raw_line.len();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            match key.trim().to_lowercase().as_str() {
+                "input" => input.push(unescape_annotation(value)),
+                "expect" => examples.push(Example {
+                    input: std::mem::take(&mut input).join(" "),
+                    expect: unescape_annotation(value),
+                    line: lines.locate(location, line).line,
+                }),
+                _ => {}
+            }
+        }
+        examples
+    }
+
     fn parse_title(block: &str) -> ParseResult<String> {
         // Chef spec requires title to be on the first line
         let first_line = block.lines().next().unwrap_or("").trim();
@@ -178,14 +326,15 @@ impl<'a> Parser<'a> {
     /// using a valueless ingredient is a run-time error). If an ingredient is
     /// repeated, the new declaration replaces earlier ones, as the spec
     /// requires.
-    #[allow(clippy::type_complexity)]
-    fn parse_ingredients(
-        text: &str,
-    ) -> ParseResult<(HashMap<Ingredient, Value>, HashMap<Ingredient, Measure>)> {
+    pub(crate) fn parse_ingredients(text: &str) -> ParseResult<IngredientSection> {
         let mut ingredients = HashMap::new();
         let mut unset_ingredients = HashMap::new();
+        let mut declarations = HashMap::new();
 
-        for raw_line in text.lines() {
+        let mut line_start = 0;
+        for raw_line in text.split_inclusive('\n') {
+            let line_offset = line_start + (raw_line.len() - raw_line.trim_start().len());
+            line_start += raw_line.len();
             let line = raw_line.trim();
             if line.is_empty() {
                 continue;
@@ -219,6 +368,7 @@ impl<'a> Parser<'a> {
             // A repeated declaration replaces the previous one in either map.
             ingredients.remove(&ingredient);
             unset_ingredients.remove(&ingredient);
+            declarations.insert(ingredient.clone(), (line_offset, line.to_string()));
             match quantity {
                 Some(amount) => {
                     ingredients.insert(ingredient, Value { amount, measure });
@@ -229,7 +379,11 @@ impl<'a> Parser<'a> {
             }
         }
 
-        Ok((ingredients, unset_ingredients))
+        Ok(IngredientSection {
+            ingredients,
+            unset_ingredients,
+            declarations,
+        })
     }
 
     fn parse_quantity(raw: &str) -> ParseResult<i64> {
@@ -372,18 +526,83 @@ impl<'a> Parser<'a> {
         Ok(())
     }
 
-    fn parse_method(text: &str) -> ParseResult<Vec<Instruction>> {
-        let sentences = Self::split_sentences(text);
+    /// Parses method sentences (without the `Method.` header) into
+    /// instructions, pairing loops with their "until" sentences.
+    pub fn parse_instructions(text: &str) -> ParseResult<Vec<Instruction>> {
+        Ok(Self::parse_method(text, ParseOptions::default())?.0)
+    }
+
+    /// Parses the method, also returning the opening sentence of every
+    /// instruction (with its offset in `text`) in pre-order, a loop before
+    /// its body, and the "until" sentence of every loop by its index in that
+    /// order.
+    #[allow(clippy::type_complexity)]
+    fn parse_method(
+        text: &str,
+        options: ParseOptions,
+    ) -> ParseResult<(
+        Vec<Instruction>,
+        Vec<(String, usize)>,
+        Vec<(usize, (String, usize))>,
+    )> {
+        let located: Vec<(String, usize)> = Self::split_sentences(text)
+            .into_iter()
+            .map(|sentence| {
+                let start = sentence.source_span(0, 0).0;
+                (sentence.text, start)
+            })
+            .collect();
+        let sentences: Vec<String> = located.iter().map(|(s, _)| s.clone()).collect();
         let mut instructions = Vec::new();
         let mut idx = 0;
 
         while idx < sentences.len() {
-            let (instruction, consumed) = Self::parse_statement(&sentences[idx..])?;
+            let (instruction, consumed) = Self::parse_statement(&sentences[idx..], options)?;
             instructions.push(instruction);
             idx += consumed;
         }
 
-        Ok(instructions)
+        let mut openings = Vec::new();
+        let mut closings = Vec::new();
+        Self::collect_openings(
+            &instructions,
+            &located,
+            &mut 0,
+            &mut openings,
+            &mut closings,
+        );
+        Ok((instructions, openings, closings))
+    }
+
+    /// Walks parsed instructions alongside their sentences, the way
+    /// `parse_statement` consumed them, recording each opening sentence and
+    /// the "until" sentence of each loop.
+    fn collect_openings(
+        instructions: &[Instruction],
+        sentences: &[(String, usize)],
+        idx: &mut usize,
+        out: &mut Vec<(String, usize)>,
+        closings: &mut Vec<(usize, (String, usize))>,
+    ) {
+        for instruction in instructions {
+            let position = out.len();
+            out.push(sentences[*idx].clone());
+            if let Instruction::Loop { verb, body, .. } = instruction {
+                let single_sentence =
+                    body.is_empty() && Self::until_matches_verb(&sentences[*idx].0, verb);
+                if single_sentence {
+                    closings.push((position, sentences[*idx].clone()));
+                }
+                *idx += 1;
+                if !single_sentence {
+                    Self::collect_openings(body, sentences, idx, out, closings);
+                    closings.push((position, sentences[*idx].clone()));
+                    *idx += 1;
+                }
+            } else {
+                *idx += 1;
+            }
+        }
     }
 
     /// Parses the next method statement, returning the instruction and the
@@ -391,40 +610,45 @@ impl<'a> Parser<'a> {
     /// other sentence of the shape "Verb [the] ingredient" starts a loop, and
     /// everything else is a parse error (the spec has no comments inside the
     /// method, so a typo'd instruction must not be silently dropped).
-    fn parse_statement(sentences: &[String]) -> ParseResult<(Instruction, usize)> {
+    fn parse_statement(
+        sentences: &[String],
+        options: ParseOptions,
+    ) -> ParseResult<(Instruction, usize)> {
         let sentence = sentences[0].as_str();
-        if let Some(instruction) = Self::parse_known_instruction(sentence)? {
+        if let Some(instruction) = Self::known_instruction(sentence, &options)? {
             return Ok((instruction, 1));
         }
         if loop_start_regex().is_match(sentence) {
-            return Self::parse_loop(sentences);
+            return Self::parse_loop(sentences, options);
         }
         Err(ParseError::UnknownInstruction(sentence.to_string()))
     }
 
-    fn split_sentences(text: &str) -> Vec<String> {
+    /// Splits the method into sentences. Line breaks inside a sentence
+    /// become spaces; each sentence keeps the offset in `text` of every byte.
+    pub(crate) fn split_sentences(text: &str) -> Vec<Sentence> {
         let mut sentences = Vec::new();
-        let mut current = String::new();
+        let mut current = Sentence::default();
 
-        for ch in text.chars() {
+        for (offset, ch) in text.char_indices() {
             match ch {
                 '.' | '!' | '?' => {
-                    if !current.trim().is_empty() {
-                        sentences.push(current.trim().to_string());
+                    if !current.text.trim().is_empty() {
+                        sentences.push(current.trimmed());
                     }
-                    current.clear();
+                    current = Sentence::default();
                 }
                 '\n' => {
-                    if !current.ends_with(' ') && !current.is_empty() {
-                        current.push(' ');
+                    if !current.text.ends_with(' ') && !current.text.is_empty() {
+                        current.push(' ', offset);
                     }
                 }
-                _ => current.push(ch),
+                _ => current.push(ch, offset),
             }
         }
 
-        if !current.trim().is_empty() {
-            sentences.push(current.trim().to_string());
+        if !current.text.trim().is_empty() {
+            sentences.push(current.trimmed());
         }
 
         sentences
@@ -432,15 +656,46 @@ impl<'a> Parser<'a> {
 
     /// Returns true if the sentence is an "until" statement closing a loop
     /// opened with `verb`: "AnyVerb [the ingredient] until verbed". The spec
-    /// says _verbed_ must match the loop verb; we use a prefix heuristic so
-    /// "Bake ... until baked" matches without conjugation tables.
-    fn until_matches_verb(sentence: &str, verb: &str) -> bool {
-        let lower = sentence.to_lowercase();
-        if let Some(until_pos) = lower.find(" until ") {
-            let after_until = lower[until_pos + " until ".len()..].trim_start();
-            after_until.starts_with(&verb.to_lowercase())
-        } else {
-            false
+    /// says _verbed_ must be the past participle of the loop verb; leniently,
+    /// any word starting with the verb is accepted too, so recipes written
+    /// against the old prefix rule keep parsing.
+    pub(crate) fn until_matches_verb(sentence: &str, verb: &str) -> bool {
+        let Some(word) = Self::until_participle(sentence) else {
+            return false;
+        };
+        conjugation::is_past_participle(word, verb)
+            || word.to_lowercase().starts_with(&verb.to_lowercase())
+    }
+
+    /// The word after "until" in an "until" statement.
+    pub(crate) fn until_participle(sentence: &str) -> Option<&str> {
+        let until_pos = sentence.to_lowercase().find(" until ")?;
+        sentence[until_pos + " until ".len()..]
+            .split_whitespace()
+            .next()
+    }
+
+    /// Whether `sentence` closes the loop opened with `verb`. In strict mode
+    /// an "until" sentence that only passes the lenient prefix rule
+    /// ("Stir ... until stirringly") is an error naming the participle the
+    /// parser expected; leniently it closes the loop, and the
+    /// `loop-verb-mismatch` lint names the participle instead.
+    fn closes_loop(sentence: &str, verb: &str, verbs: VerbMatching) -> ParseResult<bool> {
+        if !Self::until_matches_verb(sentence, verb) {
+            return Ok(false);
+        }
+        match Self::until_participle(sentence) {
+            Some(word)
+                if verbs == VerbMatching::Strict
+                    && !conjugation::is_past_participle(word, verb) =>
+            {
+                Err(ParseError::LoopVerbMismatch {
+                    verb: verb.to_string(),
+                    found: word.to_string(),
+                    expected: conjugation::past_participle(verb),
+                })
+            }
+            _ => Ok(true),
         }
     }
 
@@ -477,7 +732,11 @@ impl<'a> Parser<'a> {
     /// statements are parsed sequentially, so nested loops consume their own
     /// "until" statements before this loop looks for its own; this makes
     /// nested loops using the same verb pair up correctly.
-    fn parse_loop(sentences: &[String]) -> ParseResult<(Instruction, usize)> {
+    fn parse_loop(
+        sentences: &[String],
+        options: ParseOptions,
+    ) -> ParseResult<(Instruction, usize)> {
+        let verbs = options.verb_matching;
         let start = sentences[0].as_str();
         let caps = loop_start_regex()
             .captures(start)
@@ -486,7 +745,7 @@ impl<'a> Parser<'a> {
         let condition_var = caps.name("ingredient").unwrap().as_str().to_string();
 
         // Single-sentence loop with an empty body: "Verb the x until verbed."
-        if Self::until_matches_verb(start, &verb) {
+        if Self::closes_loop(start, &verb, verbs)? {
             let decrement_var = Self::decrement_var_from_until(start);
             return Ok((
                 Instruction::Loop {
@@ -506,7 +765,7 @@ impl<'a> Parser<'a> {
                 return Err(ParseError::UnmatchedLoop(start.to_string()));
             }
             let sentence = sentences[idx].as_str();
-            if Self::until_matches_verb(sentence, &verb) {
+            if Self::closes_loop(sentence, &verb, verbs)? {
                 let decrement_var = Self::decrement_var_from_until(sentence);
                 return Ok((
                     Instruction::Loop {
@@ -518,7 +777,7 @@ impl<'a> Parser<'a> {
                     idx + 1,
                 ));
             }
-            let (instruction, consumed) = Self::parse_statement(&sentences[idx..])?;
+            let (instruction, consumed) = Self::parse_statement(&sentences[idx..], options)?;
             body.push(instruction);
             idx += consumed;
         }
@@ -527,7 +786,15 @@ impl<'a> Parser<'a> {
     /// Parses a sentence as one of the spec's named instructions. Returns
     /// `Ok(None)` when the sentence matches no known instruction form (it may
     /// still be a loop start or end, which the caller handles).
-    fn parse_known_instruction(sentence: &str) -> ParseResult<Option<Instruction>> {
+    pub fn parse_known_instruction(sentence: &str) -> ParseResult<Option<Instruction>> {
+        Self::known_instruction(sentence, &ParseOptions::default())
+    }
+
+    fn known_instruction(
+        sentence: &str,
+        options: &ParseOptions,
+    ) -> ParseResult<Option<Instruction>> {
+        let index = |value| ordinal_to_index(value, options);
         if let Some(caps) = take_regex().captures(sentence) {
             return Ok(Some(Instruction::Take(
                 caps.name("ingredient").unwrap().as_str().to_string(),
@@ -535,7 +802,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = put_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             return Ok(Some(Instruction::Put(
                 caps.name("ingredient").unwrap().as_str().to_string(),
                 bowl,
@@ -543,7 +810,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = fold_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             return Ok(Some(Instruction::Fold(
                 caps.name("ingredient").unwrap().as_str().to_string(),
                 bowl,
@@ -553,13 +820,11 @@ impl<'a> Parser<'a> {
         // Check add_dry_regex BEFORE add_regex since "Add dry ingredients" matches both
         // More specific patterns must be checked first
         if let Some(caps) = add_dry_regex().captures(sentence) {
-            return Ok(Some(Instruction::AddDry(ordinal_to_index(
-                caps.name("bowl"),
-            ))));
+            return Ok(Some(Instruction::AddDry(index(caps.name("bowl"))?)));
         }
 
         if let Some(caps) = add_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             return Ok(Some(Instruction::Add(
                 caps.name("ingredient").unwrap().as_str().to_string(),
                 bowl,
@@ -567,7 +832,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = remove_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             return Ok(Some(Instruction::Remove(
                 caps.name("ingredient").unwrap().as_str().to_string(),
                 bowl,
@@ -575,7 +840,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = combine_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             return Ok(Some(Instruction::Combine(
                 caps.name("ingredient").unwrap().as_str().to_string(),
                 bowl,
@@ -583,7 +848,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = divide_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             return Ok(Some(Instruction::Divide(
                 caps.name("ingredient").unwrap().as_str().to_string(),
                 bowl,
@@ -591,9 +856,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = liquefy_bowl_regex().captures(sentence) {
-            return Ok(Some(Instruction::LiquefyBowl(ordinal_to_index(
-                caps.name("bowl"),
-            ))));
+            return Ok(Some(Instruction::LiquefyBowl(index(caps.name("bowl"))?)));
         }
 
         if let Some(caps) = liquefy_regex().captures(sentence) {
@@ -603,7 +866,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = stir_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             let minutes = caps
                 .name("minutes")
                 .unwrap()
@@ -614,7 +877,7 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = stir_ingredient_regex().captures(sentence) {
-            let bowl = ordinal_to_index(caps.name("bowl"));
+            let bowl = index(caps.name("bowl"))?;
             return Ok(Some(Instruction::StirIngredient(
                 caps.name("ingredient").unwrap().as_str().to_string(),
                 bowl,
@@ -622,18 +885,16 @@ impl<'a> Parser<'a> {
         }
 
         if let Some(caps) = mix_regex().captures(sentence) {
-            return Ok(Some(Instruction::Mix(ordinal_to_index(caps.name("bowl")))));
+            return Ok(Some(Instruction::Mix(index(caps.name("bowl"))?)));
         }
 
         if let Some(caps) = clean_regex().captures(sentence) {
-            return Ok(Some(Instruction::Clean(ordinal_to_index(
-                caps.name("bowl"),
-            ))));
+            return Ok(Some(Instruction::Clean(index(caps.name("bowl"))?)));
         }
 
         if let Some(caps) = pour_regex().captures(sentence) {
-            let from = ordinal_to_index(caps.name("from"));
-            let to = ordinal_to_index(caps.name("to"));
+            let from = index(caps.name("from"))?;
+            let to = index(caps.name("to"))?;
             return Ok(Some(Instruction::Pour(from, to)));
         }
 
@@ -670,12 +931,81 @@ impl<'a> Parser<'a> {
     }
 }
 
+/// The ingredient list of one recipe, with where each ingredient was last
+/// declared (byte offset in the section and the trimmed line).
+pub(crate) struct IngredientSection {
+    pub(crate) ingredients: HashMap<Ingredient, Value>,
+    pub(crate) unset_ingredients: HashMap<Ingredient, Measure>,
+    declarations: HashMap<Ingredient, (usize, String)>,
+}
+
+/// One method sentence, with where each of its bytes came from.
+#[derive(Clone, Debug, Default)]
+pub(crate) struct Sentence {
+    pub(crate) text: String,
+    /// `offsets[i]` is the offset in the method text of `text`'s byte `i`.
+    offsets: Vec<usize>,
+}
+
+impl Sentence {
+    fn push(&mut self, ch: char, offset: usize) {
+        self.text.push(ch);
+        self.offsets.extend(offset..offset + ch.len_utf8());
+    }
+
+    fn trimmed(self) -> Sentence {
+        let start = self.text.len() - self.text.trim_start().len();
+        let end = self.text.trim_end().len();
+        Sentence {
+            text: self.text[start..end].to_string(),
+            offsets: self.offsets[start..end].to_vec(),
+        }
+    }
+
+    /// Offsets in the method text spanning `text[start..end]`.
+    fn source_span(&self, start: usize, end: usize) -> (usize, usize) {
+        let from = self.offsets[start];
+        let to = if end > start {
+            self.offsets[end - 1] + 1
+        } else {
+            from
+        };
+        (from, to)
+    }
+}
+
+/// Byte offsets of line starts, for turning offsets into line/column
+/// locations.
+struct LineIndex<'a> {
+    source: &'a str,
+    starts: Vec<usize>,
+}
+
+impl<'a> LineIndex<'a> {
+    fn new(source: &'a str) -> Self {
+        let starts = std::iter::once(0)
+            .chain(source.match_indices('\n').map(|(idx, _)| idx + 1))
+            .collect();
+        Self { source, starts }
+    }
+
+    /// 1-based line and column (in characters) of `offset`.
+    fn locate(&self, offset: usize, snippet: &str) -> SourceLocation {
+        let line = self.starts.partition_point(|&start| start <= offset);
+        let line_start = self.starts[line - 1];
+        let column = self.source[line_start..offset].chars().count() + 1;
+        SourceLocation::new(line)
+            .with_column(column)
+            .with_snippet(snippet.to_string())
+    }
+}
+
 fn ingredient_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| Regex::new(r"^(?P<amount>[-\d\s/]+)\s+(?P<rest>.+)$").unwrap())
 }
 
-fn loop_start_regex() -> &'static Regex {
+pub(crate) fn loop_start_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
         Regex::new(r"^(?P<verb>[A-Za-z]+)(?: the)? (?P<ingredient>.+?)(?:\s+until\s|$)").unwrap()
@@ -692,8 +1022,7 @@ fn take_regex() -> &'static Regex {
 fn put_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Put(?: the)? (?P<ingredient>.+) into(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl$",
+        Regex::new(&with_ordinals(r"(?i)^Put(?: the)? (?P<ingredient>.+) into(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl$"),
         )
         .unwrap()
     })
@@ -702,8 +1031,7 @@ fn put_regex() -> &'static Regex {
 fn fold_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Fold(?: the)? (?P<ingredient>.+) into(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl$",
+        Regex::new(&with_ordinals(r"(?i)^Fold(?: the)? (?P<ingredient>.+) into(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl$"),
         )
         .unwrap()
     })
@@ -712,8 +1040,7 @@ fn fold_regex() -> &'static Regex {
 fn add_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Add(?: the)? (?P<ingredient>.+?)(?:\s+to(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl)?$",
+        Regex::new(&with_ordinals(r"(?i)^Add(?: the)? (?P<ingredient>.+?)(?:\s+to(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl)?$"),
         )
         .unwrap()
     })
@@ -722,8 +1049,7 @@ fn add_regex() -> &'static Regex {
 fn remove_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Remove(?: the)? (?P<ingredient>.+?)(?:\s+from(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl)?$",
+        Regex::new(&with_ordinals(r"(?i)^Remove(?: the)? (?P<ingredient>.+?)(?:\s+from(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl)?$"),
         )
         .unwrap()
     })
@@ -732,8 +1058,7 @@ fn remove_regex() -> &'static Regex {
 fn combine_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Combine(?: the)? (?P<ingredient>.+?)(?:\s+into(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl)?$",
+        Regex::new(&with_ordinals(r"(?i)^Combine(?: the)? (?P<ingredient>.+?)(?:\s+into(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl)?$"),
         )
         .unwrap()
     })
@@ -742,8 +1067,7 @@ fn combine_regex() -> &'static Regex {
 fn divide_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Divide(?: the)? (?P<ingredient>.+?)(?:\s+into(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl)?$",
+        Regex::new(&with_ordinals(r"(?i)^Divide(?: the)? (?P<ingredient>.+?)(?:\s+into(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl)?$"),
         )
         .unwrap()
     })
@@ -752,9 +1076,9 @@ fn divide_regex() -> &'static Regex {
 fn add_dry_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Add dry ingredients(?: to(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl)?$",
-        )
+        Regex::new(&with_ordinals(
+            r"(?i)^Add dry ingredients(?: to(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl)?$",
+        ))
         .unwrap()
     })
 }
@@ -767,9 +1091,9 @@ fn liquefy_regex() -> &'static Regex {
 fn liquefy_bowl_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Liqu[ei]fy(?: the)? contents of(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl$",
-        )
+        Regex::new(&with_ordinals(
+            r"(?i)^Liqu[ei]fy(?: the)? contents of(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl$",
+        ))
         .unwrap()
     })
 }
@@ -777,8 +1101,7 @@ fn liquefy_bowl_regex() -> &'static Regex {
 fn stir_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Stir(?:(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl)? for (?P<minutes>\d+) minutes?$",
+        Regex::new(&with_ordinals(r"(?i)^Stir(?:(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl)? for (?P<minutes>\d+) minutes?$"),
         )
         .unwrap()
     })
@@ -787,8 +1110,7 @@ fn stir_regex() -> &'static Regex {
 fn stir_ingredient_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Stir(?: the)? (?P<ingredient>.+) into(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl$",
+        Regex::new(&with_ordinals(r"(?i)^Stir(?: the)? (?P<ingredient>.+) into(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl$"),
         )
         .unwrap()
     })
@@ -797,9 +1119,9 @@ fn stir_ingredient_regex() -> &'static Regex {
 fn mix_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Mix(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl well$|^Mix well$",
-        )
+        Regex::new(&with_ordinals(
+            r"(?i)^Mix(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl well$|^Mix well$",
+        ))
         .unwrap()
     })
 }
@@ -807,15 +1129,17 @@ fn mix_regex() -> &'static Regex {
 fn clean_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(r"(?i)^Clean(?: the)?(?: (?P<bowl>\d+)(?:st|nd|rd|th))? mixing bowl$").unwrap()
+        Regex::new(&with_ordinals(
+            r"(?i)^Clean(?: the)?(?: (?P<bowl>{ORDINAL}))? mixing bowl$",
+        ))
+        .unwrap()
     })
 }
 
 fn pour_regex() -> &'static Regex {
     static RE: OnceLock<Regex> = OnceLock::new();
     RE.get_or_init(|| {
-        Regex::new(
-            r"(?i)^Pour contents of(?: the)?(?: (?P<from>\d+)(?:st|nd|rd|th))? mixing bowl into(?: the)?(?: (?P<to>\d+)(?:st|nd|rd|th))? baking dish(?:es)?$",
+        Regex::new(&with_ordinals(r"(?i)^Pour contents of(?: the)?(?: (?P<from>{ORDINAL}))? mixing bowl into(?: the)?(?: (?P<to>{ORDINAL}))? baking dish(?:es)?$"),
         )
         .unwrap()
     })
@@ -841,11 +1165,93 @@ fn serves_regex() -> &'static Regex {
     RE.get_or_init(|| Regex::new(r"(?i)^Serves (?P<count>\d+)$").unwrap())
 }
 
-fn ordinal_to_index(value: Option<regex::Match<'_>>) -> usize {
-    value
-        .and_then(|m| m.as_str().parse::<usize>().ok())
-        .map(|n| n.saturating_sub(1))
-        .unwrap_or(0)
+/// English ordinal words, from "first" (bowl 1) up.
+const ORDINAL_WORDS: [&str; 20] = [
+    "first",
+    "second",
+    "third",
+    "fourth",
+    "fifth",
+    "sixth",
+    "seventh",
+    "eighth",
+    "ninth",
+    "tenth",
+    "eleventh",
+    "twelfth",
+    "thirteenth",
+    "fourteenth",
+    "fifteenth",
+    "sixteenth",
+    "seventeenth",
+    "eighteenth",
+    "nineteenth",
+    "twentieth",
+];
+
+/// Fills `{ORDINAL}` in an instruction pattern with every spelling of an
+/// ordinal: digits with any English suffix, or an ordinal word. Which of
+/// them a recipe may use is checked by [`ordinal_to_index`].
+fn with_ordinals(pattern: &str) -> String {
+    let ordinal = format!(r"\d+(?:st|nd|rd|th)|{}", ORDINAL_WORDS.join("|"));
+    pattern.replace("{ORDINAL}", &ordinal)
+}
+
+/// The zero-based bowl or dish an ordinal names; no ordinal means the first.
+/// A digit ordinal with the wrong suffix ("2th") is accepted leniently and
+/// is an [`ParseError::OrdinalSuffixMismatch`] in strict mode.
+fn ordinal_to_index(value: Option<regex::Match<'_>>, options: &ParseOptions) -> ParseResult<usize> {
+    let Some(found) = value.map(|m| m.as_str()) else {
+        return Ok(0);
+    };
+    let lower = found.to_lowercase();
+    if let Some(index) = ORDINAL_WORDS.iter().position(|word| *word == lower) {
+        return match options.ordinals {
+            Ordinals::Words => Ok(index),
+            Ordinals::Digits => Err(ParseError::OrdinalWord {
+                word: found.to_string(),
+                expected: ordinal(index + 1),
+            }),
+        };
+    }
+
+    let (digits, suffix) = lower.split_at(lower.len() - 2);
+    let number: usize = digits
+        .parse()
+        .map_err(|_| ParseError::UnknownInstruction(found.to_string()))?;
+    let expected = ordinal(number);
+    if options.strictness == Strictness::Strict && !expected.ends_with(suffix) {
+        return Err(ParseError::OrdinalSuffixMismatch {
+            found: found.to_string(),
+            expected,
+        });
+    }
+    Ok(number.saturating_sub(1))
+}
+
+/// The value of an `input:` or `expect:` annotation: trimmed, unquoted and
+/// with escapes replaced.
+fn unescape_annotation(value: &str) -> String {
+    let value = value.trim();
+    let value = value
+        .strip_prefix('"')
+        .and_then(|rest| rest.strip_suffix('"'))
+        .unwrap_or(value);
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            text.push(ch);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => text.push('\n'),
+            Some('t') => text.push('\t'),
+            Some(other) => text.push(other),
+            None => text.push('\\'),
+        }
+    }
+    text
 }
 
 /// A valueless ingredient line is just a name (with optional measure), so
@@ -1012,4 +1418,203 @@ Serves 0.";
             other => panic!("expected Loop instruction, got: {:?}", other),
         }
     }
+
+    fn loop_recipe(opening: &str, closing: &str) -> String {
+        format!(
+            "Loop Dish.\n\nIngredients.\n3 g egg\n\nMethod.\n{}\nPut egg into mixing bowl.\n{}\nServes 1.",
+            opening, closing
+        )
+    }
+
+    #[test]
+    fn irregular_participles_close_loops() {
+        for (opening, closing) in [
+            ("Shake the egg.", "Shake the egg until shaken."),
+            ("Beat the egg.", "Whisk the egg until beaten."),
+            ("Grind the egg.", "Grind the egg until ground."),
+            ("Fry the egg.", "Fry the egg until fried."),
+            ("Omit the egg.", "Omit the egg until omitted."),
+            ("Prefer the egg.", "Prefer the egg until preferred."),
+        ] {
+            let source = loop_recipe(opening, closing);
+            for verb_matching in [VerbMatching::Lenient, VerbMatching::Strict] {
+                let recipe = Parser::with_options(
+                    &source,
+                    ParseOptions {
+                        verb_matching,
+                        ..ParseOptions::default()
+                    },
+                )
+                .parse_recipe()
+                .unwrap_or_else(|e| panic!("{}: {}", closing, e));
+                assert!(matches!(recipe.instructions[0], Instruction::Loop { .. }));
+            }
+        }
+    }
+
+    #[test]
+    fn strict_verb_matching_rejects_prefix_only_participles() {
+        let source = loop_recipe("Stir the egg.", "Stir the egg until stirringly.");
+        assert!(Parser::new(&source).parse_recipe().is_ok());
+
+        let options = ParseOptions {
+            verb_matching: VerbMatching::Strict,
+            ..ParseOptions::default()
+        };
+        let error = Parser::with_options(&source, options)
+            .parse_recipe()
+            .expect_err("'stirringly' is not a participle of 'Stir'");
+        match error {
+            ParseError::LoopVerbMismatch {
+                verb,
+                found,
+                expected,
+            } => {
+                assert_eq!(verb, "Stir");
+                assert_eq!(found, "stirringly");
+                assert_eq!(expected, "stirred");
+            }
+            other => panic!("expected LoopVerbMismatch, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn fixtures_parse_with_strict_verb_matching() {
+        let options = ParseOptions {
+            verb_matching: VerbMatching::Strict,
+            ..ParseOptions::default()
+        };
+        for entry in std::fs::read_dir("tests/fixtures").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "chef") {
+                let source = std::fs::read_to_string(&path).unwrap();
+                if Parser::new(&source).parse_recipe().is_ok() {
+                    assert!(
+                        Parser::with_options(&source, options)
+                            .parse_recipe()
+                            .is_ok(),
+                        "{} only parses leniently",
+                        path.display()
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn ordinal_words_name_bowls_when_enabled() {
+        let words = ParseOptions {
+            ordinals: Ordinals::Words,
+            ..ParseOptions::default()
+        };
+        let parse = |sentence| Parser::known_instruction(sentence, &words).unwrap();
+        assert!(matches!(
+            parse("Put egg into the second mixing bowl"),
+            Some(Instruction::Put(_, 1))
+        ));
+        assert!(matches!(
+            parse("Pour contents of the Third mixing bowl into the TWENTIETH baking dish"),
+            Some(Instruction::Pour(2, 19))
+        ));
+        assert!(matches!(
+            parse("Mix the 2ND mixing bowl well"),
+            Some(Instruction::Mix(1))
+        ));
+
+        match Parser::parse_known_instruction("Put egg into the second mixing bowl") {
+            Err(ParseError::OrdinalWord { word, expected }) => {
+                assert_eq!((word.as_str(), expected.as_str()), ("second", "2nd"));
+            }
+            other => panic!("expected OrdinalWord, got: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn wrong_ordinal_suffixes_are_rejected_in_strict_mode() {
+        let sentence = "Fold egg into the 3st mixing bowl";
+        assert!(matches!(
+            Parser::parse_known_instruction(sentence),
+            Ok(Some(Instruction::Fold(_, 2)))
+        ));
+
+        match Parser::known_instruction(sentence, &ParseOptions::strict()) {
+            Err(ParseError::OrdinalSuffixMismatch { found, expected }) => {
+                assert_eq!((found.as_str(), expected.as_str()), ("3st", "3rd"));
+            }
+            other => panic!("expected OrdinalSuffixMismatch, got: {:?}", other),
+        }
+        for sentence in [
+            "Fold egg into the 11th mixing bowl",
+            "Fold egg into the 22ND mixing bowl",
+        ] {
+            assert!(Parser::known_instruction(sentence, &ParseOptions::strict()).is_ok());
+        }
+    }
+
+    #[test]
+    fn comment_annotations_become_examples() {
+        let source = "Echo.\n\nEchoes its input.\n  Input: 1\ninput: 2\n\
+            expect: \"1 2\\n\"\nexpect: \\\"\n\nMethod.\nSet aside.\n";
+        let recipe = Parser::new(source).parse_recipe().unwrap();
+        assert_eq!(
+            recipe.examples,
+            vec![
+                Example {
+                    input: "1 2".to_string(),
+                    expect: "1 2\n".to_string(),
+                    line: 6,
+                },
+                Example {
+                    input: String::new(),
+                    expect: "\"".to_string(),
+                    line: 7,
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn source_map_locates_title_ingredients_and_instructions() {
+        let source = "\
+Mapped Muffins.
+
+Ingredients.
+3 g flour
+  1 egg
+
+Method.
+Sift the flour. Put flour into mixing bowl.
+Sift the flour until sifted. Stir for 0 minutes.
+Serves 1.
+
+Side Sauce.
+
+Method.
+Clean mixing bowl.";
+
+        let recipe = Parser::new(source).parse_recipe().expect("recipe parses");
+        let map = &recipe.source_map;
+        assert_eq!(map.title.as_ref().map(|l| l.line), Some(1));
+        let egg = &map.ingredients["egg"];
+        assert_eq!((egg.line, egg.column), (5, Some(3)));
+        assert_eq!(egg.snippet.as_deref(), Some("1 egg"));
+
+        // Loop, its body, Stir, Serves: the "until" sentence has no entry.
+        let located: Vec<(usize, Option<usize>)> = map
+            .instructions
+            .iter()
+            .map(|l| (l.line, l.column))
+            .collect();
+        assert_eq!(
+            located,
+            vec![(8, Some(1)), (8, Some(17)), (9, Some(30)), (10, Some(1))]
+        );
+        let end = &map.loop_ends[&0];
+        assert_eq!((end.line, end.column), (9, Some(1)));
+        assert_eq!(map.loop_ends.len(), 1);
+
+        let aux = &recipe.auxiliary_recipes["Side Sauce."].source_map;
+        assert_eq!(aux.title.as_ref().map(|l| l.line), Some(12));
+        assert_eq!(aux.instructions[0].line, 15);
+    }
 }
@@ -0,0 +1,494 @@
+//! Classifies the text of a recipe into token kinds for syntax highlighting.
+//!
+//! The classifier walks the source the way [`Parser`] does: it splits
+//! recipes with the same title heuristics, finds the sections the same way,
+//! and matches method sentences against the parser's own instruction
+//! regexes, in the parser's order. A sentence the parser would reject comes
+//! out as [`TokenKind::Unknown`], so highlighting never disagrees with what
+//! actually parses.
+
+use regex::Captures;
+
+use super::{
+    add_dry_regex, add_regex, clean_regex, combine_regex, divide_regex, fold_regex,
+    ingredient_regex, liquefy_bowl_regex, liquefy_regex, loop_start_regex, mix_regex,
+    normalize_word, pour_regex, put_regex, refrigerate_regex, remove_regex, serve_with_regex,
+    serves_regex, set_aside_regex, stir_ingredient_regex, stir_regex, take_regex, Parser, Sentence,
+};
+
+/// What a span of recipe text is.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum TokenKind {
+    /// A recipe title line.
+    Title,
+    /// Free text between a title and the first section.
+    Description,
+    /// `Ingredients.` or `Method.`
+    SectionHeader,
+    /// A "Cooking time" or "Pre-heat oven" line.
+    Metadata,
+    /// The initial value in an ingredient line.
+    Quantity,
+    /// A measure or measure modifier ("g", "heaped", "cups").
+    Measure,
+    Ingredient,
+    /// The fixed words of an instruction ("Put", "into", "mixing bowl").
+    Keyword,
+    /// A bowl or dish number ("2nd", or "second" as an extension).
+    Ordinal,
+    /// Minutes, hours or a serving count.
+    Number,
+    /// The verbs that open and close a loop.
+    LoopVerb,
+    /// The recipe named by `Serve with`.
+    RecipeName,
+    /// A method sentence the parser does not accept.
+    Unknown,
+}
+
+impl TokenKind {
+    /// A stable, lowercase name for editors and CSS classes.
+    pub fn name(self) -> &'static str {
+        match self {
+            TokenKind::Title => "title",
+            TokenKind::Description => "description",
+            TokenKind::SectionHeader => "section-header",
+            TokenKind::Metadata => "metadata",
+            TokenKind::Quantity => "quantity",
+            TokenKind::Measure => "measure",
+            TokenKind::Ingredient => "ingredient",
+            TokenKind::Keyword => "keyword",
+            TokenKind::Ordinal => "ordinal",
+            TokenKind::Number => "number",
+            TokenKind::LoopVerb => "loop-verb",
+            TokenKind::RecipeName => "recipe-name",
+            TokenKind::Unknown => "unknown",
+        }
+    }
+}
+
+/// A classified span: byte offsets `start..end` into the source.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub start: usize,
+    pub end: usize,
+}
+
+/// Classifies `source` into non-overlapping tokens in source order.
+/// Whitespace and sentence-ending periods are left unclassified.
+pub fn classify(source: &str) -> Vec<Token> {
+    let mut tokens = Vec::new();
+    for block in Parser::split_recipes(source) {
+        let offset = block.as_ptr() as usize - source.as_ptr() as usize;
+        classify_recipe(block, offset, &mut tokens);
+    }
+    tokens
+}
+
+fn push(tokens: &mut Vec<Token>, kind: TokenKind, start: usize, end: usize) {
+    if end > start {
+        tokens.push(Token { kind, start, end });
+    }
+}
+
+/// Pushes one token per non-empty line of `text`, trimmed.
+fn push_lines(tokens: &mut Vec<Token>, kind: TokenKind, text: &str, offset: usize) {
+    let mut line_start = 0;
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim();
+        if !trimmed.is_empty() {
+            let start = offset + line_start + (line.len() - line.trim_start().len());
+            push(tokens, kind, start, start + trimmed.len());
+        }
+        line_start += line.len();
+    }
+}
+
+fn classify_recipe(block: &str, offset: usize, tokens: &mut Vec<Token>) {
+    let method_idx = block.find("Method.");
+    let ingredients_idx = block
+        .find("Ingredients.")
+        .filter(|&idx| method_idx.map_or(true, |method| idx < method));
+
+    // The title is the first line; the description runs to the first
+    // section.
+    let title_start = block.len() - block.trim_start().len();
+    let title_end = block[title_start..]
+        .find('\n')
+        .map_or(block.len(), |idx| title_start + idx);
+    let first_section = ingredients_idx.or(method_idx).unwrap_or(block.len());
+    if first_section > title_start {
+        let title_end = title_end.min(first_section);
+        push_lines(tokens, TokenKind::Title, &block[..title_end], offset);
+        push_lines(
+            tokens,
+            TokenKind::Description,
+            &block[title_end..first_section],
+            offset + title_end,
+        );
+    }
+
+    if let Some(idx) = ingredients_idx {
+        let start = idx + "Ingredients.".len();
+        push(
+            tokens,
+            TokenKind::SectionHeader,
+            offset + idx,
+            offset + start,
+        );
+        let end = method_idx.unwrap_or(block.len());
+        classify_ingredients(&block[start..end], offset + start, tokens);
+    }
+
+    if let Some(idx) = method_idx {
+        let start = idx + "Method.".len();
+        push(
+            tokens,
+            TokenKind::SectionHeader,
+            offset + idx,
+            offset + start,
+        );
+        classify_method(&block[start..], offset + start, tokens);
+    }
+}
+
+fn classify_ingredients(text: &str, offset: usize, tokens: &mut Vec<Token>) {
+    let mut line_start = 0;
+    for raw_line in text.split_inclusive('\n') {
+        let line_offset = offset + line_start + (raw_line.len() - raw_line.trim_start().len());
+        line_start += raw_line.len();
+        let line = raw_line.trim();
+        if line.is_empty() {
+            continue;
+        }
+
+        let lower = line.to_lowercase();
+        if lower.starts_with("cooking time:") || lower.starts_with("pre-heat oven") {
+            push(
+                tokens,
+                TokenKind::Metadata,
+                line_offset,
+                line_offset + line.len(),
+            );
+            continue;
+        }
+
+        let rest_start = match ingredient_regex().captures(line) {
+            Some(caps) => {
+                let amount = caps.name("amount").unwrap();
+                let trimmed = amount.as_str().trim_end().len();
+                push(
+                    tokens,
+                    TokenKind::Quantity,
+                    line_offset + amount.start(),
+                    line_offset + amount.start() + trimmed,
+                );
+                caps.name("rest").unwrap().start()
+            }
+            None => 0,
+        };
+
+        // Leading measure words, then the ingredient name.
+        let mut name_start = None;
+        for (word_start, word) in words(&line[rest_start..]) {
+            let start = rest_start + word_start;
+            let normalized = normalize_word(word);
+            let is_measure =
+                Parser::is_measure_modifier(&normalized) || Parser::is_measure_word(&normalized);
+            if name_start.is_none() && is_measure {
+                push(
+                    tokens,
+                    TokenKind::Measure,
+                    line_offset + start,
+                    line_offset + start + word.len(),
+                );
+            } else {
+                name_start.get_or_insert(start);
+            }
+        }
+        if let Some(start) = name_start {
+            push(
+                tokens,
+                TokenKind::Ingredient,
+                line_offset + start,
+                line_offset + line.len(),
+            );
+        }
+    }
+}
+
+/// Whitespace-separated words with their byte offsets.
+fn words(text: &str) -> impl Iterator<Item = (usize, &str)> {
+    text.split_whitespace()
+        .map(move |word| (word.as_ptr() as usize - text.as_ptr() as usize, word))
+}
+
+fn classify_method(text: &str, offset: usize, tokens: &mut Vec<Token>) {
+    // Verbs of the loops open at this point, innermost last, tracked the
+    // way `Parser::parse_loop` pairs loops with their "until" sentences.
+    let mut open_loops: Vec<String> = Vec::new();
+
+    for sentence in Parser::split_sentences(text) {
+        let s = sentence.text.as_str();
+        let spans = if open_loops
+            .last()
+            .is_some_and(|verb| Parser::until_matches_verb(s, verb))
+        {
+            open_loops.pop();
+            loop_end_spans(s)
+        } else if let Some(spans) = known_instruction_spans(s) {
+            spans
+        } else if let Some(caps) = loop_start_regex().captures(s) {
+            let verb = caps.name("verb").unwrap();
+            if Parser::until_matches_verb(s, verb.as_str()) {
+                loop_end_spans(s)
+            } else {
+                open_loops.push(verb.as_str().to_string());
+                let ingredient = caps.name("ingredient").unwrap();
+                vec![
+                    (verb.start(), verb.end(), TokenKind::LoopVerb),
+                    (ingredient.start(), ingredient.end(), TokenKind::Ingredient),
+                ]
+            }
+        } else {
+            vec![(0, s.len(), TokenKind::Unknown)]
+        };
+        emit_sentence(&sentence, offset, spans, tokens);
+    }
+}
+
+/// Named groups of the instruction regexes and what they hold.
+const GROUP_KINDS: &[(&str, TokenKind)] = &[
+    ("ingredient", TokenKind::Ingredient),
+    ("bowl", TokenKind::Ordinal),
+    ("from", TokenKind::Ordinal),
+    ("to", TokenKind::Ordinal),
+    ("minutes", TokenKind::Number),
+    ("hours", TokenKind::Number),
+    ("count", TokenKind::Number),
+    ("recipe", TokenKind::RecipeName),
+];
+
+/// Spans of a sentence that is one of the named instructions, checking
+/// the regexes in the order `Parser::parse_known_instruction` does.
+fn known_instruction_spans(sentence: &str) -> Option<Vec<(usize, usize, TokenKind)>> {
+    let regexes = [
+        take_regex(),
+        put_regex(),
+        fold_regex(),
+        add_dry_regex(),
+        add_regex(),
+        remove_regex(),
+        combine_regex(),
+        divide_regex(),
+        liquefy_bowl_regex(),
+        liquefy_regex(),
+        stir_regex(),
+        stir_ingredient_regex(),
+        mix_regex(),
+        clean_regex(),
+        pour_regex(),
+        set_aside_regex(),
+        serve_with_regex(),
+        refrigerate_regex(),
+        serves_regex(),
+    ];
+    let caps = regexes.iter().find_map(|regex| regex.captures(sentence))?;
+    Some(group_spans(&caps))
+}
+
+fn group_spans(caps: &Captures<'_>) -> Vec<(usize, usize, TokenKind)> {
+    let mut spans = Vec::new();
+    for &(name, kind) in GROUP_KINDS {
+        let Some(group) = caps.name(name) else {
+            continue;
+        };
+        spans.push((group.start(), group.end(), kind));
+    }
+    spans
+}
+
+/// "Verb [the ingredient] until verbed": both verbs, and the ingredient
+/// decremented by the loop.
+fn loop_end_spans(sentence: &str) -> Vec<(usize, usize, TokenKind)> {
+    let mut spans = Vec::new();
+    let Some(until) = sentence.to_lowercase().find(" until ") else {
+        return spans;
+    };
+    let mut before = words(&sentence[..until]);
+    if let Some((start, verb)) = before.next() {
+        spans.push((start, start + verb.len(), TokenKind::LoopVerb));
+    }
+    let ingredient: Vec<(usize, &str)> = before
+        .skip_while(|(_, word)| word.eq_ignore_ascii_case("the"))
+        .collect();
+    if let (Some(first), Some(last)) = (ingredient.first(), ingredient.last()) {
+        spans.push((first.0, last.0 + last.1.len(), TokenKind::Ingredient));
+    }
+    let after = until + " until ".len();
+    for (start, word) in words(&sentence[after..]) {
+        spans.push((
+            after + start,
+            after + start + word.len(),
+            TokenKind::LoopVerb,
+        ));
+    }
+    spans
+}
+
+/// Pushes a sentence's spans, classifying the words between them as
+/// keywords, and maps sentence positions back to the source.
+fn emit_sentence(
+    sentence: &Sentence,
+    offset: usize,
+    mut spans: Vec<(usize, usize, TokenKind)>,
+    tokens: &mut Vec<Token>,
+) {
+    spans.sort_by_key(|&(start, _, _)| start);
+    let text = sentence.text.as_str();
+    let mut emit = |start: usize, end: usize, kind: TokenKind| {
+        let (from, to) = sentence.source_span(start, end);
+        push(tokens, kind, offset + from, offset + to);
+    };
+
+    let mut cursor = 0;
+    for (start, end, kind) in spans {
+        if start < cursor {
+            continue;
+        }
+        for (word_start, word) in words(&text[cursor..start]) {
+            let word_start = cursor + word_start;
+            emit(word_start, word_start + word.len(), TokenKind::Keyword);
+        }
+        emit(start, end, kind);
+        cursor = end;
+    }
+    for (word_start, word) in words(&text[cursor..]) {
+        let word_start = cursor + word_start;
+        emit(word_start, word_start + word.len(), TokenKind::Keyword);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spans(source: &str) -> Vec<(&'static str, &str)> {
+        classify(source)
+            .into_iter()
+            .map(|token| (token.kind.name(), &source[token.start..token.end]))
+            .collect()
+    }
+
+    #[test]
+    fn classifies_every_part_of_a_recipe() {
+        let source = "\
+Caramel Tart.
+
+A sweet test.
+
+Ingredients.
+Cooking time: 20 minutes.
+1 1/2 heaped cups sugar
+2 g brown butter
+
+Method.
+Put sugar into the 2nd mixing bowl. Stir for 3 minutes.
+Melt the sugar. Serve with caramel sauce.
+Heat the butter until melted. Juggle!
+Serves 2.";
+
+        let spans = spans(source);
+        for expected in [
+            ("title", "Caramel Tart."),
+            ("description", "A sweet test."),
+            ("section-header", "Ingredients."),
+            ("metadata", "Cooking time: 20 minutes."),
+            ("quantity", "1 1/2"),
+            ("measure", "heaped"),
+            ("measure", "cups"),
+            ("ingredient", "sugar"),
+            ("ingredient", "brown butter"),
+            ("section-header", "Method."),
+            ("keyword", "Put"),
+            ("keyword", "into"),
+            ("ordinal", "2nd"),
+            ("keyword", "mixing"),
+            ("number", "3"),
+            ("loop-verb", "Melt"),
+            ("recipe-name", "caramel sauce"),
+            ("loop-verb", "Heat"),
+            ("ingredient", "butter"),
+            ("keyword", "until"),
+            ("loop-verb", "melted"),
+            ("unknown", "Juggle"),
+            ("keyword", "Serves"),
+            ("number", "2"),
+        ] {
+            assert!(
+                spans.contains(&expected),
+                "missing {:?} in {:?}",
+                expected,
+                spans
+            );
+        }
+    }
+
+    #[test]
+    fn sentences_across_lines_map_back_to_the_source() {
+        let source = "Split.\n\nIngredients.\n1 g salt\n\nMethod.\nPut salt\ninto the mixing bowl.";
+        let tokens = classify(source);
+        let ingredient = tokens
+            .iter()
+            .rev()
+            .find(|token| token.kind == TokenKind::Ingredient)
+            .unwrap();
+        assert_eq!(&source[ingredient.start..ingredient.end], "salt");
+        assert_eq!(ingredient.start, source.rfind("salt").unwrap());
+        let into = tokens
+            .iter()
+            .find(|token| &source[token.start..token.end] == "into")
+            .unwrap();
+        assert_eq!(into.kind, TokenKind::Keyword);
+        assert!(tokens.windows(2).all(|pair| pair[0].end <= pair[1].start));
+    }
+
+    #[test]
+    fn until_closes_the_innermost_open_loop() {
+        // The closing sentence's own verb is arbitrary: "beaten" closes the
+        // Beat loop.
+        let source =
+            "Loops.\n\nIngredients.\n1 g egg\n\nMethod.\nBeat the egg. Whisk the egg until beaten.";
+        let kinds: Vec<_> = classify(source)
+            .into_iter()
+            .filter(|token| token.kind == TokenKind::LoopVerb)
+            .map(|token| &source[token.start..token.end])
+            .collect();
+        assert_eq!(kinds, vec!["Beat", "Whisk", "beaten"]);
+    }
+
+    #[test]
+    fn recipes_that_parse_have_no_unknown_sentences() {
+        let mut checked = 0;
+        for dir in ["tests/fixtures", "tests/fixtures/spec"] {
+            for entry in std::fs::read_dir(dir).unwrap() {
+                let path = entry.unwrap().path();
+                if path.extension().map_or(true, |ext| ext != "chef") {
+                    continue;
+                }
+                let source = std::fs::read_to_string(&path).unwrap();
+                let tokens = classify(&source);
+                if Parser::new(&source).parse_recipe().is_ok() {
+                    checked += 1;
+                    assert!(
+                        tokens.iter().all(|token| token.kind != TokenKind::Unknown),
+                        "{} has unknown spans",
+                        path.display()
+                    );
+                }
+            }
+        }
+        assert!(checked > 20);
+    }
+}
@@ -0,0 +1,384 @@
+//! The spec's recipe layout, enforced paragraph by paragraph.
+//!
+//! A recipe is a sequence of paragraphs separated by exactly one blank line,
+//! in this order: title, comments, ingredients, cooking time, oven
+//! temperature, method and serves. Everything but the title and the method
+//! is optional. Auxiliary recipes follow, each starting with its title right
+//! after the previous method or serves paragraph. The lenient parser guesses
+//! this structure from the text; here every deviation is a [`ParseError`].
+
+use std::sync::OnceLock;
+
+use regex::Regex;
+
+use super::serves_regex;
+use crate::types::{ParseError, ParseResult};
+
+/// A recipe section, in the order the spec lists them.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Section {
+    Title,
+    Comments,
+    Ingredients,
+    CookingTime,
+    Oven,
+    Method,
+    Serves,
+}
+
+impl Section {
+    fn name(self) -> &'static str {
+        match self {
+            Section::Title => "title",
+            Section::Comments => "comments",
+            Section::Ingredients => "ingredients",
+            Section::CookingTime => "cooking time",
+            Section::Oven => "oven temperature",
+            Section::Method => "method",
+            Section::Serves => "serves",
+        }
+    }
+
+    /// The section a paragraph starting with `line` opens, when its first
+    /// line says so. Titles and comments have no marker.
+    fn of(line: &str) -> Option<Section> {
+        let lower = line.to_lowercase();
+        if line.starts_with("Ingredients.") {
+            Some(Section::Ingredients)
+        } else if line.starts_with("Method.") {
+            Some(Section::Method)
+        } else if lower.starts_with("cooking time:") {
+            Some(Section::CookingTime)
+        } else if lower.starts_with("pre-heat oven") {
+            Some(Section::Oven)
+        } else if serves_regex().is_match(line.trim_end_matches('.')) {
+            Some(Section::Serves)
+        } else {
+            None
+        }
+    }
+}
+
+/// Consecutive non-blank lines of the source.
+struct Paragraph<'a> {
+    /// Byte range of the paragraph in the source.
+    start: usize,
+    end: usize,
+    /// One-based line numbers with the trimmed line text.
+    lines: Vec<(usize, &'a str)>,
+}
+
+/// Splits `input` into paragraphs, rejecting runs of more than one blank
+/// line between them. Trailing blank lines are fine.
+fn paragraphs(input: &str) -> ParseResult<Vec<Paragraph<'_>>> {
+    let mut paragraphs: Vec<Paragraph> = Vec::new();
+    let mut current: Option<Paragraph> = None;
+    let mut blank_lines = 0;
+    let mut offset = 0;
+
+    for (idx, segment) in input.split_inclusive('\n').enumerate() {
+        let start = offset;
+        offset += segment.len();
+        let line = segment.trim();
+        if line.is_empty() {
+            if let Some(paragraph) = current.take() {
+                paragraphs.push(paragraph);
+            }
+            blank_lines += 1;
+            continue;
+        }
+        if blank_lines > 1 && !paragraphs.is_empty() {
+            return Err(ParseError::ExtraBlankLine { line: idx });
+        }
+        blank_lines = 0;
+        let end = start + segment.trim_end().len();
+        match current.as_mut() {
+            Some(paragraph) => {
+                paragraph.end = end;
+                paragraph.lines.push((idx + 1, line));
+            }
+            None => {
+                current = Some(Paragraph {
+                    start: start + (segment.len() - segment.trim_start().len()),
+                    end,
+                    lines: vec![(idx + 1, line)],
+                })
+            }
+        }
+    }
+    paragraphs.extend(current);
+    Ok(paragraphs)
+}
+
+/// Splits the source into one block per recipe (main first), checking the
+/// layout of every paragraph on the way.
+pub(super) fn split_recipes(input: &str) -> ParseResult<Vec<&str>> {
+    let paragraphs = paragraphs(input)?;
+    let first = paragraphs
+        .first()
+        .ok_or_else(|| ParseError::MissingSection("Recipe".into()))?;
+    if first.lines[0].0 != 1 {
+        return Err(ParseError::InvalidTitle(
+            "Recipe title must be on the first line (no blank lines before title)".into(),
+        ));
+    }
+
+    let mut blocks = Vec::new();
+    // Start of the recipe being read and its latest section.
+    let mut current: Option<(usize, Section)> = None;
+    let mut end = 0;
+    for (idx, paragraph) in paragraphs.iter().enumerate() {
+        let (line, text) = paragraph.lines[0];
+        let section = match (Section::of(text), current) {
+            (Some(section), Some(_)) => section,
+            (Some(_), None) => return Err(ParseError::MissingSection("Title".into())),
+            (None, Some((_, Section::Title))) => Section::Comments,
+            // A title is a single line and a method follows it; anything else
+            // continues the method.
+            (None, Some((_, Section::Method)))
+                if paragraph.lines.len() > 1
+                    || paragraphs.get(idx + 1).map_or(true, |next| {
+                        Section::of(next.lines[0].1) == Some(Section::Serves)
+                    }) =>
+            {
+                return Err(ParseError::BlankLineInMethod { line: line - 1 })
+            }
+            (None, None | Some((_, Section::Method | Section::Serves))) => Section::Title,
+            (None, Some(_)) => {
+                return Err(ParseError::UnexpectedParagraph {
+                    line,
+                    text: text.to_string(),
+                })
+            }
+        };
+        check_paragraph(section, paragraph)?;
+
+        if section == Section::Title {
+            if let Some((start, _)) = current {
+                blocks.push(&input[start..end]);
+            }
+            current = Some((paragraph.start, Section::Title));
+        } else {
+            let (_, latest) = current.as_mut().expect("only a title starts a recipe");
+            if section <= *latest {
+                return Err(ParseError::SectionOutOfOrder {
+                    line,
+                    section: section.name().to_string(),
+                    after: latest.name().to_string(),
+                });
+            }
+            *latest = section;
+        }
+        end = paragraph.end;
+    }
+    if let Some((start, _)) = current {
+        blocks.push(&input[start..end]);
+    }
+    Ok(blocks)
+}
+
+fn check_paragraph(section: Section, paragraph: &Paragraph) -> ParseResult<()> {
+    let (line, text) = paragraph.lines[0];
+
+    // A section marker further down means a blank line is missing, except
+    // for the metadata the lenient parser tolerates in the ingredient list.
+    for &(line, text) in &paragraph.lines[1..] {
+        match Section::of(text) {
+            Some(Section::CookingTime | Section::Oven) if section == Section::Ingredients => {
+                return Err(ParseError::MetadataInIngredients {
+                    line,
+                    text: text.to_string(),
+                })
+            }
+            Some(_) => {
+                return Err(ParseError::MissingBlankLine {
+                    line,
+                    text: text.to_string(),
+                })
+            }
+            None => {}
+        }
+    }
+
+    match section {
+        Section::Title | Section::CookingTime | Section::Oven | Section::Serves
+            if paragraph.lines.len() > 1 =>
+        {
+            let (line, text) = paragraph.lines[1];
+            return Err(ParseError::MissingBlankLine {
+                line,
+                text: text.to_string(),
+            });
+        }
+        Section::Ingredients | Section::Method => {
+            let header = if section == Section::Ingredients {
+                "Ingredients."
+            } else {
+                "Method."
+            };
+            if text != header {
+                return Err(ParseError::HeaderNotOnOwnLine {
+                    line,
+                    header: header.to_string(),
+                });
+            }
+        }
+        _ => {}
+    }
+
+    let well_formed = match section {
+        Section::CookingTime => cooking_time_regex().is_match(text),
+        Section::Oven => oven_regex().is_match(text),
+        Section::Serves => text.ends_with('.'),
+        _ => true,
+    };
+    if !well_formed {
+        return Err(ParseError::InvalidMetadata {
+            line,
+            section: section.name().to_string(),
+            text: text.to_string(),
+        });
+    }
+
+    if section == Section::Method {
+        for &(line, text) in &paragraph.lines[1..] {
+            if let Some(sentence) = text
+                .split(['.', '!', '?'])
+                .map(str::trim)
+                .find(|sentence| serves_regex().is_match(sentence))
+            {
+                return Err(ParseError::ServesInMethod {
+                    line,
+                    text: sentence.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+fn cooking_time_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| Regex::new(r"^Cooking time: \d+ (?:hour|hours|minute|minutes)\.$").unwrap())
+}
+
+fn oven_regex() -> &'static Regex {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    RE.get_or_init(|| {
+        Regex::new(r"^Pre-heat oven to \d+ degrees Celsius(?: \(gas mark \d+\))?\.$").unwrap()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::parser::{ParseOptions, Parser};
+    use crate::types::ParseError;
+
+    const VALID: &str = "\
+Layout Cake.
+
+A recipe laid out exactly as the spec describes.
+
+Ingredients.
+72 g flour
+
+Cooking time: 1 hour.
+
+Pre-heat oven to 180 degrees Celsius (gas mark 4).
+
+Method.
+Put flour into the mixing bowl. Serve with glaze.
+Liquefy contents of the mixing bowl. Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Glaze.
+
+Ingredients.
+1 g sugar
+
+Method.
+Put sugar into the mixing bowl.
+
+Sprinkles.
+
+Method.
+Stir for 1 minute.
+";
+
+    fn strict(source: &str) -> Result<crate::types::Recipe, ParseError> {
+        Parser::with_options(source, ParseOptions::strict()).parse_recipe()
+    }
+
+    #[test]
+    fn accepts_the_spec_layout_with_auxiliary_recipes() {
+        let recipe = strict(VALID).expect("layout follows the spec");
+        assert_eq!(recipe.title, "Layout Cake.");
+        let mut titles: Vec<_> = recipe.auxiliary_recipes.keys().cloned().collect();
+        titles.sort();
+        assert_eq!(titles, ["Glaze.", "Sprinkles."]);
+
+        // Without a "Serves" line before it, the lenient parser reads the
+        // last title as part of the previous method.
+        assert!(Parser::new(VALID).parse_recipe().is_err());
+    }
+
+    #[test]
+    fn reports_each_deviation_with_its_own_error() {
+        type Check = fn(&ParseError) -> bool;
+        let cases: [(&str, &str, Check); 8] = [
+            ("\n\nIngredients.", "\n\n\nIngredients.", |e| {
+                matches!(e, ParseError::ExtraBlankLine { line: 5 })
+            }),
+            ("72 g flour\n", "72 g flour\nMethod.\n", |e| {
+                matches!(e, ParseError::MissingBlankLine { line: 7, .. })
+            }),
+            ("Ingredients.\n72 g flour", "Ingredients. 72 g flour", |e| {
+                matches!(e, ParseError::HeaderNotOnOwnLine { line: 5, .. })
+            }),
+            (
+                "72 g flour\n\nCooking time",
+                "72 g flour\nCooking time",
+                |e| matches!(e, ParseError::MetadataInIngredients { line: 7, .. }),
+            ),
+            ("Serve with glaze.", "Serves 2. Serve with glaze.", |e| {
+                matches!(e, ParseError::ServesInMethod { line: 13, .. })
+            }),
+            ("Serves 1.", "Serves 1", |e| {
+                matches!(e, ParseError::InvalidMetadata { line: 16, .. })
+            }),
+            ("Liquefy", "\nLiquefy", |e| {
+                matches!(e, ParseError::BlankLineInMethod { line: 14 })
+            }),
+            ("Cooking time: 1 hour.\n\n", "Extra words.\n\n", |e| {
+                matches!(e, ParseError::UnexpectedParagraph { line: 8, .. })
+            }),
+        ];
+        for (from, to, expected) in cases {
+            let source = VALID.replacen(from, to, 1);
+            let error = strict(&source).expect_err(to);
+            assert!(expected(&error), "{:?} for {:?}", error, to);
+        }
+    }
+
+    #[test]
+    fn sections_must_come_in_the_spec_order() {
+        let source = VALID.replacen(
+            "Cooking time: 1 hour.\n\nPre-heat oven to 180 degrees Celsius (gas mark 4).",
+            "Pre-heat oven to 180 degrees Celsius (gas mark 4).\n\nCooking time: 1 hour.",
+            1,
+        );
+        match strict(&source) {
+            Err(ParseError::SectionOutOfOrder {
+                line,
+                section,
+                after,
+            }) => {
+                assert_eq!(line, 10);
+                assert_eq!(section, "cooking time");
+                assert_eq!(after, "oven temperature");
+            }
+            other => panic!("expected SectionOutOfOrder, got {:?}", other.err()),
+        }
+    }
+}
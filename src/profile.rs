@@ -0,0 +1,474 @@
+//! Where a run spends its time: counts and wall time per instruction, per
+//! loop and per recipe, reported as a table or as folded stacks for
+//! flamegraph tools.
+//!
+//! The interpreter opens a scope for every recipe it runs (the main one and
+//! each `Serve with`, one per [`CallFrame`](crate::types::CallFrame) on its
+//! call stack) and for every instruction, so a stack of scopes reads like
+//! `Main Dish.;Stir the sugar (line 9);Serve with caramel sauce (line
+//! 10);Caramel Sauce.;Put cream into the mixing bowl (line 20)`.
+//! Instructions are told apart by their position in their recipe (the order
+//! of `SourceMap::instructions`), so the same sentence on two lines counts
+//! twice.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+use crate::instruction::Instruction;
+use crate::interpreter::{InstructionId, Observer};
+use crate::types::Recipe;
+
+/// What one instruction of one recipe cost.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct InstructionStat {
+    pub recipe: String,
+    /// The position of the instruction in its recipe, in pre-order.
+    pub index: usize,
+    /// The sentence that opens the instruction.
+    pub text: String,
+    /// Where the sentence starts, when the recipe was parsed from source.
+    pub line: Option<usize>,
+    /// How many times the instruction started.
+    pub count: u64,
+    /// For a loop, how many passes it made over its body in all.
+    pub passes: u64,
+    /// Wall time including the instructions and recipes it ran.
+    pub total: Duration,
+    /// Wall time excluding them.
+    pub self_time: Duration,
+}
+
+/// What one recipe cost over all the times it ran.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RecipeStat {
+    pub title: String,
+    pub calls: u64,
+    /// Wall time including the auxiliary recipes it served with.
+    pub total: Duration,
+    /// Wall time excluding them.
+    pub self_time: Duration,
+}
+
+/// A recipe that ran, by its id.
+struct RecipeLabels {
+    title: String,
+    /// Text and line of each instruction, in pre-order.
+    labels: Vec<(String, Option<usize>)>,
+}
+
+/// A recipe being run.
+struct RecipeFrame {
+    id: usize,
+    /// Time spent in the auxiliary recipes it served with.
+    nested: Duration,
+}
+
+enum ScopeKind {
+    Recipe,
+    Instruction(usize),
+}
+
+struct Scope {
+    kind: ScopeKind,
+    /// The folded stack up to and including this scope.
+    stack: String,
+    started: Instant,
+    /// Time spent in the scopes directly inside this one.
+    children: Duration,
+}
+
+/// Collects timings while the interpreter runs; see
+/// [`Interpreter::enable_profiling`](crate::Interpreter::enable_profiling).
+#[derive(Default)]
+pub struct Profiler {
+    instructions: HashMap<(String, usize), InstructionStat>,
+    recipes: HashMap<String, RecipeStat>,
+    /// Self time in nanoseconds by folded stack.
+    folded: HashMap<String, u128>,
+    known: HashMap<usize, RecipeLabels>,
+    frames: Vec<RecipeFrame>,
+    scopes: Vec<Scope>,
+    /// How many frames of each recipe, and scopes of each instruction, are
+    /// open, by recipe id and instruction index. A recipe that serves
+    /// itself adds to its total time only when its outermost frame closes,
+    /// so the time is not counted twice.
+    open: HashMap<(usize, usize), usize>,
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn open(&mut self, kind: ScopeKind, name: String) {
+        let stack = match self.scopes.last() {
+            Some(parent) => format!("{};{}", parent.stack, name),
+            None => name,
+        };
+        self.scopes.push(Scope {
+            kind,
+            stack,
+            started: Instant::now(),
+            children: Duration::ZERO,
+        });
+    }
+
+    /// Closes the innermost scope, returning it with its total and self
+    /// time.
+    fn close(&mut self) -> Option<(Scope, Duration, Duration)> {
+        let scope = self.scopes.pop()?;
+        let total = scope.started.elapsed();
+        if let Some(parent) = self.scopes.last_mut() {
+            parent.children += total;
+        }
+        let self_time = total.saturating_sub(scope.children);
+        Some((scope, total, self_time))
+    }
+
+    /// Closes one frame of a recipe (`index` is `usize::MAX`) or one scope
+    /// of an instruction, returning whether it was the outermost one.
+    fn close_open(&mut self, recipe: usize, index: usize) -> bool {
+        let key = (recipe, index);
+        match self.open.get_mut(&key) {
+            Some(count) if *count > 1 => {
+                *count -= 1;
+                false
+            }
+            _ => {
+                self.open.remove(&key);
+                true
+            }
+        }
+    }
+
+    /// Every instruction that ran, the most expensive (by self time) first.
+    pub fn instructions(&self) -> Vec<&InstructionStat> {
+        let mut stats: Vec<_> = self.instructions.values().collect();
+        stats.sort_by(|a, b| {
+            b.self_time
+                .cmp(&a.self_time)
+                .then_with(|| a.recipe.cmp(&b.recipe))
+                .then(a.index.cmp(&b.index))
+        });
+        stats
+    }
+
+    /// Every recipe that ran, the most expensive (by total time) first.
+    pub fn recipes(&self) -> Vec<&RecipeStat> {
+        let mut stats: Vec<_> = self.recipes.values().collect();
+        stats.sort_by(|a, b| b.total.cmp(&a.total).then(a.title.cmp(&b.title)));
+        stats
+    }
+
+    /// A table of the recipes and instructions, most expensive first.
+    pub fn report(&self) -> String {
+        let mut text = String::from("recipes\n");
+        text.push_str(&format!(
+            "{:>8} {:>12} {:>12}  recipe\n",
+            "calls", "total ms", "self ms"
+        ));
+        for stat in self.recipes() {
+            text.push_str(&format!(
+                "{:>8} {:>12.3} {:>12.3}  {}\n",
+                stat.calls,
+                millis(stat.total),
+                millis(stat.self_time),
+                stat.title
+            ));
+        }
+
+        text.push_str("\ninstructions\n");
+        text.push_str(&format!(
+            "{:>8} {:>8} {:>12} {:>12}  {:<6} instruction\n",
+            "count", "passes", "total ms", "self ms", "line"
+        ));
+        for stat in self.instructions() {
+            let passes = if stat.passes > 0 {
+                stat.passes.to_string()
+            } else {
+                "-".to_string()
+            };
+            let line = stat.line.map_or("-".to_string(), |line| line.to_string());
+            text.push_str(&format!(
+                "{:>8} {:>8} {:>12.3} {:>12.3}  {:<6} {} ({})\n",
+                stat.count,
+                passes,
+                millis(stat.total),
+                millis(stat.self_time),
+                line,
+                stat.text,
+                stat.recipe
+            ));
+        }
+        text
+    }
+
+    /// One `stack nanoseconds` line per distinct stack, in stack order: the
+    /// folded format flamegraph tools (`flamegraph.pl`, inferno, speedscope)
+    /// read.
+    pub fn folded(&self) -> String {
+        let mut stacks: Vec<_> = self.folded.iter().collect();
+        stacks.sort();
+        stacks
+            .into_iter()
+            .map(|(stack, nanos)| format!("{} {}\n", stack, nanos))
+            .collect()
+    }
+}
+
+impl Observer for Profiler {
+    fn enter_recipe(&mut self, id: usize, recipe: &Recipe) {
+        let known = self.known.entry(id).or_insert_with(|| RecipeLabels {
+            title: recipe.title.clone(),
+            labels: Instruction::preorder(&recipe.instructions)
+                .into_iter()
+                .enumerate()
+                .map(|(index, instruction)| {
+                    let location = recipe.source_map.instructions.get(index);
+                    let text = location
+                        .and_then(|location| location.snippet.clone())
+                        .unwrap_or_else(|| instruction.opening());
+                    (text, location.map(|location| location.line))
+                })
+                .collect(),
+        });
+        let name = frame_name(&known.title);
+        *self.open.entry((id, usize::MAX)).or_default() += 1;
+        self.frames.push(RecipeFrame {
+            id,
+            nested: Duration::ZERO,
+        });
+        self.open(ScopeKind::Recipe, name);
+    }
+
+    fn exit_recipe(&mut self) {
+        let Some((scope, total, self_time)) = self.close() else {
+            return;
+        };
+        debug_assert!(matches!(scope.kind, ScopeKind::Recipe));
+        let Some(frame) = self.frames.pop() else {
+            return;
+        };
+        if let Some(caller) = self.frames.last_mut() {
+            caller.nested += total;
+        }
+        *self.folded.entry(scope.stack).or_default() += self_time.as_nanos();
+        let outermost = self.close_open(frame.id, usize::MAX);
+
+        let title = &self.known[&frame.id].title;
+        let stat = self
+            .recipes
+            .entry(title.clone())
+            .or_insert_with(|| RecipeStat {
+                title: title.clone(),
+                calls: 0,
+                total: Duration::ZERO,
+                self_time: Duration::ZERO,
+            });
+        stat.calls += 1;
+        if outermost {
+            stat.total += total;
+        }
+        stat.self_time += total.saturating_sub(frame.nested);
+    }
+
+    fn enter_instruction(&mut self, id: Option<InstructionId>, instruction: &Instruction) {
+        let Some(frame) = self.frames.last() else {
+            // Outside of any recipe (the REPL): nothing to attribute it to.
+            self.open(ScopeKind::Instruction(usize::MAX), String::new());
+            return;
+        };
+        let labels = self.known.get(&frame.id).zip(id).and_then(|(known, id)| {
+            let label = known.labels.get(id.index)?;
+            Some((id, label))
+        });
+        let Some((id, (text, line))) = labels else {
+            self.open(ScopeKind::Instruction(usize::MAX), instruction.to_string());
+            return;
+        };
+        let name = match line {
+            Some(line) => format!("{} (line {})", text, line),
+            None => text.clone(),
+        };
+        *self.open.entry((id.recipe, id.index)).or_default() += 1;
+        self.open(ScopeKind::Instruction(id.index), frame_name(&name));
+    }
+
+    fn exit_instruction(&mut self, _: Option<InstructionId>) {
+        let Some((scope, total, self_time)) = self.close() else {
+            return;
+        };
+        let ScopeKind::Instruction(index) = scope.kind else {
+            return;
+        };
+        let Some(frame) = self.frames.last() else {
+            return;
+        };
+        if index == usize::MAX {
+            return;
+        }
+        *self.folded.entry(scope.stack).or_default() += self_time.as_nanos();
+        let id = frame.id;
+        let outermost = self.close_open(id, index);
+
+        let known = &self.known[&id];
+        let (text, line) = known.labels[index].clone();
+        let stat = self
+            .instructions
+            .entry((known.title.clone(), index))
+            .or_insert_with(|| InstructionStat {
+                recipe: known.title.clone(),
+                index,
+                text,
+                line,
+                count: 0,
+                passes: 0,
+                total: Duration::ZERO,
+                self_time: Duration::ZERO,
+            });
+        stat.count += 1;
+        if outermost {
+            stat.total += total;
+        }
+        stat.self_time += self_time;
+    }
+
+    /// Counts one pass of the loop whose instruction is running.
+    fn loop_pass(&mut self) {
+        let Some(Scope {
+            kind: ScopeKind::Instruction(index),
+            ..
+        }) = self.scopes.last()
+        else {
+            return;
+        };
+        let Some(known) = self
+            .frames
+            .last()
+            .and_then(|frame| self.known.get(&frame.id))
+        else {
+            return;
+        };
+        if let Some((text, line)) = known.labels.get(*index) {
+            self.instructions
+                .entry((known.title.clone(), *index))
+                .or_insert_with(|| InstructionStat {
+                    recipe: known.title.clone(),
+                    index: *index,
+                    text: text.clone(),
+                    line: *line,
+                    count: 0,
+                    passes: 0,
+                    total: Duration::ZERO,
+                    self_time: Duration::ZERO,
+                })
+                .passes += 1;
+        }
+    }
+}
+
+/// A stack frame name: folded stacks separate frames with `;`.
+fn frame_name(name: &str) -> String {
+    name.replace(';', ",")
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Interpreter, Parser};
+
+    const SOURCE: &str = "Main Dish.\n\nIngredients.\n3 g sugar\n\nMethod.\n\
+        Stir the sugar.\nServe with caramel sauce.\nStir the sugar until stirred.\n\n\
+        Serves 1.\n\nCaramel Sauce.\n\nIngredients.\n1 g cream\n\nMethod.\n\
+        Put cream into the mixing bowl.\n";
+
+    #[test]
+    fn counts_instructions_loops_and_recipes() {
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_profiling();
+        interpreter.add_recipe(Parser::new(SOURCE).parse_recipe().unwrap());
+        interpreter.run().unwrap();
+        let profiler = interpreter.profiler().unwrap();
+
+        let recipes: Vec<_> = profiler
+            .recipes()
+            .iter()
+            .map(|stat| (stat.title.as_str(), stat.calls))
+            .collect();
+        assert_eq!(recipes, vec![("Main Dish.", 1), ("Caramel Sauce.", 3)]);
+        let main = profiler.recipes()[0];
+        assert!(main.self_time <= main.total);
+
+        let mut counts: Vec<_> = profiler
+            .instructions()
+            .iter()
+            .map(|stat| (stat.text.as_str(), stat.line, stat.count, stat.passes))
+            .collect();
+        counts.sort();
+        assert_eq!(
+            counts,
+            vec![
+                ("Put cream into the mixing bowl", Some(19), 3, 0),
+                ("Serve with caramel sauce", Some(8), 3, 0),
+                ("Serves 1", Some(11), 1, 0),
+                ("Stir the sugar", Some(7), 1, 3),
+            ]
+        );
+    }
+
+    #[test]
+    fn folded_stacks_nest_recipes_and_loops() {
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_profiling();
+        interpreter.add_recipe(Parser::new(SOURCE).parse_recipe().unwrap());
+        interpreter.run().unwrap();
+
+        let folded = interpreter.profiler().unwrap().folded();
+        let stacks: Vec<&str> = folded
+            .lines()
+            .map(|line| line.rsplit_once(' ').unwrap().0)
+            .collect();
+        assert_eq!(
+            stacks,
+            vec![
+                "Main Dish.",
+                "Main Dish.;Serves 1 (line 11)",
+                "Main Dish.;Stir the sugar (line 7)",
+                "Main Dish.;Stir the sugar (line 7);Serve with caramel sauce (line 8)",
+                "Main Dish.;Stir the sugar (line 7);Serve with caramel sauce (line 8);\
+                 Caramel Sauce.",
+                "Main Dish.;Stir the sugar (line 7);Serve with caramel sauce (line 8);\
+                 Caramel Sauce.;Put cream into the mixing bowl (line 19)",
+            ]
+        );
+    }
+
+    #[test]
+    fn a_recipe_that_serves_itself_counts_its_time_once() {
+        let source = include_str!("../tests/fixtures/spec/recursive-auxiliary-test.chef");
+        let mut interpreter = Interpreter::new();
+        interpreter.enable_profiling();
+        interpreter.add_recipe(Parser::new(source).parse_recipe().unwrap());
+        let started = std::time::Instant::now();
+        interpreter.run().unwrap();
+        let elapsed = started.elapsed();
+        let profiler = interpreter.profiler().unwrap();
+
+        let main = profiler.recipes()[0];
+        assert_eq!(main.title, "Recursive Auxiliary Test.");
+        assert!(main.total <= elapsed);
+        let summer = profiler
+            .recipes()
+            .into_iter()
+            .find(|stat| stat.title == "Summer.")
+            .unwrap();
+        assert_eq!(summer.calls, 6);
+        assert!(summer.total <= main.total, "{:?} > {:?}", summer, main);
+        for stat in profiler.instructions() {
+            assert!(stat.total <= main.total, "{:?} > {:?}", stat, main);
+        }
+    }
+}
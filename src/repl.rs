@@ -0,0 +1,489 @@
+//! An interactive kitchen: declare ingredients, type method sentences one at
+//! a time, and see the mixing bowls and baking dishes after each.
+//!
+//! Sentences go through [`Parser::parse_known_instruction`] and run with
+//! [`Interpreter::execute_instruction`]. A loop is buffered until its
+//! matching "until" sentence and then parsed and run as a whole.
+
+use std::fs;
+
+use crate::instruction::Instruction;
+use crate::interpreter::Interpreter;
+use crate::parser::{loop_start_regex, Parser};
+use crate::types::{Measure, RuntimeError, Value};
+
+pub const HELP: &str = "\
+Type method sentences (\"Put flour into the mixing bowl.\") to run them.
+Lines starting with a number declare ingredients (\"72 g haricot beans\").
+A loop is collected until its matching \"until\" sentence, then run.
+
+:bowls            show the mixing bowls
+:dishes           show the baking dishes
+:define Title     start an auxiliary recipe; type its ingredients and
+                  method, then :end
+:load file.chef   declare a recipe's ingredients and auxiliary recipes
+:reset            start over with an empty kitchen, keeping the recipes
+:help             show this help
+:quit             leave";
+
+/// What the session wants the caller to do after a line.
+#[derive(Debug, PartialEq)]
+pub enum Reply {
+    /// Print this (possibly empty) text and read the next line.
+    Text(String),
+    Quit,
+}
+
+/// An auxiliary recipe being typed in after `:define`.
+struct Definition {
+    title: String,
+    ingredients: Vec<String>,
+    method: Vec<String>,
+}
+
+/// One REPL session.
+pub struct Repl {
+    interpreter: Interpreter,
+    /// Sentences of a loop that is not closed yet.
+    pending: Vec<String>,
+    /// Verbs of the loops open in `pending`, innermost last.
+    open_loops: Vec<String>,
+    definition: Option<Definition>,
+    /// How much of the interpreter's output has been shown.
+    shown_output: usize,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Self::with_interpreter(Interpreter::new())
+    }
+
+    /// Starts a session with a configured interpreter (e.g. with input
+    /// values for `Take`).
+    pub fn with_interpreter(interpreter: Interpreter) -> Self {
+        Self {
+            interpreter,
+            pending: Vec::new(),
+            open_loops: Vec::new(),
+            definition: None,
+            shown_output: 0,
+        }
+    }
+
+    /// The prompt for the next line: a continuation prompt while a loop or
+    /// a definition is open.
+    pub fn prompt(&self) -> &'static str {
+        if self.definition.is_some() {
+            "recipe> "
+        } else if self.open_loops.is_empty() {
+            "chef> "
+        } else {
+            "...> "
+        }
+    }
+
+    pub fn eval(&mut self, line: &str) -> Reply {
+        let line = line.trim();
+        if let Some(command) = line.strip_prefix(':') {
+            return self.command(command.trim());
+        }
+        if line.is_empty() {
+            return Reply::Text(String::new());
+        }
+        if self.definition.is_some() {
+            self.define_line(line);
+            return Reply::Text(String::new());
+        }
+        if starts_with_digit(line) {
+            return Reply::Text(self.declare(line));
+        }
+        Reply::Text(self.method_line(line))
+    }
+
+    fn command(&mut self, command: &str) -> Reply {
+        let (name, argument) = command
+            .split_once(char::is_whitespace)
+            .map(|(name, argument)| (name, argument.trim()))
+            .unwrap_or((command, ""));
+        let text = match name {
+            "q" | "quit" | "exit" => return Reply::Quit,
+            "h" | "help" => HELP.to_string(),
+            "bowls" => self.show_bowls(),
+            "dishes" => self.show_dishes(),
+            "reset" => {
+                self.interpreter.clear_kitchen();
+                self.pending.clear();
+                self.open_loops.clear();
+                self.definition = None;
+                "The kitchen is clean.".to_string()
+            }
+            "define" if argument.is_empty() => "usage: :define Recipe Title".to_string(),
+            "define" => {
+                self.definition = Some(Definition {
+                    title: argument.trim_end_matches('.').to_string(),
+                    ingredients: Vec::new(),
+                    method: Vec::new(),
+                });
+                "Type the recipe's ingredients and method, then :end.".to_string()
+            }
+            "end" => self.finish_definition(),
+            "load" if argument.is_empty() => "usage: :load file.chef".to_string(),
+            "load" => self.load(argument),
+            _ => format!("unknown command ':{}' (try :help)", name),
+        };
+        Reply::Text(text)
+    }
+
+    fn declare(&mut self, line: &str) -> String {
+        match Parser::parse_ingredients(line) {
+            Ok(section) => {
+                let mut names: Vec<String> = section
+                    .ingredients
+                    .keys()
+                    .chain(section.unset_ingredients.keys())
+                    .cloned()
+                    .collect();
+                names.sort();
+                self.interpreter
+                    .declare_ingredients(section.ingredients, section.unset_ingredients);
+                format!("declared {}", names.join(", "))
+            }
+            Err(error) => format!("error: {}", error),
+        }
+    }
+
+    fn method_line(&mut self, line: &str) -> String {
+        let mut report = Vec::new();
+        for sentence in Parser::split_sentences(line) {
+            let sentence = sentence.text;
+            match self.take_sentence(sentence) {
+                Ok(Some(text)) => report.push(text),
+                Ok(None) => {}
+                Err(error) => {
+                    report.push(format!("error: {}", error));
+                    if !self.pending.is_empty() {
+                        report.push("(the unfinished loop was discarded)".to_string());
+                        self.pending.clear();
+                        self.open_loops.clear();
+                    }
+                    break;
+                }
+            }
+        }
+        report.join("\n")
+    }
+
+    /// Runs a sentence, or buffers it while a loop is open. Returns the
+    /// report to show once something ran.
+    fn take_sentence(&mut self, sentence: String) -> Result<Option<String>, String> {
+        if let Some(verb) = self.open_loops.last() {
+            if Parser::until_matches_verb(&sentence, verb) {
+                self.open_loops.pop();
+            } else {
+                self.open_if_loop_start(&sentence)?;
+            }
+            self.pending.push(sentence);
+            if !self.open_loops.is_empty() {
+                return Ok(None);
+            }
+            let text = std::mem::take(&mut self.pending).join(". ");
+            let instructions = Parser::parse_instructions(&text).map_err(|e| e.to_string())?;
+            return self.run(&instructions).map(Some);
+        }
+
+        match Parser::parse_known_instruction(&sentence).map_err(|e| e.to_string())? {
+            Some(instruction) => self.run(std::slice::from_ref(&instruction)).map(Some),
+            None => {
+                self.open_if_loop_start(&sentence)?;
+                if self.open_loops.is_empty() {
+                    // A loop that opens and closes in one sentence.
+                    let instructions =
+                        Parser::parse_instructions(&sentence).map_err(|e| e.to_string())?;
+                    return self.run(&instructions).map(Some);
+                }
+                self.pending.push(sentence);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Records a loop opened by `sentence`, the way the parser would see it.
+    /// Known instructions and single-sentence loops open nothing.
+    fn open_if_loop_start(&mut self, sentence: &str) -> Result<(), String> {
+        if Parser::parse_known_instruction(sentence)
+            .map_err(|e| e.to_string())?
+            .is_some()
+        {
+            return Ok(());
+        }
+        let caps = loop_start_regex()
+            .captures(sentence)
+            .ok_or_else(|| format!("unknown instruction: {}", sentence))?;
+        let verb = caps.name("verb").unwrap().as_str();
+        if !Parser::until_matches_verb(sentence, verb) {
+            self.open_loops.push(verb.to_string());
+        }
+        Ok(())
+    }
+
+    fn run(&mut self, instructions: &[Instruction]) -> Result<String, String> {
+        let mut notes = Vec::new();
+        for instruction in instructions {
+            match self.interpreter.execute_instruction(instruction) {
+                Ok(()) => {}
+                Err(RuntimeError::EarlyTermination) => {
+                    notes.push("(refrigerated: a recipe would stop here)".to_string());
+                    break;
+                }
+                Err(RuntimeError::BreakLoop) => {
+                    notes.push(format!("error: {}", RuntimeError::SetAsideOutsideLoop));
+                    break;
+                }
+                Err(error) => {
+                    notes.push(format!("error: {}", error));
+                    break;
+                }
+            }
+        }
+
+        let output = &self.interpreter.output()[self.shown_output..];
+        if !output.is_empty() {
+            notes.push(format!("served: {}", output));
+            self.shown_output = self.interpreter.output().len();
+        }
+        notes.push(self.show_bowls());
+        notes.push(self.show_dishes());
+        Ok(notes.join("\n"))
+    }
+
+    fn define_line(&mut self, line: &str) {
+        let Some(definition) = self.definition.as_mut() else {
+            return;
+        };
+        if starts_with_digit(line) {
+            definition.ingredients.push(line.to_string());
+        } else {
+            definition.method.push(line.to_string());
+        }
+    }
+
+    fn finish_definition(&mut self) -> String {
+        let Some(definition) = self.definition.take() else {
+            return "nothing to end: start a recipe with :define".to_string();
+        };
+        let mut source = format!("{}.\n\n", definition.title);
+        if !definition.ingredients.is_empty() {
+            source.push_str("Ingredients.\n");
+            source.push_str(&definition.ingredients.join("\n"));
+            source.push_str("\n\n");
+        }
+        source.push_str("Method.\n");
+        source.push_str(&definition.method.join("\n"));
+
+        match Parser::new(&source).parse_recipe() {
+            Ok(recipe) => {
+                self.interpreter.add_auxiliary_recipe(recipe);
+                format!(
+                    "defined '{}'; use it with \"Serve with {}.\"",
+                    definition.title,
+                    definition.title.to_lowercase()
+                )
+            }
+            Err(error) => format!("error: {}", error),
+        }
+    }
+
+    fn load(&mut self, path: &str) -> String {
+        let source = match fs::read_to_string(path) {
+            Ok(source) => source,
+            Err(error) => return format!("error: cannot read {}: {}", path, error),
+        };
+        let mut recipe = match Parser::new(&source).parse_recipe() {
+            Ok(recipe) => recipe,
+            Err(error) => return format!("error: {}", error),
+        };
+
+        let mut titles: Vec<String> = Vec::new();
+        for (_, auxiliary) in recipe.auxiliary_recipes.drain() {
+            titles.push(auxiliary.title.trim_end_matches('.').to_string());
+            self.interpreter.add_auxiliary_recipe(auxiliary);
+        }
+        titles.sort();
+        let count = recipe.ingredients.len() + recipe.unset_ingredients.len();
+        self.interpreter.declare_ingredients(
+            std::mem::take(&mut recipe.ingredients),
+            std::mem::take(&mut recipe.unset_ingredients),
+        );
+        // The recipe itself can be served too (recursive recipes do).
+        self.interpreter.add_auxiliary_recipe(recipe);
+
+        let mut text = format!("declared {} ingredient(s) from {}", count, path);
+        if !titles.is_empty() {
+            text.push_str(&format!("\nauxiliary recipes: {}", titles.join(", ")));
+        }
+        text
+    }
+
+    fn show_bowls(&self) -> String {
+        show_containers("mixing bowl", self.interpreter.mixing_bowls())
+    }
+
+    fn show_dishes(&self) -> String {
+        show_containers("baking dish", self.interpreter.baking_dishes())
+    }
+}
+
+fn starts_with_digit(line: &str) -> bool {
+    line.starts_with(|c: char| c.is_ascii_digit())
+}
+
+/// One line per container, top value first.
+pub(crate) fn show_containers<'a, I>(kind: &str, containers: &'a [I]) -> String
+where
+    &'a I: IntoIterator<Item = &'a Value>,
+{
+    containers
+        .iter()
+        .enumerate()
+        .map(|(idx, container)| {
+            let values: Vec<String> = container.into_iter().map(show_value).collect();
+            if values.is_empty() {
+                format!("{} {}: empty", kind, idx + 1)
+            } else {
+                format!("{} {}: [{}] (top first)", kind, idx + 1, values.join(", "))
+            }
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// A liquid shows the character it stands for.
+pub(crate) fn show_value(value: &Value) -> String {
+    let character = u32::try_from(value.amount)
+        .ok()
+        .and_then(char::from_u32)
+        .filter(|ch| !ch.is_control());
+    match (value.measure, character) {
+        (Measure::Liquid, Some(ch)) => format!("{} {:?}", value.amount, ch),
+        _ => value.amount.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(reply: Reply) -> String {
+        match reply {
+            Reply::Text(text) => text,
+            Reply::Quit => panic!("unexpected quit"),
+        }
+    }
+
+    #[test]
+    fn shows_the_kitchen_after_each_sentence() {
+        let mut repl = Repl::new();
+        assert_eq!(
+            text(repl.eval("72 g haricot beans")),
+            "declared haricot beans"
+        );
+        text(repl.eval("105 ml water"));
+
+        let report = text(repl.eval("Put haricot beans into the mixing bowl."));
+        assert!(
+            report.contains("mixing bowl 1: [72] (top first)"),
+            "{}",
+            report
+        );
+        let report = text(repl.eval("Put water into the 2nd mixing bowl."));
+        assert!(report.contains("mixing bowl 2: [105 'i']"), "{}", report);
+
+        let report = text(repl.eval(
+            "Liquefy contents of the mixing bowl. Pour contents of the 2nd mixing bowl into the baking dish. Pour contents of the mixing bowl into the baking dish. Serves 1.",
+        ));
+        assert!(report.contains("served: Hi"), "{}", report);
+        assert_eq!(text(repl.eval(":dishes")), "baking dish 1: empty");
+    }
+
+    #[test]
+    fn loops_are_buffered_until_their_until() {
+        let mut repl = Repl::new();
+        text(repl.eval("3 g counter"));
+        assert_eq!(text(repl.eval("Count the counter.")), "");
+        assert_eq!(repl.prompt(), "...> ");
+        assert_eq!(text(repl.eval("Put counter into the mixing bowl.")), "");
+        let report = text(repl.eval("Count the counter until counted."));
+        assert_eq!(repl.prompt(), "chef> ");
+        assert!(
+            report.contains("mixing bowl 1: [1, 2, 3] (top first)"),
+            "{}",
+            report
+        );
+    }
+
+    #[test]
+    fn inline_auxiliary_recipes_can_be_served() {
+        let mut repl = Repl::new();
+        text(repl.eval(":define Sauce"));
+        assert_eq!(repl.prompt(), "recipe> ");
+        text(repl.eval("7 g pepper"));
+        text(repl.eval("Put pepper into the mixing bowl."));
+        assert!(text(repl.eval(":end")).contains("defined 'Sauce'"));
+
+        let report = text(repl.eval("Serve with sauce."));
+        assert!(report.contains("mixing bowl 1: [7]"), "{}", report);
+    }
+
+    #[test]
+    fn errors_and_commands() {
+        let mut repl = Repl::new();
+        assert!(text(repl.eval("Put salt into the mixing bowl.")).starts_with("error:"));
+        assert!(text(repl.eval("Juggle.")).contains("unknown instruction"));
+        assert!(text(repl.eval(":frobnicate")).contains("unknown command"));
+        text(repl.eval("1 g salt"));
+        text(repl.eval("Put salt into the mixing bowl."));
+        text(repl.eval(":reset"));
+        assert_eq!(text(repl.eval(":bowls")), "mixing bowl 1: empty");
+        assert_eq!(repl.eval(":quit"), Reply::Quit);
+    }
+
+    #[test]
+    fn reset_keeps_the_configured_interpreter() {
+        let mut interpreter = Interpreter::new();
+        interpreter.set_input_text("5 7");
+        let mut repl = Repl::with_interpreter(interpreter);
+        text(repl.eval("0 g flour"));
+        text(repl.eval("Take flour from refrigerator."));
+        text(repl.eval("Put flour into the mixing bowl."));
+        text(repl.eval("Sift the flour."));
+        assert_eq!(repl.prompt(), "...> ");
+
+        assert_eq!(text(repl.eval(":reset")), "The kitchen is clean.");
+        assert_eq!(repl.prompt(), "chef> ");
+        assert_eq!(text(repl.eval(":bowls")), "mixing bowl 1: empty");
+        assert!(text(repl.eval("Put flour into the mixing bowl.")).starts_with("error:"));
+        text(repl.eval("0 g flour"));
+        text(repl.eval("Take flour from refrigerator."));
+        let report = text(repl.eval("Put flour into the mixing bowl."));
+        assert!(report.contains("mixing bowl 1: [7]"), "{}", report);
+    }
+
+    #[test]
+    fn load_declares_ingredients_and_auxiliary_recipes() {
+        let mut repl = Repl::new();
+        let report = text(repl.eval(":load tests/fixtures/fibonacci.chef"));
+        assert!(
+            report.contains("auxiliary recipes: Caramel Sauce"),
+            "{}",
+            report
+        );
+        let report = text(repl.eval("Put flour into the mixing bowl."));
+        assert!(report.contains("mixing bowl 1: [100]"), "{}", report);
+    }
+}
@@ -0,0 +1,469 @@
+//! Saving the state of a run and picking it up again, possibly in another
+//! process.
+//!
+//! [`Interpreter::pause_after`](crate::Interpreter::pause_after) stops a
+//! run between two instructions; [`Interpreter::snapshot`] then captures the
+//! whole kitchen (ingredients, bowls, dishes and the sous-chefs' call
+//! frames), the output so far, the unread input, the `Mix` generator and
+//! where the run stopped. [`Interpreter::restore`] puts it into another
+//! interpreter holding the same recipe, and
+//! [`Interpreter::resume`](crate::Interpreter::resume) continues.
+//!
+//! A snapshot is plain text, one item per line:
+//!
+//! ```text
+//! cheffers snapshot 1
+//! recipe "Main Dish."
+//! resume 2 loop:3 1
+//! rng 42
+//! input 7 8
+//! output "6\n"
+//! serves 1
+//! kitchen
+//! ingredient "sugar" 5d
+//! bowl 1d 2l
+//! dish
+//! frame 0
+//! ingredient "sugar" 6d
+//! ```
+//!
+//! `resume` lists, outermost first, the instruction to continue with in each
+//! method or loop body, and `loop:N` for a loop in its Nth pass. Values are
+//! an amount and `d` (dry), `l` (liquid) or `u` (unspecified); bowls and
+//! dishes list their top first. The `kitchen` section is the current
+//! recipe's; each `frame` is a caller waiting for a `Serve with`, innermost
+//! last.
+//!
+//! [`Interpreter::snapshot`]: crate::Interpreter::snapshot
+//! [`Interpreter::restore`]: crate::Interpreter::restore
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::types::{
+    CallFrame, ExecutionContext, Ingredient, Measure, MixingBowl, SnapshotError, Value,
+};
+
+/// The first line of every snapshot; the number changes with the format.
+pub const SNAPSHOT_HEADER: &str = "cheffers snapshot 1";
+
+/// One level of where a paused run stopped.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResumePoint {
+    /// The instruction of a method or loop body to run next.
+    Instruction(usize),
+    /// A loop part way through this many passes.
+    Loop(usize),
+}
+
+/// The state of an interpreter between two instructions.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Snapshot {
+    /// The recipe the paused run was running; `None` when no run is paused.
+    pub recipe: Option<String>,
+    /// Where the run stopped, outermost first.
+    pub resume: Vec<ResumePoint>,
+    pub context: ExecutionContext,
+    pub output: String,
+    /// `Serves` counts waiting for the end of each running method,
+    /// outermost first.
+    pub pending_serves: Vec<Vec<usize>>,
+    /// The input `Take` has not read yet; `None` when it reads stdin.
+    pub input: Option<Vec<String>>,
+    pub rng_state: u64,
+}
+
+impl fmt::Display for Snapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{}", SNAPSHOT_HEADER)?;
+        if let Some(recipe) = &self.recipe {
+            writeln!(f, "recipe {}", quote(recipe))?;
+        }
+        if !self.resume.is_empty() {
+            let points: Vec<String> = self
+                .resume
+                .iter()
+                .map(|point| match point {
+                    ResumePoint::Instruction(index) => index.to_string(),
+                    ResumePoint::Loop(passes) => format!("loop:{}", passes),
+                })
+                .collect();
+            writeln!(f, "resume {}", points.join(" "))?;
+        }
+        writeln!(f, "rng {}", self.rng_state)?;
+        match &self.input {
+            Some(tokens) => writeln!(f, "{}", line("input", tokens))?,
+            None => writeln!(f, "input stdin")?,
+        }
+        writeln!(f, "output {}", quote(&self.output))?;
+        for counts in &self.pending_serves {
+            writeln!(f, "{}", line("serves", counts))?;
+        }
+
+        writeln!(f, "kitchen")?;
+        let context = &self.context;
+        write_kitchen(
+            f,
+            &context.variables,
+            &context.unset_ingredients,
+            &context.mixing_bowls,
+            &context.baking_dishes,
+        )?;
+        for frame in &context.call_stack {
+            writeln!(f, "frame {}", frame.return_address)?;
+            write_kitchen(
+                f,
+                &frame.variables,
+                &frame.unset_ingredients,
+                &frame.mixing_bowls,
+                &frame.baking_dishes,
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Snapshot {
+    type Err = SnapshotError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let mut lines = text.lines().enumerate();
+        if lines.next().map(|(_, first)| first.trim()) != Some(SNAPSHOT_HEADER) {
+            return Err(SnapshotError::NotASnapshot {
+                expected: SNAPSHOT_HEADER,
+            });
+        }
+
+        let mut snapshot = Snapshot {
+            input: Some(Vec::new()),
+            ..Snapshot::default()
+        };
+        // The kitchen section first, then one per frame.
+        let mut kitchens: Vec<CallFrame> = Vec::new();
+        for (idx, text) in lines {
+            let malformed = |reason: String| SnapshotError::Malformed {
+                line: idx + 1,
+                reason,
+            };
+            let words = split_words(text).map_err(malformed)?;
+            let Some((keyword, args)) = words.split_first() else {
+                continue;
+            };
+            let kitchen = kitchens.last_mut();
+            match (keyword.as_str(), kitchen) {
+                ("recipe", _) => snapshot.recipe = Some(single(args).map_err(malformed)?),
+                ("resume", _) => {
+                    snapshot.resume = args
+                        .iter()
+                        .map(|arg| parse_resume_point(arg))
+                        .collect::<Result<_, _>>()
+                        .map_err(malformed)?
+                }
+                ("rng", _) => {
+                    snapshot.rng_state =
+                        number(&single(args).map_err(malformed)?).map_err(malformed)?
+                }
+                ("input", _) if args == ["stdin"] => snapshot.input = None,
+                ("input", _) => snapshot.input = Some(args.to_vec()),
+                ("output", _) => snapshot.output = single(args).map_err(malformed)?,
+                ("serves", _) => snapshot.pending_serves.push(
+                    args.iter()
+                        .map(|arg| number(arg))
+                        .collect::<Result<_, _>>()
+                        .map_err(malformed)?,
+                ),
+                ("kitchen", None) => kitchens.push(CallFrame::default()),
+                ("frame", Some(_)) => kitchens.push(CallFrame {
+                    return_address: number(&single(args).map_err(malformed)?).map_err(malformed)?,
+                    ..CallFrame::default()
+                }),
+                ("ingredient", Some(kitchen)) => {
+                    let [name, value] = args else {
+                        return Err(malformed("expected a name and a value".to_string()));
+                    };
+                    let value = parse_value(value).map_err(malformed)?;
+                    kitchen.variables.insert(name.clone(), value);
+                }
+                ("unset", Some(kitchen)) => {
+                    let [name, measure] = args else {
+                        return Err(malformed("expected a name and a measure".to_string()));
+                    };
+                    let measure = parse_measure(measure).map_err(malformed)?;
+                    kitchen.unset_ingredients.insert(name.clone(), measure);
+                }
+                ("bowl", Some(kitchen)) => kitchen
+                    .mixing_bowls
+                    .push(parse_values(args).map_err(malformed)?),
+                ("dish", Some(kitchen)) => kitchen
+                    .baking_dishes
+                    .push(parse_values(args).map_err(malformed)?),
+                (keyword, _) => return Err(malformed(format!("unexpected '{}'", keyword))),
+            }
+        }
+
+        let mut kitchens = kitchens.into_iter();
+        let Some(kitchen) = kitchens.next() else {
+            return Err(SnapshotError::Malformed {
+                line: text.lines().count(),
+                reason: "the kitchen section is missing".to_string(),
+            });
+        };
+        snapshot.context = ExecutionContext {
+            variables: kitchen.variables,
+            unset_ingredients: kitchen.unset_ingredients,
+            mixing_bowls: kitchen.mixing_bowls,
+            baking_dishes: kitchen.baking_dishes,
+            call_stack: kitchens.collect(),
+        };
+        Ok(snapshot)
+    }
+}
+
+fn write_kitchen(
+    f: &mut fmt::Formatter<'_>,
+    variables: &HashMap<Ingredient, Value>,
+    unset_ingredients: &HashMap<Ingredient, Measure>,
+    bowls: &[MixingBowl],
+    dishes: &[MixingBowl],
+) -> fmt::Result {
+    let mut names: Vec<_> = variables.iter().collect();
+    names.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, value) in names {
+        writeln!(f, "ingredient {} {}", quote(name), format_value(value))?;
+    }
+    let mut names: Vec<_> = unset_ingredients.iter().collect();
+    names.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, measure) in names {
+        writeln!(f, "unset {} {}", quote(name), measure_letter(*measure))?;
+    }
+    for (keyword, stacks) in [("bowl", bowls), ("dish", dishes)] {
+        for stack in stacks {
+            let values: Vec<String> = stack.iter().map(format_value).collect();
+            writeln!(f, "{}", line(keyword, &values))?;
+        }
+    }
+    Ok(())
+}
+
+/// `keyword` followed by the items, space-separated.
+fn line<T: fmt::Display>(keyword: &str, items: &[T]) -> String {
+    let mut text = keyword.to_string();
+    for item in items {
+        text.push(' ');
+        text.push_str(&item.to_string());
+    }
+    text
+}
+
+fn format_value(value: &Value) -> String {
+    format!("{}{}", value.amount, measure_letter(value.measure))
+}
+
+fn measure_letter(measure: Measure) -> char {
+    match measure {
+        Measure::Dry => 'd',
+        Measure::Liquid => 'l',
+        Measure::Unspecified => 'u',
+    }
+}
+
+fn parse_measure(text: &str) -> Result<Measure, String> {
+    match text {
+        "d" => Ok(Measure::Dry),
+        "l" => Ok(Measure::Liquid),
+        "u" => Ok(Measure::Unspecified),
+        _ => Err(format!("'{}' is not a measure (d, l or u)", text)),
+    }
+}
+
+fn parse_value(text: &str) -> Result<Value, String> {
+    let invalid = |_| format!("'{}' is not a value", text);
+    let letter = text.chars().last().unwrap_or_default();
+    let (amount, measure) = text.split_at(text.len() - letter.len_utf8());
+    Ok(Value {
+        amount: number(amount).map_err(invalid)?,
+        measure: parse_measure(measure).map_err(invalid)?,
+    })
+}
+
+fn parse_values(args: &[String]) -> Result<VecDeque<Value>, String> {
+    args.iter().map(|arg| parse_value(arg)).collect()
+}
+
+fn parse_resume_point(text: &str) -> Result<ResumePoint, String> {
+    match text.strip_prefix("loop:") {
+        Some(passes) => Ok(ResumePoint::Loop(number(passes)?)),
+        None => Ok(ResumePoint::Instruction(number(text)?)),
+    }
+}
+
+fn number<T: FromStr>(text: &str) -> Result<T, String> {
+    text.parse()
+        .map_err(|_| format!("'{}' is not a number", text))
+}
+
+fn single(args: &[String]) -> Result<String, String> {
+    match args {
+        [arg] => Ok(arg.clone()),
+        _ => Err(format!("expected one value, found {}", args.len())),
+    }
+}
+
+/// A string in double quotes, with `\`, `"` and control characters escaped.
+fn quote(text: &str) -> String {
+    let mut quoted = String::from('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            c if c.is_control() => quoted.push_str(&format!("\\u{{{:x}}}", c as u32)),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Splits a line on spaces, reading quoted strings as one word.
+fn split_words(line: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut chars = line.trim().chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c == ' ' {
+            chars.next();
+            continue;
+        }
+        if c != '"' {
+            let mut word = String::new();
+            while let Some(&c) = chars.peek() {
+                if c == ' ' {
+                    break;
+                }
+                word.push(c);
+                chars.next();
+            }
+            words.push(word);
+            continue;
+        }
+
+        chars.next();
+        let mut word = String::new();
+        loop {
+            match chars.next() {
+                None => return Err("a quoted string is not closed".to_string()),
+                Some('"') => break,
+                Some('\\') => match chars.next() {
+                    Some('n') => word.push('\n'),
+                    Some('t') => word.push('\t'),
+                    Some('r') => word.push('\r'),
+                    Some('u') => {
+                        let code: String = chars
+                            .by_ref()
+                            .skip_while(|&c| c == '{')
+                            .take_while(|&c| c != '}')
+                            .collect();
+                        let c = u32::from_str_radix(&code, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| format!("'\\u{{{}}}' is not a character", code))?;
+                        word.push(c);
+                    }
+                    Some(c) => word.push(c),
+                    None => return Err("a quoted string is not closed".to_string()),
+                },
+                Some(c) => word.push(c),
+            }
+        }
+        words.push(word);
+    }
+    Ok(words)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Interpreter, Parser};
+
+    /// Serves the numbers it takes, doubled, through a sauce.
+    const SOURCE: &str = "Doubled.\n\nIngredients.\n3 g count\n1 g item\n\nMethod.\n\
+        Heat the count. Take item from the refrigerator. Put item into the mixing bowl. \
+        Serve with double sauce. Heat the count until heated. \
+        Pour contents of the mixing bowl into the baking dish.\n\n\
+        Serves 1.\n\nDouble Sauce.\n\nIngredients.\n2 g factor\n\nMethod.\n\
+        Fold item into the mixing bowl. Put item into the mixing bowl. \
+        Combine factor into the mixing bowl.\n";
+
+    fn interpreter() -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(Parser::new(SOURCE).parse_recipe().unwrap());
+        interpreter.set_input_text("5 7 9");
+        interpreter.set_mix_seed(3);
+        interpreter
+    }
+
+    #[test]
+    fn a_restored_run_finishes_like_an_uninterrupted_one() {
+        let mut whole = interpreter();
+        whole.run().unwrap();
+
+        // Every pause point, including those inside the loop and the sauce.
+        for steps in 0..20 {
+            let mut first = interpreter();
+            first.pause_after(steps);
+            first.run().unwrap();
+            assert!(first.is_paused(), "{} steps", steps);
+            let text = first.snapshot().to_string();
+
+            let mut second = Interpreter::new();
+            second.add_recipe(Parser::new(SOURCE).parse_recipe().unwrap());
+            second.restore(text.parse().unwrap());
+            second.resume().unwrap();
+            assert!(!second.is_paused());
+            assert_eq!(second.output(), whole.output(), "{} steps", steps);
+            assert_eq!(second.mixing_bowls(), whole.mixing_bowls());
+        }
+    }
+
+    #[test]
+    fn snapshots_survive_the_text_format() {
+        let mut interpreter = interpreter();
+        interpreter.pause_after(11);
+        interpreter.run().unwrap();
+        let snapshot = interpreter.snapshot();
+        assert_eq!(snapshot.recipe.as_deref(), Some("Doubled."));
+        assert_eq!(
+            snapshot.resume,
+            vec![
+                ResumePoint::Instruction(0),
+                ResumePoint::Loop(2),
+                ResumePoint::Instruction(2),
+                ResumePoint::Instruction(1),
+            ]
+        );
+        assert_eq!(snapshot.input, Some(vec!["9".to_string()]));
+        assert_eq!(snapshot.context.call_stack.len(), 1);
+        assert_eq!(snapshot.to_string().parse::<Snapshot>().unwrap(), snapshot);
+
+        let strings = Snapshot {
+            output: "say \"hi\"\\\n\u{7}".to_string(),
+            ..Snapshot::default()
+        };
+        assert_eq!(strings.to_string().parse::<Snapshot>().unwrap(), strings);
+    }
+
+    #[test]
+    fn malformed_snapshots_name_the_line() {
+        assert!(matches!(
+            "hello".parse::<Snapshot>(),
+            Err(SnapshotError::NotASnapshot { .. })
+        ));
+        let error = format!("{}\nkitchen\nbowl 3x\n", SNAPSHOT_HEADER)
+            .parse::<Snapshot>()
+            .unwrap_err();
+        assert_eq!(error.to_string(), "snapshot line 3: '3x' is not a value");
+    }
+}
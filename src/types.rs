@@ -2,9 +2,10 @@ use std::collections::{HashMap, VecDeque};
 
 use thiserror::Error;
 
+use crate::error_context::SourceLocation;
 use crate::instruction::Instruction;
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub enum Measure {
     Dry,
     Liquid,
@@ -12,7 +13,7 @@ pub enum Measure {
     Unspecified,
 }
 
-#[derive(Clone, Copy, Debug, Default)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
 pub struct Value {
     pub amount: i64,
     pub measure: Measure,
@@ -32,9 +33,40 @@ pub struct Recipe {
     pub unset_ingredients: HashMap<Ingredient, Measure>,
     pub instructions: Vec<Instruction>,
     pub auxiliary_recipes: HashMap<String, Recipe>,
+    /// Where the parser found each part of the recipe; empty for recipes
+    /// built in code.
+    pub source_map: SourceMap,
+    /// Test annotations from the comment paragraph.
+    pub examples: Vec<Example>,
 }
 
+/// A doctest-style annotation in a recipe's comments: run with `input`,
+/// the recipe must serve `expect`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Example {
+    /// What `Take` reads, as whitespace-separated numbers.
+    pub input: String,
+    pub expect: String,
+    /// The 1-based line of the `expect:` annotation.
+    pub line: usize,
+}
+
+/// Source locations for a parsed recipe.
 #[derive(Clone, Debug, Default)]
+pub struct SourceMap {
+    /// The title line.
+    pub title: Option<SourceLocation>,
+    /// The (last) declaration line of each ingredient.
+    pub ingredients: HashMap<Ingredient, SourceLocation>,
+    /// The opening sentence of every instruction in pre-order (a loop before
+    /// its body), matching a depth-first walk of `Recipe::instructions`.
+    pub instructions: Vec<SourceLocation>,
+    /// The "until" sentence of each loop, keyed by the loop's index in
+    /// `instructions`. A one-sentence loop closes on its opening sentence.
+    pub loop_ends: HashMap<usize, SourceLocation>,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct ExecutionContext {
     pub variables: HashMap<Ingredient, Value>,
     /// Ingredients declared without a value in the current recipe.
@@ -56,7 +88,7 @@ impl ExecutionContext {
     }
 }
 
-#[derive(Clone, Debug, Default)]
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
 pub struct CallFrame {
     pub variables: HashMap<Ingredient, Value>,
     pub unset_ingredients: HashMap<Ingredient, Measure>,
@@ -80,6 +112,42 @@ pub enum ParseError {
     InvalidLoop,
     #[error("unrecognized instruction or loop without a matching 'until' statement: {0}")]
     UnmatchedLoop(String),
+    #[error("loop verb '{verb}' is closed with 'until {found}'; expected 'until {expected}'")]
+    LoopVerbMismatch {
+        verb: String,
+        found: String,
+        expected: String,
+    },
+    #[error("'{found}' has the wrong suffix; expected '{expected}'")]
+    OrdinalSuffixMismatch { found: String, expected: String },
+    #[error("ordinal words such as '{word}' are an extension; write '{expected}'")]
+    OrdinalWord { word: String, expected: String },
+    #[error("line {line}: sections are separated by a single blank line")]
+    ExtraBlankLine { line: usize },
+    #[error("line {line}: the method cannot contain blank lines")]
+    BlankLineInMethod { line: usize },
+    #[error("line {line}: a blank line must come before '{text}'")]
+    MissingBlankLine { line: usize, text: String },
+    #[error("line {line}: the {section} section cannot come after the {after} section")]
+    SectionOutOfOrder {
+        line: usize,
+        section: String,
+        after: String,
+    },
+    #[error("line {line}: '{header}' must be on a line of its own")]
+    HeaderNotOnOwnLine { line: usize, header: String },
+    #[error("line {line}: '{text}' belongs in its own paragraph after the ingredient list")]
+    MetadataInIngredients { line: usize, text: String },
+    #[error("line {line}: '{text}' belongs in its own paragraph after the method")]
+    ServesInMethod { line: usize, text: String },
+    #[error("line {line}: malformed {section} line: '{text}'")]
+    InvalidMetadata {
+        line: usize,
+        section: String,
+        text: String,
+    },
+    #[error("line {line}: unexpected paragraph: '{text}'")]
+    UnexpectedParagraph { line: usize, text: String },
     #[error("invalid title: {0}")]
     InvalidTitle(String),
     #[error("invalid measure: {0}")]
@@ -127,6 +195,78 @@ pub enum RuntimeError {
     EarlyTermination,
     #[error("break loop")]
     BreakLoop,
+    #[error("run paused")]
+    Paused,
+}
+
+/// Errors from the recipe generators (`chefgen` stack programs).
+#[derive(Debug, Error)]
+pub enum GenerateError {
+    #[error("line {line}: unknown operation '{op}'")]
+    UnknownOperation { line: usize, op: String },
+    #[error("line {line}: '{op}' needs {expected}")]
+    MissingArgument {
+        line: usize,
+        op: String,
+        expected: &'static str,
+    },
+    #[error("line {line}: '{text}' is not a valid number")]
+    InvalidNumber { line: usize, text: String },
+    #[error("line {line}: 'end' without a matching 'loop' or 'def'")]
+    UnmatchedEnd { line: usize },
+    #[error("line {line}: '{op}' is never closed with 'end'")]
+    UnclosedBlock { line: usize, op: String },
+    #[error("line {line}: {reason}")]
+    Misplaced { line: usize, reason: String },
+    #[error("line {line}: procedure '{name}' is not defined")]
+    UnknownProcedure { line: usize, name: String },
+    #[error("line {line}: procedure '{name}' is defined twice")]
+    DuplicateProcedure { line: usize, name: String },
+}
+
+/// Errors reading a saved interpreter state (see `Snapshot`).
+#[derive(Debug, Error)]
+pub enum SnapshotError {
+    #[error("not a cheffers snapshot (the first line should be '{expected}')")]
+    NotASnapshot { expected: &'static str },
+    #[error("snapshot line {line}: {reason}")]
+    Malformed { line: usize, reason: String },
+}
+
+/// Errors reading a watchpoint (see `Watch`).
+#[derive(Debug, Error)]
+pub enum WatchError {
+    #[error("a watch needs an ingredient, a bowl or dish, or a condition")]
+    Empty,
+    #[error("'{text}' needs something on both sides of '{comparison}'")]
+    MissingOperand {
+        text: String,
+        comparison: &'static str,
+    },
+    #[error("'{text}' is not a mixing bowl or baking dish (try 'bowl 2' or 'dish 1')")]
+    NotAStack { text: String },
+    #[error("'{text}' is not a comparison (use ==, !=, <, <=, > or >=)")]
+    NotAComparison { text: String },
+    #[error("{recipe} declares no ingredient '{name}'")]
+    UnknownIngredient { name: String, recipe: String },
+}
+
+/// Errors loading recipe books (shared recipes from other files).
+#[derive(Debug, Error)]
+pub enum BookError {
+    #[error("cannot read recipe book {path}: {source}")]
+    Unreadable {
+        path: String,
+        source: std::io::Error,
+    },
+    #[error("{path}: {source}")]
+    Parse { path: String, source: ParseError },
+    #[error("recipe '{title}' is defined in both {first} and {second}")]
+    DuplicateRecipe {
+        title: String,
+        first: String,
+        second: String,
+    },
 }
 
 #[derive(Debug, Error)]
@@ -136,6 +276,14 @@ pub enum ChefError {
     #[error(transparent)]
     Runtime(#[from] RuntimeError),
     #[error(transparent)]
+    Generate(#[from] GenerateError),
+    #[error(transparent)]
+    Book(#[from] BookError),
+    #[error(transparent)]
+    Snapshot(#[from] SnapshotError),
+    #[error(transparent)]
+    Watch(#[from] WatchError),
+    #[error(transparent)]
     Io(#[from] std::io::Error),
 }
 
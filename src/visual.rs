@@ -0,0 +1,455 @@
+//! The kitchen as a terminal animation, behind `cheffers watch`.
+//!
+//! A [`Visual`] steps a [`Debugger`] and draws a frame of plain ANSI text:
+//! the source with the instruction about to run highlighted, the
+//! `Serve with` calls the run is inside, every mixing bowl and baking dish
+//! as a vertical stack (liquids as characters, everything else as numbers)
+//! and the end of the output. The caller owns the terminal: it reads keys,
+//! keeps time and prints frames.
+
+use std::collections::VecDeque;
+
+use crate::debugger::Debugger;
+use crate::types::{Measure, Value};
+
+/// Steps per second the speed keys choose from.
+pub const SPEEDS: [u32; 7] = [1, 2, 5, 10, 20, 50, 100];
+
+pub const KEYS: &str = "space play/pause  s/right step  b/left back  +/- speed  r restart  q quit";
+
+/// Width of a bowl or dish column, including the gap after it.
+const COLUMN: usize = 8;
+
+const BOLD: &str = "\x1b[1m";
+const REVERSE: &str = "\x1b[7m";
+const DIM: &str = "\x1b[2m";
+const RESET: &str = "\x1b[0m";
+
+/// A key the terminal sent.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Left,
+    Right,
+}
+
+impl Key {
+    /// The keys in bytes read from a terminal in raw mode. Escape sequences
+    /// other than the left and right arrows are dropped.
+    pub fn parse(bytes: &[u8]) -> Vec<Key> {
+        let mut keys = Vec::new();
+        let text = String::from_utf8_lossy(bytes);
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c != '\x1b' {
+                keys.push(Key::Char(c));
+                continue;
+            }
+            if chars.next_if_eq(&'[').is_none() {
+                continue;
+            }
+            // Skip parameters up to the sequence's final byte.
+            for c in chars.by_ref() {
+                if c.is_ascii_alphabetic() || c == '~' {
+                    match c {
+                        'C' => keys.push(Key::Right),
+                        'D' => keys.push(Key::Left),
+                        _ => {}
+                    }
+                    break;
+                }
+            }
+        }
+        keys
+    }
+}
+
+pub struct Visual {
+    debugger: Debugger,
+    source: Vec<String>,
+    playing: bool,
+    /// Index into [`SPEEDS`].
+    speed: usize,
+}
+
+impl Visual {
+    /// Shows `debugger`'s run of the recipe read from `source`, paused.
+    pub fn new(debugger: Debugger, source: &str) -> Self {
+        Self {
+            debugger,
+            source: source.lines().map(str::to_string).collect(),
+            playing: false,
+            speed: 3,
+        }
+    }
+
+    pub fn debugger(&self) -> &Debugger {
+        &self.debugger
+    }
+
+    pub fn is_playing(&self) -> bool {
+        self.playing
+    }
+
+    pub fn steps_per_second(&self) -> u32 {
+        SPEEDS[self.speed]
+    }
+
+    /// Handles a key. Returns `false` for the key that quits.
+    pub fn key(&mut self, key: Key) -> bool {
+        match key {
+            Key::Char('q') | Key::Char('Q') | Key::Char('\x03') => return false,
+            Key::Char(' ') | Key::Char('p') => {
+                self.playing = !self.playing && self.debugger.location().is_some();
+            }
+            Key::Char('s') | Key::Char('n') | Key::Right => {
+                self.playing = false;
+                self.debugger.step();
+            }
+            Key::Char('b') | Key::Left => {
+                self.playing = false;
+                self.debugger.step_back();
+            }
+            Key::Char('+') | Key::Char('=') => self.speed = (self.speed + 1).min(SPEEDS.len() - 1),
+            Key::Char('-') => self.speed = self.speed.saturating_sub(1),
+            Key::Char('r') => {
+                self.playing = false;
+                while self.debugger.step_back() {}
+            }
+            _ => {}
+        }
+        true
+    }
+
+    /// Runs up to `steps` instructions while playing; playing stops when
+    /// the run ends.
+    pub fn advance(&mut self, steps: usize) {
+        for _ in 0..steps {
+            if !self.playing {
+                return;
+            }
+            if !self.debugger.step() || self.debugger.location().is_none() {
+                self.playing = false;
+            }
+        }
+    }
+
+    /// A whole frame for a `width` by `height` terminal, starting with the
+    /// cursor at the top left; each line is cleared to its end.
+    pub fn render(&self, width: usize, height: usize) -> String {
+        let width = width.max(40);
+        let body = height.max(8) - 2;
+        let left_width = width / 2;
+        let right_width = width - left_width - 3;
+
+        let mut frame = String::from("\x1b[H");
+        frame.push_str(&format!(
+            "{}{}{}\x1b[K\r\n",
+            BOLD,
+            fit(&self.header(), width),
+            RESET
+        ));
+
+        let source = self.source_pane(body);
+        let kitchen = self.kitchen_pane(body, right_width);
+        for row in 0..body {
+            let (text, current) = source.get(row).cloned().unwrap_or_default();
+            let left = fit(&text, left_width);
+            if current {
+                frame.push_str(&format!("{}{}{}", REVERSE, left, RESET));
+            } else {
+                frame.push_str(&left);
+            }
+            let right = kitchen.get(row).map_or("", String::as_str);
+            frame.push_str(&format!(" | {}\x1b[K\r\n", fit(right, right_width)));
+        }
+        frame.push_str(&format!("{}{}{}\x1b[K", DIM, fit(KEYS, width), RESET));
+        frame
+    }
+
+    fn header(&self) -> String {
+        let debugger = self.debugger();
+        let state = match (debugger.location(), debugger.error()) {
+            (Some(_), _) if self.playing => {
+                format!("playing, {} steps/s", self.steps_per_second())
+            }
+            (Some(_), _) => format!("paused ({} steps/s)", self.steps_per_second()),
+            (None, Some(error)) => format!("failed: {}", error),
+            (None, None) => "finished".to_string(),
+        };
+        match debugger.dropped_steps() {
+            0 => format!(" cheffers watch - step {} - {}", debugger.steps(), state),
+            dropped => format!(
+                " cheffers watch - step {} (first {} dropped) - {}",
+                debugger.steps(),
+                dropped,
+                state
+            ),
+        }
+    }
+
+    /// The source lines around the instruction about to run, numbered;
+    /// `true` marks that instruction's line.
+    fn source_pane(&self, rows: usize) -> Vec<(String, bool)> {
+        let current = self.debugger.location().and_then(|location| location.line);
+        let callers: Vec<usize> = self
+            .debugger
+            .call_stack()
+            .iter()
+            .filter_map(|location| location.line)
+            .collect();
+        // Keep the current line a third of the way down.
+        let start = current
+            .map_or(0, |line| line.saturating_sub(1 + rows / 3))
+            .min(self.source.len().saturating_sub(rows));
+        self.source
+            .iter()
+            .enumerate()
+            .skip(start)
+            .take(rows)
+            .map(|(index, text)| {
+                let line = index + 1;
+                let marker = if Some(line) == current {
+                    '>'
+                } else if callers.contains(&line) {
+                    '*'
+                } else {
+                    ' '
+                };
+                (
+                    format!("{}{:>4} {}", marker, line, text),
+                    Some(line) == current,
+                )
+            })
+            .collect()
+    }
+
+    fn kitchen_pane(&self, rows: usize, width: usize) -> Vec<String> {
+        let debugger = self.debugger();
+        let interpreter = debugger.interpreter();
+        let mut pane = vec!["Call stack".to_string()];
+        let calls = debugger.call_stack();
+        if calls.is_empty() {
+            pane.push("  (not running)".to_string());
+        }
+        for (depth, location) in calls.iter().enumerate() {
+            let line = location
+                .line
+                .map_or_else(String::new, |line| format!(" line {}", line));
+            pane.push(format!(
+                "{}{}{}: {}",
+                "  ".repeat(depth + 1),
+                location.recipe,
+                line,
+                location.text
+            ));
+        }
+        pane.push(String::new());
+
+        // The output gets the last three rows.
+        let stack_rows = rows.saturating_sub(pane.len() + 4).max(3);
+        let stacks: Vec<(String, &VecDeque<Value>)> = interpreter
+            .mixing_bowls()
+            .iter()
+            .enumerate()
+            .map(|(index, bowl)| (format!("bowl {}", index + 1), bowl))
+            .chain(
+                interpreter
+                    .baking_dishes()
+                    .iter()
+                    .enumerate()
+                    .map(|(index, dish)| (format!("dish {}", index + 1), dish)),
+            )
+            .collect();
+        pane.extend(draw_stacks(&stacks, stack_rows, width));
+
+        pane.push("Output".to_string());
+        let output = interpreter.output();
+        let lines: Vec<&str> = output.split('\n').collect();
+        let shown = lines.len().saturating_sub(2);
+        pane.extend(lines[shown..].iter().map(|line| format!("  {}", line)));
+        pane
+    }
+}
+
+/// The stacks side by side, names on the bottom row, each stack's top value
+/// highest. A stack taller than `rows - 1` shows its top values and how
+/// many more lie below them.
+fn draw_stacks(stacks: &[(String, &VecDeque<Value>)], rows: usize, width: usize) -> Vec<String> {
+    let columns = (width / COLUMN).max(1);
+    let shown = &stacks[..stacks.len().min(columns)];
+    let height = rows - 1;
+    let mut lines = vec![String::new(); height];
+    for (_, values) in shown {
+        let mut cells: Vec<String> = if values.len() > height {
+            let mut cells: Vec<String> = values.iter().take(height - 1).map(cell).collect();
+            cells.push(format!("+{}", values.len() - (height - 1)));
+            cells
+        } else {
+            values.iter().map(cell).collect()
+        };
+        // Bottom-aligned: blank cells above the top value.
+        let blank = height - cells.len();
+        cells.splice(0..0, std::iter::repeat(String::new()).take(blank));
+        for (line, cell) in lines.iter_mut().zip(cells) {
+            line.push_str(&format!(
+                "{:<width$}",
+                fit(&cell, COLUMN - 1),
+                width = COLUMN
+            ));
+        }
+    }
+    let mut names: String = shown
+        .iter()
+        .map(|(name, _)| format!("{:<width$}", name, width = COLUMN))
+        .collect();
+    if stacks.len() > shown.len() {
+        names.push_str(&format!("+{}", stacks.len() - shown.len()));
+    }
+    lines.push(names);
+    lines
+}
+
+/// A liquid as its (escaped) character, anything else as its number.
+fn cell(value: &Value) -> String {
+    let character = u32::try_from(value.amount).ok().and_then(char::from_u32);
+    match (value.measure, character) {
+        (Measure::Liquid, Some(c)) => format!("'{}'", c.escape_debug()),
+        _ => value.amount.to_string(),
+    }
+}
+
+/// `text` cut or padded to exactly `width` characters.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted: String = text.chars().take(width).collect();
+    let length = fitted.chars().count();
+    fitted.extend(std::iter::repeat(' ').take(width - length));
+    fitted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+
+    const SOURCE: &str = "\
+Letters.
+
+Ingredients.
+72 ml h
+105 ml i
+3 g count
+
+Method.
+Put count into the mixing bowl.
+Put i into the mixing bowl.
+Put h into the mixing bowl.
+Serve with caramel sauce.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Caramel sauce.
+
+Ingredients.
+33 ml bang
+
+Method.
+Put bang into the 2nd mixing bowl.
+Fold bang into the 2nd mixing bowl.
+Put bang into the mixing bowl.
+";
+
+    fn visual() -> Visual {
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(Parser::new(SOURCE).parse_recipe().unwrap());
+        Visual::new(Debugger::new(interpreter).unwrap(), SOURCE)
+    }
+
+    /// The frame without its escape sequences.
+    fn plain(frame: &str) -> String {
+        let mut text = String::new();
+        let mut chars = frame.chars();
+        while let Some(c) = chars.next() {
+            if c == '\x1b' {
+                for c in chars.by_ref() {
+                    if c.is_ascii_alphabetic() {
+                        break;
+                    }
+                }
+            } else if c != '\r' {
+                text.push(c);
+            }
+        }
+        text
+    }
+
+    #[test]
+    fn reads_keys_and_arrows() {
+        assert_eq!(
+            Key::parse(b"s\x1b[C\x1b[D\x1b[A+"),
+            [Key::Char('s'), Key::Right, Key::Left, Key::Char('+')]
+        );
+    }
+
+    #[test]
+    fn draws_stacks_source_and_calls() {
+        let mut visual = visual();
+        for _ in 0..6 {
+            visual.key(Key::Right);
+        }
+        let frame = visual.render(80, 16);
+        assert!(
+            frame.contains(&format!("{}>  25 Put bang", REVERSE)),
+            "{}",
+            frame
+        );
+        let text = plain(&frame);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 16);
+        assert!(
+            lines[0].contains("step 6 - paused (10 steps/s)"),
+            "{}",
+            text
+        );
+        assert!(text.contains("*  12 Serve with caramel sauce."), "{}", text);
+        assert!(
+            text.contains("    Caramel sauce line 25: Put bang"),
+            "{}",
+            text
+        );
+        // Bottom-aligned stacks: the sous-chef's copy of the bowl, its
+        // empty second bowl, and the dish.
+        let stacks: Vec<&str> = lines
+            .iter()
+            .filter_map(|line| line.split(" | ").nth(1))
+            .collect();
+        let names = stacks
+            .iter()
+            .position(|line| line.starts_with("bowl 1"))
+            .unwrap();
+        assert_eq!(stacks[names - 3].trim_end(), "'H'");
+        assert_eq!(stacks[names - 2].trim_end(), "'i'");
+        assert_eq!(stacks[names - 1].trim_end(), "3");
+        assert_eq!(stacks[names].trim_end(), "bowl 1  bowl 2  dish 1");
+    }
+
+    #[test]
+    fn plays_to_the_end_and_restarts() {
+        let mut visual = visual();
+        visual.key(Key::Char('+'));
+        assert_eq!(visual.steps_per_second(), 20);
+        visual.key(Key::Char(' '));
+        assert!(visual.is_playing());
+        visual.advance(100);
+        assert!(!visual.is_playing());
+        assert_eq!(visual.debugger().interpreter().output(), "!Hi3Hi3");
+        assert!(plain(&visual.render(60, 12)).contains("finished"));
+
+        visual.key(Key::Char('r'));
+        assert_eq!(visual.debugger().steps(), 0);
+        assert!(visual.key(Key::Char('x')));
+        assert!(!visual.key(Key::Char('q')));
+    }
+}
@@ -0,0 +1,605 @@
+//! Watchpoints: an ingredient, a mixing bowl or baking dish, or a condition
+//! over them, looked at around every instruction of a run.
+//!
+//! An ingredient or a bowl watch is hit when its value changes (by `Fold`,
+//! `Take`, `Liquefy`, a loop's decrement, ...), a condition watch when the
+//! condition turns true. Each hit names the instruction that caused it; a
+//! change a loop or `Serve with` makes between the instructions inside it
+//! (a decrement, a sous-chef's bowl coming back) is put down to the loop or
+//! the `Serve with`.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::debugger::Location;
+use crate::history::{Kitchen, Stack};
+use crate::instruction::Instruction;
+use crate::interpreter::{InstructionId, Observer};
+use crate::repl::show_value;
+use crate::types::{ExecutionContext, Ingredient, Recipe, Value, WatchError};
+
+/// What a [`Watch`] keeps an eye on.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Watch {
+    /// Hit when the ingredient's value (amount or measure) changes.
+    Ingredient(Ingredient),
+    /// Hit when anything in the bowl or dish changes.
+    Stack(Stack),
+    /// Hit when the comparison turns true.
+    Condition {
+        left: Operand,
+        comparison: Comparison,
+        right: Operand,
+    },
+}
+
+/// One side of a [`Watch::Condition`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Operand {
+    Number(i64),
+    /// The ingredient's amount; a comparison with an ingredient that has no
+    /// value is false.
+    Ingredient(Ingredient),
+    /// How many values the bowl or dish holds: `len(bowl 2)`.
+    Len(Stack),
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Comparison {
+    Eq,
+    Ne,
+    Le,
+    Ge,
+    Lt,
+    Gt,
+}
+
+impl Comparison {
+    /// Two-character operators first, so `<=` is not read as `<`.
+    const ALL: [Comparison; 6] = [
+        Comparison::Eq,
+        Comparison::Ne,
+        Comparison::Le,
+        Comparison::Ge,
+        Comparison::Lt,
+        Comparison::Gt,
+    ];
+
+    fn symbol(self) -> &'static str {
+        match self {
+            Comparison::Eq => "==",
+            Comparison::Ne => "!=",
+            Comparison::Le => "<=",
+            Comparison::Ge => ">=",
+            Comparison::Lt => "<",
+            Comparison::Gt => ">",
+        }
+    }
+
+    fn holds(self, left: i64, right: i64) -> bool {
+        match self {
+            Comparison::Eq => left == right,
+            Comparison::Ne => left != right,
+            Comparison::Le => left <= right,
+            Comparison::Ge => left >= right,
+            Comparison::Lt => left < right,
+            Comparison::Gt => left > right,
+        }
+    }
+}
+
+/// Reads `sugar`, `bowl 2`, `dish 1`, or a comparison such as `sugar == 0`
+/// or `len(bowl 2) > 100`. Text with a comparison character that is not one
+/// of these, such as `sugar = 0`, is an error rather than an ingredient name.
+impl FromStr for Watch {
+    type Err = WatchError;
+
+    fn from_str(text: &str) -> Result<Self, WatchError> {
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(WatchError::Empty);
+        }
+        for comparison in Comparison::ALL {
+            let Some((left, right)) = text.split_once(comparison.symbol()) else {
+                continue;
+            };
+            let (left, right) = (left.trim(), right.trim());
+            if left.is_empty() || right.is_empty() {
+                return Err(WatchError::MissingOperand {
+                    text: text.to_string(),
+                    comparison: comparison.symbol(),
+                });
+            }
+            if has_comparison_characters(left) || has_comparison_characters(right) {
+                return Err(not_a_comparison(text));
+            }
+            return Ok(Watch::Condition {
+                left: parse_operand(left)?,
+                comparison,
+                right: parse_operand(right)?,
+            });
+        }
+        if has_comparison_characters(text) {
+            return Err(not_a_comparison(text));
+        }
+        if starts_like_stack(text) {
+            return Ok(Watch::Stack(parse_stack(text)?));
+        }
+        Ok(Watch::Ingredient(text.to_string()))
+    }
+}
+
+fn has_comparison_characters(text: &str) -> bool {
+    text.contains(['=', '!', '<', '>'])
+}
+
+fn not_a_comparison(text: &str) -> WatchError {
+    WatchError::NotAComparison {
+        text: text.to_string(),
+    }
+}
+
+fn parse_operand(text: &str) -> Result<Operand, WatchError> {
+    if let Ok(number) = text.parse() {
+        return Ok(Operand::Number(number));
+    }
+    match text
+        .strip_prefix("len(")
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        Some(stack) => Ok(Operand::Len(parse_stack(stack.trim())?)),
+        None => Ok(Operand::Ingredient(text.to_string())),
+    }
+}
+
+fn starts_like_stack(text: &str) -> bool {
+    ["bowl ", "dish ", "mixing bowl ", "baking dish "]
+        .iter()
+        .any(|prefix| text.starts_with(prefix))
+}
+
+/// `bowl 2`, `mixing bowl 2`, `dish 1` or `baking dish 1`, counted from 1.
+fn parse_stack(text: &str) -> Result<Stack, WatchError> {
+    let not_a_stack = || WatchError::NotAStack {
+        text: text.to_string(),
+    };
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let (kind, number) = match words.as_slice() {
+        [kind, number] | ["mixing" | "baking", kind, number] => (*kind, *number),
+        _ => return Err(not_a_stack()),
+    };
+    let number: usize = number.parse().map_err(|_| not_a_stack())?;
+    match (kind, number) {
+        (_, 0) => Err(not_a_stack()),
+        ("bowl", n) => Ok(Stack::Bowl(n - 1)),
+        ("dish", n) => Ok(Stack::Dish(n - 1)),
+        _ => Err(not_a_stack()),
+    }
+}
+
+impl fmt::Display for Watch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Watch::Ingredient(name) => write!(f, "{}", name),
+            Watch::Stack(stack) => write!(f, "{}", stack),
+            Watch::Condition {
+                left,
+                comparison,
+                right,
+            } => write!(f, "{} {} {}", left, comparison.symbol(), right),
+        }
+    }
+}
+
+impl fmt::Display for Operand {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operand::Number(number) => write!(f, "{}", number),
+            Operand::Ingredient(name) => write!(f, "{}", name),
+            Operand::Len(stack) => write!(f, "len({})", stack),
+        }
+    }
+}
+
+/// What a watch saw the last time it looked.
+#[derive(Clone, Debug, PartialEq)]
+enum Seen {
+    Ingredient(Option<Value>),
+    Stack(Option<VecDeque<Value>>),
+    Condition(bool),
+}
+
+impl Watch {
+    /// Fails if the watch names an ingredient `recipe` does not declare,
+    /// whose value could then never change.
+    pub fn check(&self, recipe: &Recipe) -> Result<(), WatchError> {
+        let names: Vec<&Ingredient> = match self {
+            Watch::Ingredient(name) => vec![name],
+            Watch::Stack(_) => Vec::new(),
+            Watch::Condition { left, right, .. } => [left, right]
+                .into_iter()
+                .filter_map(|operand| match operand {
+                    Operand::Ingredient(name) => Some(name),
+                    _ => None,
+                })
+                .collect(),
+        };
+        for name in names {
+            if !recipe.ingredients.contains_key(name)
+                && !recipe.unset_ingredients.contains_key(name)
+            {
+                return Err(WatchError::UnknownIngredient {
+                    name: name.clone(),
+                    recipe: recipe.title.trim_end_matches('.').to_string(),
+                });
+            }
+        }
+        Ok(())
+    }
+
+    fn look(&self, kitchen: &Kitchen) -> Seen {
+        match self {
+            Watch::Ingredient(name) => Seen::Ingredient(kitchen.variables.get(name).copied()),
+            Watch::Stack(stack) => Seen::Stack(kitchen.stack(*stack).cloned()),
+            Watch::Condition {
+                left,
+                comparison,
+                right,
+            } => Seen::Condition(match (left.amount(kitchen), right.amount(kitchen)) {
+                (Some(left), Some(right)) => comparison.holds(left, right),
+                _ => false,
+            }),
+        }
+    }
+
+    /// What changed between two looks, or `None` if the watch is not hit.
+    fn change(&self, before: &Seen, now: &Seen) -> Option<String> {
+        match (before, now) {
+            (Seen::Condition(before), Seen::Condition(now)) => {
+                (!before && *now).then(|| format!("{} became true", self))
+            }
+            (Seen::Ingredient(before), Seen::Ingredient(now)) if before != now => Some(format!(
+                "{}: {} -> {}",
+                self,
+                show_ingredient(before.as_ref()),
+                show_ingredient(now.as_ref())
+            )),
+            (Seen::Stack(before), Seen::Stack(now)) if before != now => Some(format!(
+                "{}: {} -> {}",
+                self,
+                show_stack(before.as_ref()),
+                show_stack(now.as_ref())
+            )),
+            _ => None,
+        }
+    }
+}
+
+impl Operand {
+    fn amount(&self, kitchen: &Kitchen) -> Option<i64> {
+        match self {
+            Operand::Number(number) => Some(*number),
+            Operand::Ingredient(name) => kitchen.variables.get(name).map(|value| value.amount),
+            Operand::Len(stack) => Some(kitchen.stack(*stack).map_or(0, VecDeque::len) as i64),
+        }
+    }
+}
+
+fn show_ingredient(value: Option<&Value>) -> String {
+    value.map_or_else(|| "no value".to_string(), show_value)
+}
+
+/// The values top first, the first few of a long stack only.
+fn show_stack(values: Option<&VecDeque<Value>>) -> String {
+    const SHOWN: usize = 8;
+    let Some(values) = values else {
+        return "[]".to_string();
+    };
+    let mut shown: Vec<String> = values.iter().take(SHOWN).map(show_value).collect();
+    if values.len() > SHOWN {
+        shown.push(format!("... {} more", values.len() - SHOWN));
+    }
+    format!("[{}]", shown.join(", "))
+}
+
+/// A watch that was hit.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Hit {
+    pub watch: Watch,
+    /// The instruction that caused it; `None` when the main recipe's
+    /// deferred `Serves` did, after its last instruction.
+    pub location: Option<Location>,
+    /// What changed, e.g. "sugar: 3 -> 0".
+    pub change: String,
+}
+
+impl fmt::Display for Hit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.location {
+            Some(location) => write!(f, "{}, at {}", self.change, location),
+            None => write!(f, "{}, as the recipe was served", self.change),
+        }
+    }
+}
+
+struct Entry {
+    watch: Watch,
+    /// The call depth of the recipe whose kitchen the watch looks at.
+    level: usize,
+    seen: Option<Seen>,
+}
+
+/// The instructions of a recipe that ran, for naming where a hit happened.
+struct RecipeLabels {
+    title: String,
+    /// The line and opening sentence of each instruction, in pre-order.
+    labels: Vec<(Option<usize>, String)>,
+}
+
+/// What a [`Watcher`] tells about each hit as it happens.
+pub(crate) type Report = Box<dyn FnMut(&Hit)>;
+
+/// The watches of a run and the hits not yet collected.
+#[derive(Default)]
+pub(crate) struct Watcher {
+    entries: Vec<Entry>,
+    /// The labels of each recipe that ran, by id.
+    recipes: HashMap<usize, RecipeLabels>,
+    /// The running instructions, innermost last.
+    running: Vec<Option<InstructionId>>,
+    hits: Vec<Hit>,
+    /// Called with each hit as it happens, instead of keeping it in `hits`.
+    report: Option<Report>,
+}
+
+impl Watcher {
+    /// Watches the kitchen of the recipe at call depth `level`. The first
+    /// look only notes what the watch sees.
+    pub(crate) fn add(&mut self, watch: Watch, level: usize) {
+        self.entries.push(Entry {
+            watch,
+            level,
+            seen: None,
+        });
+    }
+
+    pub(crate) fn remove(&mut self, index: usize) -> Option<Watch> {
+        (index < self.entries.len()).then(|| self.entries.remove(index).watch)
+    }
+
+    pub(crate) fn watches(&self) -> impl Iterator<Item = &Watch> {
+        self.entries.iter().map(|entry| &entry.watch)
+    }
+
+    /// Makes the next look only note what each watch sees, after the run
+    /// was put back to an earlier state.
+    pub(crate) fn forget(&mut self) {
+        for entry in &mut self.entries {
+            entry.seen = None;
+        }
+    }
+
+    pub(crate) fn report_hits(&mut self, report: Report) {
+        self.report = Some(report);
+    }
+
+    pub(crate) fn take_hits(&mut self) -> Vec<Hit> {
+        std::mem::take(&mut self.hits)
+    }
+
+    /// Looks at every watch, putting the hits down to the innermost running
+    /// instruction.
+    pub(crate) fn check(&mut self, context: &ExecutionContext) {
+        for index in 0..self.entries.len() {
+            let entry = &self.entries[index];
+            let Some(kitchen) = Kitchen::at(context, entry.level) else {
+                continue;
+            };
+            let now = entry.watch.look(&kitchen);
+            let change = entry
+                .seen
+                .as_ref()
+                .and_then(|before| entry.watch.change(before, &now));
+            if let Some(change) = change {
+                let hit = Hit {
+                    watch: entry.watch.clone(),
+                    location: self.location(),
+                    change,
+                };
+                match &mut self.report {
+                    Some(report) => report(&hit),
+                    None => self.hits.push(hit),
+                }
+            }
+            self.entries[index].seen = Some(now);
+        }
+    }
+
+    fn location(&self) -> Option<Location> {
+        let id = (*self.running.last()?)?;
+        let recipe = self.recipes.get(&id.recipe)?;
+        let (line, text) = recipe.labels.get(id.index)?;
+        Some(Location {
+            recipe: recipe.title.trim_end_matches('.').to_string(),
+            instruction: id.index,
+            line: *line,
+            text: text.clone(),
+        })
+    }
+}
+
+impl Observer for Watcher {
+    fn enter_recipe(&mut self, id: usize, recipe: &Recipe) {
+        self.recipes.entry(id).or_insert_with(|| RecipeLabels {
+            title: recipe.title.clone(),
+            labels: Instruction::preorder(&recipe.instructions)
+                .into_iter()
+                .enumerate()
+                .map(|(index, instruction)| {
+                    let line = recipe
+                        .source_map
+                        .instructions
+                        .get(index)
+                        .map(|location| location.line);
+                    (line, instruction.opening())
+                })
+                .collect(),
+        });
+    }
+
+    fn exit_recipe(&mut self) {}
+
+    fn enter_instruction(&mut self, id: Option<InstructionId>, _: &Instruction) {
+        self.running.push(id);
+    }
+
+    fn exit_instruction(&mut self, _: Option<InstructionId>) {
+        self.running.pop();
+    }
+
+    fn loop_pass(&mut self) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::interpreter::Interpreter;
+    use crate::parser::Parser;
+
+    #[test]
+    fn reads_ingredients_stacks_and_conditions() {
+        assert_eq!(
+            "brown sugar".parse::<Watch>().unwrap(),
+            Watch::Ingredient("brown sugar".to_string())
+        );
+        assert_eq!(
+            "mixing bowl 2".parse::<Watch>().unwrap(),
+            Watch::Stack(Stack::Bowl(1))
+        );
+        let watch: Watch = "len(bowl 2) > 100".parse().unwrap();
+        assert_eq!(
+            watch,
+            Watch::Condition {
+                left: Operand::Len(Stack::Bowl(1)),
+                comparison: Comparison::Gt,
+                right: Operand::Number(100),
+            }
+        );
+        assert_eq!(watch.to_string(), "len(bowl 2) > 100");
+        let watch: Watch = "sugar<=-1".parse().unwrap();
+        assert_eq!(watch.to_string(), "sugar <= -1");
+
+        assert!(matches!("".parse::<Watch>(), Err(WatchError::Empty)));
+        assert!(matches!(
+            "sugar ==".parse::<Watch>(),
+            Err(WatchError::MissingOperand { .. })
+        ));
+        assert!(matches!(
+            "len(pan 1) > 2".parse::<Watch>(),
+            Err(WatchError::NotAStack { .. })
+        ));
+        assert!(matches!(
+            "bowl 0".parse::<Watch>(),
+            Err(WatchError::NotAStack { .. })
+        ));
+        for text in ["sugar = 0", "a = 3", "sugar === 0", "sugar => 1", "!sugar"] {
+            assert!(
+                matches!(
+                    text.parse::<Watch>(),
+                    Err(WatchError::NotAComparison { .. })
+                ),
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn watches_must_name_declared_ingredients() {
+        let source =
+            "Sweet.\n\nIngredients.\n3 g sugar\nflour\n\nMethod.\nPut sugar into the mixing bowl.";
+        let recipe = Parser::new(source).parse_recipe().unwrap();
+        for watch in ["sugar", "flour", "bowl 2", "len(bowl 1) < sugar"] {
+            assert!(watch.parse::<Watch>().unwrap().check(&recipe).is_ok());
+        }
+        for watch in ["salt", "sugar > salt"] {
+            let error = watch.parse::<Watch>().unwrap().check(&recipe).unwrap_err();
+            assert_eq!(error.to_string(), "Sweet declares no ingredient 'salt'");
+        }
+    }
+
+    #[test]
+    fn hits_name_the_instruction_that_caused_them() {
+        let source = "\
+Countdown.
+
+Ingredients.
+2 g counter
+7 g sugar
+
+Method.
+Put sugar into the mixing bowl.
+Count the counter.
+Put counter into the mixing bowl.
+Count the counter until counted.
+Fold sugar into the mixing bowl.
+Serve with doubled.
+Pour contents of the mixing bowl into the baking dish.
+
+Serves 1.
+
+Doubled.
+
+Ingredients.
+5 g sugar
+
+Method.
+Fold sugar into the mixing bowl.
+Put sugar into the mixing bowl.
+Put sugar into the mixing bowl.
+";
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(Parser::new(source).parse_recipe().unwrap());
+        for watch in ["counter", "sugar", "len(bowl 1) > 2", "dish 1"] {
+            interpreter.watch(watch.parse().unwrap());
+        }
+        interpreter.run().unwrap();
+        let hits: Vec<String> = interpreter
+            .take_watch_hits()
+            .iter()
+            .map(Hit::to_string)
+            .collect();
+        assert_eq!(
+            hits,
+            [
+                // The loop's decrements.
+                "counter: 2 -> 1, at line 9 (Countdown): Count the counter",
+                "len(bowl 1) > 2 became true, at line 10 (Countdown): \
+                 Put counter into the mixing bowl",
+                "counter: 1 -> 0, at line 9 (Countdown): Count the counter",
+                "sugar: 7 -> 1, at line 12 (Countdown): Fold sugar into the mixing bowl",
+                // The sous-chef's own sugar is not the one watched, but the
+                // bowl it hands back is.
+                "len(bowl 1) > 2 became true, at line 13 (Countdown): Serve with doubled",
+                "dish 1: [] -> [2, 2, 7, 2, 7], at line 14 (Countdown): \
+                 Pour contents of the mixing bowl into the baking dish",
+                "dish 1: [2, 2, 7, 2, 7] -> [], at line 16 (Countdown): Serves 1",
+            ]
+        );
+    }
+
+    #[test]
+    fn reported_hits_are_not_kept() {
+        let source = "Counter.\n\nIngredients.\n2 g counter\n\nMethod.\nCount the counter.\nPut counter into the mixing bowl.\nCount the counter until counted.";
+        let mut interpreter = Interpreter::new();
+        interpreter.add_recipe(Parser::new(source).parse_recipe().unwrap());
+        interpreter.watch("counter".parse().unwrap());
+        let reported = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let sink = reported.clone();
+        interpreter.report_watch_hits(move |hit| sink.borrow_mut().push(hit.change.clone()));
+        interpreter.run().unwrap();
+        assert_eq!(*reported.borrow(), ["counter: 2 -> 1", "counter: 1 -> 0"]);
+        assert!(interpreter.take_watch_hits().is_empty());
+    }
+}
@@ -0,0 +1,163 @@
+//! Runs recipes compiled by the JavaScript backend under Node.js and checks
+//! that they serve exactly what the interpreter serves. Skipped (with a note)
+//! when `node` is not installed.
+
+use std::error::Error;
+use std::fs;
+use std::process::Command;
+
+use cheffers::compile::compile_js;
+use cheffers::parser::Parser;
+use cheffers::Interpreter;
+
+type TestResult<T> = Result<T, Box<dyn Error>>;
+
+const SEED: u64 = 42;
+
+fn node_available() -> bool {
+    Command::new("node")
+        .arg("--version")
+        .output()
+        .map(|output| output.status.success())
+        .unwrap_or(false)
+}
+
+/// Interprets `source` with the given input, returning the output or the
+/// runtime error message.
+fn interpret(source: &str, input: &str) -> TestResult<Result<String, String>> {
+    let recipe = Parser::new(source).parse_recipe()?;
+    let mut interpreter = Interpreter::new();
+    interpreter.set_input_text(input);
+    interpreter.set_mix_seed(SEED);
+    interpreter.add_recipe(recipe);
+    Ok(match interpreter.run() {
+        Ok(()) => Ok(interpreter.output().to_string()),
+        Err(error) => Err(error.to_string()),
+    })
+}
+
+/// Compiles `source` to a module, runs it with Node and returns the output or
+/// the thrown error message.
+fn run_compiled(name: &str, source: &str, input: &str) -> TestResult<Result<String, String>> {
+    let recipe = Parser::new(source).parse_recipe()?;
+    let dir = std::env::temp_dir().join(format!("cheffers-js-{}", std::process::id()));
+    fs::create_dir_all(&dir)?;
+    let module = dir.join(format!("{}.mjs", name));
+    fs::write(&module, compile_js(&recipe))?;
+
+    let script = format!(
+        "import {{ run }} from {:?};\n\
+         try {{ process.stdout.write(JSON.stringify({{ ok: run({{ input: {:?}, seed: {} }}) }})); }}\n\
+         catch (e) {{ process.stdout.write(JSON.stringify({{ err: e.message }})); }}\n",
+        format!("file://{}", module.display()),
+        input,
+        SEED
+    );
+    let output = Command::new("node")
+        .args(["--input-type=module", "-e", &script])
+        .output()?;
+    assert!(
+        output.status.success(),
+        "node failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    let text = String::from_utf8(output.stdout)?;
+    Ok(parse_result(&text))
+}
+
+/// Reads back `{"ok":"..."}` / `{"err":"..."}` without a JSON dependency.
+fn parse_result(json: &str) -> Result<String, String> {
+    let (key, rest) = json
+        .trim_start_matches('{')
+        .split_once(':')
+        .expect("result object");
+    let body = rest.trim_end_matches('}');
+    let value = unescape_json_string(body);
+    if key == "\"ok\"" {
+        Ok(value)
+    } else {
+        Err(value)
+    }
+}
+
+fn unescape_json_string(quoted: &str) -> String {
+    let inner = &quoted[1..quoted.len() - 1];
+    let mut out = String::new();
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => out.push('\n'),
+            Some('t') => out.push('\t'),
+            Some('r') => out.push('\r'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                let code = u32::from_str_radix(&hex, 16).expect("hex escape");
+                out.push(char::from_u32(code).unwrap_or('\u{fffd}'));
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+fn assert_same_behavior(name: &str, source: &str, input: &str) -> TestResult<()> {
+    let expected = interpret(source, input)?;
+    let actual = run_compiled(name, source, input)?;
+    assert_eq!(
+        actual, expected,
+        "compiled {} diverged from the interpreter",
+        name
+    );
+    Ok(())
+}
+
+macro_rules! compiled_matches_interpreter {
+    ($test_name:ident, $fixture:literal, $input:literal) => {
+        #[test]
+        fn $test_name() -> TestResult<()> {
+            if !node_available() {
+                eprintln!("skipping: node is not installed");
+                return Ok(());
+            }
+            let source = fs::read_to_string(concat!("tests/fixtures/", $fixture))?;
+            assert_same_behavior(stringify!($test_name), &source, $input)
+        }
+    };
+}
+
+compiled_matches_interpreter!(hello_world, "hello-world.chef", "");
+compiled_matches_interpreter!(countdown_cake, "countdown-cake.chef", "");
+compiled_matches_interpreter!(fibonacci_with_caramel_sauce, "fibonacci.chef", "");
+compiled_matches_interpreter!(factorial_fruitcake, "factorial-fruitcake.chef", "");
+compiled_matches_interpreter!(stack_reversal_stew, "stack-reversal-stew.chef", "");
+compiled_matches_interpreter!(two_number_tart, "two-number-tart.chef", "40 2");
+compiled_matches_interpreter!(spaghetti_emoji, "spaghetti-emoji.chef", "");
+compiled_matches_interpreter!(stir_rollover, "spec/stir-rollover-test.chef", "");
+compiled_matches_interpreter!(mix_with_fixed_seed, "spec/mix-randomization-test.chef", "");
+compiled_matches_interpreter!(set_aside, "spec/set-aside-test.chef", "");
+compiled_matches_interpreter!(
+    recursive_auxiliary,
+    "spec/recursive-auxiliary-test.chef",
+    ""
+);
+compiled_matches_interpreter!(empty_bowl_error, "spec/empty-bowl-error-test.chef", "");
+compiled_matches_interpreter!(
+    division_by_zero_error,
+    "spec/division-by-zero-error-test.chef",
+    ""
+);
+
+#[test]
+fn missing_input_reports_the_interpreter_message() -> TestResult<()> {
+    if !node_available() {
+        eprintln!("skipping: node is not installed");
+        return Ok(());
+    }
+    let source = fs::read_to_string("tests/fixtures/echo-pasta.chef")?;
+    assert_same_behavior("missing_input", &source, "")
+}
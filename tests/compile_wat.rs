@@ -0,0 +1,179 @@
+//! Assembles recipes compiled by the WebAssembly backend, runs them under the
+//! `wasmi` interpreter and checks that they serve exactly what the
+//! interpreter serves.
+
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fs;
+
+use cheffers::compile::compile_wat;
+use cheffers::parser::Parser;
+use cheffers::Interpreter;
+use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+
+type TestResult<T> = Result<T, Box<dyn Error>>;
+
+const SEED: u64 = 42;
+
+/// What the host imports see while a compiled recipe runs.
+#[derive(Default)]
+struct Kitchen {
+    input: VecDeque<String>,
+    output: String,
+    error: Option<String>,
+}
+
+impl Kitchen {
+    /// Records a runtime error and aborts execution with it.
+    fn fail(&mut self, message: String) -> wasmi::Error {
+        let error = wasmi::Error::new(message.clone());
+        self.error = Some(message);
+        error
+    }
+}
+
+fn read_string(caller: &Caller<'_, Kitchen>, ptr: i32, len: i32) -> String {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .expect("module exports its memory");
+    let bytes = &memory.data(caller)[ptr as usize..(ptr + len) as usize];
+    String::from_utf8_lossy(bytes).into_owned()
+}
+
+fn interpret(source: &str, input: &str) -> TestResult<Result<String, String>> {
+    let recipe = Parser::new(source).parse_recipe()?;
+    let mut interpreter = Interpreter::new();
+    interpreter.set_input_text(input);
+    interpreter.set_mix_seed(SEED);
+    interpreter.add_recipe(recipe);
+    Ok(match interpreter.run() {
+        Ok(()) => Ok(interpreter.output().to_string()),
+        Err(error) => Err(error.to_string()),
+    })
+}
+
+fn run_compiled(source: &str, input: &str) -> TestResult<Result<String, String>> {
+    let recipe = Parser::new(source).parse_recipe()?;
+    let wasm = wat::parse_str(compile_wat(&recipe))?;
+
+    let engine = Engine::default();
+    let module = Module::new(&engine, &wasm[..])?;
+    let kitchen = Kitchen {
+        input: input.split_whitespace().map(str::to_string).collect(),
+        ..Kitchen::default()
+    };
+    let mut store = Store::new(&engine, kitchen);
+    let mut linker = <Linker<Kitchen>>::new(&engine);
+
+    linker.func_wrap(
+        "env",
+        "read_input",
+        |mut caller: Caller<'_, Kitchen>, ptr: i32, len: i32| -> Result<i64, wasmi::Error> {
+            let name = read_string(&caller, ptr, len);
+            let kitchen = caller.data_mut();
+            let reason = match kitchen.input.pop_front() {
+                None => "no more input values are available".to_string(),
+                Some(token) => match token.parse::<i64>() {
+                    Ok(amount) => return Ok(amount),
+                    Err(_) => format!("'{}' is not a numeric value", token),
+                },
+            };
+            Err(kitchen.fail(format!(
+                "cannot read input for ingredient '{}': {}",
+                name, reason
+            )))
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "write_char",
+        |mut caller: Caller<'_, Kitchen>, code: i64| -> Result<(), wasmi::Error> {
+            let kitchen = caller.data_mut();
+            match u32::try_from(code).ok().and_then(char::from_u32) {
+                Some(ch) => {
+                    kitchen.output.push(ch);
+                    Ok(())
+                }
+                None => Err(kitchen.fail(format!(
+                    "value {} is not a valid Unicode code point for liquid output",
+                    code
+                ))),
+            }
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "write_int",
+        |mut caller: Caller<'_, Kitchen>, amount: i64| {
+            caller.data_mut().output.push_str(&amount.to_string());
+        },
+    )?;
+    linker.func_wrap(
+        "env",
+        "fail",
+        |mut caller: Caller<'_, Kitchen>, ptr: i32, len: i32| -> Result<(), wasmi::Error> {
+            let message = read_string(&caller, ptr, len);
+            Err(caller.data_mut().fail(message))
+        },
+    )?;
+
+    let instance = linker.instantiate(&mut store, &module)?.start(&mut store)?;
+    instance
+        .get_typed_func::<i64, ()>(&store, "set_seed")?
+        .call(&mut store, SEED as i64)?;
+    let result = instance
+        .get_typed_func::<(), ()>(&store, "run")?
+        .call(&mut store, ());
+
+    let kitchen = store.into_data();
+    Ok(match (result, kitchen.error) {
+        (Ok(()), _) => Ok(kitchen.output),
+        (Err(_), Some(message)) => Err(message),
+        (Err(trap), None) => return Err(format!("unexpected trap: {}", trap).into()),
+    })
+}
+
+fn assert_same_behavior(name: &str, source: &str, input: &str) -> TestResult<()> {
+    let expected = interpret(source, input)?;
+    let actual = run_compiled(source, input)?;
+    assert_eq!(
+        actual, expected,
+        "compiled {} diverged from the interpreter",
+        name
+    );
+    Ok(())
+}
+
+macro_rules! compiled_matches_interpreter {
+    ($test_name:ident, $fixture:literal, $input:literal) => {
+        #[test]
+        fn $test_name() -> TestResult<()> {
+            let source = fs::read_to_string(concat!("tests/fixtures/", $fixture))?;
+            assert_same_behavior(stringify!($test_name), &source, $input)
+        }
+    };
+}
+
+compiled_matches_interpreter!(hello_world, "hello-world.chef", "");
+compiled_matches_interpreter!(countdown_cake, "countdown-cake.chef", "");
+compiled_matches_interpreter!(fibonacci_with_caramel_sauce, "fibonacci.chef", "");
+compiled_matches_interpreter!(factorial_fruitcake, "factorial-fruitcake.chef", "");
+compiled_matches_interpreter!(stack_reversal_stew, "stack-reversal-stew.chef", "");
+compiled_matches_interpreter!(two_number_tart, "two-number-tart.chef", "40 2");
+compiled_matches_interpreter!(spaghetti_emoji, "spaghetti-emoji.chef", "");
+compiled_matches_interpreter!(stir_rollover, "spec/stir-rollover-test.chef", "");
+compiled_matches_interpreter!(mix_with_fixed_seed, "spec/mix-randomization-test.chef", "");
+compiled_matches_interpreter!(set_aside, "spec/set-aside-test.chef", "");
+compiled_matches_interpreter!(
+    recursive_auxiliary,
+    "spec/recursive-auxiliary-test.chef",
+    ""
+);
+compiled_matches_interpreter!(empty_bowl_error, "spec/empty-bowl-error-test.chef", "");
+compiled_matches_interpreter!(
+    division_by_zero_error,
+    "spec/division-by-zero-error-test.chef",
+    ""
+);
+compiled_matches_interpreter!(missing_input, "echo-pasta.chef", "");
@@ -1,9 +1,10 @@
 use std::error::Error;
 use std::fs;
 
+use cheffers::golden::{run_all, GoldenOptions, Outcome};
 use cheffers::parser::Parser;
 use cheffers::types::{ParseError, Recipe};
-use cheffers::Interpreter;
+use cheffers::{Interpreter, OutputOptions, ParseOptions, RecipeBook};
 
 type TestResult<T> = Result<T, Box<dyn Error>>;
 
@@ -583,3 +584,69 @@ fn input_text_tokens_are_parsed_per_take_with_ingredient_diagnostics() -> TestRe
     );
     Ok(())
 }
+
+/// Each `tests/fixtures/output/NAME.chef` is served once per output style
+/// and compared with `NAME.STYLE.out`.
+#[test]
+fn output_styles_match_their_fixtures() -> TestResult<()> {
+    let mut checked = 0;
+    for entry in fs::read_dir("tests/fixtures/output")? {
+        let path = entry?.path();
+        if path.extension().map_or(true, |ext| ext != "chef") {
+            continue;
+        }
+        let recipe = parse_recipe(&fs::read_to_string(&path)?)?;
+        for style in OutputOptions::STYLES {
+            let expected = fs::read_to_string(path.with_extension(format!("{}.out", style)))?;
+            let mut interpreter = Interpreter::new();
+            interpreter.set_output_options(OutputOptions::from_style(style).unwrap());
+            interpreter.add_recipe(recipe.clone());
+            interpreter.run()?;
+            assert_eq!(
+                interpreter.output(),
+                expected,
+                "{} with the {} style",
+                path.display(),
+                style
+            );
+            checked += 1;
+        }
+    }
+    assert!(checked > 0, "no output fixtures found");
+    Ok(())
+}
+
+#[test]
+fn serve_with_uses_recipes_from_a_book_directory() -> TestResult<()> {
+    let mut book = RecipeBook::new();
+    book.load(
+        "tests/fixtures/book/sauces".as_ref(),
+        ParseOptions::default(),
+    )?;
+    assert_eq!(book.len(), 2);
+
+    let source = read_fixture("tests/fixtures/book/sundae.chef")?;
+    let recipe = parse_recipe(&source)?;
+    assert!(book.shadowed_by(&recipe).is_empty());
+
+    let mut interpreter = Interpreter::new();
+    interpreter.add_recipe(recipe);
+    interpreter.add_book(&book);
+    interpreter.run()?;
+    assert_eq!(interpreter.output(), "Yum!");
+    Ok(())
+}
+
+#[test]
+fn fixture_comment_examples_pass() -> TestResult<()> {
+    let options = GoldenOptions {
+        doc: true,
+        ..GoldenOptions::default()
+    };
+    let cases = run_all(&["tests/fixtures".into()], &options)?;
+    assert!(cases.len() >= 3, "{:?}", cases);
+    for case in cases {
+        assert_eq!(case.outcome, Outcome::Passed, "{}", case.path.display());
+    }
+    Ok(())
+}
@@ -1,7 +1,8 @@
 use std::error::Error;
 use std::fs;
 
-use cheffers::parser::Parser;
+use cheffers::golden::{run_all, GoldenOptions, Outcome};
+use cheffers::parser::{ParseOptions, Parser};
 use cheffers::types::{ParseError, Recipe};
 use cheffers::Interpreter;
 
@@ -342,3 +343,65 @@ spec_runtime_error_test!(
     spec_undefined_ingredient_error,
     "undefined-ingredient-error-test.chef"
 );
+
+// ============================================================================
+// STRICT PARSING
+// ============================================================================
+
+/// Fixtures follow the spec's layout, so strict parsing must agree with
+/// lenient parsing on which of them are valid. Two exceptions spread their
+/// method or their comments over several paragraphs.
+#[test]
+fn strict_parsing_accepts_every_valid_fixture() -> TestResult<()> {
+    for dir in ["tests/fixtures", "tests/fixtures/spec"] {
+        for entry in fs::read_dir(dir)? {
+            let path = entry?.path();
+            if path.extension().map_or(true, |ext| ext != "chef") {
+                continue;
+            }
+            let source = fs::read_to_string(&path)?;
+            let lenient = parse_recipe(&source);
+            let strict = Parser::with_options(&source, ParseOptions::strict()).parse_recipe();
+            let name = path.file_name().unwrap().to_string_lossy();
+            match (name.as_ref(), &strict) {
+                ("golden-ratio.chef", Err(ParseError::BlankLineInMethod { line: 28 }))
+                | (
+                    "loop-with-verb-pattern-instructions-test.chef",
+                    Err(ParseError::UnexpectedParagraph { line: 7, .. }),
+                ) => {
+                    assert!(lenient.is_ok());
+                    continue;
+                }
+                _ => {}
+            }
+            assert_eq!(
+                lenient.is_ok(),
+                strict.is_ok(),
+                "{}: lenient {:?}, strict {:?}",
+                path.display(),
+                lenient.err(),
+                strict.err()
+            );
+        }
+    }
+    Ok(())
+}
+
+// ============================================================================
+// GOLDEN OUTPUT
+// ============================================================================
+
+/// Every spec fixture has a `.out` and/or `.err` file recording what it
+/// serves; `cheffers test --bless tests/fixtures/spec` rewrites them.
+#[test]
+fn spec_fixtures_match_their_golden_output() -> TestResult<()> {
+    let cases = run_all(&["tests/fixtures/spec".into()], &GoldenOptions::default())?;
+    assert!(!cases.is_empty());
+    for case in cases {
+        if let Outcome::Failed { report } = &case.outcome {
+            panic!("{}:\n{}", case.path.display(), report);
+        }
+        assert_eq!(case.outcome, Outcome::Passed, "{}", case.path.display());
+    }
+    Ok(())
+}